@@ -0,0 +1,86 @@
+// Cells of random seeds drawn as a Bevy 2D mesh, remeshed with new seeds on space. Run with
+// `cargo run --example bevy_cells --features bevy`.
+
+use bevy::prelude::*;
+use blue_noise::backend;
+use blue_noise::bevy_mesh::to_bevy_mesh;
+use blue_noise::cells::extract_cells;
+use blue_noise::config::{Backend, MesherConfig};
+use blue_noise::mesh::PolyMesh;
+use rand::Rng;
+
+const DOMAIN: (f64, f64) = (16.0, 9.0);
+const SEEDS: usize = 60;
+// Pixels per domain unit
+const SCALE: f32 = 70.0;
+
+/// Spawns the cells of random seeds, and replaces them with the cells of new ones on space.
+struct CellsPlugin;
+
+#[derive(Component)]
+struct Cells;
+
+impl Plugin for CellsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_cells)
+            .add_systems(Update, reseed);
+    }
+}
+
+// Mesh of the cells of random seeds, labeled on the CPU
+fn random_cells() -> Mesh {
+    let mut rng = rand::thread_rng();
+    let points: Vec<(f64, f64)> = (0..SEEDS)
+        .map(|_| (rng.gen::<f64>() * DOMAIN.0, rng.gen::<f64>() * DOMAIN.1))
+        .collect();
+    let options = MesherConfig {
+        backend: Backend::Cpu,
+        resolution: 512,
+        ..Default::default()
+    };
+    let labeling = backend::from_config(&options)
+        .and_then(|backend| backend.labels(&points, &[], DOMAIN, &options))
+        .expect("labeling failed");
+    let cells = extract_cells(
+        &labeling.labels,
+        (labeling.width, labeling.height),
+        points.len(),
+        DOMAIN,
+        options.boundary,
+    );
+    to_bevy_mesh(&PolyMesh::from_cells(&cells))
+}
+
+fn spawn_cells(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.spawn(Camera2d);
+    let offset = Vec3::new(-DOMAIN.0 as f32, -DOMAIN.1 as f32, 0.0) * SCALE / 2.0;
+    commands.spawn((
+        Cells,
+        Mesh2d(meshes.add(random_cells())),
+        MeshMaterial2d(materials.add(ColorMaterial::default())),
+        Transform::from_translation(offset).with_scale(Vec3::splat(SCALE)),
+    ));
+}
+
+fn reseed(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    cells: Query<&Mesh2d, With<Cells>>,
+) {
+    if !keys.just_pressed(KeyCode::Space) {
+        return;
+    }
+    for mesh in &cells {
+        if let Some(mesh) = meshes.get_mut(&mesh.0) {
+            *mesh = random_cells();
+        }
+    }
+}
+
+fn main() {
+    App::new().add_plugins((DefaultPlugins, CellsPlugin)).run();
+}
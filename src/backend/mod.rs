@@ -0,0 +1,259 @@
+// Labeling backends behind one trait, so the code consuming label grids doesn't depend on
+// which engine produced them and users can plug in their own. Backends fail with
+// `InvalidInput` on the options and weights they don't support rather than ignoring them.
+
+use crate::config::{Backend, BoundaryMode, DistanceMetric, GridShape, MesherConfig, Metric};
+use crate::error::MesherError;
+use crate::symmetry::SymmetricBackend;
+use crate::{jfa_cpu, reference};
+
+/// Label grid produced by a backend, of [`MesherConfig::grid_dimensions`] texels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Labeling {
+    /// Seed index plus one for each texel, indexed with `x + y * width`, 0 when unassigned
+    pub labels: Vec<usize>,
+    pub width: usize,
+    pub height: usize,
+    /// Distance from each texel center to its seed, in texels as the norm, metric and wrapped
+    /// axes of the options measure it, for backends computing it
+    pub distances: Option<Vec<f64>>,
+}
+
+pub trait VoronoiBackend {
+    /// Short name for logs and messages
+    fn name(&self) -> &'static str;
+
+    /// Labels the texels of the `config` box with their nearest seed, by power distance when
+    /// `weights` holds one weight per seed, in squared domain units, rather than none.
+    fn labels(
+        &self,
+        points: &[(f64, f64)],
+        weights: &[f64],
+        config: (f64, f64),
+        options: &MesherConfig,
+    ) -> Result<Labeling, MesherError>;
+}
+
+/// Backend `options.backend` selects, within a [`SymmetricBackend`] when `options.symmetry` is
+/// set. The GPU one is blocking, which browsers don't allow, so
+/// it can't be selected on wasm, where `jfa_wgpu::mesh` is awaited instead.
+pub fn from_config(options: &MesherConfig) -> Result<Box<dyn VoronoiBackend>, MesherError> {
+    let backend: Box<dyn VoronoiBackend> = match options.backend {
+        #[cfg(not(target_arch = "wasm32"))]
+        Backend::Gpu => Box::new(GpuBackend),
+        #[cfg(target_arch = "wasm32")]
+        Backend::Gpu => {
+            return Err(MesherError::InvalidInput(
+                "the GPU backend is blocking, await jfa_wgpu::mesh in browsers",
+            ))
+        }
+        Backend::Cpu => Box::new(CpuBackend),
+        Backend::Exact => Box::new(ExactBackend),
+    };
+    Ok(match options.symmetry {
+        Some(symmetry) => Box::new(SymmetricBackend::new(backend, symmetry)),
+        None => backend,
+    })
+}
+
+/// Jump flooding on the GPU.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GpuBackend;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl VoronoiBackend for GpuBackend {
+    fn name(&self) -> &'static str {
+        "GPU"
+    }
+
+    fn labels(
+        &self,
+        points: &[(f64, f64)],
+        weights: &[f64],
+        config: (f64, f64),
+        options: &MesherConfig,
+    ) -> Result<Labeling, MesherError> {
+        check_asymmetric(options)?;
+        let labels = crate::jfa_wgpu::block_on(crate::jfa_wgpu::mesh_weighted(
+            points, weights, config, options,
+        ))?;
+        let (width, height) = options.grid_dimensions(config);
+        Ok(Labeling {
+            labels,
+            width,
+            height,
+            distances: None,
+        })
+    }
+}
+
+/// Jump flooding on the CPU, for unweighted Euclidean diagrams on a bounded square grid.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CpuBackend;
+
+impl VoronoiBackend for CpuBackend {
+    fn name(&self) -> &'static str {
+        "CPU"
+    }
+
+    fn labels(
+        &self,
+        points: &[(f64, f64)],
+        weights: &[f64],
+        config: (f64, f64),
+        options: &MesherConfig,
+    ) -> Result<Labeling, MesherError> {
+        check_unweighted(weights)?;
+        check_asymmetric(options)?;
+        if options.grid != GridShape::Square {
+            return Err(MesherError::InvalidInput(
+                "the CPU backend only labels square grids",
+            ));
+        }
+        if options.boundary != BoundaryMode::Bounded {
+            return Err(MesherError::InvalidInput(
+                "the CPU backend doesn't support periodic boundaries",
+            ));
+        }
+        if options.distance != DistanceMetric::Euclidean || options.metric != Metric::default() {
+            return Err(MesherError::InvalidInput(
+                "the CPU backend only measures isotropic Euclidean distances",
+            ));
+        }
+        let labels = jfa_cpu::jfa_with_options(points, config, options)
+            .map_err(MesherError::InvalidInput)?;
+        let (width, height) = options.grid_dimensions(config);
+        Ok(Labeling {
+            labels,
+            width,
+            height,
+            distances: None,
+        })
+    }
+}
+
+/// Brute-force nearest seed at every texel center, with the distance, metric and boundary of
+/// the options, the reference for small cases.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExactBackend;
+
+impl VoronoiBackend for ExactBackend {
+    fn name(&self) -> &'static str {
+        "exact"
+    }
+
+    fn labels(
+        &self,
+        points: &[(f64, f64)],
+        weights: &[f64],
+        config: (f64, f64),
+        options: &MesherConfig,
+    ) -> Result<Labeling, MesherError> {
+        check_unweighted(weights)?;
+        check_asymmetric(options)?;
+        let (width, height) = options.grid_dimensions(config);
+        let (labels, distances) = reference::reference_nearest(points, config, options)
+            .into_iter()
+            .unzip();
+
+        Ok(Labeling {
+            labels,
+            width,
+            height,
+            distances: Some(distances),
+        })
+    }
+}
+
+fn check_unweighted(weights: &[f64]) -> Result<(), MesherError> {
+    if !weights.is_empty() {
+        return Err(MesherError::InvalidInput(
+            "only the GPU backend supports weighted seeds",
+        ));
+    }
+    Ok(())
+}
+
+// Symmetries are applied by wrapping the backend rather than by the engines
+fn check_asymmetric(options: &MesherConfig) -> Result<(), MesherError> {
+    if options.symmetry.is_some() {
+        return Err(MesherError::InvalidInput(
+            "symmetries are applied by the SymmetricBackend from_config wraps the engine in",
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_backend() {
+        let backend: &dyn VoronoiBackend = &ExactBackend;
+        let options = MesherConfig {
+            resolution: 4,
+            ..Default::default()
+        };
+
+        let labeling = backend
+            .labels(&[(1.0, 1.0), (3.0, 3.0)], &[], (4.0, 4.0), &options)
+            .unwrap();
+
+        assert_eq!((labeling.width, labeling.height), (4, 4));
+        assert_eq!(labeling.labels[0], 1);
+        assert_eq!(labeling.labels[15], 2);
+        let distances = labeling.distances.unwrap();
+        assert!((distances[0] - 0.5f64.hypot(0.5)).abs() < 1e-12);
+
+        // Texel (3, 2) is nearer the first seed across the wrapped side
+        let periodic = MesherConfig {
+            boundary: BoundaryMode::Periodic { x: true, y: false },
+            ..options.clone()
+        };
+        let labeling = backend
+            .labels(&[(0.2, 2.0), (2.0, 2.0)], &[], (4.0, 4.0), &periodic)
+            .unwrap();
+        assert_eq!(labeling.labels[3 + 2 * 4], 1);
+        let distance = labeling.distances.unwrap()[3 + 2 * 4];
+        assert!((distance - 0.7f64.hypot(0.5)).abs() < 1e-12);
+
+        let manhattan = MesherConfig {
+            distance: DistanceMetric::Manhattan,
+            ..options
+        };
+        let labeling = backend
+            .labels(&[(1.0, 1.0), (3.0, 3.0)], &[], (4.0, 4.0), &manhattan)
+            .unwrap();
+        assert!((labeling.distances.unwrap()[0] - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_unsupported_options() {
+        let points = [(1.0, 1.0), (3.0, 3.0)];
+        let options = MesherConfig {
+            backend: Backend::Cpu,
+            resolution: 4,
+            boundary: BoundaryMode::Periodic { x: true, y: true },
+            ..Default::default()
+        };
+        let backend = from_config(&options).unwrap();
+        assert_eq!(backend.name(), "CPU");
+        assert!(matches!(
+            backend.labels(&points, &[], (4.0, 4.0), &options),
+            Err(MesherError::InvalidInput(_))
+        ));
+
+        let options = MesherConfig {
+            backend: Backend::Exact,
+            ..options
+        };
+        let backend = from_config(&options).unwrap();
+        assert!(backend.labels(&points, &[], (4.0, 4.0), &options).is_ok());
+        assert!(matches!(
+            backend.labels(&points, &[0.5, 0.0], (4.0, 4.0), &options),
+            Err(MesherError::InvalidInput(_))
+        ));
+    }
+}
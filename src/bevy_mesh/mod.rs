@@ -0,0 +1,86 @@
+// Bevy meshes of the cells, with the `bevy` feature, for games and procedural levels built on
+// the diagram. Each face gets vertices of its own, so that the cell color and id attributes are
+// constant over it, and is split into triangles by `PolyMesh::face_triangles`.
+
+use bevy::color::{Color, LinearRgba};
+use bevy::render::mesh::{Indices, Mesh, MeshVertexAttribute, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::VertexFormat;
+
+use crate::mesh::PolyMesh;
+use crate::render::cell_color;
+
+/// Seed index of the cell of each vertex.
+pub const ATTRIBUTE_CELL_ID: MeshVertexAttribute =
+    MeshVertexAttribute::new("Vertex_CellId", 0x6ce1_1d00, VertexFormat::Uint32);
+
+/// Triangle list of the faces of `mesh` in the z = 0 plane, facing +z, with the cell colors of
+/// the SVG and PNG drawings in [`Mesh::ATTRIBUTE_COLOR`] and the seed indices in
+/// [`ATTRIBUTE_CELL_ID`].
+pub fn to_bevy_mesh(mesh: &PolyMesh) -> Mesh {
+    let mut positions = Vec::new();
+    let mut colors = Vec::new();
+    let mut ids = Vec::new();
+    let mut indices = Vec::new();
+    for (face, f) in mesh.faces.iter().enumerate() {
+        let first = positions.len() as u32;
+        let vertices: Vec<usize> = mesh.face_vertices(face).collect();
+        let (r, g, b) = cell_color(f.seed);
+        let color = LinearRgba::from(Color::srgb_u8(r, g, b));
+        for &v in &vertices {
+            let (x, y) = mesh.vertices[v];
+            positions.push([x as f32, y as f32, 0.0]);
+            colors.push([color.red, color.green, color.blue, color.alpha]);
+            ids.push(f.seed as u32);
+        }
+        for triangle in mesh.face_triangles(face) {
+            indices.extend(
+                triangle.map(|v| first + vertices.iter().position(|&w| w == v).unwrap() as u32),
+            );
+        }
+    }
+
+    let normals = vec![[0.0, 0.0, 1.0]; positions.len()];
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+    .with_inserted_attribute(ATTRIBUTE_CELL_ID, ids)
+    .with_inserted_indices(Indices::U32(indices))
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::render::mesh::VertexAttributeValues;
+
+    use super::*;
+
+    #[test]
+    fn test_to_bevy_mesh() {
+        let mesh = PolyMesh::from_cells(&[
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+            vec![],
+            vec![(1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0)],
+        ]);
+
+        let bevy_mesh = to_bevy_mesh(&mesh);
+
+        // Shared vertices are repeated in each face
+        assert_eq!(bevy_mesh.count_vertices(), 8);
+        assert_eq!(bevy_mesh.indices().unwrap().len(), 12);
+        let Some(VertexAttributeValues::Uint32(ids)) = bevy_mesh.attribute(ATTRIBUTE_CELL_ID)
+        else {
+            panic!("no cell ids");
+        };
+        assert_eq!(ids, &[0, 0, 0, 0, 2, 2, 2, 2]);
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            bevy_mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            panic!("no positions");
+        };
+        assert_eq!(positions[5], [2.0, 0.0, 0.0]);
+    }
+}
@@ -0,0 +1,234 @@
+// End-to-end mesher for users who don't want to write Rust: seeds read from a file or
+// generated, optionally relaxed, labeled on the GPU and written as VTK, Gmsh or SVG, picked by
+// the extension of each output.
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use blue_noise::backend::{GpuBackend, VoronoiBackend};
+use blue_noise::config::{BoundaryMode, MesherConfig};
+use blue_noise::io::seeds::Seeds;
+use blue_noise::mesh::PolyMesh;
+use blue_noise::render::svg::{self, SvgOptions};
+use blue_noise::{cells, io, jfa_wgpu, seeds};
+use clap::{Parser, ValueEnum};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// Relative difference to the mean area at which balancing stops
+const BALANCE_TOLERANCE: f64 = 0.01;
+
+// Extensions `write_output` knows
+const OUTPUT_FORMATS: [&str; 4] = ["vtk", "vtu", "msh", "svg"];
+
+/// Polygonal mesh of a rectangle from seed points.
+#[derive(Parser, Debug)]
+#[command(version, about = "Polygonal mesh of a rectangle from seed points.")]
+struct Args {
+    /// Reads the seeds, with optional weights for a power diagram and flags pinning them during
+    /// relaxation and balancing, from a CSV or JSON file, JSON needing the `serde` feature
+    #[arg(short = 's', long = "seeds", value_name = "FILE")]
+    seeds: Option<PathBuf>,
+
+    /// Sets the number of seeds to generate when none are read
+    #[arg(short = 'n', default_value_t = 100)]
+    n: usize,
+
+    /// Sets how seeds are generated
+    #[arg(short = 'g', long = "generator", default_value = "poisson", value_enum)]
+    generator: Generator,
+
+    /// Seeds the random generator, for reproducible meshes
+    #[arg(long = "rng-seed", value_name = "SEED")]
+    rng_seed: Option<u64>,
+
+    /// Sets the width of the box
+    #[arg(short = 'x', default_value_t = 1.0)]
+    x: f64,
+
+    /// Sets the height of the box
+    #[arg(short = 'y', default_value_t = 1.0)]
+    y: f64,
+
+    /// Sets the resolution of the label grid
+    #[arg(
+        short = 'r',
+        long = "resolution",
+        default_value_t = 512,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    resolution: u32,
+
+    /// Sets the number of Lloyd relaxation iterations
+    #[arg(long = "relax", default_value_t = 0)]
+    relax: usize,
+
+    /// Sets the maximum number of weight adjustments balancing the areas of the cells
+    #[arg(long = "balance", default_value_t = 0)]
+    balance: usize,
+
+    /// Wraps the domain around in x and y
+    #[arg(long = "periodic")]
+    periodic: bool,
+
+    /// Writes the mesh to these files: `.vtk`, `.vtu`, `.msh` or `.svg`
+    #[arg(short = 'o', long = "output", value_name = "FILE", required = true)]
+    outputs: Vec<PathBuf>,
+
+    /// Writes a JSON manifest of the boundary patches alongside the mesh, for case generators
+    #[arg(long = "manifest", value_name = "FILE")]
+    manifest: Option<PathBuf>,
+}
+
+/// Seed generators
+#[derive(Copy, Clone, PartialEq, Eq, Debug, ValueEnum)]
+enum Generator {
+    Random,
+    Poisson,
+}
+
+fn main() {
+    env_logger::init();
+    let args = Args::parse();
+    if let Err(err) = run(&args) {
+        eprintln!("Error: {err}");
+        std::process::exit(1);
+    }
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    // Checked before any work rather than when writing
+    let unknown = |output: &&PathBuf| !OUTPUT_FORMATS.contains(&extension(output).as_str());
+    if let Some(output) = args.outputs.iter().find(unknown) {
+        return Err(format!("{}: unknown output format", output.display()).into());
+    }
+    let config = (args.x, args.y);
+    let options = MesherConfig {
+        resolution: args.resolution as usize,
+        boundary: if args.periodic {
+            BoundaryMode::Periodic { x: true, y: true }
+        } else {
+            BoundaryMode::Bounded
+        },
+        ..Default::default()
+    };
+
+    let seeds = match &args.seeds {
+        Some(path) => {
+            io::seeds::read_seeds(path).map_err(|err| format!("{}: {err}", path.display()))?
+        }
+        None => Seeds {
+            points: generate_seeds(args, config),
+            ..Default::default()
+        },
+    };
+    let mut points = seeds.points.clone();
+    println!(
+        "{} seeds in a {} x {} box",
+        points.len(),
+        config.0,
+        config.1
+    );
+    if args.relax > 0 {
+        println!("Relaxing the seeds {} times...", args.relax);
+        points = jfa_wgpu::block_on(jfa_wgpu::relax(
+            &points,
+            config,
+            args.relax,
+            seeds.pinned(),
+            &options,
+        ))?;
+    }
+
+    println!("Labeling a {0} x {0} grid...", args.resolution);
+    let labels = match seeds.weights {
+        Some(_) if args.balance > 0 => {
+            return Err("the seeds' weights can't be balanced, remove them or --balance".into())
+        }
+        None if args.balance > 0 => {
+            let balanced = jfa_wgpu::block_on(jfa_wgpu::balance(
+                &points,
+                config,
+                args.balance,
+                BALANCE_TOLERANCE,
+                seeds.pinned(),
+                &options,
+            ))?;
+            println!(
+                "Cell areas within {:.1}% of the mean after {} adjustments",
+                balanced.area_error * 100.0,
+                balanced.iterations
+            );
+            balanced
+                .labels
+                .into_iter()
+                .map(|label| label as usize)
+                .collect()
+        }
+        // Relaxation moves the seeds but keeps their weights
+        weights => {
+            let weights = weights.unwrap_or_default();
+            GpuBackend
+                .labels(&points, &weights, config, &options)?
+                .labels
+        }
+    };
+    let dimensions = options.grid_dimensions(config);
+    let polygons =
+        cells::extract_cells(&labels, dimensions, points.len(), config, options.boundary);
+    let mesh = PolyMesh::from_cells(&polygons);
+    println!("{} cells", mesh.faces.len());
+
+    for output in &args.outputs {
+        write_output(output, &mesh, &polygons, &points, config)?;
+        println!("Mesh written to {}", output.display());
+    }
+    if let Some(manifest) = &args.manifest {
+        io::manifest::write_manifest(manifest, &mesh, None)?;
+        println!("Boundary manifest written to {}", manifest.display());
+    }
+    Ok(())
+}
+
+fn generate_seeds(args: &Args, config: (f64, f64)) -> Vec<(f64, f64)> {
+    let mut rng = match args.rng_seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    match args.generator {
+        Generator::Random => (0..args.n)
+            .map(|_| (rng.gen_range(0.0..config.0), rng.gen_range(0.0..config.1)))
+            .collect(),
+        Generator::Poisson => {
+            let radius = seeds::radius_for_count(config, args.n);
+            seeds::poisson_disk(config, radius, &mut rng)
+        }
+    }
+}
+
+fn write_output(
+    path: &Path,
+    mesh: &PolyMesh,
+    polygons: &[Vec<(f64, f64)>],
+    points: &[(f64, f64)],
+    config: (f64, f64),
+) -> Result<(), Box<dyn Error>> {
+    match extension(path).as_str() {
+        "vtk" => io::vtk::write_vtk(path, mesh)?,
+        "vtu" => io::vtk::write_vtu(path, mesh)?,
+        "msh" => io::gmsh::write_msh(path, mesh)?,
+        "svg" => fs::write(
+            path,
+            svg::polygons_svg(polygons, points, config, &SvgOptions::default()),
+        )?,
+        _ => return Err(format!("{}: unknown output format", path.display()).into()),
+    }
+    Ok(())
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
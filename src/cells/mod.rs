@@ -0,0 +1,284 @@
+// Cell polygons traced from a label grid. Boundaries follow the texel edges, and only the
+// lattice points where three labels (or a label, a neighbor and the domain boundary) meet are
+// kept, so straight Voronoi edges come out as single segments rather than staircases.
+
+use std::collections::BTreeMap;
+
+use crate::config::BoundaryMode;
+
+mod repair;
+
+pub use repair::{disconnected_cells, repair_disconnected_cells, RepairReport};
+
+type Lattice = (isize, isize);
+
+/// Counter-clockwise outer boundary of every seed's cell in world coordinates, empty for seeds
+/// which have no texel. `labels` is a grid of `(width, height)` texels covering the `config` box.
+///
+/// With a periodic boundary, cells crossing a wrapped edge are stitched into one polygon which
+/// extends past the box, on the side where most of the cell lies. This assumes cells narrower
+/// than half the box.
+pub fn extract_cells(
+    labels: &[usize],
+    (width, height): (usize, usize),
+    cell_count: usize,
+    config: (f64, f64),
+    boundary: BoundaryMode,
+) -> Vec<Vec<(f64, f64)>> {
+    assert_eq!(labels.len(), width * height, "label grid size mismatch");
+    let (columns, rows) = (width as isize, height as isize);
+    let (wrap_x, wrap_y) = boundary.wraps();
+    let wrap = |v: isize, side: isize, wraps: bool| if wraps { v.rem_euclid(side) } else { v };
+    let label_at = |x: isize, y: isize| {
+        let (x, y) = (wrap(x, columns, wrap_x), wrap(y, rows, wrap_y));
+        if x < 0 || y < 0 || x >= columns || y >= rows {
+            None
+        } else {
+            Some(labels[x as usize + y as usize * width])
+        }
+    };
+
+    let mut texels: Vec<Vec<Lattice>> = vec![Vec::new(); cell_count];
+    for y in 0..rows {
+        for x in 0..columns {
+            let label = labels[x as usize + y as usize * width];
+            if label != 0 {
+                texels[label - 1].push((x, y));
+            }
+        }
+    }
+
+    let is_corner = |(x, y): Lattice| {
+        let around = [
+            label_at(x - 1, y - 1),
+            label_at(x, y - 1),
+            label_at(x - 1, y),
+            label_at(x, y),
+        ];
+        let mut distinct: Vec<Option<usize>> = Vec::with_capacity(4);
+        for label in around {
+            if !distinct.contains(&label) {
+                distinct.push(label);
+            }
+        }
+        let outside = around.iter().filter(|label| label.is_none()).count();
+        distinct.len() >= 3 || outside == 3
+    };
+
+    texels
+        .into_iter()
+        .enumerate()
+        .map(|(i, mut cell_texels)| {
+            let label = Some(i + 1);
+            if wrap_x {
+                unwrap_axis(&mut cell_texels, columns, |texel| &mut texel.0);
+            }
+            if wrap_y {
+                unwrap_axis(&mut cell_texels, rows, |texel| &mut texel.1);
+            }
+
+            // Directed edges with the cell on their left
+            let mut edges: BTreeMap<Lattice, Vec<Lattice>> = BTreeMap::new();
+            let mut add = |from: Lattice, to: Lattice| edges.entry(from).or_default().push(to);
+            for (x, y) in cell_texels {
+                if label_at(x, y - 1) != label {
+                    add((x, y), (x + 1, y));
+                }
+                if label_at(x + 1, y) != label {
+                    add((x + 1, y), (x + 1, y + 1));
+                }
+                if label_at(x, y + 1) != label {
+                    add((x + 1, y + 1), (x, y + 1));
+                }
+                if label_at(x - 1, y) != label {
+                    add((x, y + 1), (x, y));
+                }
+            }
+
+            let outer = trace_loops(edges)
+                .into_iter()
+                .max_by_key(|lattice_loop| signed_double_area(lattice_loop))
+                .unwrap_or_default();
+            let mut corners: Vec<Lattice> =
+                outer.iter().copied().filter(|&p| is_corner(p)).collect();
+            if corners.len() < 3 {
+                corners = remove_collinear(&outer);
+            }
+            corners
+                .into_iter()
+                .map(|(x, y)| {
+                    (
+                        x as f64 * config.0 / width as f64,
+                        y as f64 * config.1 / height as f64,
+                    )
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Moves the texels of a cell split by a wrapped edge next to each other, shifting the smaller
+// part by a period
+fn unwrap_axis(texels: &mut [Lattice], side: isize, axis: impl Fn(&mut Lattice) -> &mut isize) {
+    let (mut low, mut high) = (false, false);
+    let mut low_count = 0;
+    for texel in texels.iter_mut() {
+        let v = *axis(texel);
+        low |= v == 0;
+        high |= v == side - 1;
+        low_count += usize::from(v < side / 2);
+    }
+    if !(low && high) {
+        return;
+    }
+
+    let shift_low = low_count * 2 <= texels.len();
+    for texel in texels.iter_mut() {
+        let v = axis(texel);
+        if shift_low && *v < side / 2 {
+            *v += side;
+        } else if !shift_low && *v >= side / 2 {
+            *v -= side;
+        }
+    }
+}
+
+fn trace_loops(mut edges: BTreeMap<Lattice, Vec<Lattice>>) -> Vec<Vec<Lattice>> {
+    let mut loops = Vec::new();
+    while let Some(&start) = edges.keys().next() {
+        let mut lattice_loop = vec![start];
+        let mut current = start;
+        loop {
+            let ends = edges.get_mut(&current).unwrap();
+            let next = ends.pop().unwrap();
+            if ends.is_empty() {
+                edges.remove(&current);
+            }
+            if next == start {
+                break;
+            }
+            lattice_loop.push(next);
+            current = next;
+        }
+        loops.push(lattice_loop);
+    }
+    loops
+}
+
+fn signed_double_area(polygon: &[Lattice]) -> i64 {
+    (0..polygon.len())
+        .map(|i| {
+            let (a, b) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+            (a.0 * b.1 - b.0 * a.1) as i64
+        })
+        .sum()
+}
+
+fn remove_collinear(polygon: &[Lattice]) -> Vec<Lattice> {
+    let n = polygon.len();
+    (0..n)
+        .filter(|&i| {
+            let (a, b, c) = (polygon[(i + n - 1) % n], polygon[i], polygon[(i + 1) % n]);
+            let cross = (b.0 - a.0) * (c.1 - b.1) - (b.1 - a.1) * (c.0 - b.0);
+            cross != 0
+        })
+        .map(|i| polygon[i])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rotate_to_min(mut polygon: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+        let first = (0..polygon.len())
+            .min_by(|&a, &b| polygon[a].partial_cmp(&polygon[b]).unwrap())
+            .unwrap();
+        polygon.rotate_left(first);
+        polygon
+    }
+
+    #[test]
+    fn test_two_cells() {
+        #[rustfmt::skip]
+        let labels = vec![
+            1, 1, 2, 2,
+            1, 1, 2, 2,
+            1, 1, 2, 2,
+            1, 1, 2, 2,
+        ];
+
+        let cells = extract_cells(&labels, (4, 4), 3, (8.0, 4.0), BoundaryMode::Bounded);
+
+        assert_eq!(
+            rotate_to_min(cells[0].clone()),
+            vec![(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0)]
+        );
+        assert_eq!(
+            rotate_to_min(cells[1].clone()),
+            vec![(4.0, 0.0), (8.0, 0.0), (8.0, 4.0), (4.0, 4.0)]
+        );
+        assert!(cells[2].is_empty());
+    }
+
+    #[test]
+    fn test_three_cell_junction() {
+        #[rustfmt::skip]
+        let labels = vec![
+            1, 1, 2, 2,
+            1, 1, 2, 2,
+            3, 3, 3, 3,
+            3, 3, 3, 3,
+        ];
+
+        let cells = extract_cells(&labels, (4, 4), 3, (4.0, 4.0), BoundaryMode::Bounded);
+
+        // The staircase corner at (2, 2) is where the three cells meet
+        assert_eq!(
+            rotate_to_min(cells[2].clone()),
+            vec![(0.0, 2.0), (2.0, 2.0), (4.0, 2.0), (4.0, 4.0), (0.0, 4.0)]
+        );
+    }
+
+    #[test]
+    fn test_periodic_stitching() {
+        // Cell 1 wraps across the left and right edges
+        #[rustfmt::skip]
+        let labels = vec![
+            1, 2, 2, 1,
+            1, 2, 2, 1,
+            1, 2, 2, 1,
+            1, 2, 2, 1,
+        ];
+        let boundary = BoundaryMode::Periodic { x: true, y: false };
+
+        let cells = extract_cells(&labels, (4, 4), 2, (4.0, 4.0), boundary);
+
+        assert_eq!(
+            rotate_to_min(cells[0].clone()),
+            vec![(3.0, 0.0), (5.0, 0.0), (5.0, 4.0), (3.0, 4.0)]
+        );
+        assert_eq!(
+            rotate_to_min(cells[1].clone()),
+            vec![(1.0, 0.0), (3.0, 0.0), (3.0, 4.0), (1.0, 4.0)]
+        );
+    }
+
+    #[test]
+    fn test_rectangular_grid() {
+        // Texels of 2 x 2 domain units over an 8 x 4 box
+        #[rustfmt::skip]
+        let labels = vec![
+            1, 1, 1, 2,
+            1, 1, 2, 2,
+        ];
+
+        let cells = extract_cells(&labels, (4, 2), 2, (8.0, 4.0), BoundaryMode::Bounded);
+
+        // The staircase between the cells is a single slanted edge
+        assert_eq!(
+            rotate_to_min(cells[1].clone()),
+            vec![(4.0, 4.0), (6.0, 0.0), (8.0, 0.0), (8.0, 4.0)]
+        );
+    }
+}
@@ -0,0 +1,198 @@
+// Cells whose label covers several disconnected blobs of texels, which unlucky seeds or coarse
+// resolutions produce and which `extract_cells` would reduce to their largest blob. Blobs are
+// 4-connected, across the wrapped edges of a periodic boundary, and the largest blob of a label
+// is the cell, the others being minority blobs to reassign.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::config::BoundaryMode;
+
+/// Outcome of [`repair_disconnected_cells`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepairReport {
+    /// Sorted cells which had minority blobs reassigned
+    pub repaired: Vec<usize>,
+    /// Texels which changed label
+    pub reassigned_texels: usize,
+}
+
+/// Sorted cells whose label covers more than one blob of `labels`, a grid of `(width, height)`
+/// texels.
+pub fn disconnected_cells(
+    labels: &[usize],
+    dimensions: (usize, usize),
+    cell_count: usize,
+    boundary: BoundaryMode,
+) -> Vec<usize> {
+    let mut blob_counts = vec![0; cell_count];
+    for blob in blobs(labels, dimensions, boundary) {
+        blob_counts[labels[blob[0]] - 1] += 1;
+    }
+    (0..cell_count)
+        .filter(|&cell| blob_counts[cell] > 1)
+        .collect()
+}
+
+/// Reassigns every minority blob to the adjacent label sharing the most texel sides with it,
+/// the smallest on ties. Blobs next to no other label, only to unlabeled texels or the domain
+/// boundary, are left alone.
+pub fn repair_disconnected_cells(
+    labels: &mut [usize],
+    dimensions: (usize, usize),
+    cell_count: usize,
+    boundary: BoundaryMode,
+) -> RepairReport {
+    let mut by_label: Vec<Vec<Vec<usize>>> = vec![Vec::new(); cell_count];
+    for blob in blobs(labels, dimensions, boundary) {
+        by_label[labels[blob[0]] - 1].push(blob);
+    }
+
+    let mut report = RepairReport {
+        repaired: Vec::new(),
+        reassigned_texels: 0,
+    };
+    for (cell, mut cell_blobs) in by_label.into_iter().enumerate() {
+        if cell_blobs.len() < 2 {
+            continue;
+        }
+        // The first of the largest blobs stays
+        let kept = (0..cell_blobs.len())
+            .rev()
+            .max_by_key(|&i| cell_blobs[i].len())
+            .unwrap();
+        cell_blobs.swap_remove(kept);
+
+        let mut repaired = false;
+        for blob in cell_blobs {
+            let mut shared: BTreeMap<usize, usize> = BTreeMap::new();
+            for &texel in &blob {
+                for neighbor in neighbors(texel, dimensions, boundary).into_iter().flatten() {
+                    let label = labels[neighbor];
+                    if label != 0 && label != cell + 1 {
+                        *shared.entry(label).or_insert(0) += 1;
+                    }
+                }
+            }
+            // Iterating in reverse, the last maximum is the smallest label
+            let Some((&label, _)) = shared.iter().rev().max_by_key(|&(_, &count)| count) else {
+                continue;
+            };
+            for &texel in &blob {
+                labels[texel] = label;
+            }
+            report.reassigned_texels += blob.len();
+            repaired = true;
+        }
+        if repaired {
+            report.repaired.push(cell);
+        }
+    }
+    report
+}
+
+// Texels of each 4-connected blob of equal nonzero labels
+fn blobs(labels: &[usize], dimensions: (usize, usize), boundary: BoundaryMode) -> Vec<Vec<usize>> {
+    assert_eq!(
+        labels.len(),
+        dimensions.0 * dimensions.1,
+        "label grid size mismatch"
+    );
+    let mut visited = vec![false; labels.len()];
+    let mut blobs = Vec::new();
+    for start in 0..labels.len() {
+        if visited[start] || labels[start] == 0 {
+            continue;
+        }
+        visited[start] = true;
+        let mut blob = Vec::new();
+        let mut queue = VecDeque::from([start]);
+        while let Some(texel) = queue.pop_front() {
+            blob.push(texel);
+            for neighbor in neighbors(texel, dimensions, boundary).into_iter().flatten() {
+                if !visited[neighbor] && labels[neighbor] == labels[start] {
+                    visited[neighbor] = true;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        blobs.push(blob);
+    }
+    blobs
+}
+
+// Texels sharing a side with `texel` in a grid of `(width, height)` texels, `None` past a
+// bounded edge
+fn neighbors(
+    texel: usize,
+    (width, height): (usize, usize),
+    boundary: BoundaryMode,
+) -> [Option<usize>; 4] {
+    let (wrap_x, wrap_y) = boundary.wraps();
+    let (x, y) = (texel % width, texel / width);
+    let step = |v: usize, side: usize, forward: bool, wraps: bool| match (forward, wraps) {
+        (true, _) if v + 1 < side => Some(v + 1),
+        (false, _) if v > 0 => Some(v - 1),
+        (true, true) => Some(0),
+        (false, true) => Some(side - 1),
+        _ => None,
+    };
+    [
+        step(x, width, false, wrap_x).map(|x| x + y * width),
+        step(x, width, true, wrap_x).map(|x| x + y * width),
+        step(y, height, false, wrap_y).map(|y| x + y * width),
+        step(y, height, true, wrap_y).map(|y| x + y * width),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repair_disconnected_cells() {
+        // Cells 0 and 2 each have a stray texel on the right side, next to cell 1 only
+        #[rustfmt::skip]
+        let mut labels = vec![
+            1, 1, 2, 2,
+            1, 1, 2, 1,
+            1, 1, 2, 2,
+            3, 3, 2, 3,
+        ];
+
+        assert_eq!(
+            disconnected_cells(&labels, (4, 4), 3, BoundaryMode::Bounded),
+            vec![0, 2]
+        );
+        let report = repair_disconnected_cells(&mut labels, (4, 4), 3, BoundaryMode::Bounded);
+
+        assert_eq!(
+            report,
+            RepairReport {
+                repaired: vec![0, 2],
+                reassigned_texels: 2,
+            }
+        );
+        assert_eq!(labels[7], 2);
+        assert_eq!(labels[15], 2);
+        assert!(disconnected_cells(&labels, (4, 4), 3, BoundaryMode::Bounded).is_empty());
+    }
+
+    #[test]
+    fn test_periodic_blobs() {
+        // Cell 0 touches both sides, which only join when x wraps
+        #[rustfmt::skip]
+        let labels = vec![
+            1, 2, 2, 1,
+            1, 2, 2, 1,
+            1, 2, 2, 1,
+            1, 2, 2, 1,
+        ];
+
+        assert_eq!(
+            disconnected_cells(&labels, (4, 4), 2, BoundaryMode::Bounded),
+            vec![0]
+        );
+        let periodic = BoundaryMode::Periodic { x: true, y: false };
+        assert!(disconnected_cells(&labels, (4, 4), 2, periodic).is_empty());
+    }
+}
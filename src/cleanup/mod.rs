@@ -0,0 +1,237 @@
+// Post-processing of the mesh for finite-volume solvers, which tiny cells and very short edges
+// break: cells below an area threshold are merged into their largest neighbor, then short edges
+// are collapsed. Both passes rebuild the half-edges, so the twins, and the vertices shared by
+// neighboring cells, stay consistent.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::mesh::PolyMesh;
+
+/// Mesh after [`clean_mesh`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CleanedMesh {
+    pub mesh: PolyMesh,
+    /// Seed each merged seed's cell is now part of
+    pub merged: BTreeMap<usize, usize>,
+    /// Vertices merged with another one
+    pub collapsed_vertices: usize,
+}
+
+/// Merges the cells smaller than `min_area` into their neighbor of largest area, smallest cells
+/// first, then collapses the edges shorter than `min_edge`.
+pub fn clean_mesh(mesh: &PolyMesh, min_area: f64, min_edge: f64) -> CleanedMesh {
+    let (merged_mesh, merged) = merge_small_cells(mesh, min_area);
+    let (mesh, collapsed_vertices) = collapse_short_edges(&merged_mesh, min_edge);
+    CleanedMesh {
+        mesh,
+        merged,
+        collapsed_vertices,
+    }
+}
+
+/// Mesh with the cells below `min_area` merged into their largest neighbor, and the seed each
+/// merged seed's cell is now part of. A cell with no neighbor, such as one enclosed by a single
+/// other cell whose face is only its outer boundary, is left alone.
+pub fn merge_small_cells(mesh: &PolyMesh, min_area: f64) -> (PolyMesh, BTreeMap<usize, usize>) {
+    let vertices = &mesh.vertices;
+    let mut polygons: Vec<Vec<usize>> = (0..mesh.faces.len())
+        .map(|face| mesh.face_vertices(face).collect())
+        .collect();
+    let mut edges: BTreeMap<(usize, usize), usize> = BTreeMap::new();
+    for (face, polygon) in polygons.iter().enumerate() {
+        edges.extend(directed_edges(polygon).map(|edge| (edge, face)));
+    }
+
+    let mut owners: Vec<usize> = (0..polygons.len()).collect();
+    let mut order = owners.clone();
+    order.sort_by(|&a, &b| area(&polygons[a], vertices).total_cmp(&area(&polygons[b], vertices)));
+    for small in order {
+        // Cells only grow, so the remaining ones are all large enough
+        if area(&polygons[small], vertices) >= min_area {
+            break;
+        }
+        let neighbors: BTreeSet<usize> = directed_edges(&polygons[small])
+            .filter_map(|(a, b)| edges.get(&(b, a)).copied())
+            .collect();
+        let Some(large) = neighbors
+            .into_iter()
+            .max_by(|&a, &b| area(&polygons[a], vertices).total_cmp(&area(&polygons[b], vertices)))
+        else {
+            continue;
+        };
+
+        let merged = union(&polygons[small], &polygons[large], vertices);
+        for edge in directed_edges(&polygons[small]).chain(directed_edges(&polygons[large])) {
+            edges.remove(&edge);
+        }
+        edges.extend(directed_edges(&merged).map(|edge| (edge, large)));
+        polygons[large] = merged;
+        polygons[small].clear();
+        for owner in owners.iter_mut().filter(|owner| **owner == small) {
+            *owner = large;
+        }
+    }
+
+    let seed = |face: usize| mesh.faces[face].seed;
+    let merged = (0..owners.len())
+        .filter(|&face| owners[face] != face)
+        .map(|face| (seed(face), seed(owners[face])))
+        .collect();
+    let kept: Vec<usize> = (0..polygons.len())
+        .filter(|&face| !polygons[face].is_empty())
+        .collect();
+    let polygons: Vec<Vec<usize>> = kept.iter().map(|&face| polygons[face].clone()).collect();
+    let seeds: Vec<usize> = kept.iter().map(|&face| seed(face)).collect();
+    let mesh = PolyMesh::from_polygons(vertices.clone(), &polygons, &seeds);
+    (mesh, merged)
+}
+
+/// Mesh with the edges shorter than `tolerance` collapsed, each cluster of vertices joined by
+/// such edges moving to its mean, and how many vertices were merged. Cells left with fewer than
+/// three vertices are dropped.
+pub fn collapse_short_edges(mesh: &PolyMesh, tolerance: f64) -> (PolyMesh, usize) {
+    let vertices = &mesh.vertices;
+    let mut parents: Vec<usize> = (0..vertices.len()).collect();
+    fn root(parents: &mut [usize], mut v: usize) -> usize {
+        while parents[v] != v {
+            parents[v] = parents[parents[v]];
+            v = parents[v];
+        }
+        v
+    }
+
+    let mut collapsed = 0;
+    for (h, half_edge) in mesh.half_edges.iter().enumerate() {
+        let root_a = root(&mut parents, half_edge.origin);
+        let root_b = root(&mut parents, mesh.destination(h));
+        if mesh.edge_length(h) < tolerance && root_a != root_b {
+            parents[root_b] = root_a;
+            collapsed += 1;
+        }
+    }
+
+    let mut sums = vec![(0.0, 0.0, 0); vertices.len()];
+    for (v, &(x, y)) in vertices.iter().enumerate() {
+        let sum = &mut sums[root(&mut parents, v)];
+        *sum = (sum.0 + x, sum.1 + y, sum.2 + 1);
+    }
+    let means = sums
+        .iter()
+        .map(|&(x, y, count)| (x / count as f64, y / count as f64))
+        .collect();
+    let mut polygons = Vec::new();
+    let mut seeds = Vec::new();
+    for face in 0..mesh.faces.len() {
+        let mut roots: Vec<usize> = mesh
+            .face_vertices(face)
+            .map(|v| root(&mut parents, v))
+            .collect();
+        roots.dedup();
+        if roots.len() > 1 && roots.first() == roots.last() {
+            roots.pop();
+        }
+        if roots.len() >= 3 {
+            polygons.push(roots);
+            seeds.push(mesh.faces[face].seed);
+        }
+    }
+    (PolyMesh::from_polygons(means, &polygons, &seeds), collapsed)
+}
+
+fn directed_edges(polygon: &[usize]) -> impl Iterator<Item = (usize, usize)> + '_ {
+    (0..polygon.len()).map(|i| (polygon[i], polygon[(i + 1) % polygon.len()]))
+}
+
+fn area(polygon: &[usize], vertices: &[(f64, f64)]) -> f64 {
+    directed_edges(polygon)
+        .map(|(a, b)| vertices[a].0 * vertices[b].1 - vertices[b].0 * vertices[a].1)
+        .sum::<f64>()
+        / 2.0
+}
+
+// Outer boundary of two adjacent polygons: their edges but the shared ones, chained into loops
+// of which the largest is kept, any other being a hole between the two
+fn union(a: &[usize], b: &[usize], vertices: &[(f64, f64)]) -> Vec<usize> {
+    let all: BTreeSet<(usize, usize)> = directed_edges(a).chain(directed_edges(b)).collect();
+    let mut next: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for &(from, to) in &all {
+        if !all.contains(&(to, from)) {
+            next.entry(from).or_default().push(to);
+        }
+    }
+
+    let mut loops = Vec::new();
+    while let Some((&start, _)) = next.first_key_value() {
+        let mut boundary = Vec::new();
+        let mut vertex = start;
+        while let Some(outgoing) = next.get_mut(&vertex) {
+            let to = outgoing.pop().unwrap();
+            if outgoing.is_empty() {
+                next.remove(&vertex);
+            }
+            boundary.push(vertex);
+            vertex = to;
+            if vertex == start {
+                break;
+            }
+        }
+        loops.push(boundary);
+    }
+    loops
+        .into_iter()
+        .max_by(|a, b| area(a, vertices).total_cmp(&area(b, vertices)))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_small_cells() {
+        // A sliver on top of the right column, touching both other cells
+        let mesh = PolyMesh::from_cells(&[
+            vec![(0.0, 0.0), (2.0, 0.0), (2.0, 1.9), (2.0, 2.0), (0.0, 2.0)],
+            vec![(2.0, 0.0), (3.0, 0.0), (3.0, 1.9), (2.0, 1.9)],
+            vec![(2.0, 1.9), (3.0, 1.9), (3.0, 2.0), (2.0, 2.0)],
+        ]);
+
+        let (merged, owners) = merge_small_cells(&mesh, 0.5);
+
+        assert_eq!(owners, BTreeMap::from([(2, 0)]));
+        let cells = merged.to_cells(3);
+        assert_eq!(
+            cells[0],
+            vec![
+                (0.0, 0.0),
+                (2.0, 0.0),
+                (2.0, 1.9),
+                (3.0, 1.9),
+                (3.0, 2.0),
+                (2.0, 2.0),
+                (0.0, 2.0)
+            ]
+        );
+        assert_eq!(cells[1], mesh.face_polygon(1));
+        assert!(cells[2].is_empty());
+        assert_eq!(merged.face_neighbors(0).collect::<Vec<_>>(), [1, 1]);
+    }
+
+    #[test]
+    fn test_collapse_short_edges() {
+        let mesh = PolyMesh::from_cells(&[
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+            vec![(1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.01, 1.0), (1.0, 1.0)],
+        ]);
+
+        let (collapsed, count) = collapse_short_edges(&mesh, 0.05);
+
+        assert_eq!(count, 1);
+        assert_eq!(collapsed.vertices.len(), 6);
+        assert_eq!(collapsed.face_polygon(0)[2], (1.005, 1.0));
+        assert_eq!(
+            collapsed.face_polygon(1),
+            vec![(1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.005, 1.0)]
+        );
+    }
+}
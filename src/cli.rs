@@ -1,6 +1,9 @@
 use clap::{Parser, ValueEnum};
 use std::path::PathBuf;
 
+use crate::config::{Backend, BoundaryMode, MesherConfig};
+use crate::debug::{DebugDump, Stage};
+
 /// Point generation on a rectangle.
 #[derive(Parser, Debug)]
 #[command(version, about = "Point generation on a rectangle.")]
@@ -9,6 +12,11 @@ pub struct Cli {
     #[arg(short = 'm', long = "mode", default_value = "poisson-disk", value_enum)]
     pub mode: Mode,
 
+    /// Reads the points from a CSV or JSON file instead of generating them. Weighted seeds are
+    /// rejected, as this binary can't mesh power diagrams, and tags are ignored
+    #[arg(short = 's', long = "seeds", value_name = "FILE")]
+    pub seeds: Option<PathBuf>,
+
     /// Sets the number of points to generate
     #[arg(short = 'n', default_value_t = 10)]
     pub n: u32,
@@ -33,13 +41,47 @@ pub struct Cli {
     #[arg(short = 'p', long = "plot", default_value = "jfa", value_enum)]
     pub plot: PlotMode,
 
-    /// Sets the JFA mode: `cpu`, `gpu`, or `none`
+    /// Sets the JFA mode: `cpu`, `gpu`, `exact`, or `none`
     #[arg(short = 'j', long = "jfa-mode", default_value = "gpu", value_enum)]
     pub jfa_mode: JfaMode,
 
     /// Sets the resolution for JFA
-    #[arg(short = 'r', long = "res", default_value_t = 512)]
+    #[arg(
+        short = 'r',
+        long = "res",
+        default_value_t = 512,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
     pub res: u32,
+
+    /// Compares the JFA cells against the exact Voronoi diagram (slow for many points)
+    #[arg(long = "cross-check")]
+    pub cross_check: bool,
+
+    /// Prints shape metrics of the cells: edge counts, lengths, aspect ratios and regularity
+    #[arg(long = "quality")]
+    pub quality: bool,
+
+    /// Wraps the domain around in x and y, for periodic tessellations (GPU only)
+    #[arg(long = "periodic")]
+    pub periodic: bool,
+
+    /// Fails GPU runs that would allocate more than this many bytes
+    #[arg(long = "memory-budget", value_name = "BYTES")]
+    pub memory_budget: Option<u64>,
+
+    /// Writes intermediate artifacts of the JFA to this directory
+    #[arg(long = "dump-dir", value_name = "DIR")]
+    pub dump_dir: Option<PathBuf>,
+
+    /// Artifacts written to the dump directory
+    #[arg(
+        long = "dump-stages",
+        value_enum,
+        value_delimiter = ',',
+        default_values_t = [Stage::Seeds, Stage::Passes]
+    )]
+    pub dump_stages: Vec<Stage>,
 }
 
 /// Point generation modes
@@ -63,13 +105,17 @@ pub enum PlotMode {
 pub enum JfaMode {
     Cpu,
     Gpu,
+    Exact,
     None,
 }
 
 pub fn print_config(cli: &Cli) {
     println!("Display help with option -h or --help.");
     println!("The program will run with the following configuration:");
-    println!("Mode: {:?}", cli.mode);
+    match cli.seeds {
+        Some(ref seeds_path) => println!("Seeds: {}", seeds_path.display()),
+        None => println!("Mode: {:?}", cli.mode),
+    }
     println!("Number of points (n): {}", cli.n);
     println!("Minimal distance (d): {}", cli.d);
     println!(
@@ -84,9 +130,51 @@ pub fn print_config(cli: &Cli) {
     if cli.jfa_mode != JfaMode::None {
         println!("JFA resolution: {}", cli.res);
     }
+    if cli.periodic {
+        println!("Boundary: periodic");
+    }
+    if let Some(budget) = cli.memory_budget {
+        println!("GPU memory budget: {} B", budget);
+    }
+    if let Some(ref dump_dir) = cli.dump_dir {
+        println!(
+            "Debug dump: {:?} to {}",
+            cli.dump_stages,
+            dump_dir.display()
+        );
+    }
+    if cli.cross_check {
+        println!("Cross-check against exact diagram: enabled");
+    }
+    if cli.quality {
+        println!("Quality report: enabled");
+    }
     println!();
 }
 
+pub fn mesher_config(cli: &Cli) -> MesherConfig {
+    MesherConfig {
+        // No cells are generated with `JfaMode::None`, whatever the backend
+        backend: match cli.jfa_mode {
+            JfaMode::Cpu => Backend::Cpu,
+            JfaMode::Gpu | JfaMode::None => Backend::Gpu,
+            JfaMode::Exact => Backend::Exact,
+        },
+        resolution: cli.res as usize,
+        boundary: if cli.periodic {
+            BoundaryMode::Periodic { x: true, y: true }
+        } else {
+            BoundaryMode::Bounded
+        },
+        memory_budget: cli.memory_budget,
+        debug_dump: cli
+            .dump_dir
+            .as_ref()
+            .map(|directory| DebugDump::new(directory, &cli.dump_stages)),
+        ..Default::default()
+    }
+}
+
 pub fn parse() -> Cli {
     Cli::parse()
 }
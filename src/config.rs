@@ -0,0 +1,370 @@
+use crate::debug::DebugDump;
+use crate::domain::Domain;
+use crate::jfa_wgpu::{AdapterSelection, RecoveryPolicy};
+use crate::progress::{CancellationToken, ProgressCallback};
+use crate::symmetry::Symmetry;
+
+pub const DEFAULT_RESOLUTION: usize = 512;
+
+/// Options of a meshing run. With the `serde` feature, missing fields deserialize to their
+/// defaults, and the progress callback and cancellation token, which only exist at run time,
+/// are skipped, as is the domain, whose region can be a closure.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct MesherConfig {
+    /// Engine [`crate::backend::from_config`] labels the grid with
+    pub backend: Backend,
+    /// Side of the square label grid, or its longer side with [`GridShape::FitDomain`], in
+    /// texels
+    pub resolution: usize,
+    pub grid: GridShape,
+    pub boundary: BoundaryMode,
+    /// Symmetry the seeds must be invariant under, as [`crate::symmetry::symmetrize`] returns
+    /// them, applied by the backends of [`crate::backend::from_config`]
+    pub symmetry: Option<Symmetry>,
+    /// Norm distances are measured with, only supported by the GPU engine
+    pub distance: DistanceMetric,
+    /// Metric tensor of Euclidean distances, only supported by the GPU engine
+    pub metric: Metric,
+    /// How hard the GPU engine tries to label every texel with its nearest seed
+    pub accuracy: Accuracy,
+    /// JFA variant to run instead of the one `accuracy` picks
+    pub variant: Option<JfaVariant>,
+    /// Memory the GPU engine keeps the label grid in during the JFA passes
+    pub storage: GridStorage,
+    /// Runs each pass of the GPU engine only on the tiles of 16 x 16 texels whose labels can
+    /// still change, as the GPU lists them before the pass, for seeds clustered in a small part
+    /// of a large grid. Only with [`GridStorage::Buffer`]
+    pub active_tiles: bool,
+    /// Runs the JFA on a grid this many times coarser along each axis first, then only passes of
+    /// steps up to this factor on the upsampled labels at full resolution, ignoring `variant`
+    /// there. Far fewer full resolution passes on large grids, but cells narrower than the
+    /// factor can come out with mislabeled texels
+    pub coarse_factor: Option<usize>,
+    /// Side of the tiles the GPU engine splits the grid into, in texels. When `None`, only grids
+    /// too large for a storage buffer are split, into tiles of
+    /// [`crate::jfa_wgpu::DEFAULT_TILE_SIZE`] texels
+    pub tile_size: Option<usize>,
+    /// Spreads the tiles of tiled runs across every GPU matching `adapter`, whose `index` and
+    /// `power` are then ignored. Browsers don't list their GPUs, so no adapter is found there
+    pub multi_gpu: bool,
+    /// Breaks distance ties towards the lowest seed index, so that labels don't depend on the
+    /// order threads and invocations run in
+    pub deterministic: bool,
+    /// Format the GPU engine stores and reads back distance fields in
+    pub distance_precision: DistancePrecision,
+    /// Region within the box the cells are clipped to, and constraints they are split along,
+    /// which [`crate::domain::mesh_cells`] applies after labeling
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub domain: Option<Domain>,
+    /// What the GPU engine does with seeds outside the domain box
+    pub out_of_domain: OutOfDomain,
+    /// What the GPU backend does when its device is lost
+    pub recovery: RecoveryPolicy,
+    /// Adapter the GPU backend creates its device on
+    pub adapter: AdapterSelection,
+    /// GPU memory a run may allocate, in bytes, unbounded when `None`
+    pub memory_budget: Option<u64>,
+    /// Intermediate artifacts to write out, none when `None`
+    pub debug_dump: Option<DebugDump>,
+    /// Called after each JFA pass or relaxation iteration of the GPU engine
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub progress: Option<ProgressCallback>,
+    /// Aborts GPU runs between submissions once cancelled
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub cancellation: Option<CancellationToken>,
+}
+
+impl Default for MesherConfig {
+    fn default() -> Self {
+        MesherConfig {
+            backend: Backend::default(),
+            resolution: DEFAULT_RESOLUTION,
+            grid: GridShape::default(),
+            boundary: BoundaryMode::default(),
+            symmetry: None,
+            distance: DistanceMetric::default(),
+            metric: Metric::default(),
+            accuracy: Accuracy::default(),
+            variant: None,
+            storage: GridStorage::default(),
+            active_tiles: false,
+            coarse_factor: None,
+            tile_size: None,
+            multi_gpu: false,
+            deterministic: false,
+            distance_precision: DistancePrecision::default(),
+            domain: None,
+            out_of_domain: OutOfDomain::default(),
+            recovery: RecoveryPolicy::default(),
+            adapter: AdapterSelection::default(),
+            memory_budget: None,
+            debug_dump: None,
+            progress: None,
+            cancellation: None,
+        }
+    }
+}
+
+impl MesherConfig {
+    /// Width and height of the label grid over the `config` box, in texels.
+    pub fn grid_dimensions(&self, config: (f64, f64)) -> (usize, usize) {
+        let reso = self.resolution;
+        match self.grid {
+            GridShape::Square => (reso, reso),
+            GridShape::FitDomain if config.0 >= config.1 => (
+                reso,
+                ((reso as f64 * config.1 / config.0).round() as usize).max(1),
+            ),
+            GridShape::FitDomain => (
+                ((reso as f64 * config.0 / config.1).round() as usize).max(1),
+                reso,
+            ),
+            GridShape::Texels { width, height } => (width, height),
+        }
+    }
+
+    /// JFA variant the GPU engine runs.
+    pub fn jfa_variant(&self) -> JfaVariant {
+        self.variant.unwrap_or(self.accuracy.variant())
+    }
+}
+
+/// Engine labeling the grid, each one implementing [`crate::backend::VoronoiBackend`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Backend {
+    /// JFA on the GPU, supporting every option
+    #[default]
+    Gpu,
+    /// JFA on the CPU, in parallel over the rows of the grid, for unweighted Euclidean diagrams
+    /// on a bounded square grid
+    Cpu,
+    /// Nearest seed of every texel by brute force, for small unweighted diagrams
+    Exact,
+}
+
+/// Speed and precision trade-off of the JFA. Plain JFA leaves a small fraction of texels with
+/// the label of a seed which isn't their nearest one.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Accuracy {
+    /// Plain JFA, the fewest passes
+    Fast,
+    /// 1+JFA, one more pass for far fewer mislabeled texels
+    #[default]
+    Balanced,
+    /// JFA², followed by a correction checking every texel against the seeds of its 8
+    /// neighbors, twice. Exact in practice, though the JFA gives no guarantee
+    Exact,
+}
+
+impl Accuracy {
+    pub fn variant(self) -> JfaVariant {
+        match self {
+            Accuracy::Fast => JfaVariant::Jfa,
+            Accuracy::Balanced => JfaVariant::OnePlusJfa,
+            Accuracy::Exact => JfaVariant::JfaSquared,
+        }
+    }
+
+    /// Whether the passes end with the correction.
+    pub fn corrects(self) -> bool {
+        self == Accuracy::Exact
+    }
+}
+
+/// Step sizes of the JFA passes, halving from half the grid down to 1.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum JfaVariant {
+    Jfa,
+    /// A step 1 pass first
+    #[default]
+    OnePlusJfa,
+    /// A step 1 pass last
+    JfaPlusOne,
+    /// The halving steps twice over
+    JfaSquared,
+}
+
+/// Where the GPU engine keeps the label grid during the JFA passes. Either way the labels end
+/// up in a storage buffer once the passes are done.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GridStorage {
+    /// Flat `width * height` buffers of labels
+    #[default]
+    Buffer,
+    /// `R32Uint` textures of labels, whose tiled layout keeps the 2D neighborhood of a pass in
+    /// cache. Limited to grids of at most `max_texture_dimension_2d` texels along each side
+    Texture,
+}
+
+/// Format of the distances of [`crate::jfa_wgpu::DistanceField`] on the GPU. The 16-bit
+/// formats halve the memory and the transfer of the field, and are packed with WGSL builtins,
+/// so they don't need devices supporting f16 in shaders.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DistancePrecision {
+    #[default]
+    F32,
+    /// Half precision floats, with a relative error under 2^-11, overflowing to infinity past
+    /// 65504 texels
+    F16,
+    /// 16-bit fixed point from 0 to twice the longer side of the grid, in steps of that side
+    /// over 32767
+    Fixed16,
+}
+
+/// What the GPU engine does with seeds outside the domain box.
+/// [`crate::jfa_wgpu::DispatchStats::outside_seeds`] lists them after a run.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OutOfDomain {
+    /// Moves them to the nearest border texel, distorting the border cells
+    Clamp,
+    /// Fails the run with `MesherError::SeedOutsideDomain`
+    #[default]
+    Reject,
+    /// Leaves them out, so their labels appear nowhere on the grid
+    Ignore,
+}
+
+/// Shape of the label grid.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GridShape {
+    /// `resolution`×`resolution` texels whatever the box, stretched when it isn't square
+    #[default]
+    Square,
+    /// `resolution` texels along the longer side of the box and as many as fit along the other
+    /// one, keeping texels square. The consumers of label grids take their dimensions from
+    /// [`MesherConfig::grid_dimensions`], and the CPU backend doesn't support it
+    FitDomain,
+    /// Exactly `width`×`height` texels, ignoring `resolution`, for subgrids of a larger run.
+    /// The CPU backend doesn't support it either
+    Texels { width: usize, height: usize },
+}
+
+/// How the edges of the domain box behave.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BoundaryMode {
+    /// Cells are clipped by the box
+    #[default]
+    Bounded,
+    /// Opposite edges are identified along the chosen axes, so cells wrap across them
+    Periodic { x: bool, y: bool },
+}
+
+impl BoundaryMode {
+    /// Whether the x and y axes wrap around.
+    pub fn wraps(self) -> (bool, bool) {
+        match self {
+            BoundaryMode::Bounded => (false, false),
+            BoundaryMode::Periodic { x, y } => (x, y),
+        }
+    }
+}
+
+/// Norm of the offset between a texel and a seed, which sets the shape of the cells.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DistanceMetric {
+    /// Straight-line distance, possibly anisotropic following [`MesherConfig::metric`]
+    #[default]
+    Euclidean,
+    /// `|dx| + |dy|`, giving diamond-shaped distance balls
+    Manhattan,
+    /// `max(|dx|, |dy|)`, giving square balls
+    Chebyshev,
+    /// `(|dx|^p + |dy|^p)^(1/p)` for a finite `p` of at least 1, below which it isn't a norm
+    Minkowski(f64),
+}
+
+impl DistanceMetric {
+    /// Whether it is a norm, which a Minkowski exponent that isn't finite or is below 1 isn't.
+    pub fn is_norm(self) -> bool {
+        match self {
+            DistanceMetric::Minkowski(p) => p.is_finite() && p >= 1.0,
+            _ => true,
+        }
+    }
+}
+
+/// Symmetric 2×2 tensor `M` measuring the offset `d` between a texel and a seed, in texels, as
+/// `dᵀ M d`. Anything but the identity gives stretched cells, for boundary-layer meshes.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metric {
+    pub xx: f64,
+    pub xy: f64,
+    pub yy: f64,
+}
+
+impl Default for Metric {
+    fn default() -> Self {
+        Metric {
+            xx: 1.0,
+            xy: 0.0,
+            yy: 1.0,
+        }
+    }
+}
+
+impl Metric {
+    /// Metric whose cells are `ratio` times longer along the direction at `angle` radians from
+    /// the x axis than across it.
+    pub fn stretched(angle: f64, ratio: f64) -> Metric {
+        let (sin, cos) = angle.sin_cos();
+        // Distances along the direction shrink by `ratio`, those across it are unchanged
+        let along = 1.0 / (ratio * ratio);
+        Metric {
+            xx: along * cos * cos + sin * sin,
+            xy: (along - 1.0) * sin * cos,
+            yy: along * sin * sin + cos * cos,
+        }
+    }
+
+    /// Whether every non-zero offset has a positive length, without which there is no diagram.
+    pub fn is_positive_definite(&self) -> bool {
+        self.xx > 0.0 && self.xx * self.yy - self.xy * self.xy > 0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_dimensions() {
+        let mut options = MesherConfig {
+            resolution: 100,
+            ..Default::default()
+        };
+        assert_eq!(options.grid_dimensions((4.0, 1.0)), (100, 100));
+
+        options.grid = GridShape::FitDomain;
+        assert_eq!(options.grid_dimensions((4.0, 1.0)), (100, 25));
+        assert_eq!(options.grid_dimensions((1.0, 3.0)), (33, 100));
+        assert_eq!(options.grid_dimensions((1000.0, 1.0)), (100, 1));
+    }
+
+    #[test]
+    fn test_stretched_metric() {
+        let metric = Metric::stretched(std::f64::consts::FRAC_PI_4, 2.0);
+        let squared =
+            |(x, y): (f64, f64)| metric.xx * x * x + 2.0 * metric.xy * x * y + metric.yy * y * y;
+
+        assert!((squared((1.0, 1.0)) - 0.5).abs() < 1e-12);
+        assert!((squared((1.0, -1.0)) - 2.0).abs() < 1e-12);
+        assert!(metric.is_positive_definite());
+        assert!(!Metric {
+            xy: 1.0,
+            ..Default::default()
+        }
+        .is_positive_definite());
+    }
+}
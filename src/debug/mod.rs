@@ -0,0 +1,106 @@
+// Dumps of intermediate meshing state in plain text, so a bug report can ship the exact seeds
+// and label grids that led to a wrong mesh. Write failures are logged and never fail the run.
+
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+/// Intermediate artifacts which can be dumped.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, clap::ValueEnum)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Stage {
+    /// Seed positions after quantization to the grid, as `x,y` lines
+    Seeds,
+    /// Label grid after each JFA pass, one row per line
+    Passes,
+}
+
+/// Where and which stages to dump.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DebugDump {
+    pub directory: PathBuf,
+    pub stages: Vec<Stage>,
+}
+
+impl DebugDump {
+    pub fn new(directory: impl Into<PathBuf>, stages: &[Stage]) -> Self {
+        DebugDump {
+            directory: directory.into(),
+            stages: stages.to_vec(),
+        }
+    }
+
+    pub fn enabled(&self, stage: Stage) -> bool {
+        self.stages.contains(&stage)
+    }
+
+    /// Writes `seeds.csv`.
+    pub fn seeds<T: Display>(&self, seeds: &[(T, T)]) {
+        if !self.enabled(Stage::Seeds) {
+            return;
+        }
+        self.write("seeds.csv", |file| {
+            for (x, y) in seeds {
+                writeln!(file, "{x},{y}")?;
+            }
+            Ok(())
+        });
+    }
+
+    /// Writes `pass_<pass>_step_<step>.txt`, a `width height` header followed by the rows of
+    /// the label grid, `width` texels each.
+    pub fn labels<T: Display>(&self, pass: usize, step: u32, width: usize, labels: &[T]) {
+        if !self.enabled(Stage::Passes) {
+            return;
+        }
+        let width = width.max(1);
+        let height = labels.len() / width;
+        self.write(&format!("pass_{pass:02}_step_{step}.txt"), |file| {
+            writeln!(file, "{width} {height}")?;
+            for row in labels.chunks(width) {
+                let row: Vec<String> = row.iter().map(|label| label.to_string()).collect();
+                writeln!(file, "{}", row.join(" "))?;
+            }
+            Ok(())
+        });
+    }
+
+    fn write(
+        &self,
+        name: &str,
+        contents: impl FnOnce(&mut BufWriter<File>) -> std::io::Result<()>,
+    ) {
+        let path = self.directory.join(name);
+        let result = fs::create_dir_all(&self.directory)
+            .and_then(|_| File::create(&path))
+            .and_then(|file| {
+                let mut file = BufWriter::new(file);
+                contents(&mut file)?;
+                file.flush()
+            });
+        if let Err(err) = result {
+            log::warn!("Unable to write debug dump {}: {err}", path.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dump_stages() {
+        let directory = std::env::temp_dir().join(format!("debug_dump_{}", std::process::id()));
+        let dump = DebugDump::new(&directory, &[Stage::Passes]);
+
+        dump.seeds(&[(1, 2)]);
+        dump.labels(3, 16, 3, &[1, 1, 2, 0, 2, 2]);
+
+        assert!(!directory.join("seeds.csv").exists());
+        let grid = fs::read_to_string(directory.join("pass_03_step_16.txt")).unwrap();
+        assert_eq!(grid, "3 2\n1 1 2\n0 2 2\n");
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
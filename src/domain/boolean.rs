@@ -0,0 +1,264 @@
+// Exact boolean operations on polygon domains, such as a panel outline with a fastener pattern
+// cut out of it, before meshing.
+//
+// Every boundary edge of each operand is cut where it crosses or runs along the boundary of the
+// other, and a piece is kept by where its midpoint lies in the other operand. A piece running
+// along the other boundary is decided once, from the first operand, by whether the two regions
+// are on the same side of it. The kept pieces are chained into loops like clipped cells, the
+// counter-clockwise loops becoming outer boundaries and the clockwise ones their holes.
+//
+// An operand is a set of polygons which don't overlap, as the results are, so the results can be
+// combined further.
+
+use super::polygon::{
+    chain, crossing, distance, even_odd, extent, lerp, overlap, signed_area, Point, TOLERANCE,
+};
+use super::PolygonDomain;
+
+// Distance off the middle of a hole edge, relative to its length, at which to look for the outer
+// boundary around the hole
+const NUDGE: f64 = 1e-6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+/// Region covered by `first` or `second`.
+pub fn polygon_union(first: &[PolygonDomain], second: &[PolygonDomain]) -> Vec<PolygonDomain> {
+    combine(first, second, Operation::Union)
+}
+
+/// Region covered by both `first` and `second`.
+pub fn polygon_intersection(
+    first: &[PolygonDomain],
+    second: &[PolygonDomain],
+) -> Vec<PolygonDomain> {
+    combine(first, second, Operation::Intersection)
+}
+
+/// Region covered by `first` but not by `second`.
+pub fn polygon_difference(first: &[PolygonDomain], second: &[PolygonDomain]) -> Vec<PolygonDomain> {
+    combine(first, second, Operation::Difference)
+}
+
+fn combine(
+    first: &[PolygonDomain],
+    second: &[PolygonDomain],
+    operation: Operation,
+) -> Vec<PolygonDomain> {
+    let edges = |polygons: &[PolygonDomain]| -> Vec<(Point, Point)> {
+        polygons.iter().flat_map(PolygonDomain::edges).collect()
+    };
+    let (first_edges, second_edges) = (edges(first), edges(second));
+    let points: Vec<Point> = first_edges
+        .iter()
+        .chain(&second_edges)
+        .map(|&(a, _)| a)
+        .collect();
+    if points.is_empty() {
+        return Vec::new();
+    }
+    let tolerance = TOLERANCE * extent(&points);
+    let inside = |edges: &[(Point, Point)], (a, b): (Point, Point)| {
+        even_odd(edges.iter().copied(), lerp(a, b, 0.5))
+    };
+
+    let mut segments = Vec::new();
+    for &(a, b) in &first_edges {
+        for (piece, along) in split(a, b, &second_edges, tolerance) {
+            let kept = match along {
+                // Both regions on the left bound the union and the intersection, and the first
+                // region alone bounds the difference
+                Some(same_direction) => same_direction != (operation == Operation::Difference),
+                None => inside(&second_edges, piece) == (operation == Operation::Intersection),
+            };
+            if kept {
+                segments.push(piece);
+            }
+        }
+    }
+    for &(a, b) in &second_edges {
+        for (piece, along) in split(a, b, &first_edges, tolerance) {
+            if along.is_some() || inside(&first_edges, piece) == (operation == Operation::Union) {
+                continue;
+            }
+            // The difference is on the right of the second boundary
+            segments.push(match operation {
+                Operation::Difference => (piece.1, piece.0),
+                _ => piece,
+            });
+        }
+    }
+    assemble(chain(segments, tolerance, &[]), tolerance)
+}
+
+// Pieces of `a -> b` between its crossings with `edges`, each with whether it runs along one of
+// them in the same direction, `None` when it doesn't run along any
+fn split(
+    a: Point,
+    b: Point,
+    edges: &[(Point, Point)],
+    tolerance: f64,
+) -> Vec<((Point, Point), Option<bool>)> {
+    let mut cuts = vec![0.0, 1.0];
+    let mut along = Vec::new();
+    for &(p, q) in edges {
+        match overlap(a, b, p, q, tolerance) {
+            Some(interval) => {
+                let same_direction = (q.0 - p.0) * (b.0 - a.0) + (q.1 - p.1) * (b.1 - a.1) > 0.0;
+                along.push((interval, same_direction));
+            }
+            None => cuts.extend(crossing(a, b, p, q).map(|(t, _)| t)),
+        }
+    }
+    for &((t0, t1), _) in &along {
+        cuts.extend([t0, t1]);
+    }
+    cuts.sort_by(f64::total_cmp);
+
+    cuts.windows(2)
+        .filter(|piece| (piece[1] - piece[0]) * distance(a, b) > tolerance)
+        .map(|piece| {
+            let (t0, t1) = (piece[0], piece[1]);
+            let middle = (t0 + t1) / 2.0;
+            let same_direction = along
+                .iter()
+                .find(|((s0, s1), _)| *s0 <= middle && middle <= *s1)
+                .map(|&(_, same_direction)| same_direction);
+            ((lerp(a, b, t0), lerp(a, b, t1)), same_direction)
+        })
+        .collect()
+}
+
+// Polygons of counter-clockwise outer loops, each with the clockwise loops inside it as holes
+fn assemble(loops: Vec<Vec<Point>>, tolerance: f64) -> Vec<PolygonDomain> {
+    let (mut polygons, mut holes) = (Vec::new(), Vec::new());
+    for ring in loops {
+        if ring.len() < 3 {
+            continue;
+        }
+        let area = signed_area(&ring);
+        if area.abs() <= tolerance * extent(&ring) {
+            continue;
+        }
+        if area > 0.0 {
+            polygons.push(PolygonDomain {
+                outer: ring,
+                holes: Vec::new(),
+            });
+        } else {
+            holes.push(ring);
+        }
+    }
+
+    for hole in holes {
+        // The region is on the left of the hole's edges, inside its outer boundary even where
+        // the two touch, and nested outer boundaries are inside holes of larger ones
+        let (a, b) = (hole[0], hole[1]);
+        let middle = lerp(a, b, 0.5);
+        let point = (
+            middle.0 - NUDGE * (b.1 - a.1),
+            middle.1 + NUDGE * (b.0 - a.0),
+        );
+        let outer = polygons
+            .iter_mut()
+            .filter(|polygon| {
+                let ring = &polygon.outer;
+                let edges = (0..ring.len()).map(|i| (ring[i], ring[(i + 1) % ring.len()]));
+                even_odd(edges, point)
+            })
+            .min_by(|p, q| signed_area(&p.outer).total_cmp(&signed_area(&q.outer)));
+        match outer {
+            Some(polygon) => polygon.holes.push(hole),
+            None => log::warn!("dropping a hole outside of every boundary at {:?}", hole[0]),
+        }
+    }
+    polygons
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::polygon::circle_polygon;
+
+    fn square(x: f64, y: f64, size: f64) -> PolygonDomain {
+        PolygonDomain::new(vec![
+            (x, y),
+            (x + size, y),
+            (x + size, y + size),
+            (x, y + size),
+        ])
+        .unwrap()
+    }
+
+    fn area(polygons: &[PolygonDomain]) -> f64 {
+        polygons
+            .iter()
+            .map(|polygon| {
+                let holes: f64 = polygon.holes.iter().map(|hole| signed_area(hole)).sum();
+                signed_area(&polygon.outer) + holes
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_union_intersection() {
+        let (a, b) = ([square(0.0, 0.0, 2.0)], [square(1.0, 1.0, 2.0)]);
+
+        let union = polygon_union(&a, &b);
+        let intersection = polygon_intersection(&a, &b);
+
+        assert_eq!(union.len(), 1);
+        assert_eq!(union[0].outer.len(), 8);
+        assert!((area(&union) - 7.0).abs() < 1e-12);
+        assert_eq!(intersection.len(), 1);
+        assert!((area(&intersection) - 1.0).abs() < 1e-12);
+        assert!(intersection[0].outer.contains(&(1.0, 2.0)));
+
+        // Squares sharing an edge merge into one rectangle, disjoint ones stay apart
+        let side = polygon_union(&a, &[square(2.0, 0.0, 2.0)]);
+        assert_eq!(side.len(), 1);
+        assert_eq!(side[0].outer.len(), 4);
+        assert!((area(&side) - 8.0).abs() < 1e-12);
+        assert_eq!(polygon_union(&a, &[square(5.0, 0.0, 1.0)]).len(), 2);
+        assert!(polygon_intersection(&a, &[square(5.0, 0.0, 1.0)]).is_empty());
+    }
+
+    #[test]
+    fn test_difference() {
+        // A panel with a fastener pattern of four holes
+        let panel = [square(0.0, 0.0, 10.0)];
+        let fasteners: Vec<PolygonDomain> = [(2.0, 2.0), (8.0, 2.0), (8.0, 8.0), (2.0, 8.0)]
+            .iter()
+            .map(|&center| PolygonDomain::new(circle_polygon(center, 1.0, 32)).unwrap())
+            .collect();
+
+        let drilled = polygon_difference(&panel, &fasteners);
+
+        assert_eq!(drilled.len(), 1);
+        assert_eq!(drilled[0].holes.len(), 4);
+        let hole = signed_area(&circle_polygon((0.0, 0.0), 1.0, 32));
+        assert!((area(&drilled) - (100.0 - 4.0 * hole)).abs() < 1e-9);
+        assert!(!drilled[0].contains(2.0, 2.0));
+        assert!(drilled[0].contains(5.0, 5.0));
+
+        // A bar across the panel cuts it in two, and a fastener on the edge notches it
+        let bar = PolygonDomain::new(vec![(-1.0, 4.0), (11.0, 4.0), (11.0, 6.0), (-1.0, 6.0)]);
+        let halves = polygon_difference(&panel, &[bar.unwrap()]);
+        assert_eq!(halves.len(), 2);
+        assert!((area(&halves) - 80.0).abs() < 1e-9);
+        let notch = PolygonDomain::new(circle_polygon((10.0, 5.0), 1.0, 32)).unwrap();
+        let notched = polygon_difference(&panel, &[notch]);
+        assert_eq!(notched.len(), 1);
+        assert!(notched[0].holes.is_empty());
+        assert!((area(&notched) - (100.0 - hole / 2.0)).abs() < 1e-9);
+
+        // Results combine further
+        let filled = polygon_union(&drilled, &fasteners[..1]);
+        assert_eq!(filled[0].holes.len(), 3);
+        assert!(polygon_difference(&fasteners, &panel).is_empty());
+    }
+}
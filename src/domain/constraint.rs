@@ -0,0 +1,356 @@
+// Internal constraints: polylines, such as material interfaces or cracks, which the mesh has to
+// conform to. Every cell a constraint runs through is split along it, so that the constraint
+// comes out as a union of piece edges, and every piece is tagged with its side of each
+// constraint.
+//
+// The pieces of a cell are the faces of the planar graph made of the cell's counter-clockwise
+// edges and of the constraint pieces inside it, in both directions, all cut where they cross.
+// Faces are traced by taking the leftmost turn at every vertex, so a constraint ending inside a
+// cell, at a crack tip, comes out as a slit running into the piece and back.
+
+use super::polygon::{
+    convex_hull, cross, crossing, distance, even_odd, extent, lerp, segment_distance, signed_area,
+    Point, TOLERANCE,
+};
+
+/// Side of a constraint polyline, looking along it. The inside of a closed counter-clockwise
+/// polyline is on its left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// Part of a cell between the constraints.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstrainedCell {
+    /// Index of the cell the piece comes from
+    pub cell: usize,
+    /// Counter-clockwise boundary, with the points where constraints cross the cell's edges
+    pub polygon: Vec<Point>,
+    /// Side of each constraint the piece is on
+    pub sides: Vec<Side>,
+    /// Whether each constraint runs along an edge of the piece
+    pub along: Vec<bool>,
+}
+
+/// Splits cells, replaced by their convex hull as in `PolygonDomain::clip_cells`, along the
+/// constraint polylines. A closed constraint repeats its first vertex at the end.
+///
+/// Pieces away from a constraint are tagged with the side of its nearest segment, which for an
+/// open polyline is only meaningful close to it.
+pub fn conform_cells(cells: &[Vec<Point>], constraints: &[Vec<Point>]) -> Vec<ConstrainedCell> {
+    cells
+        .iter()
+        .enumerate()
+        .flat_map(|(index, cell)| conform_polygon(index, &convex_hull(cell), constraints))
+        .collect()
+}
+
+/// Splits the simple counter-clockwise `polygon` of cell `cell`, convex or not, along the
+/// constraint polylines, as [`conform_cells`] splits the hulls.
+pub fn conform_polygon(
+    cell: usize,
+    polygon: &[Point],
+    constraints: &[Vec<Point>],
+) -> Vec<ConstrainedCell> {
+    if polygon.len() < 3 {
+        return Vec::new();
+    }
+    split(polygon, constraints)
+        .into_iter()
+        .map(|(piece, bounding)| {
+            let inner = interior_point(&piece, TOLERANCE * extent(polygon));
+            let sides = bounding
+                .iter()
+                .zip(constraints)
+                .map(|(side, constraint)| side.unwrap_or_else(|| side_of(constraint, inner)))
+                .collect();
+            ConstrainedCell {
+                cell,
+                polygon: piece,
+                sides,
+                along: bounding.iter().map(Option::is_some).collect(),
+            }
+        })
+        .collect()
+}
+
+// Edge between two vertices, with the constraint it follows and the side of it its face is on
+type HalfEdge = (usize, usize, Option<(usize, Side)>);
+// Boundary of a piece, with its side of the constraints running along it
+type Face = (Vec<Point>, Vec<Option<Side>>);
+
+// Half-edges between vertices merged within tolerance
+struct Graph {
+    tolerance: f64,
+    vertices: Vec<Point>,
+    edges: Vec<HalfEdge>,
+}
+
+impl Graph {
+    // The same point comes out of different crossings, so vertices are merged within tolerance
+    fn vertex(&mut self, point: Point) -> usize {
+        match self
+            .vertices
+            .iter()
+            .position(|&v| distance(v, point) <= self.tolerance)
+        {
+            Some(index) => index,
+            None => {
+                self.vertices.push(point);
+                self.vertices.len() - 1
+            }
+        }
+    }
+
+    // Adds the pieces of `a -> b` between consecutive cuts which `keep` accepts
+    fn add_cut(
+        &mut self,
+        a: Point,
+        b: Point,
+        mut cuts: Vec<f64>,
+        constraint: Option<usize>,
+        keep: impl Fn(Point) -> bool,
+    ) {
+        cuts.sort_by(f64::total_cmp);
+        for piece in cuts.windows(2) {
+            let (t0, t1) = (piece[0], piece[1]);
+            if (t1 - t0) * distance(a, b) <= self.tolerance || !keep(lerp(a, b, (t0 + t1) / 2.0)) {
+                continue;
+            }
+            let (u, v) = (self.vertex(lerp(a, b, t0)), self.vertex(lerp(a, b, t1)));
+            if u == v {
+                continue;
+            }
+            match constraint {
+                Some(k) => {
+                    self.edges.push((u, v, Some((k, Side::Left))));
+                    self.edges.push((v, u, Some((k, Side::Right))));
+                }
+                None => self.edges.push((u, v, None)),
+            }
+        }
+    }
+
+    fn faces(&self, constraints: usize) -> Vec<Face> {
+        let mut outgoing = vec![Vec::new(); self.vertices.len()];
+        for (e, &(u, _, _)) in self.edges.iter().enumerate() {
+            outgoing[u].push(e);
+        }
+
+        let mut used = vec![false; self.edges.len()];
+        let mut faces = Vec::new();
+        for start in 0..self.edges.len() {
+            if used[start] {
+                continue;
+            }
+            let mut polygon = Vec::new();
+            let mut bounding = vec![None; constraints];
+            let mut e = start;
+            loop {
+                used[e] = true;
+                let (u, v, constraint) = self.edges[e];
+                polygon.push(self.vertices[u]);
+                if let Some((k, side)) = constraint {
+                    bounding[k] = Some(side);
+                }
+                match self.next(&outgoing[v], e) {
+                    Some(next) if next == start => break,
+                    Some(next) if !used[next] => e = next,
+                    _ => {
+                        log::warn!("open face while splitting a cell along constraints");
+                        break;
+                    }
+                }
+            }
+            faces.push((polygon, bounding));
+        }
+        faces
+    }
+
+    // Leftmost turn after `e`, going back along it only at a dead end
+    fn next(&self, outgoing: &[usize], e: usize) -> Option<usize> {
+        let (u, v, _) = self.edges[e];
+        let (a, b) = (self.vertices[u], self.vertices[v]);
+        let incoming = (b.0 - a.0, b.1 - a.1);
+        let turn = |&f: &usize| {
+            let w = self.edges[f].1;
+            if w == u {
+                return -std::f64::consts::PI;
+            }
+            let c = self.vertices[w];
+            let out = (c.0 - b.0, c.1 - b.1);
+            (incoming.0 * out.1 - incoming.1 * out.0).atan2(incoming.0 * out.0 + incoming.1 * out.1)
+        };
+        outgoing
+            .iter()
+            .copied()
+            .max_by(|f, g| turn(f).total_cmp(&turn(g)))
+    }
+}
+
+fn split(polygon: &[Point], constraints: &[Vec<Point>]) -> Vec<Face> {
+    let tolerance = TOLERANCE * extent(polygon);
+    let polygon_edges: Vec<(Point, Point)> = (0..polygon.len())
+        .map(|i| (polygon[i], polygon[(i + 1) % polygon.len()]))
+        .collect();
+
+    // Constraint segments whose bounding box meets the cell's
+    let (mut min, mut max) = ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN));
+    for &(x, y) in polygon {
+        min = (min.0.min(x), min.1.min(y));
+        max = (max.0.max(x), max.1.max(y));
+    }
+    let segments: Vec<(usize, Point, Point)> = constraints
+        .iter()
+        .enumerate()
+        .flat_map(|(k, polyline)| polyline.windows(2).map(move |w| (k, w[0], w[1])))
+        .filter(|&(_, a, b)| {
+            a.0.max(b.0) >= min.0 - tolerance
+                && a.0.min(b.0) <= max.0 + tolerance
+                && a.1.max(b.1) >= min.1 - tolerance
+                && a.1.min(b.1) <= max.1 + tolerance
+        })
+        .collect();
+    let whole = || vec![(polygon.to_vec(), vec![None; constraints.len()])];
+    if segments.is_empty() {
+        return whole();
+    }
+
+    let mut graph = Graph {
+        tolerance,
+        vertices: polygon.to_vec(),
+        edges: Vec::new(),
+    };
+    for &(p, q) in &polygon_edges {
+        let mut cuts = vec![0.0, 1.0];
+        cuts.extend(
+            segments
+                .iter()
+                .filter_map(|&(_, a, b)| crossing(p, q, a, b))
+                .map(|(t, _)| t),
+        );
+        graph.add_cut(p, q, cuts, None, |_| true);
+    }
+    let cell_edges = graph.edges.len();
+
+    // Strictly inside, so that constraints running along a cell edge are left to the edge
+    let inside = |m: Point| {
+        even_odd(polygon_edges.iter().copied(), m)
+            && polygon_edges
+                .iter()
+                .all(|&(p, q)| segment_distance(m, p, q) > tolerance)
+    };
+    for (j, &(k, a, b)) in segments.iter().enumerate() {
+        let mut cuts = vec![0.0, 1.0];
+        for &(p, q) in &polygon_edges {
+            cuts.extend(crossing(a, b, p, q).map(|(t, _)| t));
+        }
+        for (other, &(_, c, d)) in segments.iter().enumerate() {
+            if other != j {
+                cuts.extend(crossing(a, b, c, d).map(|(t, _)| t));
+            }
+        }
+        graph.add_cut(a, b, cuts, Some(k), inside);
+    }
+    if graph.edges.len() == cell_edges {
+        return whole();
+    }
+
+    // Faces around constraint pieces which don't reach the cell's boundary have no area
+    graph
+        .faces(constraints.len())
+        .into_iter()
+        .filter(|(piece, _)| signed_area(piece) > tolerance * extent(polygon))
+        .collect()
+}
+
+// Point just left of the middle of the longest edge, inside the counter-clockwise `polygon`
+fn interior_point(polygon: &[Point], tolerance: f64) -> Point {
+    let n = polygon.len();
+    let (a, b) = (0..n)
+        .map(|i| (polygon[i], polygon[(i + 1) % n]))
+        .max_by(|e, f| distance(e.0, e.1).total_cmp(&distance(f.0, f.1)))
+        .unwrap_or_default();
+    let length = distance(a, b).max(f64::MIN_POSITIVE);
+    let offset = 1e3 * tolerance / length;
+    let m = lerp(a, b, 0.5);
+    (m.0 - (b.1 - a.1) * offset, m.1 + (b.0 - a.0) * offset)
+}
+
+fn side_of(polyline: &[Point], point: Point) -> Side {
+    let nearest = polyline.windows(2).min_by(|s, t| {
+        segment_distance(point, s[0], s[1]).total_cmp(&segment_distance(point, t[0], t[1]))
+    });
+    match nearest {
+        Some(s) if cross(s[0], s[1], point) < 0.0 => Side::Right,
+        _ => Side::Left,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x: f64, y: f64, size: f64) -> Vec<Point> {
+        vec![(x, y), (x + size, y), (x + size, y + size), (x, y + size)]
+    }
+
+    #[test]
+    fn test_interface() {
+        // A horizontal interface through the two bottom cells, the top one is away from it
+        let cells = vec![
+            square(0.0, 0.0, 2.0),
+            square(2.0, 0.0, 2.0),
+            square(0.0, 3.0, 2.0),
+        ];
+        let interface = vec![(-1.0, 1.0), (2.0, 1.5), (5.0, 1.0)];
+
+        let pieces = conform_cells(&cells, &[interface]);
+
+        assert_eq!(pieces.len(), 5);
+        for (cell, expected) in [(0, 4.0), (1, 4.0), (2, 4.0)] {
+            let area: f64 = pieces
+                .iter()
+                .filter(|piece| piece.cell == cell)
+                .map(|piece| signed_area(&piece.polygon))
+                .sum();
+            assert!((area - expected).abs() < 1e-9);
+        }
+        // Both cells have the interface's vertex on their shared edge
+        let (left, right) = (&pieces[0], &pieces[2]);
+        assert!(left.polygon.contains(&(2.0, 1.5)) && right.polygon.contains(&(2.0, 1.5)));
+        let below = pieces.iter().filter(|p| p.sides[0] == Side::Right).count();
+        assert_eq!(below, 2);
+        assert_eq!(pieces[4].sides, vec![Side::Left]);
+    }
+
+    #[test]
+    fn test_crossing_constraints() {
+        let horizontal = vec![(-1.0, 1.0), (3.0, 1.0)];
+        let vertical = vec![(1.0, -1.0), (1.0, 3.0)];
+
+        let pieces = conform_cells(&[square(0.0, 0.0, 2.0)], &[horizontal, vertical]);
+
+        assert_eq!(pieces.len(), 4);
+        let sides: Vec<_> = pieces.iter().map(|piece| piece.sides.clone()).collect();
+        assert!((1..4).all(|i| !sides[..i].contains(&sides[i])));
+        for piece in &pieces {
+            assert!((signed_area(&piece.polygon) - 1.0).abs() < 1e-9);
+            assert!(piece.polygon.contains(&(1.0, 1.0)));
+        }
+    }
+
+    #[test]
+    fn test_crack_tip() {
+        // The crack stops in the middle of the cell, which keeps a slit instead of splitting
+        let crack = vec![(-1.0, 1.0), (1.0, 1.0)];
+
+        let pieces = conform_cells(&[square(0.0, 0.0, 2.0)], &[crack]);
+
+        assert_eq!(pieces.len(), 1);
+        assert_eq!(pieces[0].polygon.len(), 7);
+        assert!((signed_area(&pieces[0].polygon) - 4.0).abs() < 1e-9);
+    }
+}
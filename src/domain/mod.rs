@@ -0,0 +1,515 @@
+// Meshing domains, given as a signed distance function `f(x, y)` which is negative inside the
+// domain and positive outside, or as a polygon with holes, along with internal constraints.
+//
+// `mesh_cells` is the step of the pipeline after labeling which applies the domain of the
+// options. Cells are extracted from the unmasked label grid, since clipping needs whole cells,
+// clipped against the region and split along the constraints, and only then is the grid masked.
+// Cells the region boundary doesn't reach are kept as traced, so that they still share their
+// vertices bitwise with their neighbors, and the vertices computed on the region boundary or on
+// a constraint, once from each cell around them, are merged within a tolerance.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::cells;
+use crate::config::{BoundaryMode, MesherConfig};
+use crate::error::MesherError;
+use crate::mesh::PolyMesh;
+
+mod boolean;
+mod constraint;
+mod polygon;
+mod svg_path;
+
+pub use boolean::{polygon_difference, polygon_intersection, polygon_union};
+pub use constraint::{conform_cells, conform_polygon, ConstrainedCell, Side};
+pub use polygon::{ClippedCell, PolygonDomain};
+pub use svg_path::flatten_svg_path;
+
+use polygon::{signed_area, Point};
+
+const BISECTION_STEPS: usize = 52;
+// Pieces each polygon edge is split into when looking for boundary crossings
+const EDGE_SUBDIVISIONS: usize = 16;
+// Distance within which `mesh_cells` merges vertices, relative to the box, far below a texel of
+// any grid a device can hold
+const MERGE_TOLERANCE: f64 = 1e-7;
+
+/// Region the cells are meshed in, within the `config` box.
+#[derive(Clone)]
+pub enum Region {
+    /// Signed distance function, positive outside the region
+    Implicit(Arc<dyn Fn(f64, f64) -> f64 + Send + Sync>),
+    Polygon(PolygonDomain),
+    /// Union of polygons which don't overlap
+    Polygons(Vec<PolygonDomain>),
+}
+
+impl fmt::Debug for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Region::Implicit(_) => f.write_str("Implicit"),
+            Region::Polygon(polygon) => f.debug_tuple("Polygon").field(polygon).finish(),
+            Region::Polygons(polygons) => f.debug_tuple("Polygons").field(polygons).finish(),
+        }
+    }
+}
+
+/// Domain of [`MesherConfig::domain`], which [`mesh_cells`] applies.
+#[derive(Debug, Clone)]
+pub struct Domain {
+    /// Region the cells are clipped to, the whole box when `None`
+    pub region: Option<Region>,
+    /// Polylines the cells are split along, a closed one repeating its first vertex at the end
+    pub constraints: Vec<Vec<(f64, f64)>>,
+}
+
+impl Domain {
+    pub fn implicit(sdf: impl Fn(f64, f64) -> f64 + Send + Sync + 'static) -> Domain {
+        Domain {
+            region: Some(Region::Implicit(Arc::new(sdf))),
+            constraints: Vec::new(),
+        }
+    }
+
+    pub fn polygon(polygon: PolygonDomain) -> Domain {
+        Domain {
+            region: Some(Region::Polygon(polygon)),
+            constraints: Vec::new(),
+        }
+    }
+
+    pub fn polygons(polygons: Vec<PolygonDomain>) -> Domain {
+        Domain {
+            region: Some(Region::Polygons(polygons)),
+            constraints: Vec::new(),
+        }
+    }
+
+    pub fn with_constraint(mut self, polyline: Vec<(f64, f64)>) -> Domain {
+        self.constraints.push(polyline);
+        self
+    }
+
+    fn distance(&self, x: f64, y: f64) -> f64 {
+        match &self.region {
+            Some(Region::Implicit(sdf)) => sdf(x, y),
+            Some(Region::Polygon(polygon)) => polygon.distance(x, y),
+            Some(Region::Polygons(polygons)) => polygons
+                .iter()
+                .map(|polygon| polygon.distance(x, y))
+                .fold(f64::MAX, f64::min),
+            None => -1.0,
+        }
+    }
+
+    // Loops of the part of `cell` inside the region, `cell` itself when the boundary doesn't
+    // reach it
+    fn clip(&self, cell: &[Point]) -> Vec<Vec<Point>> {
+        match &self.region {
+            Some(Region::Implicit(sdf)) => {
+                let clipped = clip_polygon(cell, |x, y| sdf(x, y));
+                if clipped.len() < 3 {
+                    return Vec::new();
+                }
+                vec![clipped]
+            }
+            Some(Region::Polygon(polygon)) => clip_to_polygons(std::slice::from_ref(polygon), cell),
+            Some(Region::Polygons(polygons)) => clip_to_polygons(polygons, cell),
+            None => vec![cell.to_vec()],
+        }
+    }
+}
+
+// Loops of the part of `cell` inside the union of `polygons`, `cell` itself when it is inside
+// one of them without their boundaries reaching it
+fn clip_to_polygons(polygons: &[PolygonDomain], cell: &[Point]) -> Vec<Vec<Point>> {
+    let clipped: Vec<ClippedCell> = polygons
+        .iter()
+        .map(|polygon| polygon.clip_cell(cell))
+        .collect();
+    if clipped.iter().any(|clipped| clipped.on_boundary) {
+        clipped
+            .into_iter()
+            .flat_map(|clipped| clipped.loops)
+            .collect()
+    } else if clipped.iter().all(|clipped| clipped.loops.is_empty()) {
+        Vec::new()
+    } else {
+        vec![cell.to_vec()]
+    }
+}
+
+/// Half-edge mesh of the cells of a domain, with tags for each of its faces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DomainMesh {
+    /// Faces of the cells, several for a cell the region or the constraints cut apart
+    pub mesh: PolyMesh,
+    /// Whether each face has an edge on the boundary of the region, or of the box without one
+    pub boundary: Vec<bool>,
+    /// Whether each face has an edge along a constraint
+    pub interface: Vec<bool>,
+    /// Side of each constraint each face is on
+    pub sides: Vec<Vec<Side>>,
+}
+
+/// Meshes the cells of `labels`, a grid of `dimensions` texels covering the `config` box, within
+/// the domain of `options`, then sets the labels of the texels outside its region to 0. Without
+/// a domain the cells are meshed as [`cells::extract_cells`] traces them.
+///
+/// Domains need a bounded grid, and the faces of the mesh have no holes, so a hole of a polygon
+/// region inside a single cell is an error. One of an implicit region is missed, crossings being
+/// searched along the cell edges.
+pub fn mesh_cells(
+    labels: &mut [usize],
+    dimensions: (usize, usize),
+    cell_count: usize,
+    config: (f64, f64),
+    options: &MesherConfig,
+) -> Result<DomainMesh, MesherError> {
+    let cells = cells::extract_cells(labels, dimensions, cell_count, config, options.boundary);
+    let Some(domain) = &options.domain else {
+        let mesh = PolyMesh::from_cells(&cells);
+        let faces = mesh.faces.len();
+        return Ok(DomainMesh {
+            boundary: boundary_faces(&mesh),
+            interface: vec![false; faces],
+            sides: vec![Vec::new(); faces],
+            mesh,
+        });
+    };
+    if options.boundary != BoundaryMode::Bounded {
+        return Err(MesherError::InvalidInput(
+            "domains are only for bounded grids",
+        ));
+    }
+
+    let mut pieces = Vec::new();
+    for (cell, polygon) in cells.iter().enumerate() {
+        for part in domain.clip(polygon) {
+            if signed_area(&part) < 0.0 {
+                return Err(MesherError::InvalidInput(
+                    "a hole of the domain is inside a single cell, add seeds around it",
+                ));
+            }
+            pieces.extend(conform_polygon(cell, &part, &domain.constraints));
+        }
+    }
+
+    let tolerance = MERGE_TOLERANCE * config.0.max(config.1);
+    let mut merged = Merged::new(tolerance);
+    let mut polygons = Vec::with_capacity(pieces.len());
+    let mut seeds = Vec::with_capacity(pieces.len());
+    let (mut interface, mut sides) = (Vec::new(), Vec::new());
+    for piece in pieces {
+        let mut polygon: Vec<usize> = piece.polygon.iter().map(|&p| merged.vertex(p)).collect();
+        polygon.dedup();
+        while polygon.len() > 1 && polygon.first() == polygon.last() {
+            polygon.pop();
+        }
+        if polygon.len() < 3 {
+            continue;
+        }
+        polygons.push(polygon);
+        seeds.push(piece.cell);
+        interface.push(piece.along.contains(&true));
+        sides.push(piece.sides);
+    }
+    let mesh = PolyMesh::from_polygons(merged.vertices, &polygons, &seeds);
+
+    if domain.region.is_some() {
+        mask_labels(labels, dimensions, config, |x, y| domain.distance(x, y));
+    }
+    Ok(DomainMesh {
+        boundary: boundary_faces(&mesh),
+        interface,
+        sides,
+        mesh,
+    })
+}
+
+fn boundary_faces(mesh: &PolyMesh) -> Vec<bool> {
+    (0..mesh.faces.len())
+        .map(|face| {
+            mesh.face_edges(face)
+                .any(|h| mesh.half_edges[h].twin.is_none())
+        })
+        .collect()
+}
+
+// Vertices merged within `tolerance`, bucketed by the square of that side they fall in
+struct Merged {
+    tolerance: f64,
+    vertices: Vec<Point>,
+    buckets: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl Merged {
+    fn new(tolerance: f64) -> Merged {
+        Merged {
+            tolerance,
+            vertices: Vec::new(),
+            buckets: HashMap::new(),
+        }
+    }
+
+    fn vertex(&mut self, point: Point) -> usize {
+        let bucket = (
+            (point.0 / self.tolerance).floor() as i64,
+            (point.1 / self.tolerance).floor() as i64,
+        );
+        // A point within tolerance can be in any of the neighboring buckets
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let Some(indices) = self.buckets.get(&(bucket.0 + dx, bucket.1 + dy)) else {
+                    continue;
+                };
+                for &index in indices {
+                    let v = self.vertices[index];
+                    if (v.0 - point.0).hypot(v.1 - point.1) <= self.tolerance {
+                        return index;
+                    }
+                }
+            }
+        }
+        self.vertices.push(point);
+        let index = self.vertices.len() - 1;
+        self.buckets.entry(bucket).or_default().push(index);
+        index
+    }
+}
+
+/// Sets the label of every texel whose center lies outside the domain to 0, the unassigned color.
+/// `labels` is a grid of `(width, height)` texels covering the `config` box.
+pub fn mask_labels(
+    labels: &mut [usize],
+    (width, height): (usize, usize),
+    config: (f64, f64),
+    sdf: impl Fn(f64, f64) -> f64,
+) {
+    assert_eq!(labels.len(), width * height, "label grid size mismatch");
+    for y in 0..height {
+        for x in 0..width {
+            let center_x = (x as f64 + 0.5) * config.0 / width as f64;
+            let center_y = (y as f64 + 0.5) * config.1 / height as f64;
+            if sdf(center_x, center_y) > 0.0 {
+                labels[x + y * width] = 0;
+            }
+        }
+    }
+}
+
+/// Point where the domain boundary crosses the segment `[a, b]`, found by bisection, or `None`
+/// if both ends are on the same side.
+pub fn zero_crossing(
+    sdf: impl Fn(f64, f64) -> f64,
+    a: (f64, f64),
+    b: (f64, f64),
+) -> Option<(f64, f64)> {
+    let (mut inside, mut outside) = match (sdf(a.0, a.1) > 0.0, sdf(b.0, b.1) > 0.0) {
+        (false, true) => (a, b),
+        (true, false) => (b, a),
+        _ => return None,
+    };
+
+    for _ in 0..BISECTION_STEPS {
+        let middle = ((inside.0 + outside.0) / 2.0, (inside.1 + outside.1) / 2.0);
+        if sdf(middle.0, middle.1) > 0.0 {
+            outside = middle;
+        } else {
+            inside = middle;
+        }
+    }
+
+    Some(inside)
+}
+
+/// Clips a polygon, such as a boundary cell, against the domain: outside vertices are dropped
+/// and edges crossing the boundary are cut on its zero level set.
+///
+/// Edges are searched for crossings in `EDGE_SUBDIVISIONS` pieces, so an edge can both enter and
+/// leave the domain. Boundary stretches between an exit and the next entry are closed with a
+/// straight edge, so curved boundaries are only followed up to the polygon's own edge length.
+pub fn clip_polygon(polygon: &[(f64, f64)], sdf: impl Fn(f64, f64) -> f64) -> Vec<(f64, f64)> {
+    let mut clipped = Vec::with_capacity(polygon.len());
+    for (i, &current) in polygon.iter().enumerate() {
+        let next = polygon[(i + 1) % polygon.len()];
+        if sdf(current.0, current.1) <= 0.0 {
+            clipped.push(current);
+        }
+
+        let lerp = |t: f64| {
+            (
+                current.0 + t * (next.0 - current.0),
+                current.1 + t * (next.1 - current.1),
+            )
+        };
+        for piece in 0..EDGE_SUBDIVISIONS {
+            let a = lerp(piece as f64 / EDGE_SUBDIVISIONS as f64);
+            let b = lerp((piece + 1) as f64 / EDGE_SUBDIVISIONS as f64);
+            if let Some(crossing) = zero_crossing(&sdf, a, b) {
+                clipped.push(crossing);
+            }
+        }
+    }
+    clipped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{ExactBackend, VoronoiBackend};
+    use crate::test_util::scattered;
+
+    fn circle(x: f64, y: f64) -> f64 {
+        ((x - 5.0).powi(2) + (y - 5.0).powi(2)).sqrt() - 4.0
+    }
+
+    #[test]
+    fn test_mask_labels() {
+        let mut labels = vec![1; 100];
+
+        mask_labels(&mut labels, (10, 10), (10.0, 10.0), circle);
+
+        assert_eq!(labels[0], 0);
+        assert_eq!(labels[99], 0);
+        assert_eq!(labels[5 + 5 * 10], 1);
+        assert_eq!(labels.iter().filter(|&&l| l == 1).count(), 52);
+    }
+
+    #[test]
+    fn test_zero_crossing() {
+        let crossing = zero_crossing(circle, (5.0, 5.0), (10.0, 5.0)).unwrap();
+        assert!((crossing.0 - 9.0).abs() < 1e-9);
+        assert!((crossing.1 - 5.0).abs() < 1e-12);
+
+        assert!(zero_crossing(circle, (5.0, 5.0), (6.0, 6.0)).is_none());
+    }
+
+    #[test]
+    fn test_clip_polygon() {
+        let square = [(2.0, 2.0), (8.0, 2.0), (8.0, 8.0), (2.0, 8.0)];
+
+        let clipped = clip_polygon(&square, circle);
+
+        // Every edge dips into the circle, the corners all being outside
+        assert_eq!(clipped.len(), 8);
+        for (x, y) in clipped {
+            assert!(circle(x, y).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_mesh_cells() {
+        // The 8 × 8 box without its top right quarter, with a hole spanning several cells and an
+        // interface across the upper arm
+        let region = PolygonDomain::new(vec![
+            (0.0, 0.0),
+            (8.0, 0.0),
+            (8.0, 4.0),
+            (4.0, 4.0),
+            (4.0, 8.0),
+            (0.0, 8.0),
+        ])
+        .unwrap()
+        .with_hole(vec![(1.0, 1.0), (3.0, 1.0), (3.0, 3.0), (1.0, 3.0)])
+        .unwrap();
+        let domain = Domain::polygon(region.clone()).with_constraint(vec![(0.0, 6.0), (4.0, 6.0)]);
+        let options = MesherConfig {
+            resolution: 64,
+            domain: Some(domain),
+            ..Default::default()
+        };
+        let (config, points) = ((8.0, 8.0), scattered(40, (8.0, 8.0)));
+        let mut labels = ExactBackend
+            .labels(&points, &[], config, &options)
+            .unwrap()
+            .labels;
+
+        let meshed = mesh_cells(&mut labels, (64, 64), points.len(), config, &options).unwrap();
+
+        let mesh = &meshed.mesh;
+        let area: f64 = (0..mesh.faces.len())
+            .map(|face| signed_area(&mesh.face_polygon(face)))
+            .sum();
+        assert!((area - 44.0).abs() < 1e-9, "{area}");
+        // The faces conform to each other, only edges on the region boundary having no twin
+        for h in mesh.boundary_edges() {
+            let (a, b) = (
+                mesh.vertices[mesh.half_edges[h].origin],
+                mesh.vertices[mesh.destination(h)],
+            );
+            let middle = ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+            assert!(
+                region.distance(middle.0, middle.1).abs() < 1e-9,
+                "{middle:?}"
+            );
+        }
+        assert!(meshed.boundary.iter().any(|&b| b) && !meshed.boundary.iter().all(|&b| b));
+
+        // The interface comes out as edges on both of its sides
+        let along: f64 = (0..mesh.half_edges.len())
+            .filter(|&h| {
+                let (a, b) = (
+                    mesh.vertices[mesh.half_edges[h].origin],
+                    mesh.vertices[mesh.destination(h)],
+                );
+                a.1 == 6.0 && b.1 == 6.0
+            })
+            .map(|h| mesh.edge_length(h))
+            .sum();
+        assert!((along - 8.0).abs() < 1e-9, "{along}");
+        assert!(meshed.interface.iter().any(|&i| i));
+        for face in (0..mesh.faces.len()).filter(|&face| meshed.interface[face]) {
+            let polygon = mesh.face_polygon(face);
+            let above = polygon.iter().all(|&(_, y)| y >= 6.0);
+            assert_eq!(
+                meshed.sides[face],
+                [if above { Side::Left } else { Side::Right }]
+            );
+        }
+
+        // The hole and the notch are masked out of the grid
+        assert_eq!(labels[16 + 16 * 64], 0);
+        assert_eq!(labels[48 + 48 * 64], 0);
+        assert_ne!(labels[8 + 40 * 64], 0);
+
+        let periodic = MesherConfig {
+            boundary: BoundaryMode::Periodic { x: true, y: true },
+            ..options
+        };
+        let err = mesh_cells(&mut labels, (64, 64), points.len(), config, &periodic).unwrap_err();
+        assert!(matches!(err, MesherError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn test_mesh_cells_polygons() {
+        // Two squares in opposite corners of the box
+        let square = |x: f64, y: f64| {
+            PolygonDomain::new(vec![(x, y), (x + 3.0, y), (x + 3.0, y + 3.0), (x, y + 3.0)])
+                .unwrap()
+        };
+        let options = MesherConfig {
+            resolution: 64,
+            domain: Some(Domain::polygons(vec![square(0.0, 0.0), square(5.0, 5.0)])),
+            ..Default::default()
+        };
+        let (config, points) = ((8.0, 8.0), scattered(30, (8.0, 8.0)));
+        let mut labels = ExactBackend
+            .labels(&points, &[], config, &options)
+            .unwrap()
+            .labels;
+
+        let meshed = mesh_cells(&mut labels, (64, 64), points.len(), config, &options).unwrap();
+
+        let mesh = &meshed.mesh;
+        let area: f64 = (0..mesh.faces.len())
+            .map(|face| signed_area(&mesh.face_polygon(face)))
+            .sum();
+        assert!((area - 18.0).abs() < 1e-9, "{area}");
+        assert_eq!(labels[32 + 32 * 64], 0);
+        assert_ne!(labels[8 + 8 * 64], 0);
+        assert_ne!(labels[56 + 56 * 64], 0);
+    }
+}
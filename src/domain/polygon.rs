@@ -0,0 +1,493 @@
+// Meshing domains bounded by a polygon, possibly non-convex, with polygonal holes. Texels
+// outside are masked out of the label grid, and the cells extracted from the unmasked grid are
+// clipped exactly against the boundary.
+//
+// The intersection of a simple cell polygon with the domain is bounded by the domain's edges
+// inside the cell and by the cell's edges inside the domain, and those pieces are chained back
+// into loops. A non-convex domain can cut a cell into several pieces, and a hole inside a cell
+// comes out as an inner loop.
+
+// Relative tolerance for points to coincide and segments to be collinear
+pub(super) const TOLERANCE: f64 = 1e-9;
+
+pub(super) type Point = (f64, f64);
+
+/// Polygonal meshing domain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolygonDomain {
+    /// Counter-clockwise boundary
+    pub outer: Vec<Point>,
+    /// Clockwise boundaries of the holes, which are inside `outer` and don't overlap
+    pub holes: Vec<Vec<Point>>,
+}
+
+/// Part of a cell inside the domain.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ClippedCell {
+    /// Counter-clockwise outer loops and clockwise loops around the holes inside the cell,
+    /// empty when the cell is outside the domain
+    pub loops: Vec<Vec<Point>>,
+    /// Whether the domain boundary runs through or along the cell
+    pub on_boundary: bool,
+}
+
+impl PolygonDomain {
+    /// Domain inside the simple polygon `outer`, given in either orientation.
+    pub fn new(outer: Vec<Point>) -> Result<PolygonDomain, &'static str> {
+        Ok(PolygonDomain {
+            outer: oriented(outer, true)?,
+            holes: Vec::new(),
+        })
+    }
+
+    /// Cuts out the simple polygon `hole`, given in either orientation.
+    pub fn with_hole(mut self, hole: Vec<Point>) -> Result<PolygonDomain, &'static str> {
+        let hole = oriented(hole, false)?;
+        if hole.iter().any(|&(x, y)| !self.contains(x, y)) {
+            return Err("a hole must be inside the domain");
+        }
+        self.holes.push(hole);
+        Ok(self)
+    }
+
+    // Boundary loops with the domain on their left
+    fn loops(&self) -> impl Iterator<Item = &Vec<Point>> {
+        std::iter::once(&self.outer).chain(&self.holes)
+    }
+
+    pub(super) fn edges(&self) -> impl Iterator<Item = (Point, Point)> + '_ {
+        self.loops().flat_map(|boundary| {
+            (0..boundary.len()).map(|i| (boundary[i], boundary[(i + 1) % boundary.len()]))
+        })
+    }
+
+    /// Even-odd rule over all the boundary loops.
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        even_odd(self.edges(), (x, y))
+    }
+
+    /// Signed distance to the boundary, negative inside, to use with the other `domain`
+    /// functions.
+    pub fn distance(&self, x: f64, y: f64) -> f64 {
+        let distance = self
+            .edges()
+            .map(|(a, b)| segment_distance((x, y), a, b))
+            .fold(f64::MAX, f64::min);
+        if self.contains(x, y) {
+            -distance
+        } else {
+            distance
+        }
+    }
+
+    /// Sets the label of the texels whose center is outside the domain to 0.
+    pub fn mask_labels(
+        &self,
+        labels: &mut [usize],
+        dimensions: (usize, usize),
+        config: (f64, f64),
+    ) {
+        super::mask_labels(labels, dimensions, config, |x, y| self.distance(x, y));
+    }
+
+    /// Clips cells, as extracted from the unmasked label grid, against the domain. Each cell is
+    /// replaced by its convex hull first, which is what a Voronoi cell is up to the
+    /// rasterization.
+    pub fn clip_cells(&self, cells: &[Vec<Point>]) -> Vec<ClippedCell> {
+        cells
+            .iter()
+            .map(|cell| self.clip_cell(&convex_hull(cell)))
+            .collect()
+    }
+
+    /// Clips a simple counter-clockwise polygon, convex or not, against the domain. Its vertices
+    /// inside the domain are kept as they are, so cells clipped one by one still share them.
+    pub fn clip_cell(&self, cell: &[Point]) -> ClippedCell {
+        if cell.len() < 3 {
+            return ClippedCell::default();
+        }
+        let tolerance = TOLERANCE * extent(cell);
+        let cell_edges: Vec<(Point, Point)> = (0..cell.len())
+            .map(|i| (cell[i], cell[(i + 1) % cell.len()]))
+            .collect();
+        let mut segments = Vec::new();
+
+        // Domain edges inside the cell, those along a cell edge are kept only when the domain
+        // is on the same side
+        for (a, b) in self.edges() {
+            let mut cuts = vec![0.0, 1.0];
+            let mut along = Vec::new();
+            for &(p, q) in &cell_edges {
+                match overlap(a, b, p, q, tolerance) {
+                    Some(interval) => {
+                        let same_direction =
+                            (q.0 - p.0) * (b.0 - a.0) + (q.1 - p.1) * (b.1 - a.1) > 0.0;
+                        along.push((interval, same_direction));
+                    }
+                    None => cuts.extend(crossing(a, b, p, q).map(|(t, _)| t)),
+                }
+            }
+            for &((t0, t1), _) in &along {
+                cuts.extend([t0, t1]);
+            }
+            cuts.sort_by(f64::total_cmp);
+
+            for piece in cuts.windows(2) {
+                let (t0, t1) = (piece[0], piece[1]);
+                if (t1 - t0) * distance(a, b) <= tolerance {
+                    continue;
+                }
+                let middle = (t0 + t1) / 2.0;
+                let kept = match along
+                    .iter()
+                    .find(|((s0, s1), _)| *s0 <= middle && middle <= *s1)
+                {
+                    Some(&(_, same_direction)) => same_direction,
+                    None => even_odd(cell_edges.iter().copied(), lerp(a, b, middle)),
+                };
+                if kept {
+                    segments.push((lerp(a, b, t0), lerp(a, b, t1)));
+                }
+            }
+        }
+        let on_boundary = !segments.is_empty();
+
+        // Cell edges inside the domain, leaving out the parts the domain boundary runs along
+        for &(p, q) in &cell_edges {
+            let mut cuts = vec![0.0, 1.0];
+            let mut along = Vec::new();
+            for (a, b) in self.edges() {
+                match overlap(p, q, a, b, tolerance) {
+                    Some(interval) => along.push(interval),
+                    None => cuts.extend(crossing(p, q, a, b).map(|(t, _)| t)),
+                }
+            }
+            for &(t0, t1) in &along {
+                cuts.extend([t0, t1]);
+            }
+            cuts.sort_by(f64::total_cmp);
+
+            for piece in cuts.windows(2) {
+                let (t0, t1) = (piece[0], piece[1]);
+                let middle = (t0 + t1) / 2.0;
+                if (t1 - t0) * distance(p, q) <= tolerance
+                    || along.iter().any(|&(a, b)| a <= middle && middle <= b)
+                {
+                    continue;
+                }
+                let m = lerp(p, q, middle);
+                if self.contains(m.0, m.1) {
+                    segments.push((lerp(p, q, t0), lerp(p, q, t1)));
+                }
+            }
+        }
+
+        ClippedCell {
+            loops: chain(segments, tolerance, cell),
+            on_boundary,
+        }
+    }
+}
+
+// Reorients a simple polygon, counter-clockwise when `ccw`
+fn oriented(mut polygon: Vec<Point>, ccw: bool) -> Result<Vec<Point>, &'static str> {
+    if polygon.len() < 3 {
+        return Err("a domain polygon needs at least 3 vertices");
+    }
+    let area = signed_area(&polygon);
+    if area.abs() <= TOLERANCE * extent(&polygon).powi(2) {
+        return Err("a domain polygon must have a non-zero area");
+    }
+    if (area > 0.0) != ccw {
+        polygon.reverse();
+    }
+    Ok(polygon)
+}
+
+/// Regular polygon with `sides` vertices approximating a circle, e.g. for circular holes.
+pub fn circle_polygon(center: Point, radius: f64, sides: usize) -> Vec<Point> {
+    (0..sides)
+        .map(|i| {
+            let angle = std::f64::consts::TAU * i as f64 / sides as f64;
+            (
+                center.0 + radius * angle.cos(),
+                center.1 + radius * angle.sin(),
+            )
+        })
+        .collect()
+}
+
+pub(super) fn signed_area(polygon: &[Point]) -> f64 {
+    let mut area = 0.0;
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        area += a.0 * b.1 - b.0 * a.1;
+    }
+    area / 2.0
+}
+
+pub(super) fn extent(points: &[Point]) -> f64 {
+    let (mut min, mut max) = ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN));
+    for &(x, y) in points {
+        min = (min.0.min(x), min.1.min(y));
+        max = (max.0.max(x), max.1.max(y));
+    }
+    (max.0 - min.0).max(max.1 - min.1)
+}
+
+pub(super) fn cross(o: Point, a: Point, b: Point) -> f64 {
+    (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+}
+
+pub(super) fn lerp(a: Point, b: Point, t: f64) -> Point {
+    (a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1))
+}
+
+pub(super) fn distance(a: Point, b: Point) -> f64 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+pub(super) fn segment_distance(p: Point, a: Point, b: Point) -> f64 {
+    let (abx, aby) = (b.0 - a.0, b.1 - a.1);
+    let length_squared = abx * abx + aby * aby;
+    let t = if length_squared > 0.0 {
+        (((p.0 - a.0) * abx + (p.1 - a.1) * aby) / length_squared).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    distance(p, lerp(a, b, t))
+}
+
+// Even-odd rule over the edges of one or more closed loops
+pub(super) fn even_odd(edges: impl Iterator<Item = (Point, Point)>, (x, y): Point) -> bool {
+    let mut inside = false;
+    for (a, b) in edges {
+        if (a.1 > y) != (b.1 > y) && x < a.0 + (y - a.1) / (b.1 - a.1) * (b.0 - a.0) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+// Counter-clockwise convex hull, by the monotone chain
+pub(super) fn convex_hull(points: &[Point]) -> Vec<Point> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return sorted;
+    }
+
+    let mut hull: Vec<Point> = Vec::with_capacity(sorted.len() + 1);
+    for pass in 0..2 {
+        let start = hull.len();
+        for &point in &sorted {
+            while hull.len() >= start + 2
+                && cross(hull[hull.len() - 2], hull[hull.len() - 1], point) <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(point);
+        }
+        hull.pop();
+        if pass == 0 {
+            sorted.reverse();
+        }
+    }
+    hull
+}
+
+// Parameter range of `p -> q` along which the collinear segment `a -> b` runs
+pub(super) fn overlap(
+    p: Point,
+    q: Point,
+    a: Point,
+    b: Point,
+    tolerance: f64,
+) -> Option<(f64, f64)> {
+    let length = distance(p, q);
+    if (cross(p, q, a) / length).abs() > tolerance || (cross(p, q, b) / length).abs() > tolerance {
+        return None;
+    }
+    let project = |point: Point| {
+        ((point.0 - p.0) * (q.0 - p.0) + (point.1 - p.1) * (q.1 - p.1)) / (length * length)
+    };
+    let (ta, tb) = (project(a), project(b));
+    let (t0, t1) = (ta.min(tb).max(0.0), ta.max(tb).min(1.0));
+    (t1 > t0).then_some((t0, t1))
+}
+
+// Parameters along `p -> q` and `a -> b` where they cross
+pub(super) fn crossing(p: Point, q: Point, a: Point, b: Point) -> Option<(f64, f64)> {
+    let denominator = (q.0 - p.0) * (b.1 - a.1) - (q.1 - p.1) * (b.0 - a.0);
+    if denominator == 0.0 {
+        return None;
+    }
+    let t = ((a.0 - p.0) * (b.1 - a.1) - (a.1 - p.1) * (b.0 - a.0)) / denominator;
+    let u = ((a.0 - p.0) * (q.1 - p.1) - (a.1 - p.1) * (q.0 - p.0)) / denominator;
+    ((0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u)).then_some((t, u))
+}
+
+// Links directed segments end to start into closed loops. The same point can come out of two
+// different computations, so ends are matched to the nearest start. Collinear points are dropped
+// unless they are vertices of `cell`, which its neighbors may need
+pub(super) fn chain(
+    mut segments: Vec<(Point, Point)>,
+    tolerance: f64,
+    cell: &[Point],
+) -> Vec<Vec<Point>> {
+    let mut loops = Vec::new();
+    while let Some((start, mut end)) = segments.pop() {
+        let mut current = vec![start];
+        while distance(end, start) > tolerance {
+            let next = segments
+                .iter()
+                .enumerate()
+                .map(|(i, segment)| (i, distance(segment.0, end)))
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            let Some((i, gap)) = next else {
+                break;
+            };
+            if gap > tolerance * 1e3 {
+                log::warn!("open boundary loop, gap of {gap}");
+                break;
+            }
+            current.push(end);
+            end = segments.swap_remove(i).1;
+        }
+        if current.len() >= 3 {
+            loops.push(remove_collinear(current, tolerance, cell));
+        }
+    }
+    loops
+}
+
+fn remove_collinear(polygon: Vec<Point>, tolerance: f64, cell: &[Point]) -> Vec<Point> {
+    let n = polygon.len();
+    (0..n)
+        .filter(|&i| {
+            let (a, b, c) = (polygon[(i + n - 1) % n], polygon[i], polygon[(i + 1) % n]);
+            cross(a, b, c).abs() > tolerance * distance(a, c)
+                || cell.iter().any(|&v| distance(v, b) <= tolerance)
+        })
+        .map(|i| polygon[i])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn l_shape() -> PolygonDomain {
+        // Clockwise on purpose, the notch at the top right is outside
+        PolygonDomain::new(vec![
+            (0.0, 0.0),
+            (0.0, 4.0),
+            (2.0, 4.0),
+            (2.0, 2.0),
+            (4.0, 2.0),
+            (4.0, 0.0),
+        ])
+        .unwrap()
+    }
+
+    fn area(clipped: &ClippedCell) -> f64 {
+        clipped.loops.iter().map(|l| signed_area(l)).sum()
+    }
+
+    #[test]
+    fn test_clip_cells() {
+        let domain = l_shape();
+        let cells = vec![
+            // Inside, away from the boundary
+            vec![(0.5, 0.5), (1.5, 0.5), (1.5, 1.5), (0.5, 1.5)],
+            // Across the notch corner
+            vec![(1.0, 1.0), (3.0, 1.0), (3.0, 3.0), (1.0, 3.0)],
+            // In the notch
+            vec![(3.0, 3.0), (4.0, 3.0), (4.0, 4.0), (3.0, 4.0)],
+            // Along the outer boundary
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+        ];
+
+        let clipped = domain.clip_cells(&cells);
+
+        assert_eq!(clipped[0].loops.len(), 1);
+        assert!(!clipped[0].on_boundary);
+        assert!((area(&clipped[0]) - 1.0).abs() < 1e-9);
+
+        assert!(clipped[1].on_boundary);
+        assert_eq!(clipped[1].loops[0].len(), 6);
+        assert!((area(&clipped[1]) - 3.0).abs() < 1e-9);
+
+        assert!(clipped[2].loops.is_empty());
+
+        assert!(clipped[3].on_boundary);
+        assert_eq!(clipped[3].loops[0].len(), 4);
+        assert!((area(&clipped[3]) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_split_cell() {
+        // A U-shaped domain cuts a cell spanning its opening into two pieces
+        let domain = PolygonDomain::new(vec![
+            (0.0, 0.0),
+            (3.0, 0.0),
+            (3.0, 3.0),
+            (2.0, 3.0),
+            (2.0, 1.0),
+            (1.0, 1.0),
+            (1.0, 3.0),
+            (0.0, 3.0),
+        ])
+        .unwrap();
+
+        let clipped = domain.clip_cells(&[vec![(0.0, 2.0), (3.0, 2.0), (3.0, 3.0), (0.0, 3.0)]]);
+
+        assert_eq!(clipped[0].loops.len(), 2);
+        assert!((area(&clipped[0]) - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_holes() {
+        let plate = PolygonDomain::new(vec![(0.0, 0.0), (6.0, 0.0), (6.0, 6.0), (0.0, 6.0)]);
+        let plate = plate
+            .unwrap()
+            .with_hole(vec![(1.0, 1.0), (2.0, 1.0), (2.0, 2.0), (1.0, 2.0)])
+            .unwrap()
+            .with_hole(circle_polygon((4.5, 4.5), 1.0, 32))
+            .unwrap();
+        let cells = vec![
+            vec![(0.0, 0.0), (3.0, 0.0), (3.0, 3.0), (0.0, 3.0)],
+            vec![(3.0, 4.5), (6.0, 4.5), (6.0, 6.0), (3.0, 6.0)],
+        ];
+
+        let clipped = plate.clip_cells(&cells);
+
+        // The square hole is a clockwise loop inside the first cell
+        assert_eq!(clipped[0].loops.len(), 2);
+        let mut areas: Vec<f64> = clipped[0].loops.iter().map(|l| signed_area(l)).collect();
+        areas.sort_by(f64::total_cmp);
+        assert!((areas[0] + 1.0).abs() < 1e-9 && (areas[1] - 9.0).abs() < 1e-9);
+        assert!(clipped[0].on_boundary);
+
+        // The second cell cuts the circle through its center, keeping a notch of half of it
+        assert_eq!(clipped[1].loops.len(), 1);
+        let half_circle = signed_area(&circle_polygon((4.5, 4.5), 1.0, 32)) / 2.0;
+        assert!((area(&clipped[1]) - (4.5 - half_circle)).abs() < 1e-9);
+
+        assert!(!plate.contains(1.5, 1.5));
+        assert!(plate.with_hole(circle_polygon((6.0, 3.0), 1.0, 8)).is_err());
+    }
+
+    #[test]
+    fn test_mask_labels() {
+        let domain = l_shape();
+        let mut labels = vec![1; 16];
+
+        domain.mask_labels(&mut labels, (4, 4), (4.0, 4.0));
+
+        assert_eq!(labels.iter().filter(|&&l| l == 0).count(), 4);
+        assert_eq!(labels[3 + 3 * 4], 0);
+        assert!(PolygonDomain::new(vec![(0.0, 0.0), (1.0, 1.0)]).is_err());
+    }
+}
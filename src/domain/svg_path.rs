@@ -0,0 +1,435 @@
+// Domains drawn in a vector editor: the `d` attribute of an SVG path, with its lines, Bézier
+// curves and elliptical arcs, is flattened into polygons within a distance tolerance, one per
+// subpath. Every subpath is closed, whether or not it ends with `Z`.
+//
+// Curves are split into segments of equal parameter steps, as many as the bound on their second
+// derivative needs for the chords to stay within the tolerance, and arcs into segments of equal
+// angles, from their center parameterization as the SVG specification converts it.
+
+use std::f64::consts::TAU;
+
+use super::polygon::{signed_area, Point, PolygonDomain};
+
+impl PolygonDomain {
+    /// Domain bounded by the subpaths of the SVG path data `d`, flattened within `tolerance`.
+    /// The subpath of the largest area is the outer boundary and the others are holes, in
+    /// either orientation. Coordinates are kept as they are, so a path drawn in a document
+    /// whose y axis points down comes out mirrored.
+    pub fn from_svg_path(d: &str, tolerance: f64) -> Result<PolygonDomain, &'static str> {
+        let mut loops = flatten_svg_path(d, tolerance)?;
+        let outer = (0..loops.len())
+            .max_by(|&a, &b| {
+                signed_area(&loops[a])
+                    .abs()
+                    .total_cmp(&signed_area(&loops[b]).abs())
+            })
+            .ok_or("the path has no subpath")?;
+        let mut domain = PolygonDomain::new(loops.remove(outer))?;
+        for hole in loops {
+            domain = domain.with_hole(hole)?;
+        }
+        Ok(domain)
+    }
+}
+
+/// Polygons of the subpaths of the SVG path data `d`, whose curves are within `tolerance` of
+/// their chords.
+pub fn flatten_svg_path(d: &str, tolerance: f64) -> Result<Vec<Vec<Point>>, &'static str> {
+    if !(tolerance > 0.0 && tolerance.is_finite()) {
+        return Err("the tolerance must be positive");
+    }
+    let mut tokens = Tokens {
+        chars: d.as_bytes(),
+        position: 0,
+    };
+    let mut loops = Vec::new();
+    let mut path: Vec<Point> = Vec::new();
+    let mut current = (0.0, 0.0);
+    // Control point the next smooth curve reflects, None after anything but a curve of its kind
+    let mut cubic_control: Option<Point> = None;
+    let mut quadratic_control: Option<Point> = None;
+    let mut command = None;
+
+    while let Some(token) = tokens.command_or_number()? {
+        let letter = match token {
+            Token::Command(letter) => letter,
+            // Numbers after a command repeat it, moves repeating as lines
+            Token::Number => match command {
+                Some(b'M') => b'L',
+                Some(b'm') => b'l',
+                Some(letter) if !matches!(letter, b'Z' | b'z') => letter,
+                Some(_) => return Err("expected a command in the path data"),
+                None => return Err("the path data must start with a move"),
+            },
+        };
+        if command.is_none() && !matches!(letter, b'M' | b'm') {
+            return Err("the path data must start with a move");
+        }
+        command = Some(letter);
+        let relative = letter.is_ascii_lowercase();
+        let offset = |(x, y): Point| {
+            if relative {
+                (current.0 + x, current.1 + y)
+            } else {
+                (x, y)
+            }
+        };
+
+        let (mut next_cubic, mut next_quadratic) = (None, None);
+        match letter.to_ascii_uppercase() {
+            b'M' => {
+                close(&mut loops, &mut path)?;
+                current = offset(tokens.pair()?);
+                path.push(current);
+            }
+            b'Z' => {
+                if let Some(&start) = path.first() {
+                    current = start;
+                }
+                close(&mut loops, &mut path)?;
+                path.push(current);
+            }
+            b'L' => {
+                current = offset(tokens.pair()?);
+                path.push(current);
+            }
+            b'H' => {
+                let x = tokens.number()?;
+                current.0 = if relative { current.0 + x } else { x };
+                path.push(current);
+            }
+            b'V' => {
+                let y = tokens.number()?;
+                current.1 = if relative { current.1 + y } else { y };
+                path.push(current);
+            }
+            b'C' | b'S' => {
+                let first = match letter.to_ascii_uppercase() {
+                    b'C' => offset(tokens.pair()?),
+                    _ => reflect(cubic_control, current),
+                };
+                let second = offset(tokens.pair()?);
+                let end = offset(tokens.pair()?);
+                cubic(&mut path, [current, first, second, end], tolerance);
+                next_cubic = Some(second);
+                current = end;
+            }
+            b'Q' | b'T' => {
+                let control = match letter.to_ascii_uppercase() {
+                    b'Q' => offset(tokens.pair()?),
+                    _ => reflect(quadratic_control, current),
+                };
+                let end = offset(tokens.pair()?);
+                quadratic(&mut path, [current, control, end], tolerance);
+                next_quadratic = Some(control);
+                current = end;
+            }
+            b'A' => {
+                let radii = (tokens.number()?.abs(), tokens.number()?.abs());
+                let rotation = tokens.number()?.to_radians();
+                let large = tokens.flag()?;
+                let sweep = tokens.flag()?;
+                let end = offset(tokens.pair()?);
+                arc(
+                    &mut path,
+                    current,
+                    end,
+                    radii,
+                    rotation,
+                    (large, sweep),
+                    tolerance,
+                );
+                current = end;
+            }
+            _ => return Err("unknown path command"),
+        }
+        (cubic_control, quadratic_control) = (next_cubic, next_quadratic);
+    }
+    close(&mut loops, &mut path)?;
+    Ok(loops)
+}
+
+// Ends the subpath in `path`, keeping it unless it is a lone move
+fn close(loops: &mut Vec<Vec<Point>>, path: &mut Vec<Point>) -> Result<(), &'static str> {
+    if path.len() > 1 && path.first() == path.last() {
+        path.pop();
+    }
+    match path.len() {
+        0 | 1 => {}
+        2 => return Err("a subpath must have at least 3 vertices"),
+        _ => loops.push(std::mem::take(path)),
+    }
+    path.clear();
+    Ok(())
+}
+
+// Reflection of the previous control point about the current point, or the current point
+fn reflect(control: Option<Point>, current: Point) -> Point {
+    control.map_or(current, |(x, y)| (2.0 * current.0 - x, 2.0 * current.1 - y))
+}
+
+// Segments of an equal parameter step whose chords are within `tolerance` of a curve whose
+// second derivative is at most `bound`
+fn segment_count(bound: f64, tolerance: f64) -> usize {
+    ((bound / (8.0 * tolerance)).sqrt().ceil() as usize).max(1)
+}
+
+fn norm((x, y): Point) -> f64 {
+    x.hypot(y)
+}
+
+// Appends the points of the cubic Bézier curve after its start
+fn cubic(path: &mut Vec<Point>, [p0, p1, p2, p3]: [Point; 4], tolerance: f64) {
+    let second = |a: Point, b: Point, c: Point| (a.0 - 2.0 * b.0 + c.0, a.1 - 2.0 * b.1 + c.1);
+    let bound = 6.0 * norm(second(p0, p1, p2)).max(norm(second(p1, p2, p3)));
+    let n = segment_count(bound, tolerance);
+    path.extend((1..=n).map(|i| {
+        let t = i as f64 / n as f64;
+        let s = 1.0 - t;
+        let (a, b, c, d) = (s * s * s, 3.0 * s * s * t, 3.0 * s * t * t, t * t * t);
+        (
+            a * p0.0 + b * p1.0 + c * p2.0 + d * p3.0,
+            a * p0.1 + b * p1.1 + c * p2.1 + d * p3.1,
+        )
+    }));
+}
+
+// Appends the points of the quadratic Bézier curve after its start
+fn quadratic(path: &mut Vec<Point>, [p0, p1, p2]: [Point; 3], tolerance: f64) {
+    let bound = 2.0 * norm((p0.0 - 2.0 * p1.0 + p2.0, p0.1 - 2.0 * p1.1 + p2.1));
+    let n = segment_count(bound, tolerance);
+    path.extend((1..=n).map(|i| {
+        let t = i as f64 / n as f64;
+        let s = 1.0 - t;
+        let (a, b, c) = (s * s, 2.0 * s * t, t * t);
+        (
+            a * p0.0 + b * p1.0 + c * p2.0,
+            a * p0.1 + b * p1.1 + c * p2.1,
+        )
+    }));
+}
+
+// Appends the points of the elliptical arc from `start` to `end` after its start, as the SVG
+// specification's implementation notes parameterize it
+fn arc(
+    path: &mut Vec<Point>,
+    start: Point,
+    end: Point,
+    (mut rx, mut ry): (f64, f64),
+    rotation: f64,
+    (large, sweep): (bool, bool),
+    tolerance: f64,
+) {
+    if start == end {
+        return;
+    }
+    if rx == 0.0 || ry == 0.0 {
+        path.push(end);
+        return;
+    }
+    let (sin, cos) = rotation.sin_cos();
+    let (hx, hy) = ((start.0 - end.0) / 2.0, (start.1 - end.1) / 2.0);
+    let (x1, y1) = (cos * hx + sin * hy, -sin * hx + cos * hy);
+    // Radii too small to reach the end are scaled up
+    let lambda = (x1 / rx).powi(2) + (y1 / ry).powi(2);
+    if lambda > 1.0 {
+        (rx, ry) = (rx * lambda.sqrt(), ry * lambda.sqrt());
+    }
+    let numerator = (rx * ry).powi(2) - (rx * y1).powi(2) - (ry * x1).powi(2);
+    let denominator = (rx * y1).powi(2) + (ry * x1).powi(2);
+    let mut coefficient = (numerator / denominator).max(0.0).sqrt();
+    if large == sweep {
+        coefficient = -coefficient;
+    }
+    let (cx1, cy1) = (coefficient * rx * y1 / ry, -coefficient * ry * x1 / rx);
+    let center = (
+        cos * cx1 - sin * cy1 + (start.0 + end.0) / 2.0,
+        sin * cx1 + cos * cy1 + (start.1 + end.1) / 2.0,
+    );
+    let theta = ((y1 - cy1) / ry).atan2((x1 - cx1) / rx);
+    let mut delta = ((-y1 - cy1) / ry).atan2((-x1 - cx1) / rx) - theta;
+    if sweep && delta < 0.0 {
+        delta += TAU;
+    } else if !sweep && delta > 0.0 {
+        delta -= TAU;
+    }
+
+    // Chords of the larger radius within the tolerance
+    let radius = rx.max(ry);
+    let step = 2.0 * (1.0 - (tolerance / radius).min(1.0)).acos();
+    let n = ((delta.abs() / step).ceil() as usize).max(1);
+    path.extend((1..n).map(|i| {
+        let angle = theta + delta * i as f64 / n as f64;
+        let (x, y) = (rx * angle.cos(), ry * angle.sin());
+        (center.0 + cos * x - sin * y, center.1 + sin * x + cos * y)
+    }));
+    path.push(end);
+}
+
+enum Token {
+    Command(u8),
+    /// A number starts next
+    Number,
+}
+
+struct Tokens<'a> {
+    chars: &'a [u8],
+    position: usize,
+}
+
+impl Tokens<'_> {
+    fn skip_separators(&mut self) {
+        while self
+            .chars
+            .get(self.position)
+            .is_some_and(|&c| c.is_ascii_whitespace() || c == b',')
+        {
+            self.position += 1;
+        }
+    }
+
+    // Next command letter, consumed, or whether a number follows, None at the end
+    fn command_or_number(&mut self) -> Result<Option<Token>, &'static str> {
+        self.skip_separators();
+        match self.chars.get(self.position) {
+            None => Ok(None),
+            Some(&c) if c.is_ascii_alphabetic() => {
+                self.position += 1;
+                Ok(Some(Token::Command(c)))
+            }
+            Some(&c) if c.is_ascii_digit() || matches!(c, b'+' | b'-' | b'.') => {
+                Ok(Some(Token::Number))
+            }
+            Some(_) => Err("invalid character in the path data"),
+        }
+    }
+
+    // Longest number at the position, `1.5.5` being 1.5 then .5 as SVG reads it
+    fn number(&mut self) -> Result<f64, &'static str> {
+        self.skip_separators();
+        let start = self.position;
+        let at = |i: usize| self.chars.get(i).copied();
+        let mut end = start;
+        if matches!(at(end), Some(b'+' | b'-')) {
+            end += 1;
+        }
+        let digits = |mut i: usize| {
+            while at(i).is_some_and(|c| c.is_ascii_digit()) {
+                i += 1;
+            }
+            i
+        };
+        end = digits(end);
+        if at(end) == Some(b'.') {
+            end = digits(end + 1);
+        }
+        if matches!(at(end), Some(b'e' | b'E')) {
+            let mut exponent = end + 1;
+            if matches!(at(exponent), Some(b'+' | b'-')) {
+                exponent += 1;
+            }
+            if at(exponent).is_some_and(|c| c.is_ascii_digit()) {
+                end = digits(exponent);
+            }
+        }
+        let number = std::str::from_utf8(&self.chars[start..end])
+            .ok()
+            .and_then(|text| text.parse().ok())
+            .ok_or("expected a number in the path data")?;
+        self.position = end;
+        Ok(number)
+    }
+
+    fn pair(&mut self) -> Result<Point, &'static str> {
+        Ok((self.number()?, self.number()?))
+    }
+
+    // Arc flags are single digits, which needn't be separated from what follows
+    fn flag(&mut self) -> Result<bool, &'static str> {
+        self.skip_separators();
+        let flag = match self.chars.get(self.position) {
+            Some(b'0') => false,
+            Some(b'1') => true,
+            _ => return Err("expected an arc flag in the path data"),
+        };
+        self.position += 1;
+        Ok(flag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_and_holes() {
+        let domain =
+            PolygonDomain::from_svg_path("M1 1 h1 v1 h-1 z M0,0 H4 V4 H0 Z", 0.01).unwrap();
+
+        assert_eq!(
+            domain.outer,
+            [(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0)]
+        );
+        assert_eq!(domain.holes.len(), 1);
+        assert_eq!(signed_area(&domain.holes[0]), -1.0);
+        assert!(!domain.contains(1.5, 1.5));
+        assert!(domain.contains(3.0, 3.0));
+    }
+
+    #[test]
+    fn test_numbers() {
+        // Implicit lines after a move, numbers running into each other and exponents
+        let loops = flatten_svg_path("m0,0 1.5.5-1e1,2L-.5-1e-1", 0.1).unwrap();
+
+        assert_eq!(
+            loops,
+            [vec![(0.0, 0.0), (1.5, 0.5), (-8.5, 2.5), (-0.5, -0.1)]]
+        );
+        assert!(flatten_svg_path("L1 1 2 2", 0.1).is_err());
+        assert!(flatten_svg_path("M0 0 L1 x", 0.1).is_err());
+        assert!(flatten_svg_path("M0 0 L1 1 2 2", 0.0).is_err());
+        assert!(flatten_svg_path("M0 0 L1 1", 0.1).is_err());
+    }
+
+    #[test]
+    fn test_arcs() {
+        // Unit circle from two half arcs, flags without separators
+        let tolerance = 1e-3;
+        let loops = flatten_svg_path("M0 1A1 1 0 0 1 0-1a1,1 0 01 0,2z", tolerance).unwrap();
+
+        assert_eq!(loops.len(), 1);
+        let circle = &loops[0];
+        assert!(circle.iter().all(|&p| (norm(p) - 1.0).abs() < 1e-9));
+        // Counter-clockwise, and chords within the tolerance of the circle
+        let area = signed_area(circle);
+        assert!(area > 0.0 && area < std::f64::consts::PI);
+        assert!(std::f64::consts::PI - area < 2.0 * std::f64::consts::PI * tolerance);
+        for (i, &a) in circle.iter().enumerate() {
+            let b = circle[(i + 1) % circle.len()];
+            let middle = ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+            assert!(1.0 - norm(middle) <= tolerance + 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_curves() {
+        // Quarter circles approximated by a cubic and its smooth continuation, then by a quadratic
+        // and its smooth continuation
+        let tolerance = 1e-2;
+        let d = "M1 0 C1 0.55 0.55 1 0 1 S-1 0.55 -1 0 Q-1 -1 0 -1 T1 0 Z";
+        let loops = flatten_svg_path(d, tolerance).unwrap();
+
+        let path = &loops[0];
+        assert_eq!(path[0], (1.0, 0.0));
+        assert!(path.contains(&(0.0, 1.0)));
+        assert!(path.contains(&(-1.0, 0.0)));
+        assert!(path.contains(&(0.0, -1.0)));
+        // The smooth cubic mirrors the first one, and the smooth quadratic the first quadratic
+        let mirrored = path.iter().filter(|&&(x, y)| x < 0.0 && y > 0.0).count();
+        let first = path.iter().filter(|&&(x, y)| x > 0.0 && y > 0.0).count();
+        assert_eq!(mirrored, first);
+        let third = path.iter().filter(|&&(x, y)| x < 0.0 && y < 0.0).count();
+        let fourth = path.iter().filter(|&&(x, y)| x > 0.0 && y < 0.0).count();
+        assert_eq!(third, fourth);
+    }
+}
@@ -0,0 +1,69 @@
+use std::fmt;
+
+/// Errors of a meshing run, so that callers, e.g. on headless machines without a GPU, can
+/// recover instead of panicking.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MesherError {
+    /// No GPU adapter is available
+    NoAdapter,
+    /// The adapter refused to create a device
+    DeviceRequestFailed(String),
+    /// The device was lost, e.g. after a driver reset or a TDR
+    DeviceLost { reason: String, message: String },
+    /// A validation, out-of-memory or internal error not caught by an error scope
+    Uncaptured(String),
+    /// A buffer couldn't be mapped to read the results back
+    BufferMapFailed(String),
+    /// The run would allocate more GPU memory than the configured budget, in bytes
+    OverBudget { required: u64, budget: u64 },
+    /// The seeds or options can't be meshed
+    InvalidInput(&'static str),
+    /// There are no seeds to mesh
+    NoSeeds,
+    /// More seeds than labels fit in a `u32`
+    TooManySeeds { count: usize },
+    /// Seed `index` has a coordinate outside the domain box, or one which isn't finite
+    SeedOutsideDomain { index: usize },
+    /// Seeds `first` and `second` fall on the same texel, the first one getting no cell
+    CoincidentSeeds { first: usize, second: usize },
+    /// The run's cancellation token was set, after `completed` of its `total` steps
+    Cancelled { completed: usize, total: usize },
+}
+
+impl fmt::Display for MesherError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MesherError::NoAdapter => write!(f, "no GPU adapter found"),
+            MesherError::DeviceRequestFailed(message) => {
+                write!(f, "GPU device request failed: {message}")
+            }
+            MesherError::DeviceLost { reason, message } => {
+                write!(f, "GPU device lost ({reason}): {message}")
+            }
+            MesherError::Uncaptured(message) => write!(f, "GPU error: {message}"),
+            MesherError::BufferMapFailed(message) => {
+                write!(f, "GPU buffer mapping failed: {message}")
+            }
+            MesherError::OverBudget { required, budget } => write!(
+                f,
+                "GPU run needs {required} B, over the memory budget of {budget} B"
+            ),
+            MesherError::InvalidInput(message) => write!(f, "invalid input: {message}"),
+            MesherError::NoSeeds => write!(f, "at least one seed is needed"),
+            MesherError::TooManySeeds { count } => {
+                write!(f, "{count} seeds, more than fit in the u32 labels")
+            }
+            MesherError::SeedOutsideDomain { index } => {
+                write!(f, "seed {index} is outside the domain")
+            }
+            MesherError::CoincidentSeeds { first, second } => {
+                write!(f, "seeds {first} and {second} fall on the same texel")
+            }
+            MesherError::Cancelled { completed, total } => {
+                write!(f, "run cancelled after {completed} of {total} steps")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MesherError {}
@@ -0,0 +1,369 @@
+// Exact Voronoi cells by half-plane clipping, O(n²) so only meant for small inputs and to
+// cross-check the raster results of the JFA.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::config::{BoundaryMode, DistanceMetric, MesherConfig, Metric};
+use crate::error::MesherError;
+use crate::reference::{self, MismatchReport};
+
+const EPSILON: f64 = 1e-9;
+
+/// Exact Voronoi cell of a seed, clipped to the domain box.
+#[derive(Debug, Clone)]
+pub struct ExactCell {
+    /// Counter-clockwise vertex loop
+    pub polygon: Vec<(f64, f64)>,
+    pub area: f64,
+    /// Adjacent seed indices with the length of the shared edge
+    pub neighbors: Vec<(usize, f64)>,
+}
+
+pub fn voronoi_cells(points: &[(f64, f64)], config: (f64, f64)) -> Vec<ExactCell> {
+    points
+        .iter()
+        .enumerate()
+        .map(|(i, &seed)| {
+            // Each vertex carries the seed whose bisector supports its outgoing edge,
+            // `None` for the domain boundary
+            let mut polygon: Vec<((f64, f64), Option<usize>)> = vec![
+                ((0.0, 0.0), None),
+                ((config.0, 0.0), None),
+                ((config.0, config.1), None),
+                ((0.0, config.1), None),
+            ];
+            for (j, &other) in points.iter().enumerate() {
+                if i != j && other != seed {
+                    polygon = clip(&polygon, seed, other, j);
+                }
+            }
+            build_cell(&polygon)
+        })
+        .collect()
+}
+
+/// Discrepancies between a JFA label grid and the exact diagram of the same seeds.
+#[derive(Debug, Clone)]
+pub struct CrossCheckReport {
+    /// Per seed: exact area, JFA area
+    pub areas: Vec<(f64, f64)>,
+    /// Seeds whose relative area error exceeds the tolerance
+    pub area_mismatches: Vec<usize>,
+    /// Texels not labeled with their nearest seed, from [`crate::reference::validate`]
+    pub texels: MismatchReport,
+    /// Exact adjacencies, with a shared edge longer than a pixel, missing from the JFA grid
+    pub missing_adjacencies: Vec<(usize, usize)>,
+    /// Adjacencies in the JFA grid that don't exist in the exact diagram
+    pub spurious_adjacencies: Vec<(usize, usize)>,
+}
+
+impl CrossCheckReport {
+    pub fn is_consistent(&self) -> bool {
+        self.area_mismatches.is_empty()
+            && self.missing_adjacencies.is_empty()
+            && self.spurious_adjacencies.is_empty()
+    }
+
+    pub fn max_relative_area_error(&self) -> f64 {
+        self.areas
+            .iter()
+            .map(|&(exact, jfa)| relative_error(exact, jfa))
+            .fold(0.0, f64::max)
+    }
+}
+
+impl fmt::Display for CrossCheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Max relative area error: {:.2}%",
+            self.max_relative_area_error() * 100.0
+        )?;
+        writeln!(f, "Cells with mismatching area: {:?}", self.area_mismatches)?;
+        writeln!(f, "{}", self.texels)?;
+        writeln!(f, "Missing adjacencies: {:?}", self.missing_adjacencies)?;
+        write!(f, "Spurious adjacencies: {:?}", self.spurious_adjacencies)
+    }
+}
+
+/// Compares the cell areas and adjacency of a JFA label grid, on the grid `options` describes
+/// over the `config` box, against the exact diagram, which is bounded and Euclidean.
+pub fn cross_check(
+    labels: &[usize],
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    options: &MesherConfig,
+    area_tolerance: f64,
+) -> Result<CrossCheckReport, MesherError> {
+    if options.boundary != BoundaryMode::Bounded
+        || options.distance != DistanceMetric::Euclidean
+        || options.metric != Metric::default()
+    {
+        return Err(MesherError::InvalidInput(
+            "the exact diagram is only for bounded Euclidean grids",
+        ));
+    }
+    let texels = reference::validate(labels, points, config, options)?;
+    let (width, height) = options.grid_dimensions(config);
+    let pixel_size = (config.0 / width as f64, config.1 / height as f64);
+    let pixel_area = pixel_size.0 * pixel_size.1;
+    let pixel_diagonal = pixel_size.0.hypot(pixel_size.1);
+
+    let mut counts = vec![0usize; points.len()];
+    let mut jfa_adjacency = BTreeSet::new();
+    for y in 0..height {
+        for x in 0..width {
+            let label = labels[x + y * width];
+            if label == 0 {
+                continue;
+            }
+            counts[label - 1] += 1;
+
+            for (nx, ny) in [(x + 1, y), (x, y + 1)] {
+                if nx < width && ny < height {
+                    let other = labels[nx + ny * width];
+                    if other != 0 && other != label {
+                        jfa_adjacency.insert(ordered(label - 1, other - 1));
+                    }
+                }
+            }
+        }
+    }
+
+    let cells = voronoi_cells(points, config);
+    let mut exact_adjacency = BTreeSet::new();
+    let mut missing_adjacencies = Vec::new();
+    for (i, cell) in cells.iter().enumerate() {
+        for &(j, length) in &cell.neighbors {
+            let pair = ordered(i, j);
+            if i < j && length > pixel_diagonal && !jfa_adjacency.contains(&pair) {
+                missing_adjacencies.push(pair);
+            }
+            exact_adjacency.insert(pair);
+        }
+    }
+    let spurious_adjacencies = jfa_adjacency
+        .difference(&exact_adjacency)
+        .copied()
+        .collect();
+
+    let areas: Vec<(f64, f64)> = cells
+        .iter()
+        .zip(&counts)
+        .map(|(cell, &count)| (cell.area, count as f64 * pixel_area))
+        .collect();
+    let area_mismatches = areas
+        .iter()
+        .enumerate()
+        .filter(|(_, &(exact, jfa))| relative_error(exact, jfa) > area_tolerance)
+        .map(|(i, _)| i)
+        .collect();
+
+    Ok(CrossCheckReport {
+        areas,
+        area_mismatches,
+        texels,
+        missing_adjacencies,
+        spurious_adjacencies,
+    })
+}
+
+// Sutherland-Hodgman step keeping the half-plane closer to `seed` than to `other`
+fn clip(
+    polygon: &[((f64, f64), Option<usize>)],
+    seed: (f64, f64),
+    other: (f64, f64),
+    other_index: usize,
+) -> Vec<((f64, f64), Option<usize>)> {
+    // p is kept when a·p <= b
+    let a = (other.0 - seed.0, other.1 - seed.1);
+    let b = (squared_norm(other) - squared_norm(seed)) / 2.0;
+    let side = |p: (f64, f64)| a.0 * p.0 + a.1 * p.1 - b;
+
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+    for k in 0..polygon.len() {
+        let (current, tag) = polygon[k];
+        let (next, _) = polygon[(k + 1) % polygon.len()];
+        let (side_current, side_next) = (side(current), side(next));
+        let current_in = side_current <= 0.0;
+        let next_in = side_next <= 0.0;
+
+        if current_in {
+            clipped.push((current, tag));
+        }
+        if current_in != next_in {
+            let t = side_current / (side_current - side_next);
+            let intersection = (
+                current.0 + t * (next.0 - current.0),
+                current.1 + t * (next.1 - current.1),
+            );
+            // Leaving the half-plane, the new edge follows the bisector
+            clipped.push((
+                intersection,
+                if current_in { Some(other_index) } else { tag },
+            ));
+        }
+    }
+    clipped
+}
+
+fn build_cell(polygon: &[((f64, f64), Option<usize>)]) -> ExactCell {
+    // Drop zero-length edges, the merged vertex keeps the tag of the edge that follows it
+    let mut vertices: Vec<((f64, f64), Option<usize>)> = Vec::with_capacity(polygon.len());
+    for &(point, tag) in polygon {
+        match vertices.last_mut() {
+            Some(last) if squared_distance(last.0, point) < EPSILON * EPSILON => last.1 = tag,
+            _ => vertices.push((point, tag)),
+        }
+    }
+    while vertices.len() > 1
+        && squared_distance(vertices[0].0, vertices[vertices.len() - 1].0) < EPSILON * EPSILON
+    {
+        vertices.pop();
+    }
+
+    let mut area = 0.0;
+    let mut neighbors: Vec<(usize, f64)> = Vec::new();
+    for k in 0..vertices.len() {
+        let (current, tag) = vertices[k];
+        let (next, _) = vertices[(k + 1) % vertices.len()];
+        area += current.0 * next.1 - next.0 * current.1;
+
+        if let Some(j) = tag {
+            let length = squared_distance(current, next).sqrt();
+            match neighbors.iter_mut().find(|(n, _)| *n == j) {
+                Some(neighbor) => neighbor.1 += length,
+                None => neighbors.push((j, length)),
+            }
+        }
+    }
+
+    ExactCell {
+        polygon: vertices.into_iter().map(|(point, _)| point).collect(),
+        area: area.abs() / 2.0,
+        neighbors,
+    }
+}
+
+fn ordered(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+fn relative_error(exact: f64, approximation: f64) -> f64 {
+    if exact > 0.0 {
+        (approximation - exact).abs() / exact
+    } else {
+        approximation.abs()
+    }
+}
+
+fn squared_norm(p: (f64, f64)) -> f64 {
+    p.0 * p.0 + p.1 * p.1
+}
+
+fn squared_distance(pa: (f64, f64), pb: (f64, f64)) -> f64 {
+    let dx = pa.0 - pb.0;
+    let dy = pa.1 - pb.1;
+    dx * dx + dy * dy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GridShape;
+
+    #[test]
+    fn test_two_seeds_split_the_box() {
+        let cells = voronoi_cells(&[(1.0, 1.0), (3.0, 1.0)], (4.0, 2.0));
+
+        assert_eq!(cells.len(), 2);
+        for cell in &cells {
+            assert!((cell.area - 4.0).abs() < 1e-12);
+            assert_eq!(cell.polygon.len(), 4);
+        }
+        assert_eq!(cells[0].neighbors.len(), 1);
+        assert_eq!(cells[0].neighbors[0].0, 1);
+        assert!((cells[0].neighbors[0].1 - 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_areas_cover_the_box() {
+        let points = [(1.0, 1.0), (8.0, 2.0), (4.0, 7.0), (6.5, 5.5), (2.0, 9.0)];
+        let cells = voronoi_cells(&points, (10.0, 10.0));
+
+        let total: f64 = cells.iter().map(|cell| cell.area).sum();
+        assert!((total - 100.0).abs() < 1e-9);
+
+        // Adjacency is symmetric
+        for (i, cell) in cells.iter().enumerate() {
+            for &(j, _) in &cell.neighbors {
+                assert!(cells[j].neighbors.iter().any(|&(n, _)| n == i));
+            }
+        }
+    }
+
+    #[test]
+    fn test_cross_check_exact_labels() {
+        let points = [(2.5, 2.5), (7.5, 2.5), (2.5, 7.5), (7.5, 7.5)];
+        let config = (10.0, 10.0);
+
+        let options = MesherConfig {
+            resolution: 64,
+            ..Default::default()
+        };
+        let labels = reference::reference_labels(&points, config, &options);
+        let report = cross_check(&labels, &points, config, &options, 0.01).unwrap();
+
+        assert!(report.is_consistent(), "{report}");
+        assert!(report.texels.is_exact());
+        assert!(report.max_relative_area_error() < 1e-12);
+
+        // A grid fitted to a box which isn't square
+        let points = [(2.5, 2.5), (7.5, 2.5)];
+        let options = MesherConfig {
+            grid: GridShape::Texels {
+                width: 64,
+                height: 32,
+            },
+            ..Default::default()
+        };
+        let labels = reference::reference_labels(&points, (10.0, 5.0), &options);
+        let report = cross_check(&labels, &points, (10.0, 5.0), &options, 0.01).unwrap();
+
+        assert!(report.is_consistent(), "{report}");
+        assert!(report.max_relative_area_error() < 1e-12);
+    }
+
+    #[test]
+    fn test_cross_check_reports_mismatch() {
+        let points = [(2.5, 5.0), (7.5, 5.0)];
+        let config = (10.0, 10.0);
+
+        let options = MesherConfig {
+            resolution: 4,
+            ..Default::default()
+        };
+
+        // Every pixel given to the first seed
+        let report = cross_check(&[1; 16], &points, config, &options, 0.01).unwrap();
+
+        assert_eq!(report.area_mismatches, vec![0, 1]);
+        assert_eq!(report.missing_adjacencies, vec![(0, 1)]);
+        assert!(report.spurious_adjacencies.is_empty());
+        assert_eq!(report.texels.mismatched.len(), 8);
+
+        assert_eq!(
+            cross_check(&[1; 15], &points, config, &options, 0.01).unwrap_err(),
+            MesherError::InvalidInput("labels must have one entry per texel of the grid")
+        );
+        let periodic = MesherConfig {
+            boundary: BoundaryMode::Periodic { x: true, y: false },
+            ..options
+        };
+        assert_eq!(
+            cross_check(&[1; 16], &points, config, &periodic, 0.01).unwrap_err(),
+            MesherError::InvalidInput("the exact diagram is only for bounded Euclidean grids")
+        );
+    }
+}
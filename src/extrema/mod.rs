@@ -0,0 +1,154 @@
+// Watershed-marker style seeding: one seed per basin of a scalar raster, keeping only the
+// extrema which are prominent enough.
+
+/// Which extrema of the field become seeds.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Extremum {
+    Maxima,
+    Minima,
+}
+
+/// Places a seed at the center of every local extremum of `field` whose topographic prominence
+/// is above `prominence`, most prominent first.
+///
+/// `field` holds `resolution.0 * resolution.1` samples indexed with `x + y * resolution.0`,
+/// covering the `config` box.
+pub fn generate_points(
+    field: &[f64],
+    resolution: (usize, usize),
+    config: (f64, f64),
+    extremum: Extremum,
+    prominence: f64,
+) -> Vec<(f64, f64)> {
+    let (width, height) = resolution;
+    assert_eq!(field.len(), width * height, "field size mismatch");
+
+    let values: Vec<f64> = match extremum {
+        Extremum::Maxima => field.to_vec(),
+        Extremum::Minima => field.iter().map(|v| -v).collect(),
+    };
+
+    let mut prominences = peak_prominences(&values, width, height);
+    prominences.retain(|&(_, p)| p > prominence);
+    prominences.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    prominences
+        .into_iter()
+        .map(|(index, _)| {
+            (
+                ((index % width) as f64 + 0.5) * config.0 / width as f64,
+                ((index / width) as f64 + 0.5) * config.1 / height as f64,
+            )
+        })
+        .collect()
+}
+
+// Floods the field from the top with a union-find: when two components meet, the one with the
+// lower peak dies and its prominence is the height of its peak above the meeting level. The
+// root of a component is always its peak, since the higher root survives each merge
+fn peak_prominences(values: &[f64], width: usize, height: usize) -> Vec<(usize, f64)> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[b].total_cmp(&values[a]));
+
+    let mut parent: Vec<usize> = (0..values.len()).collect();
+    let mut flooded = vec![false; values.len()];
+    let mut prominences = Vec::new();
+
+    for &index in &order {
+        flooded[index] = true;
+        let (x, y) = ((index % width) as isize, (index / width) as isize);
+
+        for dx in [-1, 0, 1] {
+            for dy in [-1, 0, 1] {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || ny < 0 || nx >= width as isize || ny >= height as isize {
+                    continue;
+                }
+                let neighbor = nx as usize + ny as usize * width;
+                if !flooded[neighbor] {
+                    continue;
+                }
+
+                let root = find(&mut parent, index);
+                let other = find(&mut parent, neighbor);
+                if root == other {
+                    continue;
+                }
+
+                let (high, low) = if values[root] >= values[other] {
+                    (root, other)
+                } else {
+                    (other, root)
+                };
+                prominences.push((low, values[low] - values[index]));
+                parent[low] = high;
+            }
+        }
+    }
+
+    // The highest peak of each connected field only ends at the lowest value
+    if let Some(&lowest) = order.last() {
+        for index in 0..values.len() {
+            if find(&mut parent, index) == index {
+                prominences.push((index, values[index] - values[lowest]));
+            }
+        }
+    }
+
+    prominences
+}
+
+fn find(parent: &mut [usize], mut index: usize) -> usize {
+    while parent[index] != index {
+        parent[index] = parent[parent[index]];
+        index = parent[index];
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two bumps of heights 1 and 0.5 on a flat field
+    fn two_bumps(width: usize, height: usize) -> Vec<f64> {
+        let mut field = vec![0.0; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                let bump = |cx: f64, cy: f64, h: f64| {
+                    let d2 = (x as f64 - cx).powi(2) + (y as f64 - cy).powi(2);
+                    h * (-d2 / 8.0).exp()
+                };
+                field[x + y * width] = bump(5.0, 5.0, 1.0) + bump(14.0, 5.0, 0.5);
+            }
+        }
+        field
+    }
+
+    #[test]
+    fn test_maxima() {
+        let field = two_bumps(20, 10);
+
+        let points = generate_points(&field, (20, 10), (20.0, 10.0), Extremum::Maxima, 0.1);
+        assert_eq!(points, vec![(5.5, 5.5), (14.5, 5.5)]);
+
+        let points = generate_points(&field, (20, 10), (20.0, 10.0), Extremum::Maxima, 0.6);
+        assert_eq!(points, vec![(5.5, 5.5)]);
+    }
+
+    #[test]
+    fn test_minima() {
+        let field: Vec<f64> = two_bumps(20, 10).iter().map(|v| 1.0 - v).collect();
+
+        let points = generate_points(&field, (20, 10), (40.0, 20.0), Extremum::Minima, 0.1);
+        assert_eq!(points, vec![(11.0, 11.0), (29.0, 11.0)]);
+    }
+
+    #[test]
+    fn test_slope_has_one_basin() {
+        let field: Vec<f64> = (0..16).map(|i| (i % 4 + i / 4) as f64).collect();
+
+        let points = generate_points(&field, (4, 4), (4.0, 4.0), Extremum::Maxima, 0.0);
+        assert_eq!(points, vec![(3.5, 3.5)]);
+    }
+}
@@ -0,0 +1,150 @@
+// Extrusion of the cells into layers of prisms, the simplest 3D mesh of layered geometries such
+// as plates, batteries or pavements. The prisms are polyhedral cells described by their faces,
+// each with its owner and neighbour cells as finite-volume codes store them, and every boundary
+// face is tagged as a side, bottom or top face.
+
+use crate::mesh::PolyMesh;
+
+/// What a face of a [`PrismMesh`] lies on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaceTag {
+    /// Between two prisms
+    Interior,
+    /// On the extrusion of a boundary edge of the 2D mesh
+    Side,
+    /// At the bottom of the first layer, z = 0
+    Bottom,
+    /// At the top of the last layer
+    Top,
+}
+
+/// Prisms of the cells of a 2D mesh, layer by layer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrismMesh {
+    pub points: Vec<(f64, f64, f64)>,
+    /// Faces, oriented out of their owner
+    pub faces: Vec<Vec<usize>>,
+    pub owner: Vec<usize>,
+    /// Cell across each face, `None` on the boundary
+    pub neighbour: Vec<Option<usize>>,
+    pub tags: Vec<FaceTag>,
+    /// Seed and layer of each cell, cell `layer * faces + face` being the prism of `face`
+    pub cells: Vec<(usize, usize)>,
+}
+
+/// Prisms of the faces of `mesh` in layers of the given thicknesses, from z = 0 up.
+pub fn extrude(mesh: &PolyMesh, layers: &[f64]) -> PrismMesh {
+    assert!(!layers.is_empty(), "at least one layer is needed");
+    assert!(
+        layers.iter().all(|&thickness| thickness > 0.0),
+        "layers need a positive thickness"
+    );
+    let n = mesh.vertices.len();
+    let count = mesh.faces.len();
+    let mut levels = vec![0.0];
+    for thickness in layers {
+        levels.push(levels[levels.len() - 1] + thickness);
+    }
+    let points = levels
+        .iter()
+        .flat_map(|&z| mesh.vertices.iter().map(move |&(x, y)| (x, y, z)))
+        .collect();
+
+    let mut extruded = PrismMesh {
+        points,
+        faces: Vec::new(),
+        owner: Vec::new(),
+        neighbour: Vec::new(),
+        tags: Vec::new(),
+        cells: (0..layers.len())
+            .flat_map(|layer| mesh.faces.iter().map(move |face| (face.seed, layer)))
+            .collect(),
+    };
+    let mut push = |face: Vec<usize>, owner: usize, neighbour: Option<usize>, tag: FaceTag| {
+        extruded.faces.push(face);
+        extruded.owner.push(owner);
+        extruded.neighbour.push(neighbour);
+        extruded.tags.push(tag);
+    };
+    let polygon = |face: usize, level: usize| -> Vec<usize> {
+        mesh.face_vertices(face).map(|v| v + level * n).collect()
+    };
+    for layer in 0..layers.len() {
+        let cell = |face: usize| layer * count + face;
+        // The side quad of a counter-clockwise edge `a -> b` faces out of its cell, and the
+        // lower cell of two twins owns their face
+        for (h, half_edge) in mesh.half_edges.iter().enumerate() {
+            let (a, b) = (
+                half_edge.origin + layer * n,
+                mesh.destination(h) + layer * n,
+            );
+            let side = vec![a, b, b + n, a + n];
+            match half_edge.twin {
+                Some(twin) if half_edge.face < mesh.half_edges[twin].face => {
+                    let other = cell(mesh.half_edges[twin].face);
+                    push(side, cell(half_edge.face), Some(other), FaceTag::Interior);
+                }
+                Some(_) => {}
+                None => push(side, cell(half_edge.face), None, FaceTag::Side),
+            }
+        }
+        for face in 0..count {
+            if layer == 0 {
+                let mut bottom = polygon(face, 0);
+                bottom.reverse();
+                push(bottom, cell(face), None, FaceTag::Bottom);
+            }
+            if layer + 1 < layers.len() {
+                let above = Some(cell(face) + count);
+                push(
+                    polygon(face, layer + 1),
+                    cell(face),
+                    above,
+                    FaceTag::Interior,
+                );
+            } else {
+                push(polygon(face, layer + 1), cell(face), None, FaceTag::Top);
+            }
+        }
+    }
+    extruded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extrude() {
+        let mesh = PolyMesh::from_cells(&[
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+            vec![(1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0)],
+        ]);
+
+        let prisms = extrude(&mesh, &[0.1, 0.2]);
+
+        assert_eq!(prisms.points.len(), 18);
+        assert!((prisms.points[17].2 - 0.3).abs() < 1e-12);
+        assert_eq!(prisms.cells, vec![(0, 0), (1, 0), (0, 1), (1, 1)]);
+        let count = |tag: FaceTag| prisms.tags.iter().filter(|&&t| t == tag).count();
+        assert_eq!(count(FaceTag::Interior), 2 + 2);
+        assert_eq!(count(FaceTag::Side), 6 * 2);
+        assert_eq!(count(FaceTag::Bottom), 2);
+        assert_eq!(count(FaceTag::Top), 2);
+        for (i, tag) in prisms.tags.iter().enumerate() {
+            assert_eq!(prisms.neighbour[i].is_some(), *tag == FaceTag::Interior);
+            if let Some(neighbour) = prisms.neighbour[i] {
+                assert!(prisms.owner[i] < neighbour);
+            }
+        }
+
+        // The face between the layers of cell 0 faces up, into the upper prism
+        let i = (0..prisms.faces.len())
+            .find(|&i| prisms.owner[i] == 0 && prisms.neighbour[i] == Some(2))
+            .unwrap();
+        let [a, b, c] = [0, 1, 2].map(|k| prisms.points[prisms.faces[i][k]]);
+        let normal = (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0);
+        assert!(normal > 0.0);
+        assert!(prisms.faces[i].iter().all(|&p| prisms.points[p].2 == 0.1));
+    }
+}
@@ -0,0 +1,119 @@
+// Conversions with the `geo` geometry types, with the `geo` feature, so that domains can be
+// built with the boolean, buffer and simplify operations of the geo ecosystem and the cells
+// handed back to it. geo rings repeat their first point at the end, the mesher's don't.
+
+use geo_types::{Coord, LineString, MultiPolygon, Polygon};
+
+use crate::domain::{Domain, PolygonDomain};
+use crate::mesh::PolyMesh;
+
+type Point = (f64, f64);
+
+// Points of a closed geo ring, without the repeated first point
+fn ring_points(ring: &LineString<f64>) -> Vec<Point> {
+    let mut points: Vec<Point> = ring.coords().map(|c| (c.x, c.y)).collect();
+    if points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+    points
+}
+
+fn to_ring(points: &[Point]) -> LineString<f64> {
+    // LineString::new doesn't close the ring, Polygon::new does
+    LineString::new(points.iter().map(|&(x, y)| Coord { x, y }).collect())
+}
+
+impl TryFrom<&Polygon<f64>> for PolygonDomain {
+    type Error = &'static str;
+
+    fn try_from(polygon: &Polygon<f64>) -> Result<PolygonDomain, &'static str> {
+        polygon.interiors().iter().try_fold(
+            PolygonDomain::new(ring_points(polygon.exterior()))?,
+            |domain, hole| domain.with_hole(ring_points(hole)),
+        )
+    }
+}
+
+impl TryFrom<&Polygon<f64>> for Domain {
+    type Error = &'static str;
+
+    fn try_from(polygon: &Polygon<f64>) -> Result<Domain, &'static str> {
+        Ok(Domain::polygon(polygon.try_into()?))
+    }
+}
+
+/// The polygons must not overlap, as geo's boolean operations leave them.
+impl TryFrom<&MultiPolygon<f64>> for Domain {
+    type Error = &'static str;
+
+    fn try_from(polygons: &MultiPolygon<f64>) -> Result<Domain, &'static str> {
+        if polygons.0.is_empty() {
+            return Err("empty multipolygon");
+        }
+        let polygons = polygons
+            .iter()
+            .map(PolygonDomain::try_from)
+            .collect::<Result<_, _>>()?;
+        Ok(Domain::polygons(polygons))
+    }
+}
+
+/// Polygon of each of `cells`, as [`crate::cells::extract_cells`] returns them, `None` for the
+/// empty ones.
+pub fn cell_polygons(cells: &[Vec<Point>]) -> Vec<Option<Polygon<f64>>> {
+    cells
+        .iter()
+        .map(|cell| (!cell.is_empty()).then(|| Polygon::new(to_ring(cell), Vec::new())))
+        .collect()
+}
+
+/// Polygons of the faces of `mesh`, in the order of `mesh.faces`.
+pub fn face_polygons(mesh: &PolyMesh) -> MultiPolygon<f64> {
+    (0..mesh.faces.len())
+        .map(|face| Polygon::new(to_ring(&mesh.face_polygon(face)), Vec::new()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use geo_types::polygon;
+
+    use super::*;
+
+    #[test]
+    fn test_polygon_domain() {
+        // Clockwise exterior, counter-clockwise hole
+        let polygon = polygon!(
+            exterior: [(x: 0.0, y: 0.0), (x: 0.0, y: 4.0), (x: 4.0, y: 4.0), (x: 4.0, y: 0.0)],
+            interiors: [[(x: 1.0, y: 1.0), (x: 2.0, y: 1.0), (x: 2.0, y: 2.0), (x: 1.0, y: 2.0)]]
+        );
+
+        let domain = PolygonDomain::try_from(&polygon).unwrap();
+
+        assert_eq!(domain.outer.len(), 4);
+        assert_eq!(domain.holes.len(), 1);
+        assert_eq!(domain.holes[0].len(), 4);
+        assert!(domain.contains(3.0, 3.0));
+        assert!(!domain.contains(1.5, 1.5));
+        assert!(Domain::try_from(&MultiPolygon::<f64>::new(Vec::new())).is_err());
+    }
+
+    #[test]
+    fn test_face_polygons() {
+        let cells = vec![
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+            Vec::new(),
+            vec![(1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0)],
+        ];
+
+        let polygons = cell_polygons(&cells);
+        let faces = face_polygons(&PolyMesh::from_cells(&cells));
+
+        assert!(polygons[1].is_none());
+        let exterior = polygons[0].as_ref().unwrap().exterior();
+        assert_eq!(exterior.0.len(), 5);
+        assert!(exterior.is_closed());
+        assert_eq!(faces.0.len(), 2);
+        assert!(faces.iter().all(|face| face.exterior().0.len() == 5));
+    }
+}
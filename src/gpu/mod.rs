@@ -0,0 +1,56 @@
+pub mod scan;
+
+/// Requests a default device for GPU tests, `None` when the machine has no adapter.
+#[cfg(test)]
+pub(crate) fn test_device() -> Option<(wgpu::Device, wgpu::Queue)> {
+    crate::jfa_wgpu::block_on(async {
+        let instance = wgpu::Instance::default();
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions::default())
+            .await?;
+        adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    required_features: wgpu::Features::empty(),
+                    required_limits: wgpu::Limits::downlevel_defaults(),
+                    memory_hints: wgpu::MemoryHints::Performance,
+                },
+                None,
+            )
+            .await
+            .ok()
+    })
+}
+
+/// Reads back the first `len` elements of a `COPY_SRC` buffer.
+#[cfg(test)]
+pub(crate) fn download(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    buffer: &wgpu::Buffer,
+    len: usize,
+) -> Vec<u32> {
+    let size = (len.max(1) * std::mem::size_of::<u32>()) as wgpu::BufferAddress;
+    let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.copy_buffer_to_buffer(buffer, 0, &staging_buffer, 0, size);
+    queue.submit(Some(encoder.finish()));
+
+    let buffer_slice = staging_buffer.slice(..);
+    let (sender, receiver) = flume::bounded(1);
+    buffer_slice.map_async(wgpu::MapMode::Read, move |r| sender.send(r).unwrap());
+    device.poll(wgpu::Maintain::wait()).panic_on_timeout();
+    receiver.recv().unwrap().unwrap();
+    let data: Vec<u32> = bytemuck::cast_slice(&buffer_slice.get_mapped_range()[..]).to_vec();
+    staging_buffer.unmap();
+
+    data[..len].to_vec()
+}
@@ -0,0 +1,241 @@
+use wgpu::util::DeviceExt;
+
+const WORKGROUP_SIZE: u32 = 256;
+
+/// Exclusive prefix sum and stream compaction over `u32` storage buffers.
+///
+/// Work is recorded into a caller-provided `CommandEncoder`, so it can be chained with other
+/// compute passes before a single submission. Inputs are limited to
+/// `65535 * 256` elements by the 1D dispatch.
+pub struct Scan {
+    scan_pipeline: wgpu::ComputePipeline,
+    add_pipeline: wgpu::ComputePipeline,
+    scatter_pipeline: wgpu::ComputePipeline,
+}
+
+impl Scan {
+    pub fn new(device: &wgpu::Device) -> Scan {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("scan.wgsl"));
+        let pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: None,
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+
+        Scan {
+            scan_pipeline: pipeline("scan_blocks"),
+            add_pipeline: pipeline("add_offsets"),
+            scatter_pipeline: pipeline("scatter"),
+        }
+    }
+
+    /// Records the exclusive prefix sum of the first `len` elements of `input` into `output`.
+    pub fn encode_exclusive_scan(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &wgpu::Buffer,
+        output: &wgpu::Buffer,
+        len: u32,
+    ) {
+        let blocks = len.div_ceil(WORKGROUP_SIZE).max(1);
+        let params = params_buffer(device, len);
+        let block_sums = scratch_buffer(device, blocks);
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &self.scan_pipeline.get_bind_group_layout(0),
+            entries: &[
+                entry(0, input),
+                entry(1, output),
+                entry(2, &block_sums),
+                entry(3, &params),
+            ],
+        });
+        dispatch(encoder, &self.scan_pipeline, &bind_group, blocks);
+
+        if blocks > 1 {
+            // Scan the block totals recursively, then add them back to each block
+            let block_offsets = scratch_buffer(device, blocks);
+            self.encode_exclusive_scan(device, encoder, &block_sums, &block_offsets, blocks);
+
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &self.add_pipeline.get_bind_group_layout(0),
+                entries: &[
+                    entry(1, output),
+                    entry(3, &params),
+                    entry(4, &block_offsets),
+                ],
+            });
+            dispatch(encoder, &self.add_pipeline, &bind_group, blocks);
+        }
+    }
+
+    /// Records the compaction of the first `len` elements of `values`: every value whose flag is
+    /// 1 is written to `output`, in order, and the number of kept values is written to `count`.
+    ///
+    /// `flags` must only hold 0 or 1, since the output indices are its prefix sum.
+    #[allow(clippy::too_many_arguments)]
+    pub fn encode_compact(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        values: &wgpu::Buffer,
+        flags: &wgpu::Buffer,
+        output: &wgpu::Buffer,
+        count: &wgpu::Buffer,
+        len: u32,
+    ) {
+        let indices = scratch_buffer(device, len.max(1));
+        self.encode_exclusive_scan(device, encoder, flags, &indices, len);
+
+        let params = params_buffer(device, len);
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &self.scatter_pipeline.get_bind_group_layout(0),
+            entries: &[
+                entry(0, values),
+                entry(1, &indices),
+                entry(3, &params),
+                entry(5, flags),
+                entry(6, output),
+                entry(7, count),
+            ],
+        });
+        dispatch(
+            encoder,
+            &self.scatter_pipeline,
+            &bind_group,
+            len.div_ceil(WORKGROUP_SIZE).max(1),
+        );
+    }
+}
+
+fn params_buffer(device: &wgpu::Device, len: u32) -> wgpu::Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: None,
+        contents: bytemuck::cast_slice(&[len]),
+        usage: wgpu::BufferUsages::UNIFORM,
+    })
+}
+
+fn scratch_buffer(device: &wgpu::Device, len: u32) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: (len as usize * std::mem::size_of::<u32>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::STORAGE,
+        mapped_at_creation: false,
+    })
+}
+
+fn entry(binding: u32, buffer: &wgpu::Buffer) -> wgpu::BindGroupEntry<'_> {
+    wgpu::BindGroupEntry {
+        binding,
+        resource: buffer.as_entire_binding(),
+    }
+}
+
+fn dispatch(
+    encoder: &mut wgpu::CommandEncoder,
+    pipeline: &wgpu::ComputePipeline,
+    bind_group: &wgpu::BindGroup,
+    workgroups: u32,
+) {
+    let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+        label: None,
+        timestamp_writes: None,
+    });
+    compute_pass.set_pipeline(pipeline);
+    compute_pass.set_bind_group(0, bind_group, &[]);
+    compute_pass.dispatch_workgroups(workgroups, 1, 1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gpu::{download, test_device};
+
+    fn upload(device: &wgpu::Device, data: &[u32]) -> wgpu::Buffer {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(data),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        })
+    }
+
+    fn output(device: &wgpu::Device, len: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (len.max(1) * std::mem::size_of::<u32>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        })
+    }
+
+    #[test]
+    fn test_exclusive_scan() {
+        let Some((device, queue)) = test_device() else {
+            return;
+        };
+        let scan = Scan::new(&device);
+
+        // Spans several levels of block sums
+        let data: Vec<u32> = (0..70_000).map(|i| i % 7).collect();
+        let input = upload(&device, &data);
+        let result = output(&device, data.len());
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        scan.encode_exclusive_scan(&device, &mut encoder, &input, &result, data.len() as u32);
+        queue.submit(Some(encoder.finish()));
+
+        let expected: Vec<u32> = data
+            .iter()
+            .scan(0, |sum, &x| {
+                let before = *sum;
+                *sum += x;
+                Some(before)
+            })
+            .collect();
+        assert_eq!(download(&device, &queue, &result, data.len()), expected);
+    }
+
+    #[test]
+    fn test_compact() {
+        let Some((device, queue)) = test_device() else {
+            return;
+        };
+        let scan = Scan::new(&device);
+
+        let values: Vec<u32> = (0..1000).collect();
+        let flags: Vec<u32> = values.iter().map(|v| (v % 3 == 0) as u32).collect();
+        let values_buffer = upload(&device, &values);
+        let flags_buffer = upload(&device, &flags);
+        let result = output(&device, values.len());
+        let count = output(&device, 1);
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        scan.encode_compact(
+            &device,
+            &mut encoder,
+            &values_buffer,
+            &flags_buffer,
+            &result,
+            &count,
+            values.len() as u32,
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let expected: Vec<u32> = values.iter().copied().filter(|v| v % 3 == 0).collect();
+        let count = download(&device, &queue, &count, 1)[0] as usize;
+        assert_eq!(count, expected.len());
+        assert_eq!(download(&device, &queue, &result, count), expected);
+    }
+}
@@ -0,0 +1,84 @@
+struct Params {
+    len: u32,
+}
+
+@group(0) @binding(0) var<storage, read> input: array<u32>;
+@group(0) @binding(1) var<storage, read_write> output: array<u32>;
+@group(0) @binding(2) var<storage, read_write> block_sums: array<u32>;
+@group(0) @binding(3) var<uniform> params: Params;
+@group(0) @binding(4) var<storage, read> block_offsets: array<u32>;
+@group(0) @binding(5) var<storage, read> flags: array<u32>;
+@group(0) @binding(6) var<storage, read_write> compacted: array<u32>;
+@group(0) @binding(7) var<storage, read_write> count: array<u32>;
+
+const WORKGROUP_SIZE: u32 = 256;
+
+var<workgroup> shared_data: array<u32, 256>;
+
+// Exclusive scan of one block of WORKGROUP_SIZE elements, the block total goes to block_sums
+@compute @workgroup_size(256)
+fn scan_blocks(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(local_invocation_id) local_id: vec3<u32>,
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+) {
+    let i = global_id.x;
+    var value = 0u;
+    if (i < params.len) {
+        value = input[i];
+    }
+    shared_data[local_id.x] = value;
+    workgroupBarrier();
+
+    // Hillis-Steele inclusive scan in workgroup memory
+    for (var offset = 1u; offset < WORKGROUP_SIZE; offset = offset * 2u) {
+        var addend = 0u;
+        if (local_id.x >= offset) {
+            addend = shared_data[local_id.x - offset];
+        }
+        workgroupBarrier();
+        shared_data[local_id.x] = shared_data[local_id.x] + addend;
+        workgroupBarrier();
+    }
+
+    let inclusive = shared_data[local_id.x];
+    if (i < params.len) {
+        output[i] = inclusive - value;
+    }
+    if (local_id.x == WORKGROUP_SIZE - 1u) {
+        block_sums[workgroup_id.x] = inclusive;
+    }
+}
+
+// Adds the scanned block totals back to every element of their block
+@compute @workgroup_size(256)
+fn add_offsets(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+) {
+    if (global_id.x < params.len) {
+        output[global_id.x] = output[global_id.x] + block_offsets[workgroup_id.x];
+    }
+}
+
+// Writes flagged values at their scanned index, `output` holding the scanned flags
+@compute @workgroup_size(256)
+fn scatter(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let i = global_id.x;
+    if (params.len == 0u) {
+        if (i == 0u) {
+            count[0] = 0u;
+        }
+        return;
+    }
+    if (i >= params.len) {
+        return;
+    }
+
+    if (flags[i] != 0u) {
+        compacted[output[i]] = input[i];
+    }
+    if (i == params.len - 1u) {
+        count[0] = output[i] + flags[i];
+    }
+}
@@ -0,0 +1,175 @@
+// Rough grain boundaries for microstructure meshes, whose interfaces aren't straight. Every edge
+// between two cells is split at regular steps and the new vertices are moved off it by smooth
+// random noise, the same for the two cells, so the mesh stays conforming. Vertices already in the
+// mesh, triple points and the domain boundary among them, don't move, and boundary edges aren't
+// split.
+//
+// The noise is 1D value noise along the edge with lattice points a wavelength apart, faded out
+// towards the ends of the edge. A split edge stays inside the two triangles joining it to the
+// centroids of its cells, which don't overlap the triangles of the other edges when the cells are
+// star-shaped around their centroids, as convex ones are, so perturbed edges never cross.
+
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use rand::Rng;
+
+use crate::mesh::PolyMesh;
+
+// Vertices an edge gets per wavelength of the noise
+const STEPS_PER_WAVELENGTH: f64 = 8.0;
+// Fraction of the way to the centroid of a cell a split edge may go, which keeps a core of every
+// cell untouched
+const MAX_DEPTH: f64 = 0.5;
+
+/// Mesh with the edges between cells perturbed by noise of at most `amplitude` and of about
+/// `wavelength`, both in domain units.
+pub fn perturb_boundaries(
+    mesh: &PolyMesh,
+    amplitude: f64,
+    wavelength: f64,
+    rng: &mut impl Rng,
+) -> PolyMesh {
+    assert!(wavelength > 0.0, "the wavelength must be positive");
+    let mut vertices = mesh.vertices.clone();
+    // Vertices inserted along each half-edge, in its direction
+    let mut inserted: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (h, half_edge) in mesh.half_edges.iter().enumerate() {
+        let Some(twin) = half_edge.twin.filter(|&twin| h < twin) else {
+            continue;
+        };
+        let (a, b) = (
+            mesh.vertices[half_edge.origin],
+            mesh.vertices[mesh.destination(h)],
+        );
+        let length = (b.0 - a.0).hypot(b.1 - a.1);
+        let steps = (length / wavelength * STEPS_PER_WAVELENGTH).ceil() as usize;
+        if steps < 2 {
+            continue;
+        }
+
+        // Edge frame, the normal pointing into the face of `h`, on its left
+        let direction = ((b.0 - a.0) / length, (b.1 - a.1) / length);
+        let normal = (-direction.1, direction.0);
+        // Position along the edge and height above it of a point, in edge lengths
+        let frame = |(x, y): (f64, f64)| {
+            let (dx, dy) = (x - a.0, y - a.1);
+            let along = (dx * direction.0 + dy * direction.1) / length;
+            let height = (dx * normal.0 + dy * normal.1) / length;
+            (along, height)
+        };
+        let left = frame(mesh.face_centroid(half_edge.face));
+        let (along, height) = frame(mesh.face_centroid(mesh.half_edges[twin].face));
+        let right = (along, -height);
+
+        let lattice: Vec<f64> = (0..(length / wavelength).ceil() as usize + 2)
+            .map(|_| 2.0 * rng.gen::<f64>() - 1.0)
+            .collect();
+        let mut points = Vec::with_capacity(steps - 1);
+        for step in 1..steps {
+            let t = step as f64 / steps as f64;
+            let offset =
+                amplitude * (PI * t).sin() * value_noise(&lattice, t * length / wavelength);
+            let offset = offset.clamp(
+                -MAX_DEPTH * fan_height(right, t) * length,
+                MAX_DEPTH * fan_height(left, t) * length,
+            );
+            vertices.push((
+                a.0 + t * (b.0 - a.0) + offset * normal.0,
+                a.1 + t * (b.1 - a.1) + offset * normal.1,
+            ));
+            points.push(vertices.len() - 1);
+        }
+        inserted.insert(twin, points.iter().rev().copied().collect());
+        inserted.insert(h, points);
+    }
+
+    let polygons: Vec<Vec<usize>> = (0..mesh.faces.len())
+        .map(|face| {
+            let mut polygon = Vec::new();
+            for h in mesh.face_edges(face) {
+                polygon.push(mesh.half_edges[h].origin);
+                if let Some(points) = inserted.get(&h) {
+                    polygon.extend(points);
+                }
+            }
+            polygon
+        })
+        .collect();
+    let seeds: Vec<usize> = mesh.faces.iter().map(|face| face.seed).collect();
+    PolyMesh::from_polygons(vertices, &polygons, &seeds)
+}
+
+// Smoothly interpolated `lattice` values at `x` lattice steps
+fn value_noise(lattice: &[f64], x: f64) -> f64 {
+    let i = (x.floor() as usize).min(lattice.len() - 2);
+    let f = (x - i as f64).clamp(0.0, 1.0);
+    let smooth = f * f * (3.0 - 2.0 * f);
+    lattice[i] + (lattice[i + 1] - lattice[i]) * smooth
+}
+
+// Height at position `t` of the triangle over the unit edge with apex `(along, height)`, 0 when
+// the apex isn't above the edge
+fn fan_height((along, height): (f64, f64), t: f64) -> f64 {
+    if height <= 0.0 {
+        return 0.0;
+    }
+    let mut limit = f64::INFINITY;
+    if along > 0.0 {
+        limit = limit.min(t / along);
+    }
+    if along < 1.0 {
+        limit = limit.min((1.0 - t) / (1.0 - along));
+    }
+    height * limit.min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    // Whether segments ab and cd cross at a point inside both
+    fn cross(a: (f64, f64), b: (f64, f64), c: (f64, f64), d: (f64, f64)) -> bool {
+        let side = |p: (f64, f64), q: (f64, f64), r: (f64, f64)| {
+            (q.0 - p.0) * (r.1 - p.1) - (q.1 - p.1) * (r.0 - p.0)
+        };
+        side(a, b, c) * side(a, b, d) < 0.0 && side(c, d, a) * side(c, d, b) < 0.0
+    }
+
+    #[test]
+    fn test_perturb_boundaries() {
+        let square = |x: f64, y: f64| vec![(x, y), (x + 1.0, y), (x + 1.0, y + 1.0), (x, y + 1.0)];
+        let cells: Vec<_> = (0..9)
+            .map(|i| square((i % 3) as f64, (i / 3) as f64))
+            .collect();
+        let mesh = PolyMesh::from_cells(&cells);
+        let mut rng = StdRng::seed_from_u64(7);
+
+        let rough = perturb_boundaries(&mesh, 0.3, 0.5, &mut rng);
+
+        assert_eq!(rough.faces.len(), 9);
+        assert!(rough.vertices.len() > mesh.vertices.len());
+        assert!(mesh.vertices.iter().all(|v| rough.vertices.contains(v)));
+        let area: f64 = (0..9).map(|face| rough.face_area(face)).sum();
+        assert!((area - 9.0).abs() < 1e-9);
+        let boundary: f64 = rough.boundary_edges().map(|h| rough.edge_length(h)).sum();
+        assert!((boundary - 12.0).abs() < 1e-9);
+        // Some vertex moved off the lattice lines, none deeper than half way to a centroid
+        let off = |(x, y): (f64, f64)| (x - x.round()).abs().min((y - y.round()).abs());
+        assert!(rough.vertices.iter().any(|&v| off(v) > 0.01));
+        assert!(rough.vertices.iter().all(|&v| off(v) <= 0.25 + 1e-9));
+
+        let edges: Vec<usize> = (0..rough.half_edges.len()).collect();
+        for &g in &edges {
+            for &h in &edges {
+                let (a, b) = (rough.half_edges[g].origin, rough.destination(g));
+                let (c, d) = (rough.half_edges[h].origin, rough.destination(h));
+                let [a, b, c, d] = [a, b, c, d].map(|v| rough.vertices[v]);
+                assert!(!cross(a, b, c, d), "{a:?} {b:?} {c:?} {d:?}");
+            }
+        }
+    }
+}
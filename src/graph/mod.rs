@@ -0,0 +1,511 @@
+// Adjacency graph of the cells, from a JFA label grid or an exact diagram, with the
+// post-processing utilities which only need the connectivity.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use crate::config::BoundaryMode;
+use crate::exact::ExactCell;
+use crate::mesh::PolyMesh;
+
+/// Cell adjacency, with cell `i` for seed `i`, i.e. label `i + 1`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellGraph {
+    /// Sorted indices of the cells sharing an edge with each cell
+    pub neighbors: Vec<Vec<usize>>,
+}
+
+impl CellGraph {
+    /// Scans a label grid of `(width, height)` texels, cells being adjacent when two of their
+    /// texels share a side.
+    pub fn from_labels(
+        labels: &[usize],
+        (width, height): (usize, usize),
+        cell_count: usize,
+    ) -> Self {
+        assert_eq!(labels.len(), width * height, "label grid size mismatch");
+        let mut neighbors = vec![BTreeSet::new(); cell_count];
+        for y in 0..height {
+            for x in 0..width {
+                let label = labels[x + y * width];
+                for (nx, ny) in [(x + 1, y), (x, y + 1)] {
+                    if nx >= width || ny >= height {
+                        continue;
+                    }
+                    let other = labels[nx + ny * width];
+                    if label != 0 && other != 0 && other != label {
+                        neighbors[label - 1].insert(other - 1);
+                        neighbors[other - 1].insert(label - 1);
+                    }
+                }
+            }
+        }
+        CellGraph {
+            neighbors: neighbors
+                .into_iter()
+                .map(|set| set.into_iter().collect())
+                .collect(),
+        }
+    }
+
+    pub fn from_exact(cells: &[ExactCell]) -> Self {
+        CellGraph {
+            neighbors: cells
+                .iter()
+                .map(|cell| {
+                    let set: BTreeSet<usize> = cell.neighbors.iter().map(|&(j, _)| j).collect();
+                    set.into_iter().collect()
+                })
+                .collect(),
+        }
+    }
+
+    /// Faces of `mesh` being adjacent when they share an edge, cells being indexed by seed.
+    pub fn from_mesh(mesh: &PolyMesh, cell_count: usize) -> Self {
+        let mut neighbors = vec![BTreeSet::new(); cell_count];
+        for (face, f) in mesh.faces.iter().enumerate() {
+            neighbors[f.seed].extend(
+                mesh.face_neighbors(face)
+                    .map(|neighbor| mesh.faces[neighbor].seed),
+            );
+        }
+        CellGraph {
+            neighbors: neighbors
+                .into_iter()
+                .map(|set| set.into_iter().collect())
+                .collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.neighbors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.neighbors.is_empty()
+    }
+
+    /// Greedy coloring where no two adjacent cells share a color, so that cells of one color
+    /// can be assembled in parallel. Cells are colored by decreasing degree (Welsh-Powell),
+    /// which keeps the color count at most one more than the maximum degree.
+    pub fn color_cells(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.len()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(self.neighbors[i].len()));
+
+        let mut colors = vec![usize::MAX; self.len()];
+        let mut used = Vec::new();
+        for cell in order {
+            used.clear();
+            used.resize(self.neighbors[cell].len() + 1, false);
+            for &neighbor in &self.neighbors[cell] {
+                if let Some(slot) = used.get_mut(colors[neighbor]) {
+                    *slot = true;
+                }
+            }
+            colors[cell] = used.iter().position(|&taken| !taken).unwrap();
+        }
+        colors
+    }
+
+    /// Hop distance from `cell` to every cell, `None` where unreachable.
+    pub fn bfs_from(&self, cell: usize) -> Vec<Option<usize>> {
+        let mut distances = vec![None; self.len()];
+        distances[cell] = Some(0);
+        let mut queue = VecDeque::from([cell]);
+        while let Some(current) = queue.pop_front() {
+            let next = distances[current].map(|d| d + 1);
+            for &neighbor in &self.neighbors[current] {
+                if distances[neighbor].is_none() {
+                    distances[neighbor] = next;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        distances
+    }
+
+    /// Sorted cells reachable from `seed_cells` through cells accepted by `predicate`. The seed
+    /// cells are always part of the region.
+    pub fn grow_region(
+        &self,
+        seed_cells: &[usize],
+        predicate: impl Fn(usize) -> bool,
+    ) -> Vec<usize> {
+        let mut in_region = vec![false; self.len()];
+        let mut queue = VecDeque::new();
+        for &cell in seed_cells {
+            if !in_region[cell] {
+                in_region[cell] = true;
+                queue.push_back(cell);
+            }
+        }
+        while let Some(current) = queue.pop_front() {
+            for &neighbor in &self.neighbors[current] {
+                if !in_region[neighbor] && predicate(neighbor) {
+                    in_region[neighbor] = true;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        (0..self.len()).filter(|&cell| in_region[cell]).collect()
+    }
+
+    /// Component index of every cell, adjacent cells being connected when they have the same
+    /// material. Components are numbered from 0 in order of their smallest cell, so islands
+    /// are the components of a material beyond its first.
+    pub fn connected_components<M: PartialEq>(&self, material: impl Fn(usize) -> M) -> Vec<usize> {
+        let mut components = vec![usize::MAX; self.len()];
+        let mut count = 0;
+        for start in 0..self.len() {
+            if components[start] != usize::MAX {
+                continue;
+            }
+            let start_material = material(start);
+            for cell in self.grow_region(&[start], |cell| material(cell) == start_material) {
+                components[cell] = count;
+            }
+            count += 1;
+        }
+        components
+    }
+}
+
+/// Adjacent cells of each seed with the length of the shared boundary, from a label grid of
+/// `(width, height)` texels covering the `config` box, in the same form as
+/// [`ExactCell::neighbors`]. Cells are also adjacent across the sides `boundary` wraps.
+///
+/// Voronoi edges are straight, so each connected run of a shared boundary is as long as the
+/// diagonal of the box spanned by its texel edges, followed across wrapped sides, and a pair
+/// sharing several runs gets the sum of their lengths. Slanted edges aren't overestimated the
+/// way counting the staircase steps does, and are off by up to a texel at each end.
+pub fn neighbors(
+    labels: &[usize],
+    (width, height): (usize, usize),
+    cell_count: usize,
+    config: (f64, f64),
+    boundary: BoundaryMode,
+) -> Vec<Vec<(usize, f64)>> {
+    assert_eq!(labels.len(), width * height, "label grid size mismatch");
+    let (wrap_x, wrap_y) = boundary.wraps();
+    let mut edges: BTreeMap<(usize, usize), Vec<Edge>> = BTreeMap::new();
+    for y in 0..height {
+        for x in 0..width {
+            let label = labels[x + y * width];
+            // The right and top edges of the texel
+            for vertical in [true, false] {
+                let neighbor = match vertical {
+                    true if x + 1 < width => x + 1 + y * width,
+                    true if wrap_x => y * width,
+                    false if y + 1 < height => x + (y + 1) * width,
+                    false if wrap_y => x,
+                    _ => continue,
+                };
+                let other = labels[neighbor];
+                if label == 0 || other == 0 || other == label {
+                    continue;
+                }
+                let start = match vertical {
+                    true => (x + 1, y),
+                    false => (x, y + 1),
+                };
+                let pair = (label.min(other) - 1, label.max(other) - 1);
+                edges.entry(pair).or_default().push((start, vertical));
+            }
+        }
+    }
+
+    let lattice = Lattice {
+        dimensions: (width as i64, height as i64),
+        wraps: (wrap_x, wrap_y),
+    };
+    let texel = (config.0 / width as f64, config.1 / height as f64);
+    let mut neighbors = vec![Vec::new(); cell_count];
+    for ((i, j), edges) in edges {
+        let length = lattice
+            .runs(&edges)
+            .into_iter()
+            .map(|(min, max)| {
+                ((max.0 - min.0) as f64 * texel.0).hypot((max.1 - min.1) as f64 * texel.1)
+            })
+            .sum();
+        neighbors[i].push((j, length));
+        neighbors[j].push((i, length));
+    }
+    for cell in &mut neighbors {
+        cell.sort_by_key(|&(j, _)| j);
+    }
+    neighbors
+}
+
+// Texel edge shared by two cells, as its lattice start point and whether it is vertical
+type Edge = ((usize, usize), bool);
+
+type Point = (i64, i64);
+
+// Lattice points of a label grid, identified across the wrapped sides
+struct Lattice {
+    dimensions: Point,
+    wraps: (bool, bool),
+}
+
+impl Lattice {
+    fn canonical(&self, (x, y): Point) -> Point {
+        let (width, height) = self.dimensions;
+        (
+            if self.wraps.0 { x.rem_euclid(width) } else { x },
+            if self.wraps.1 {
+                y.rem_euclid(height)
+            } else {
+                y
+            },
+        )
+    }
+
+    // Bounding box corners of each connected run of `edges`, whose edges are placed next to
+    // each other across the wrapped sides
+    fn runs(&self, edges: &[Edge]) -> Vec<(Point, Point)> {
+        let end = |vertical: bool| match vertical {
+            true => (0, 1),
+            false => (1, 0),
+        };
+        // Edges ending at each lattice point, with the offset of that point from their start
+        let mut at: BTreeMap<Point, Vec<(usize, Point)>> = BTreeMap::new();
+        for (edge, &((x, y), vertical)) in edges.iter().enumerate() {
+            let (dx, dy) = end(vertical);
+            let (x, y) = (x as i64, y as i64);
+            at.entry(self.canonical((x, y)))
+                .or_default()
+                .push((edge, (0, 0)));
+            at.entry(self.canonical((x + dx, y + dy)))
+                .or_default()
+                .push((edge, (dx, dy)));
+        }
+
+        // Start of each edge placed so far, unwrapped
+        let mut placed: Vec<Option<Point>> = vec![None; edges.len()];
+        let mut runs = Vec::new();
+        for first in 0..edges.len() {
+            if placed[first].is_some() {
+                continue;
+            }
+            let ((x, y), _) = edges[first];
+            placed[first] = Some((x as i64, y as i64));
+            let (mut min, mut max) = ((i64::MAX, i64::MAX), (i64::MIN, i64::MIN));
+            let mut stack = vec![first];
+            while let Some(edge) = stack.pop() {
+                let start = placed[edge].unwrap();
+                let (dx, dy) = end(edges[edge].1);
+                for point in [start, (start.0 + dx, start.1 + dy)] {
+                    min = (min.0.min(point.0), min.1.min(point.1));
+                    max = (max.0.max(point.0), max.1.max(point.1));
+                    for &(other, offset) in &at[&self.canonical(point)] {
+                        if placed[other].is_none() {
+                            placed[other] = Some((point.0 - offset.0, point.1 - offset.1));
+                            stack.push(other);
+                        }
+                    }
+                }
+            }
+            runs.push((min, max));
+        }
+        runs
+    }
+}
+
+/// Triangles of the Delaunay triangulation dual to the cells of a label grid of `(width, height)`
+/// texels, as counter-clockwise triples of seed indices starting with the smallest, so one run
+/// gives both the cells of `cells::extract_cells` and their dual.
+///
+/// Every lattice point where three cells meet gives a triangle, and one where four meet, around
+/// cocircular seeds, two. Rasterization can split a Voronoi vertex into several such points with
+/// overlapping triangles, of which only the first in order is kept. Lattice points next to
+/// unassigned texels give no triangle, and periodic boundaries aren't followed.
+pub fn delaunay_triangles(labels: &[usize], (width, height): (usize, usize)) -> Vec<[usize; 3]> {
+    assert_eq!(labels.len(), width * height, "label grid size mismatch");
+    let mut triangles = BTreeSet::new();
+    for y in 1..height {
+        for x in 1..width {
+            // Texels around the lattice point, counter-clockwise from the bottom left one
+            let around = [
+                labels[x - 1 + (y - 1) * width],
+                labels[x + (y - 1) * width],
+                labels[x + y * width],
+                labels[x - 1 + y * width],
+            ];
+            if around.contains(&0) {
+                continue;
+            }
+            let cycle: Vec<usize> = (0..4)
+                .filter(|&i| around[i] != around[(i + 3) % 4])
+                .map(|i| around[i] - 1)
+                .collect();
+            let distinct: BTreeSet<usize> = cycle.iter().copied().collect();
+            if distinct.len() != cycle.len() {
+                continue;
+            }
+            match cycle[..] {
+                [a, b, c] => {
+                    triangles.insert(smallest_first([a, b, c]));
+                }
+                [a, b, c, d] => {
+                    triangles.insert(smallest_first([a, b, c]));
+                    triangles.insert(smallest_first([a, c, d]));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Each directed edge bounds a single triangle on its left
+    let mut edges = BTreeSet::new();
+    triangles
+        .into_iter()
+        .filter(|&[a, b, c]| {
+            let sides = [(a, b), (b, c), (c, a)];
+            if sides.iter().any(|side| edges.contains(side)) {
+                return false;
+            }
+            edges.extend(sides);
+            true
+        })
+        .collect()
+}
+
+fn smallest_first(mut triangle: [usize; 3]) -> [usize; 3] {
+    let first = (0..3).min_by_key(|&i| triangle[i]).unwrap();
+    triangle.rotate_left(first);
+    triangle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_labels() {
+        #[rustfmt::skip]
+        let labels = vec![
+            1, 1, 2,
+            1, 3, 2,
+            0, 3, 3,
+        ];
+
+        let graph = CellGraph::from_labels(&labels, (3, 3), 4);
+
+        assert_eq!(
+            graph.neighbors,
+            vec![vec![1, 2], vec![0, 2], vec![0, 1], vec![]]
+        );
+    }
+
+    #[test]
+    fn test_from_mesh() {
+        let mesh = PolyMesh::from_cells(&[
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+            vec![],
+            vec![(1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0)],
+        ]);
+
+        let graph = CellGraph::from_mesh(&mesh, 3);
+
+        assert_eq!(graph.neighbors, vec![vec![2], vec![], vec![0]]);
+    }
+
+    #[test]
+    fn test_neighbors() {
+        #[rustfmt::skip]
+        let labels = vec![
+            1, 1, 2, 2,
+            1, 1, 2, 2,
+            3, 3, 3, 3,
+            3, 3, 3, 3,
+        ];
+
+        let adjacency = neighbors(&labels, (4, 4), 3, (8.0, 4.0), BoundaryMode::Bounded);
+
+        assert_eq!(adjacency[0], vec![(1, 2.0), (2, 4.0)]);
+        assert_eq!(adjacency[2], vec![(0, 4.0), (1, 4.0)]);
+
+        // A diagonal boundary is measured along the diagonal, within a texel at each end, where
+        // the staircase would be 16 long
+        let labels: Vec<usize> = (0..64)
+            .map(|i| 1 + usize::from(i % 8 + i / 8 >= 8))
+            .collect();
+        let length = neighbors(&labels, (8, 8), 2, (8.0, 8.0), BoundaryMode::Bounded)[0][0].1;
+        assert!((length - 8.0 * 2f64.sqrt()).abs() <= 2.0 * 2f64.sqrt());
+    }
+
+    #[test]
+    fn test_neighbors_runs() {
+        // Cell 1 on both sides of cell 2, and cell 3 next to cell 1 across the wrapped sides
+        let split: Vec<usize> = (0..24).map(|i| [1, 1, 2, 2, 1, 1][i % 6]).collect();
+        let ring: Vec<usize> = (0..24).map(|i| [1, 1, 2, 2, 3, 3][i % 6]).collect();
+        let periodic = BoundaryMode::Periodic { x: true, y: true };
+
+        // Two runs of 4, rather than the diagonal of the box around both
+        let adjacency = neighbors(&split, (6, 4), 2, (6.0, 4.0), BoundaryMode::Bounded);
+        assert_eq!(adjacency[0], vec![(1, 8.0)]);
+        assert_eq!(
+            neighbors(&split, (6, 4), 2, (6.0, 4.0), periodic)[0],
+            vec![(1, 8.0)]
+        );
+
+        let adjacency = neighbors(&ring, (6, 4), 3, (6.0, 4.0), BoundaryMode::Bounded);
+        assert_eq!(adjacency[0], vec![(1, 4.0)]);
+        let adjacency = neighbors(&ring, (6, 4), 3, (6.0, 4.0), periodic);
+        assert_eq!(adjacency[0], vec![(1, 4.0), (2, 4.0)]);
+        assert_eq!(adjacency[2], vec![(0, 4.0), (1, 4.0)]);
+    }
+
+    #[test]
+    fn test_delaunay_triangles() {
+        // Four cells meeting at the center, and a fifth one above them
+        #[rustfmt::skip]
+        let labels = vec![
+            1, 1, 2, 2,
+            1, 1, 2, 2,
+            3, 3, 4, 4,
+            5, 5, 5, 5,
+        ];
+
+        let triangles = delaunay_triangles(&labels, (4, 4));
+
+        assert_eq!(triangles, vec![[0, 1, 3], [0, 3, 2], [2, 3, 4]]);
+    }
+
+    #[test]
+    fn test_color_cells() {
+        // A wheel: cell 0 surrounded by a ring of five cells, which needs four colors
+        let mut neighbors = vec![vec![1, 2, 3, 4, 5]];
+        for i in 1..=5 {
+            neighbors.push(vec![0, (i + 3) % 5 + 1, i % 5 + 1]);
+        }
+        let graph = CellGraph { neighbors };
+
+        let colors = graph.color_cells();
+
+        for (cell, neighbors) in graph.neighbors.iter().enumerate() {
+            for &neighbor in neighbors {
+                assert_ne!(colors[cell], colors[neighbor]);
+            }
+        }
+        assert_eq!(colors.iter().max(), Some(&3));
+    }
+
+    #[test]
+    fn test_traversals() {
+        // A path 0 - 1 - 2 - 3 and an isolated cell 4
+        let graph = CellGraph {
+            neighbors: vec![vec![1], vec![0, 2], vec![1, 3], vec![2], vec![]],
+        };
+
+        assert_eq!(
+            graph.bfs_from(1),
+            vec![Some(1), Some(0), Some(1), Some(2), None]
+        );
+        assert_eq!(graph.grow_region(&[0], |cell| cell != 2), vec![0, 1]);
+
+        let materials = [0, 0, 1, 0, 0];
+        let components = graph.connected_components(|cell| materials[cell]);
+        assert_eq!(components, vec![0, 0, 1, 2, 3]);
+    }
+}
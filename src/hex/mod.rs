@@ -0,0 +1,202 @@
+// Hexahedral meshes of the cells, for solvers which strongly prefer hexahedra.
+//
+// 2D cells go through midpoint subdivision: a cell of n vertices is split into n quads, each
+// joining a vertex, the midpoints of its two edges and the centroid of the cell, and the quads
+// are extruded into layers of hexahedra. Edge midpoints are shared by the two cells of an edge,
+// so the split mesh is as conforming as the input, on the boundary too. The quads are convex
+// for convex cells.
+//
+// The 3D pipeline's cells are sets of voxels, whose midpoint subdivision is the voxels
+// themselves, so they are meshed with one hexahedron per voxel, sharing the lattice nodes.
+// Either way every hexahedron is tagged with the seed of its cell.
+
+use std::collections::HashMap;
+
+use crate::mesh::PolyMesh;
+
+/// Conforming hexahedral mesh.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HexMesh {
+    pub nodes: Vec<(f64, f64, f64)>,
+    /// Nodes of each hexahedron in VTK order: the bottom quad counter-clockwise seen from
+    /// above, then the top quad in the same order
+    pub hexes: Vec<[usize; 8]>,
+    /// Seed of the cell of each hexahedron
+    pub seeds: Vec<usize>,
+}
+
+/// Quads of the midpoint subdivision of the faces of `mesh`, each the face of the seed of the
+/// cell it comes from.
+pub fn split_quads(mesh: &PolyMesh) -> PolyMesh {
+    let mut vertices = mesh.vertices.clone();
+    let mut midpoints: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut polygons = Vec::new();
+    let mut seeds = Vec::new();
+    for (face, f) in mesh.faces.iter().enumerate() {
+        let ring: Vec<usize> = mesh.face_vertices(face).collect();
+        let n = ring.len();
+        vertices.push(mesh.face_centroid(face));
+        let center = vertices.len() - 1;
+        let mids: Vec<usize> = (0..n)
+            .map(|i| {
+                let (a, b) = (ring[i], ring[(i + 1) % n]);
+                *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                    let ((ax, ay), (bx, by)) = (mesh.vertices[a], mesh.vertices[b]);
+                    vertices.push(((ax + bx) / 2.0, (ay + by) / 2.0));
+                    vertices.len() - 1
+                })
+            })
+            .collect();
+        for i in 0..n {
+            polygons.push(vec![ring[i], mids[i], center, mids[(i + n - 1) % n]]);
+            seeds.push(f.seed);
+        }
+    }
+    PolyMesh::from_polygons(vertices, &polygons, &seeds)
+}
+
+/// Hexahedra of the quads of `quads`, as [`split_quads`] returns them, extruded from z = 0 to
+/// z = `thickness` in `layers` layers.
+pub fn extrude_quads(quads: &PolyMesh, thickness: f64, layers: usize) -> HexMesh {
+    assert!(layers > 0, "at least one layer is needed");
+    let n = quads.vertices.len();
+    let nodes = (0..=layers)
+        .flat_map(|layer| {
+            let z = thickness * layer as f64 / layers as f64;
+            quads.vertices.iter().map(move |&(x, y)| (x, y, z))
+        })
+        .collect();
+    let mut hexes = Vec::new();
+    let mut seeds = Vec::new();
+    for (face, f) in quads.faces.iter().enumerate() {
+        let quad: Vec<usize> = quads.face_vertices(face).collect();
+        let [a, b, c, d] = quad[..] else {
+            panic!("face {face} isn't a quad");
+        };
+        for layer in 0..layers {
+            let (bottom, top) = (layer * n, (layer + 1) * n);
+            hexes.push([
+                bottom + a,
+                bottom + b,
+                bottom + c,
+                bottom + d,
+                top + a,
+                top + b,
+                top + c,
+                top + d,
+            ]);
+            seeds.push(f.seed);
+        }
+    }
+    HexMesh {
+        nodes,
+        hexes,
+        seeds,
+    }
+}
+
+/// One hexahedron per assigned voxel of the `labels` of a `reso`³ grid covering the `extent` box,
+/// as `jfa_wgpu_3d::main` returns them.
+pub fn voxel_hexes(labels: &[usize], reso: usize, extent: (f64, f64, f64)) -> HexMesh {
+    assert_eq!(labels.len(), reso * reso * reso, "voxel grid size mismatch");
+    let size = (
+        extent.0 / reso as f64,
+        extent.1 / reso as f64,
+        extent.2 / reso as f64,
+    );
+    let mut indices: HashMap<(usize, usize, usize), usize> = HashMap::new();
+    let mut nodes = Vec::new();
+    let mut node = |x: usize, y: usize, z: usize| {
+        *indices.entry((x, y, z)).or_insert_with(|| {
+            nodes.push((x as f64 * size.0, y as f64 * size.1, z as f64 * size.2));
+            nodes.len() - 1
+        })
+    };
+    let mut hexes = Vec::new();
+    let mut seeds = Vec::new();
+    for z in 0..reso {
+        for y in 0..reso {
+            for x in 0..reso {
+                let label = labels[x + y * reso + z * reso * reso];
+                if label == 0 {
+                    continue;
+                }
+                let corner = [(0, 0), (1, 0), (1, 1), (0, 1)];
+                let mut hex = [0; 8];
+                for (i, &(dx, dy)) in corner.iter().enumerate() {
+                    hex[i] = node(x + dx, y + dy, z);
+                    hex[i + 4] = node(x + dx, y + dy, z + 1);
+                }
+                hexes.push(hex);
+                seeds.push(label - 1);
+            }
+        }
+    }
+    HexMesh {
+        nodes,
+        hexes,
+        seeds,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_quads() {
+        // A square next to a triangle
+        let mesh = PolyMesh::from_cells(&[
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+            vec![(1.0, 0.0), (2.0, 0.0), (1.0, 1.0)],
+        ]);
+
+        let quads = split_quads(&mesh);
+
+        assert_eq!(quads.faces.len(), 7);
+        assert!((0..7).all(|face| quads.face_vertices(face).count() == 4));
+        assert_eq!(quads.faces.iter().filter(|face| face.seed == 1).count(), 3);
+        // 5 vertices, 6 edge midpoints, 2 centroids
+        assert_eq!(quads.vertices.len(), 13);
+        let area: f64 = (0..7).map(|face| quads.face_area(face)).sum();
+        assert!((area - 1.5).abs() < 1e-12);
+        // The shared edge is split once, so its halves have twins
+        let interior = quads.half_edges.iter().filter(|h| h.twin.is_some()).count();
+        assert_eq!(interior, 2 * (4 + 3 + 2));
+    }
+
+    #[test]
+    fn test_extrude_quads() {
+        let quads = split_quads(&PolyMesh::from_cells(&[vec![
+            (0.0, 0.0),
+            (1.0, 0.0),
+            (1.0, 1.0),
+            (0.0, 1.0),
+        ]]));
+
+        let hexes = extrude_quads(&quads, 2.0, 2);
+
+        assert_eq!(hexes.hexes.len(), 8);
+        assert_eq!(hexes.nodes.len(), 3 * 9);
+        let hex = hexes.hexes[0];
+        let (bottom, top) = (hexes.nodes[hex[0]], hexes.nodes[hex[4]]);
+        assert_eq!((bottom.0, bottom.1), (top.0, top.1));
+        assert_eq!(top.2 - bottom.2, 1.0);
+        // The upper layer shares the nodes between the layers
+        assert_eq!(hexes.hexes[1][..4], hex[4..]);
+    }
+
+    #[test]
+    fn test_voxel_hexes() {
+        let mut labels: Vec<usize> = (0..8).map(|i| 1 + i % 2).collect();
+        labels[7] = 0;
+
+        let hexes = voxel_hexes(&labels, 2, (2.0, 2.0, 2.0));
+
+        assert_eq!(hexes.hexes.len(), 7);
+        assert_eq!(hexes.seeds.iter().filter(|&&seed| seed == 1).count(), 3);
+        // The unassigned corner voxel leaves its far node out
+        assert_eq!(hexes.nodes.len(), 26);
+        assert_eq!(hexes.nodes[hexes.hexes[0][6]], (1.0, 1.0, 1.0));
+    }
+}
@@ -0,0 +1,171 @@
+// Signed distance primitives and boolean combinators to build meshing domains, negative inside.
+// They return closures so they can be passed straight to the `domain` functions, e.g.
+// `domain::mask_labels(&mut labels, dimensions, config, difference(plate, circle(center, 1.0)))`.
+// Their 3D counterparts are in `solid`.
+
+pub mod solid;
+
+pub fn circle(center: (f64, f64), radius: f64) -> impl Fn(f64, f64) -> f64 {
+    move |x, y| (x - center.0).hypot(y - center.1) - radius
+}
+
+/// Axis-aligned rectangle between the `min` and `max` corners.
+pub fn rectangle(min: (f64, f64), max: (f64, f64)) -> impl Fn(f64, f64) -> f64 {
+    let center = ((min.0 + max.0) / 2.0, (min.1 + max.1) / 2.0);
+    let half = ((max.0 - min.0) / 2.0, (max.1 - min.1) / 2.0);
+    move |x, y| {
+        let dx = (x - center.0).abs() - half.0;
+        let dy = (y - center.1).abs() - half.1;
+        dx.max(0.0).hypot(dy.max(0.0)) + dx.max(dy).min(0.0)
+    }
+}
+
+/// Segment from `a` to `b` thickened by `radius`.
+pub fn capsule(a: (f64, f64), b: (f64, f64), radius: f64) -> impl Fn(f64, f64) -> f64 {
+    move |x, y| segment_distance((x, y), a, b) - radius
+}
+
+/// Simple polygon, possibly non-convex, in either orientation.
+pub fn polygon(vertices: Vec<(f64, f64)>) -> impl Fn(f64, f64) -> f64 {
+    move |x, y| {
+        let mut distance = f64::MAX;
+        let mut inside = false;
+        for (i, &a) in vertices.iter().enumerate() {
+            let b = vertices[(i + 1) % vertices.len()];
+            distance = distance.min(segment_distance((x, y), a, b));
+            // Even-odd rule
+            if (a.1 > y) != (b.1 > y) && x < a.0 + (y - a.1) / (b.1 - a.1) * (b.0 - a.0) {
+                inside = !inside;
+            }
+        }
+        if inside {
+            -distance
+        } else {
+            distance
+        }
+    }
+}
+
+pub fn union(a: impl Fn(f64, f64) -> f64, b: impl Fn(f64, f64) -> f64) -> impl Fn(f64, f64) -> f64 {
+    move |x, y| a(x, y).min(b(x, y))
+}
+
+pub fn intersection(
+    a: impl Fn(f64, f64) -> f64,
+    b: impl Fn(f64, f64) -> f64,
+) -> impl Fn(f64, f64) -> f64 {
+    move |x, y| a(x, y).max(b(x, y))
+}
+
+/// Points of `a` which are not in `b`.
+pub fn difference(
+    a: impl Fn(f64, f64) -> f64,
+    b: impl Fn(f64, f64) -> f64,
+) -> impl Fn(f64, f64) -> f64 {
+    move |x, y| a(x, y).max(-b(x, y))
+}
+
+/// Union rounded over a blending distance `k` (polynomial smooth minimum).
+pub fn smooth_union(
+    a: impl Fn(f64, f64) -> f64,
+    b: impl Fn(f64, f64) -> f64,
+    k: f64,
+) -> impl Fn(f64, f64) -> f64 {
+    move |x, y| smooth_min(a(x, y), b(x, y), k)
+}
+
+/// Intersection rounded over a blending distance `k`.
+pub fn smooth_intersection(
+    a: impl Fn(f64, f64) -> f64,
+    b: impl Fn(f64, f64) -> f64,
+    k: f64,
+) -> impl Fn(f64, f64) -> f64 {
+    move |x, y| -smooth_min(-a(x, y), -b(x, y), k)
+}
+
+/// Difference rounded over a blending distance `k`.
+pub fn smooth_difference(
+    a: impl Fn(f64, f64) -> f64,
+    b: impl Fn(f64, f64) -> f64,
+    k: f64,
+) -> impl Fn(f64, f64) -> f64 {
+    move |x, y| -smooth_min(-a(x, y), b(x, y), k)
+}
+
+fn smooth_min(a: f64, b: f64, k: f64) -> f64 {
+    if k <= 0.0 {
+        return a.min(b);
+    }
+    let h = (k - (a - b).abs()).max(0.0) / k;
+    a.min(b) - h * h * k / 4.0
+}
+
+fn segment_distance(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (abx, aby) = (b.0 - a.0, b.1 - a.1);
+    let length_squared = abx * abx + aby * aby;
+    let t = if length_squared > 0.0 {
+        (((p.0 - a.0) * abx + (p.1 - a.1) * aby) / length_squared).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (p.0 - a.0 - t * abx).hypot(p.1 - a.1 - t * aby)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-12, "{a} != {b}");
+    }
+
+    #[test]
+    fn test_primitives() {
+        let c = circle((1.0, 1.0), 1.0);
+        assert_close(c(1.0, 1.0), -1.0);
+        assert_close(c(4.0, 1.0), 2.0);
+
+        let r = rectangle((0.0, 0.0), (4.0, 2.0));
+        assert_close(r(2.0, 1.0), -1.0);
+        assert_close(r(7.0, 6.0), 5.0);
+        assert_close(r(5.0, 1.0), 1.0);
+
+        let s = capsule((0.0, 0.0), (2.0, 0.0), 0.5);
+        assert_close(s(1.0, 1.0), 0.5);
+        assert_close(s(-1.0, 0.0), 0.5);
+
+        // L-shaped, the notch at the top right is outside
+        let p = polygon(vec![
+            (0.0, 0.0),
+            (2.0, 0.0),
+            (2.0, 1.0),
+            (1.0, 1.0),
+            (1.0, 2.0),
+            (0.0, 2.0),
+        ]);
+        assert_close(p(0.5, 0.5), -0.5);
+        assert_close(p(1.5, 1.5), 0.5);
+        assert_close(p(3.0, 0.5), 1.0);
+    }
+
+    #[test]
+    fn test_booleans() {
+        let plate = rectangle((0.0, 0.0), (10.0, 10.0));
+        let hole = circle((5.0, 5.0), 2.0);
+
+        let with_hole = difference(&plate, &hole);
+        assert!(with_hole(5.0, 5.0) > 0.0);
+        assert!(with_hole(1.0, 1.0) < 0.0);
+
+        let both = intersection(&plate, &hole);
+        assert_close(both(5.0, 5.0), -2.0);
+
+        let either = union(circle((0.0, 0.0), 1.0), circle((3.0, 0.0), 1.0));
+        assert_close(either(1.5, 0.0), 0.5);
+
+        // Blending fills the gap between the two circles
+        let blended = smooth_union(circle((0.0, 0.0), 1.0), circle((3.0, 0.0), 1.0), 2.0);
+        assert!(blended(1.5, 0.0) < 0.5);
+        assert_close(blended(-1.0, 0.0), 0.0);
+    }
+}
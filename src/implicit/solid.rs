@@ -0,0 +1,77 @@
+// Signed distance primitives and boolean combinators in 3D, negative inside, for the voxel masks
+// of `jfa_wgpu_3d::mask_voxels`.
+
+pub fn sphere(center: (f64, f64, f64), radius: f64) -> impl Fn(f64, f64, f64) -> f64 {
+    move |x, y, z| {
+        ((x - center.0).powi(2) + (y - center.1).powi(2) + (z - center.2).powi(2)).sqrt() - radius
+    }
+}
+
+/// Axis-aligned box between the `min` and `max` corners.
+pub fn cuboid(min: (f64, f64, f64), max: (f64, f64, f64)) -> impl Fn(f64, f64, f64) -> f64 {
+    move |x, y, z| {
+        let d = [(x, min.0, max.0), (y, min.1, max.1), (z, min.2, max.2)]
+            .map(|(v, low, high)| (v - (low + high) / 2.0).abs() - (high - low) / 2.0);
+        let outside = d.iter().map(|d| d.max(0.0).powi(2)).sum::<f64>().sqrt();
+        outside + d[0].max(d[1]).max(d[2]).min(0.0)
+    }
+}
+
+pub fn union(
+    a: impl Fn(f64, f64, f64) -> f64,
+    b: impl Fn(f64, f64, f64) -> f64,
+) -> impl Fn(f64, f64, f64) -> f64 {
+    move |x, y, z| a(x, y, z).min(b(x, y, z))
+}
+
+pub fn intersection(
+    a: impl Fn(f64, f64, f64) -> f64,
+    b: impl Fn(f64, f64, f64) -> f64,
+) -> impl Fn(f64, f64, f64) -> f64 {
+    move |x, y, z| a(x, y, z).max(b(x, y, z))
+}
+
+/// Points of `a` which are not in `b`.
+pub fn difference(
+    a: impl Fn(f64, f64, f64) -> f64,
+    b: impl Fn(f64, f64, f64) -> f64,
+) -> impl Fn(f64, f64, f64) -> f64 {
+    move |x, y, z| a(x, y, z).max(-b(x, y, z))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-12, "{a} != {b}");
+    }
+
+    #[test]
+    fn test_solids() {
+        let s = sphere((1.0, 1.0, 1.0), 1.0);
+        assert_close(s(1.0, 1.0, 1.0), -1.0);
+        assert_close(s(1.0, 4.0, 1.0), 2.0);
+
+        let c = cuboid((0.0, 0.0, 0.0), (4.0, 2.0, 2.0));
+        assert_close(c(2.0, 1.0, 1.0), -1.0);
+        assert_close(c(2.0, 1.0, 5.0), 3.0);
+        assert_close(c(7.0, 6.0, 1.0), 5.0);
+
+        // A cube with a spherical cavity
+        let shell = difference(
+            cuboid((0.0, 0.0, 0.0), (4.0, 4.0, 4.0)),
+            sphere((2.0, 2.0, 2.0), 1.0),
+        );
+        assert!(shell(2.0, 2.0, 2.0) > 0.0);
+        assert!(shell(0.5, 0.5, 0.5) < 0.0);
+        assert_close(
+            union(sphere((0.0, 0.0, 0.0), 1.0), sphere((3.0, 0.0, 0.0), 1.0))(1.5, 0.0, 0.0),
+            0.5,
+        );
+        assert_close(
+            intersection(sphere((0.0, 0.0, 0.0), 2.0), sphere((3.0, 0.0, 0.0), 2.0))(1.5, 0.0, 0.0),
+            -0.5,
+        );
+    }
+}
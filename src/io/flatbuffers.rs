@@ -0,0 +1,223 @@
+// FlatBuffers encoding of a `mesh::PolyMesh`, with the `poly_mesh.fbs` schema, so other processes
+// and languages can memory-map the mesher's output and read it in place with the code `flatc`
+// generates. The vertices, half-edges and faces are vectors of structs, laid out as arrays.
+//
+// The buffer is written front to back rather than with a FlatBuffers builder: the root offset and
+// the file identifier, the table's vtable, the table, then the vectors, each aligned for its
+// structs. Readers only follow offsets, so any valid layout reads the same. Reading checks every
+// offset and index, since the bytes come from another process, and rebuilds the mesh with
+// `PolyMesh::from_parts`.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::mesh::{Face, HalfEdge, PolyMesh};
+
+/// The schema, for serving it to clients which generate their readers.
+pub const SCHEMA: &str = include_str!("poly_mesh.fbs");
+
+pub const FILE_IDENTIFIER: &[u8; 4] = b"PMSH";
+
+// Sizes of the Vertex, HalfEdge and Face structs
+const VERTEX_SIZE: usize = 16;
+const HALF_EDGE_SIZE: usize = 20;
+const FACE_SIZE: usize = 8;
+// Fields of the PolyMesh table, all vector offsets
+const FIELDS: usize = 3;
+
+/// Writes the mesh to a `.pmsh` file.
+pub fn write_pmsh(path: impl AsRef<Path>, mesh: &PolyMesh) -> io::Result<()> {
+    fs::write(path, to_flatbuffer(mesh))
+}
+
+/// Reads a mesh from a `.pmsh` file, malformed ones failing with `InvalidData`.
+pub fn read_pmsh(path: impl AsRef<Path>) -> io::Result<PolyMesh> {
+    let bytes = fs::read(path)?;
+    from_flatbuffer(&bytes).map_err(|message| io::Error::new(io::ErrorKind::InvalidData, message))
+}
+
+/// Panics for meshes with more than `u32::MAX` vertices, half-edges or faces, or `i32::MAX`
+/// half-edges, the schema's index types.
+pub fn to_flatbuffer(mesh: &PolyMesh) -> Vec<u8> {
+    assert!(
+        i32::try_from(mesh.half_edges.len()).is_ok()
+            && u32::try_from(mesh.vertices.len().max(mesh.faces.len())).is_ok(),
+        "mesh too large for 32-bit indices"
+    );
+    let index = |i: usize| (i as u32).to_le_bytes();
+
+    let vtable = 8;
+    let table = 20;
+    let mut buffer = Vec::new();
+    buffer.extend((table as u32).to_le_bytes());
+    buffer.extend(FILE_IDENTIFIER);
+    // Its size, the table's, and the position of each field in the table
+    for entry in [4 + 2 * FIELDS, 4 + 4 * FIELDS, 4, 8, 12] {
+        buffer.extend((entry as u16).to_le_bytes());
+    }
+    buffer.resize(table, 0);
+    buffer.extend(((table - vtable) as i32).to_le_bytes());
+    buffer.resize(table + 4 + 4 * FIELDS, 0);
+
+    // Each vector is offset from its slot in the table, and its length comes right before its
+    // structs
+    let mut push_vector = |field: usize, align: usize, len: usize, elements: Vec<u8>| {
+        while (buffer.len() + 4) % align != 0 {
+            buffer.push(0);
+        }
+        let slot = table + 4 + 4 * field;
+        let offset = (buffer.len() - slot) as u32;
+        buffer[slot..slot + 4].copy_from_slice(&offset.to_le_bytes());
+        buffer.extend((len as u32).to_le_bytes());
+        buffer.extend(elements);
+    };
+    let vertices = mesh
+        .vertices
+        .iter()
+        .flat_map(|&(x, y)| [x.to_le_bytes(), y.to_le_bytes()].concat())
+        .collect();
+    push_vector(0, 8, mesh.vertices.len(), vertices);
+    let half_edges = mesh
+        .half_edges
+        .iter()
+        .flat_map(|h| {
+            let twin = h.twin.map_or(-1, |twin| twin as i32).to_le_bytes();
+            [
+                index(h.origin),
+                twin,
+                index(h.next),
+                index(h.prev),
+                index(h.face),
+            ]
+            .concat()
+        })
+        .collect();
+    push_vector(1, 4, mesh.half_edges.len(), half_edges);
+    let faces = mesh
+        .faces
+        .iter()
+        .flat_map(|f| [index(f.seed), index(f.half_edge)].concat())
+        .collect();
+    push_vector(2, 4, mesh.faces.len(), faces);
+    buffer
+}
+
+pub fn from_flatbuffer(bytes: &[u8]) -> Result<PolyMesh, &'static str> {
+    const TRUNCATED: &str = "truncated or corrupted buffer";
+    let read = |at: usize, size: usize| {
+        at.checked_add(size)
+            .and_then(|end| bytes.get(at..end))
+            .ok_or(TRUNCATED)
+    };
+    let u16_at = |at: usize| Ok(u16::from_le_bytes(read(at, 2)?.try_into().unwrap()) as usize);
+    let u32_at = |at: usize| Ok(u32::from_le_bytes(read(at, 4)?.try_into().unwrap()) as usize);
+
+    if read(4, 4)? != FILE_IDENTIFIER {
+        return Err("not a PolyMesh buffer");
+    }
+    let table = u32_at(0)?;
+    let soffset = i32::from_le_bytes(read(table, 4)?.try_into().unwrap());
+    let vtable = usize::try_from(table as i64 - soffset as i64).map_err(|_| TRUNCATED)?;
+    let vtable_fields = u16_at(vtable)?.saturating_sub(4) / 2;
+
+    // Structs of a vector field, none when the field is absent
+    let vector = |field: usize, size: usize| -> Result<Vec<&[u8]>, &'static str> {
+        let position = if field < vtable_fields {
+            u16_at(vtable + 4 + 2 * field)?
+        } else {
+            0
+        };
+        if position == 0 {
+            return Ok(Vec::new());
+        }
+        let slot = table + position;
+        let start = slot.checked_add(u32_at(slot)?).ok_or(TRUNCATED)?;
+        let len = u32_at(start)?;
+        let data = read(start + 4, len.checked_mul(size).ok_or(TRUNCATED)?)?;
+        Ok(data.chunks_exact(size).collect())
+    };
+    let word =
+        |data: &[u8], i: usize| u32::from_le_bytes(data[4 * i..4 * i + 4].try_into().unwrap());
+
+    let vertices = vector(0, VERTEX_SIZE)?
+        .into_iter()
+        .map(|data| {
+            let x = f64::from_le_bytes(data[..8].try_into().unwrap());
+            let y = f64::from_le_bytes(data[8..].try_into().unwrap());
+            (x, y)
+        })
+        .collect();
+    let half_edges = vector(1, HALF_EDGE_SIZE)?
+        .into_iter()
+        .map(|data| {
+            let twin = match word(data, 1) as i32 {
+                -1 => None,
+                twin if twin >= 0 => Some(twin as usize),
+                _ => return Err("negative twin index"),
+            };
+            Ok(HalfEdge {
+                origin: word(data, 0) as usize,
+                twin,
+                next: word(data, 2) as usize,
+                prev: word(data, 3) as usize,
+                face: word(data, 4) as usize,
+            })
+        })
+        .collect::<Result<_, _>>()?;
+    let faces = vector(2, FACE_SIZE)?
+        .into_iter()
+        .map(|data| Face {
+            seed: word(data, 0) as usize,
+            half_edge: word(data, 1) as usize,
+        })
+        .collect();
+    PolyMesh::from_parts(vertices, half_edges, faces)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_squares() -> PolyMesh {
+        PolyMesh::from_cells(&[
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+            vec![],
+            vec![(1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0)],
+        ])
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mesh = two_squares();
+
+        let bytes = to_flatbuffer(&mesh);
+
+        assert_eq!(&bytes[4..8], FILE_IDENTIFIER);
+        // The vertices' structs start on 8 bytes
+        let vertices = 24 + u32::from_le_bytes(bytes[24..28].try_into().unwrap()) as usize;
+        assert_eq!((vertices + 4) % 8, 0);
+        assert_eq!(from_flatbuffer(&bytes).unwrap(), mesh);
+        let empty = PolyMesh::from_cells(&[]);
+        assert_eq!(from_flatbuffer(&to_flatbuffer(&empty)).unwrap(), empty);
+    }
+
+    #[test]
+    fn test_malformed() {
+        let bytes = to_flatbuffer(&two_squares());
+
+        assert!(from_flatbuffer(&bytes[..bytes.len() - 1]).is_err());
+        assert!(from_flatbuffer(&bytes[..6]).is_err());
+        let mut other = bytes.clone();
+        other[4] = b'X';
+        assert_eq!(from_flatbuffer(&other), Err("not a PolyMesh buffer"));
+        // The `next` of the first half-edge pointing past the last one
+        let half_edges = 28 + u32::from_le_bytes(bytes[28..32].try_into().unwrap()) as usize;
+        let mut corrupted = bytes.clone();
+        corrupted[half_edges + 12..half_edges + 16].copy_from_slice(&99u32.to_le_bytes());
+        assert_eq!(
+            from_flatbuffer(&corrupted),
+            Err("half-edge index out of range")
+        );
+    }
+}
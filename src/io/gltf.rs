@@ -0,0 +1,378 @@
+// glTF 2.0 export, as binary `.glb` files, of 2D cells extruded into prisms and of the cells of
+// a 3D voxel grid. Every cell is a mesh and a node of its own named `seed_<index>`, so viewers
+// and engines can pick, hide or move cells one by one, and cells sharing a material id share a
+// material whose base color is the color of that id in the SVG and PNG drawings.
+//
+// Faces are flat shaded, so every face gets vertices of its own with its normal. The surface of
+// a voxel cell is made of the voxel faces it doesn't share with another voxel of the cell,
+// which keeps its staircase.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::mesh::PolyMesh;
+use crate::render::cell_color;
+
+const GLB_MAGIC: u32 = 0x4654_6c67;
+const CHUNK_JSON: u32 = 0x4e4f_534a;
+const CHUNK_BIN: u32 = 0x004e_4942;
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// Writes the cells of `mesh` extruded from z = 0 to z = `thickness` to a `.glb` file, the cell
+/// of seed `i` having material id `material(i)`. A zero thickness writes the faces alone.
+pub fn write_glb(
+    path: impl AsRef<Path>,
+    mesh: &PolyMesh,
+    thickness: f64,
+    material: impl Fn(usize) -> usize,
+) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    write_extrusion(&mut file, mesh, thickness, material)?;
+    file.flush()
+}
+
+/// Writes the cells of the `labels` of a `reso`³ voxel grid covering the `extent` box, as
+/// `jfa_wgpu_3d::main` returns them, to a `.glb` file. Unassigned voxels are left out.
+pub fn write_voxel_glb(
+    path: impl AsRef<Path>,
+    labels: &[usize],
+    reso: usize,
+    extent: (f64, f64, f64),
+    material: impl Fn(usize) -> usize,
+) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    write_voxels(&mut file, labels, reso, extent, material)?;
+    file.flush()
+}
+
+pub fn write_extrusion(
+    writer: &mut impl Write,
+    mesh: &PolyMesh,
+    thickness: f64,
+    material: impl Fn(usize) -> usize,
+) -> io::Result<()> {
+    let mut cells = Vec::with_capacity(mesh.faces.len());
+    for (face, f) in mesh.faces.iter().enumerate() {
+        let mut cell = Cell::new(f.seed);
+        let vertices: Vec<usize> = mesh.face_vertices(face).collect();
+        let polygon = |z: f64| -> Vec<[f64; 3]> {
+            let point = |v: usize| [mesh.vertices[v].0, mesh.vertices[v].1, z];
+            vertices.iter().map(|&v| point(v)).collect()
+        };
+        let triangles: Vec<[usize; 3]> = mesh
+            .face_triangles(face)
+            .into_iter()
+            .map(|t| t.map(|v| vertices.iter().position(|&w| w == v).unwrap()))
+            .collect();
+        cell.add_face(&polygon(thickness), &triangles, [0.0, 0.0, 1.0]);
+        if thickness > 0.0 {
+            let reversed: Vec<[usize; 3]> = triangles.iter().map(|&[a, b, c]| [a, c, b]).collect();
+            cell.add_face(&polygon(0.0), &reversed, [0.0, 0.0, -1.0]);
+            // The side quad of a counter-clockwise edge `a -> b` faces to its right
+            let n = vertices.len();
+            for i in 0..n {
+                let (a, b) = (
+                    mesh.vertices[vertices[i]],
+                    mesh.vertices[vertices[(i + 1) % n]],
+                );
+                let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+                let length = dx.hypot(dy);
+                if length == 0.0 {
+                    continue;
+                }
+                let corners = [
+                    [a.0, a.1, 0.0],
+                    [b.0, b.1, 0.0],
+                    [b.0, b.1, thickness],
+                    [a.0, a.1, thickness],
+                ];
+                cell.add_face(
+                    &corners,
+                    &[[0, 1, 2], [0, 2, 3]],
+                    [dy / length, -dx / length, 0.0],
+                );
+            }
+        }
+        cells.push(cell);
+    }
+    write_cells(writer, &cells, material)
+}
+
+pub fn write_voxels(
+    writer: &mut impl Write,
+    labels: &[usize],
+    reso: usize,
+    extent: (f64, f64, f64),
+    material: impl Fn(usize) -> usize,
+) -> io::Result<()> {
+    assert_eq!(labels.len(), reso * reso * reso, "voxel grid size mismatch");
+    let size = [extent.0, extent.1, extent.2].map(|side| side / reso as f64);
+    let label_at = |v: [isize; 3]| {
+        if v.iter().all(|&c| c >= 0 && (c as usize) < reso) {
+            labels[v[0] as usize + v[1] as usize * reso + v[2] as usize * reso * reso]
+        } else {
+            0
+        }
+    };
+
+    let mut cells: BTreeMap<usize, Cell> = BTreeMap::new();
+    for z in 0..reso as isize {
+        for y in 0..reso as isize {
+            for x in 0..reso as isize {
+                let label = label_at([x, y, z]);
+                if label == 0 {
+                    continue;
+                }
+                let cell = cells.entry(label).or_insert_with(|| Cell::new(label - 1));
+                for axis in 0..3 {
+                    for sign in [-1, 1] {
+                        let mut neighbor = [x, y, z];
+                        neighbor[axis] += sign;
+                        if label_at(neighbor) == label {
+                            continue;
+                        }
+                        // Corners counter-clockwise around the two other axes, in cyclic order
+                        // so that they turn around +axis
+                        let (u, w) = ((axis + 1) % 3, (axis + 2) % 3);
+                        let mut corners = [[0.0; 3]; 4];
+                        for (corner, (du, dw)) in
+                            corners.iter_mut().zip([(0, 0), (1, 0), (1, 1), (0, 1)])
+                        {
+                            let mut v = [x, y, z];
+                            v[axis] += (sign + 1) / 2;
+                            v[u] += du;
+                            v[w] += dw;
+                            *corner = [0, 1, 2].map(|c| v[c] as f64 * size[c]);
+                        }
+                        if sign < 0 {
+                            corners.reverse();
+                        }
+                        let mut normal = [0.0; 3];
+                        normal[axis] = sign as f64;
+                        cell.add_face(&corners, &[[0, 1, 2], [0, 2, 3]], normal);
+                    }
+                }
+            }
+        }
+    }
+    let cells: Vec<Cell> = cells.into_values().collect();
+    write_cells(writer, &cells, material)
+}
+
+// Flat shaded triangles of a cell
+struct Cell {
+    seed: usize,
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    indices: Vec<u32>,
+}
+
+impl Cell {
+    fn new(seed: usize) -> Cell {
+        Cell {
+            seed,
+            positions: Vec::new(),
+            normals: Vec::new(),
+            indices: Vec::new(),
+        }
+    }
+
+    // Adds a planar face of `corners` split into `triangles` of corner indices
+    fn add_face(&mut self, corners: &[[f64; 3]], triangles: &[[usize; 3]], normal: [f64; 3]) {
+        let first = self.positions.len() as u32;
+        for corner in corners {
+            self.positions.push(corner.map(|c| c as f32));
+            self.normals.push(normal.map(|c| c as f32));
+        }
+        for triangle in triangles {
+            self.indices.extend(triangle.map(|i| first + i as u32));
+        }
+    }
+}
+
+// Linear component of an sRGB one, as glTF base colors are linear
+fn srgb_to_linear(value: u8) -> f64 {
+    let value = value as f64 / 255.0;
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+// glTF buffers are little-endian
+fn le_bytes<'a>(values: impl Iterator<Item = &'a f32>) -> Vec<u8> {
+    values.flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn write_cells(
+    writer: &mut impl Write,
+    cells: &[Cell],
+    material: impl Fn(usize) -> usize,
+) -> io::Result<()> {
+    // Materials in order of first use
+    let mut materials: Vec<usize> = Vec::new();
+    let mut binary: Vec<u8> = Vec::new();
+    let (mut views, mut accessors, mut meshes, mut nodes) =
+        (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    let mut push_view = |binary: &mut Vec<u8>, bytes: &[u8], target: u32| {
+        views.push(format!(
+            r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{target}}}"#,
+            binary.len(),
+            bytes.len()
+        ));
+        binary.extend_from_slice(bytes);
+        views.len() - 1
+    };
+    for (i, cell) in cells.iter().enumerate() {
+        let id = material(cell.seed);
+        let index = match materials.iter().position(|&m| m == id) {
+            Some(index) => index,
+            None => {
+                materials.push(id);
+                materials.len() - 1
+            }
+        };
+
+        let (mut min, mut max) = ([f32::MAX; 3], [f32::MIN; 3]);
+        for p in &cell.positions {
+            for c in 0..3 {
+                min[c] = min[c].min(p[c]);
+                max[c] = max[c].max(p[c]);
+            }
+        }
+        let count = cell.positions.len();
+        let view = push_view(
+            &mut binary,
+            &le_bytes(cell.positions.iter().flatten()),
+            ARRAY_BUFFER,
+        );
+        accessors.push(format!(
+            r#"{{"bufferView":{view},"componentType":{FLOAT},"count":{count},"type":"VEC3","min":[{},{},{}],"max":[{},{},{}]}}"#,
+            min[0], min[1], min[2], max[0], max[1], max[2]
+        ));
+        let view = push_view(
+            &mut binary,
+            &le_bytes(cell.normals.iter().flatten()),
+            ARRAY_BUFFER,
+        );
+        accessors.push(format!(
+            r#"{{"bufferView":{view},"componentType":{FLOAT},"count":{count},"type":"VEC3"}}"#
+        ));
+        let view = push_view(
+            &mut binary,
+            &cell
+                .indices
+                .iter()
+                .flat_map(|i| i.to_le_bytes())
+                .collect::<Vec<u8>>(),
+            ELEMENT_ARRAY_BUFFER,
+        );
+        accessors.push(format!(
+            r#"{{"bufferView":{view},"componentType":{UNSIGNED_INT},"count":{},"type":"SCALAR"}}"#,
+            cell.indices.len()
+        ));
+
+        let first = accessors.len() - 3;
+        meshes.push(format!(
+            r#"{{"name":"seed_{}","primitives":[{{"attributes":{{"POSITION":{},"NORMAL":{}}},"indices":{},"material":{index}}}]}}"#,
+            cell.seed,
+            first,
+            first + 1,
+            first + 2
+        ));
+        nodes.push(format!(r#"{{"name":"seed_{}","mesh":{i}}}"#, cell.seed));
+    }
+    let materials: Vec<String> = materials
+        .iter()
+        .map(|&id| {
+            let (r, g, b) = cell_color(id);
+            let [r, g, b] = [r, g, b].map(srgb_to_linear);
+            format!(
+                r#"{{"name":"material_{id}","pbrMetallicRoughness":{{"baseColorFactor":[{r},{g},{b},1],"metallicFactor":0,"roughnessFactor":1}}}}"#
+            )
+        })
+        .collect();
+
+    let scene: Vec<String> = (0..nodes.len()).map(|i| i.to_string()).collect();
+    let mut json = format!(
+        r#"{{"asset":{{"version":"2.0","generator":"blue_noise"}},"scene":0,"scenes":[{{"nodes":[{}]}}],"nodes":[{}],"meshes":[{}],"materials":[{}],"accessors":[{}],"bufferViews":[{}],"buffers":[{{"byteLength":{}}}]}}"#,
+        scene.join(","),
+        nodes.join(","),
+        meshes.join(","),
+        materials.join(","),
+        accessors.join(","),
+        views.join(","),
+        binary.len()
+    )
+    .into_bytes();
+
+    // Chunks are padded to 4 bytes, JSON with spaces
+    json.resize(json.len().next_multiple_of(4), b' ');
+    binary.resize(binary.len().next_multiple_of(4), 0);
+    let length = 12 + 8 + json.len() + 8 + binary.len();
+    for word in [GLB_MAGIC, 2, length as u32, json.len() as u32, CHUNK_JSON] {
+        writer.write_all(&word.to_le_bytes())?;
+    }
+    writer.write_all(&json)?;
+    writer.write_all(&(binary.len() as u32).to_le_bytes())?;
+    writer.write_all(&CHUNK_BIN.to_le_bytes())?;
+    writer.write_all(&binary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // JSON chunk of a GLB file, after checking its header
+    fn json_chunk(glb: &[u8]) -> String {
+        let word = |i: usize| u32::from_le_bytes(glb[i..i + 4].try_into().unwrap());
+        assert_eq!(word(0), GLB_MAGIC);
+        assert_eq!(word(8) as usize, glb.len());
+        assert_eq!(word(16), CHUNK_JSON);
+        let length = word(12) as usize;
+        assert_eq!(word(20 + length + 4), CHUNK_BIN);
+        String::from_utf8(glb[20..20 + length].to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_extrusion() {
+        let mesh = PolyMesh::from_cells(&[
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+            vec![(1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0)],
+        ]);
+        let mut output = Vec::new();
+
+        write_extrusion(&mut output, &mesh, 0.5, |_| 7).unwrap();
+
+        let json = json_chunk(&output);
+        assert!(json.contains(r#"{"name":"seed_1","mesh":1}"#));
+        assert_eq!(json.matches(r#""name":"material_7""#).count(), 1);
+        // Top, bottom and 4 sides of 4 vertices, 2 + 2 + 8 triangles
+        assert_eq!(json.matches(r#""count":24,"type":"VEC3""#).count(), 4);
+        assert_eq!(json.matches(r#""count":36,"type":"SCALAR""#).count(), 2);
+        assert!(json.contains(r#""min":[0,0,0],"max":[1,1,0.5]"#));
+    }
+
+    #[test]
+    fn test_voxels() {
+        // Two halves of a 2³ grid, and an unassigned voxel
+        let mut labels: Vec<usize> = (0..8).map(|i| 1 + i % 2).collect();
+        labels[7] = 0;
+        let mut output = Vec::new();
+
+        write_voxels(&mut output, &labels, 2, (2.0, 2.0, 2.0), |seed| seed).unwrap();
+
+        let json = json_chunk(&output);
+        assert_eq!(json.matches(r#""name":"material_"#).count(), 2);
+        // 16 faces around a 1 x 2 x 2 box, 14 around one missing a voxel
+        assert_eq!(json.matches(r#""count":64,"type":"VEC3""#).count(), 2);
+        assert_eq!(json.matches(r#""count":56,"type":"VEC3""#).count(), 2);
+        assert!(json.contains(r#"{"name":"seed_1","mesh":1}"#));
+    }
+}
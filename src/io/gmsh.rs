@@ -0,0 +1,138 @@
+// Gmsh MSH 4.1 export, in ASCII. Every seed's cell is a surface entity of its own with a
+// physical group named `seed_<index>`, so boundary conditions and materials can be assigned per
+// cell. MSH has no general polygon element, so cells are split into triangles around their
+// vertex average, which lies inside since the cells are convex.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::mesh::PolyMesh;
+
+const MSH_TRIANGLE: u8 = 2;
+
+/// Writes the mesh to a `.msh` file.
+pub fn write_msh(path: impl AsRef<Path>, mesh: &PolyMesh) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    write_ascii(&mut file, mesh)?;
+    file.flush()
+}
+
+pub fn write_ascii(writer: &mut impl Write, mesh: &PolyMesh) -> io::Result<()> {
+    let surfaces = mesh.faces.len();
+
+    // Shared vertices, then the center of each face
+    let mut nodes = mesh.vertices.clone();
+    for face in 0..surfaces {
+        let polygon = mesh.face_polygon(face);
+        let n = polygon.len() as f64;
+        let (sx, sy) = polygon
+            .iter()
+            .fold((0.0, 0.0), |(sx, sy), &(x, y)| (sx + x, sy + y));
+        nodes.push((sx / n, sy / n));
+    }
+    let center = |face: usize| mesh.vertices.len() + face;
+
+    // Each node is classified on the first surface using it
+    let mut owner = vec![usize::MAX; nodes.len()];
+    for face in 0..surfaces {
+        for v in mesh.face_vertices(face) {
+            if owner[v] == usize::MAX {
+                owner[v] = face;
+            }
+        }
+        owner[center(face)] = face;
+    }
+
+    writeln!(writer, "$MeshFormat\n4.1 0 8\n$EndMeshFormat")?;
+
+    writeln!(writer, "$PhysicalNames\n{surfaces}")?;
+    for (i, face) in mesh.faces.iter().enumerate() {
+        writeln!(writer, "2 {} \"seed_{}\"", i + 1, face.seed)?;
+    }
+    writeln!(writer, "$EndPhysicalNames")?;
+
+    writeln!(writer, "$Entities\n0 0 {surfaces} 0")?;
+    for i in 0..surfaces {
+        let (mut min, mut max) = ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN));
+        for (x, y) in mesh.face_polygon(i) {
+            min = (min.0.min(x), min.1.min(y));
+            max = (max.0.max(x), max.1.max(y));
+        }
+        // One physical tag, no bounding curves
+        writeln!(
+            writer,
+            "{} {} {} 0 {} {} 0 1 {} 0",
+            i + 1,
+            min.0,
+            min.1,
+            max.0,
+            max.1,
+            i + 1
+        )?;
+    }
+    writeln!(writer, "$EndEntities")?;
+
+    writeln!(
+        writer,
+        "$Nodes\n{surfaces} {} 1 {}",
+        nodes.len(),
+        nodes.len()
+    )?;
+    for surface in 0..surfaces {
+        let tags: Vec<usize> = (0..nodes.len()).filter(|&n| owner[n] == surface).collect();
+        writeln!(writer, "2 {} 0 {}", surface + 1, tags.len())?;
+        for tag in &tags {
+            writeln!(writer, "{}", tag + 1)?;
+        }
+        for &tag in &tags {
+            writeln!(writer, "{} {} 0", nodes[tag].0, nodes[tag].1)?;
+        }
+    }
+    writeln!(writer, "$EndNodes")?;
+
+    // One triangle per half-edge
+    let elements = mesh.half_edges.len();
+    writeln!(writer, "$Elements\n{surfaces} {elements} 1 {elements}")?;
+    let mut tag = 1;
+    for i in 0..surfaces {
+        writeln!(
+            writer,
+            "2 {} {MSH_TRIANGLE} {}",
+            i + 1,
+            mesh.face_edges(i).count()
+        )?;
+        for h in mesh.face_edges(i) {
+            let (a, b) = (mesh.half_edges[h].origin, mesh.destination(h));
+            writeln!(writer, "{tag} {} {} {}", center(i) + 1, a + 1, b + 1)?;
+            tag += 1;
+        }
+    }
+    writeln!(writer, "$EndElements")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_two_cells() {
+        let mesh = PolyMesh::from_cells(&[
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+            vec![(1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0)],
+        ]);
+        let mut output = Vec::new();
+
+        write_ascii(&mut output, &mesh).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("$MeshFormat\n4.1 0 8\n$EndMeshFormat\n"));
+        assert!(output.contains("$PhysicalNames\n2\n2 1 \"seed_0\"\n2 2 \"seed_1\"\n"));
+        assert!(output.contains("$Entities\n0 0 2 0\n1 0 0 0 1 1 0 1 1 0\n2 1 0 0 2 1 0 1 2 0\n"));
+        // The second cell only owns its two right vertices and its center
+        assert!(output.contains("$Nodes\n2 8 1 8\n2 1 0 5\n"));
+        assert!(output.contains("2 2 0 3\n5\n6\n8\n2 0 0\n2 1 0\n1.5 0.5 0\n"));
+        assert!(output.contains("$Elements\n2 8 1 8\n2 1 2 4\n1 7 1 2\n"));
+        assert!(output.ends_with("8 8 3 2\n$EndElements\n"));
+    }
+}
@@ -0,0 +1,163 @@
+// Boundary condition manifest, a JSON file written alongside the mesh files which lists the
+// boundary patches of a 2D mesh for the case generators of FreeFEM, FEniCS or OpenFOAM. The
+// patches are those of the OpenFOAM writer: the boundary edges on each side of the bounding box,
+// then the other ones, around holes or masked-out regions, as walls. Each patch has an integer
+// tag, from 1, to use as a FreeFEM label or a FEniCS marker, the ids of its nodes and edges,
+// which are the mesh's vertices and boundary half-edges, as half-open ranges, and its length.
+//
+// Given the thickness of an OpenFOAM export, the manifest also lists the patches of the polyMesh
+// `openfoam::write_poly_mesh` writes with that thickness, with their face ranges and areas.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use super::openfoam::{side_patches, FoamMesh, SIDE_PATCHES};
+use crate::mesh::PolyMesh;
+
+/// Writes the manifest of the mesh to a `.json` file, with the OpenFOAM patches when given the
+/// `thickness` of its polyMesh.
+pub fn write_manifest(
+    path: impl AsRef<Path>,
+    mesh: &PolyMesh,
+    thickness: Option<f64>,
+) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    write_json(&mut file, mesh, thickness)?;
+    file.flush()
+}
+
+pub fn write_json(
+    writer: &mut impl Write,
+    mesh: &PolyMesh,
+    thickness: Option<f64>,
+) -> io::Result<()> {
+    writeln!(writer, "{{\n  \"dimension\": 2,")?;
+    writeln!(writer, "  \"nodes\": {},", mesh.vertices.len())?;
+    writeln!(writer, "  \"cells\": {},", mesh.faces.len())?;
+    writeln!(writer, "  \"patches\": [")?;
+    let patches = side_patches(mesh);
+    let lengths: Vec<f64> = patches
+        .iter()
+        // Folded from 0 rather than summed, an empty sum being -0
+        .map(|edges| edges.iter().fold(0.0, |sum, &h| sum + mesh.edge_length(h)))
+        .collect();
+    for (i, (edges, (name, kind))) in patches.into_iter().zip(SIDE_PATCHES).enumerate() {
+        let nodes = edges
+            .iter()
+            .flat_map(|&h| [mesh.half_edges[h].origin, mesh.destination(h)])
+            .collect();
+        let separator = if i + 1 < SIDE_PATCHES.len() { "," } else { "" };
+        writeln!(
+            writer,
+            "    {{\"name\": \"{name}\", \"tag\": {}, \"type\": \"{kind}\", \"nodes\": {}, \
+             \"edges\": {}, \"length\": {}}}{separator}",
+            i + 1,
+            ranges(nodes),
+            ranges(edges),
+            lengths[i]
+        )?;
+    }
+    write!(writer, "  ]")?;
+
+    if let Some(thickness) = thickness {
+        let foam = FoamMesh::from_mesh(mesh, thickness);
+        let area = (0..mesh.faces.len())
+            .map(|face| mesh.face_area(face))
+            .sum::<f64>();
+        let areas = lengths
+            .iter()
+            .map(|length| length * thickness)
+            .chain([2.0 * area]);
+        writeln!(writer, ",\n  \"openfoam\": {{")?;
+        writeln!(
+            writer,
+            "    \"thickness\": {thickness},\n    \"patches\": ["
+        )?;
+        for (i, (patch, area)) in foam.patches.iter().zip(areas).enumerate() {
+            let separator = if i + 1 < foam.patches.len() { "," } else { "" };
+            writeln!(
+                writer,
+                "      {{\"name\": \"{}\", \"type\": \"{}\", \"startFace\": {}, \
+                 \"nFaces\": {}, \"area\": {area}}}{separator}",
+                patch.name, patch.kind, patch.start, patch.faces
+            )?;
+        }
+        write!(writer, "    ]\n  }}")?;
+    }
+    writeln!(writer, "\n}}")
+}
+
+// Ids as sorted `[start, end]` runs of consecutive ids, `end` excluded
+fn ranges(mut ids: Vec<usize>) -> String {
+    ids.sort_unstable();
+    ids.dedup();
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for id in ids {
+        match runs.last_mut() {
+            Some(run) if run.1 == id => run.1 += 1,
+            _ => runs.push((id, id + 1)),
+        }
+    }
+    let runs: Vec<String> = runs
+        .iter()
+        .map(|(start, end)| format!("[{start}, {end}]"))
+        .collect();
+    format!("[{}]", runs.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranges() {
+        assert_eq!(ranges(vec![4, 1, 2, 2, 7, 3]), "[[1, 5], [7, 8]]");
+        assert_eq!(ranges(Vec::new()), "[]");
+    }
+
+    #[test]
+    fn test_manifest() {
+        let mesh = PolyMesh::from_cells(&[
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+            vec![(1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0)],
+        ]);
+        let mut output = Vec::new();
+
+        write_json(&mut output, &mesh, Some(0.5)).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("{\n  \"dimension\": 2,\n  \"nodes\": 6,\n  \"cells\": 2,\n"));
+        let left = mesh
+            .boundary_edges()
+            .find(|&h| mesh.vertices[mesh.destination(h)] == (0.0, 0.0))
+            .unwrap();
+        assert!(output.contains(&format!(
+            "{{\"name\": \"left\", \"tag\": 1, \"type\": \"patch\", \"nodes\": {}, \
+             \"edges\": [[{left}, {}]], \"length\": 1}},",
+            ranges(vec![mesh.half_edges[left].origin, mesh.destination(left)]),
+            left + 1
+        )));
+        assert!(output.contains("\"tag\": 3, \"type\": \"patch\", \"nodes\": [[0, 2], [4, 5]]"));
+        assert!(output.contains(
+            "{\"name\": \"walls\", \"tag\": 5, \"type\": \"wall\", \"nodes\": [], \
+             \"edges\": [], \"length\": 0}\n"
+        ));
+        // One internal face, then the sides, bottom and top, and the front and back
+        assert!(output.contains(
+            "{\"name\": \"bottom\", \"type\": \"patch\", \"startFace\": 3, \"nFaces\": 2, \
+             \"area\": 1},"
+        ));
+        assert!(output.contains(
+            "{\"name\": \"frontAndBack\", \"type\": \"empty\", \"startFace\": 7, \
+             \"nFaces\": 4, \"area\": 4}\n"
+        ));
+        assert!(output.ends_with("    ]\n  }\n}\n"));
+
+        let mut plain = Vec::new();
+        write_json(&mut plain, &mesh, None).unwrap();
+        let plain = String::from_utf8(plain).unwrap();
+        assert!(!plain.contains("openfoam"));
+        assert!(plain.ends_with("\"length\": 0}\n  ]\n}\n"));
+    }
+}
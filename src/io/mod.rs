@@ -0,0 +1,13 @@
+// Exporters of the cells to the file formats of meshing and visualization tools, and readers of
+// the seeds. The exporters write a `mesh::PolyMesh`, whose vertices are shared by neighboring
+// cells, so the written meshes are conforming. The glTF exporter also writes the voxel cells of
+// `jfa_wgpu_3d`, and the manifest lists the boundary patches of a mesh for case generators.
+
+pub mod flatbuffers;
+pub mod gltf;
+pub mod gmsh;
+pub mod manifest;
+pub mod obj;
+pub mod openfoam;
+pub mod seeds;
+pub mod vtk;
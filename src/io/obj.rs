@@ -0,0 +1,201 @@
+// Wavefront OBJ export of the cells as separate objects, for destructibles: Blender fracture
+// add-ons and physics engines build one rigid body per piece, with its origin at its center of
+// mass. The cells are extruded by a thickness into prisms, and every prism is an object
+// `seed_<index>` whose vertices are relative to its centroid, its pivot. A JSON sidecar gives
+// each piece its position, the centroid in world coordinates, its mass properties at unit
+// density, and its neighbors with the length of the edge they share, from which joints or
+// breaking thresholds are usually derived.
+//
+// Objects don't share vertices, since engines load them as separate meshes.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::mesh::PolyMesh;
+
+/// Mass properties of a cell prism of unit density.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MassProperties {
+    pub volume: f64,
+    /// Center of mass in world coordinates
+    pub centroid: (f64, f64, f64),
+    /// Inertia tensor about the centroid, `[[xx, xy, xz], [xy, yy, yz], [xz, yz, zz]]`
+    pub inertia: [[f64; 3]; 3],
+}
+
+/// Writes the prisms of the cells of `mesh` from z = 0 to z = `thickness` to a `.obj` file at
+/// `path`, and their sidecar to the `.json` file next to it.
+pub fn write_pieces(path: impl AsRef<Path>, mesh: &PolyMesh, thickness: f64) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut file = BufWriter::new(File::create(path)?);
+    write_obj(&mut file, mesh, thickness)?;
+    file.flush()?;
+    let mut file = BufWriter::new(File::create(path.with_extension("json"))?);
+    write_sidecar(&mut file, mesh, thickness)?;
+    file.flush()
+}
+
+pub fn write_obj(writer: &mut impl Write, mesh: &PolyMesh, thickness: f64) -> io::Result<()> {
+    assert!(thickness > 0.0, "pieces need a positive thickness");
+    // OBJ indices are 1-based and global to the file
+    let mut first = 1;
+    for face in 0..mesh.faces.len() {
+        let (cx, cy, cz) = mass_properties(mesh, face, thickness).centroid;
+        let polygon = mesh.face_polygon(face);
+        let n = polygon.len();
+        writeln!(writer, "o seed_{}", mesh.faces[face].seed)?;
+        for z in [0.0, thickness] {
+            for &(x, y) in &polygon {
+                writeln!(writer, "v {} {} {}", x - cx, y - cy, z - cz)?;
+            }
+        }
+        // Bottom faces down, top up, and the side of edge `i -> i + 1` out of the cell
+        let bottom: Vec<String> = (0..n).rev().map(|i| (first + i).to_string()).collect();
+        writeln!(writer, "f {}", bottom.join(" "))?;
+        let top: Vec<String> = (0..n).map(|i| (first + n + i).to_string()).collect();
+        writeln!(writer, "f {}", top.join(" "))?;
+        for i in 0..n {
+            let (a, b) = (first + i, first + (i + 1) % n);
+            writeln!(writer, "f {a} {b} {} {}", b + n, a + n)?;
+        }
+        first += 2 * n;
+    }
+    Ok(())
+}
+
+pub fn write_sidecar(writer: &mut impl Write, mesh: &PolyMesh, thickness: f64) -> io::Result<()> {
+    assert!(thickness > 0.0, "pieces need a positive thickness");
+    writeln!(writer, "{{\n  \"thickness\": {thickness},\n  \"pieces\": [")?;
+    for face in 0..mesh.faces.len() {
+        let properties = mass_properties(mesh, face, thickness);
+        let (x, y, z) = properties.centroid;
+        let inertia: Vec<String> = properties
+            .inertia
+            .iter()
+            .map(|row| format!("[{}, {}, {}]", row[0], row[1], row[2]))
+            .collect();
+        // Neighbors by seed, with the length of all the edges shared with them
+        let mut neighbors: BTreeMap<usize, f64> = BTreeMap::new();
+        for h in mesh.face_edges(face) {
+            if let Some(twin) = mesh.half_edges[h].twin {
+                let seed = mesh.faces[mesh.half_edges[twin].face].seed;
+                *neighbors.entry(seed).or_default() += mesh.edge_length(h);
+            }
+        }
+        let neighbors: Vec<String> = neighbors
+            .iter()
+            .map(|(seed, length)| format!("{{\"seed\": {seed}, \"length\": {length}}}"))
+            .collect();
+        writeln!(writer, "    {{")?;
+        writeln!(
+            writer,
+            "      \"name\": \"seed_{}\",",
+            mesh.faces[face].seed
+        )?;
+        writeln!(writer, "      \"seed\": {},", mesh.faces[face].seed)?;
+        writeln!(writer, "      \"position\": [{x}, {y}, {z}],")?;
+        writeln!(writer, "      \"volume\": {},", properties.volume)?;
+        writeln!(writer, "      \"mass\": {},", properties.volume)?;
+        writeln!(writer, "      \"inertia\": [{}],", inertia.join(", "))?;
+        writeln!(writer, "      \"neighbors\": [{}]", neighbors.join(", "))?;
+        let separator = if face + 1 < mesh.faces.len() { "," } else { "" };
+        writeln!(writer, "    }}{separator}")?;
+    }
+    writeln!(writer, "  ]\n}}")
+}
+
+/// Mass properties of the prism of `face` from z = 0 to z = `thickness`.
+pub fn mass_properties(mesh: &PolyMesh, face: usize, thickness: f64) -> MassProperties {
+    // Vertices relative to the first one, which keeps the sums accurate for small cells far from
+    // the origin
+    let polygon = mesh.face_polygon(face);
+    let (ox, oy) = polygon[0];
+    let polygon: Vec<(f64, f64)> = polygon.iter().map(|&(x, y)| (x - ox, y - oy)).collect();
+    let n = polygon.len();
+    let edges = || (0..n).map(|i| (polygon[i], polygon[(i + 1) % n]));
+
+    let mut area = 0.0;
+    let (mut cx, mut cy) = (0.0, 0.0);
+    for ((ax, ay), (bx, by)) in edges() {
+        let cross = ax * by - bx * ay;
+        area += cross / 2.0;
+        cx += (ax + bx) * cross / 6.0;
+        cy += (ay + by) * cross / 6.0;
+    }
+    let (cx, cy) = (cx / area, cy / area);
+
+    // Second moments of area about the centroid, from the vertices relative to it
+    let (mut xx, mut yy, mut xy) = (0.0, 0.0, 0.0);
+    for ((ax, ay), (bx, by)) in edges() {
+        let (ax, ay, bx, by) = (ax - cx, ay - cy, bx - cx, by - cy);
+        let cross = ax * by - bx * ay;
+        xx += cross * (ax * ax + ax * bx + bx * bx) / 12.0;
+        yy += cross * (ay * ay + ay * by + by * by) / 12.0;
+        xy += cross * (ax * by + 2.0 * ax * ay + 2.0 * bx * by + bx * ay) / 24.0;
+    }
+
+    let t = thickness;
+    let slab = area * t * t * t / 12.0;
+    MassProperties {
+        volume: area * t,
+        centroid: (cx + ox, cy + oy, t / 2.0),
+        inertia: [
+            [yy * t + slab, -xy * t, 0.0],
+            [-xy * t, xx * t + slab, 0.0],
+            [0.0, 0.0, (xx + yy) * t],
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_boxes() -> PolyMesh {
+        PolyMesh::from_cells(&[
+            vec![(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (0.0, 1.0)],
+            vec![(2.0, 0.0), (3.0, 0.0), (3.0, 1.0), (2.0, 1.0)],
+        ])
+    }
+
+    #[test]
+    fn test_mass_properties() {
+        // A 2 x 1 x 3 box: I = m (b² + c²) / 12
+        let properties = mass_properties(&two_boxes(), 0, 3.0);
+
+        assert_eq!(properties.volume, 6.0);
+        assert_eq!(properties.centroid, (1.0, 0.5, 1.5));
+        let expected = [1.0 + 9.0, 4.0 + 9.0, 4.0 + 1.0].map(|sum| 6.0 * sum / 12.0);
+        for (axis, expected) in expected.into_iter().enumerate() {
+            assert!((properties.inertia[axis][axis] - expected).abs() < 1e-12);
+        }
+        assert!(properties.inertia[0][1].abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_obj() {
+        let mut output = Vec::new();
+
+        write_obj(&mut output, &two_boxes(), 1.0).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("o seed_0\nv -1 -0.5 -0.5\nv 1 -0.5 -0.5\n"));
+        assert!(output.contains("f 4 3 2 1\nf 5 6 7 8\nf 1 2 6 5\n"));
+        assert!(output.contains("o seed_1\nv -0.5 -0.5 -0.5\n"));
+        assert!(output.ends_with("f 12 9 13 16\n"));
+    }
+
+    #[test]
+    fn test_sidecar() {
+        let mut output = Vec::new();
+
+        write_sidecar(&mut output, &two_boxes(), 1.0).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("\"position\": [2.5, 0.5, 0.5],"));
+        assert!(output.contains("\"neighbors\": [{\"seed\": 1, \"length\": 1}]"));
+        assert!(output.contains("\"neighbors\": [{\"seed\": 0, \"length\": 1}]\n    }\n  ]\n}"));
+    }
+}
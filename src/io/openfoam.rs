@@ -0,0 +1,369 @@
+// OpenFOAM polyMesh export. OpenFOAM meshes are always 3D, so the cells are extruded by a given
+// thickness into one layer of prisms, the usual setup of 2D cases, with their bottom and top
+// faces in an `empty` patch. Domain edges go to one patch per side of the bounding box, and any
+// other boundary edge, around a masked-out region for instance, to a `walls` patch. Layers of
+// prisms from `extrude::extrude` are written with their tags as the `sides`, `bottom` and `top`
+// patches instead.
+//
+// Faces are ordered as OpenFOAM expects: internal faces sorted by owner then neighbour, then
+// the boundary faces patch by patch, all oriented out of their owner. Cells wrapping across a
+// periodic edge aren't supported, their seams would come out as walls.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::extrude::{FaceTag, PrismMesh};
+use crate::mesh::PolyMesh;
+
+// Relative tolerance for a boundary edge to lie on a side of the bounding box
+const SIDE_TOLERANCE: f64 = 1e-9;
+
+// Names and types of the patches of the boundary edges of a 2D mesh, the box sides then the
+// walls
+pub(super) const SIDE_PATCHES: [(&str, &str); 5] = [
+    ("left", "patch"),
+    ("right", "patch"),
+    ("bottom", "patch"),
+    ("top", "patch"),
+    ("walls", "wall"),
+];
+
+/// Writes `points`, `faces`, `owner`, `neighbour` and `boundary` to `directory`, typically
+/// `constant/polyMesh` of a case, creating it if needed.
+pub fn write_poly_mesh(
+    directory: impl AsRef<Path>,
+    mesh: &PolyMesh,
+    thickness: f64,
+) -> io::Result<()> {
+    write_foam_mesh(directory.as_ref(), &FoamMesh::from_mesh(mesh, thickness))
+}
+
+fn write_foam_mesh(directory: &Path, mesh: &FoamMesh) -> io::Result<()> {
+    fs::create_dir_all(directory)?;
+
+    write_file(directory, "points", "vectorField", None, |file| {
+        write_list(file, &mesh.points, |(x, y, z)| format!("({x} {y} {z})"))
+    })?;
+    write_file(directory, "faces", "faceList", None, |file| {
+        write_list(file, &mesh.faces, |face| {
+            let vertices: Vec<String> = face.iter().map(usize::to_string).collect();
+            format!("{}({})", face.len(), vertices.join(" "))
+        })
+    })?;
+    let note = format!(
+        "nPoints: {} nCells: {} nFaces: {} nInternalFaces: {}",
+        mesh.points.len(),
+        mesh.cells,
+        mesh.faces.len(),
+        mesh.neighbour.len()
+    );
+    write_file(directory, "owner", "labelList", Some(&note), |file| {
+        write_list(file, &mesh.owner, usize::to_string)
+    })?;
+    write_file(directory, "neighbour", "labelList", Some(&note), |file| {
+        write_list(file, &mesh.neighbour, usize::to_string)
+    })?;
+    write_file(directory, "boundary", "polyBoundaryMesh", None, |file| {
+        writeln!(file, "{}\n(", mesh.patches.len())?;
+        for patch in &mesh.patches {
+            writeln!(file, "    {}\n    {{", patch.name)?;
+            writeln!(file, "        type            {};", patch.kind)?;
+            if patch.kind != "patch" {
+                writeln!(file, "        inGroups        1({});", patch.kind)?;
+            }
+            writeln!(file, "        nFaces          {};", patch.faces)?;
+            writeln!(file, "        startFace       {};", patch.start)?;
+            writeln!(file, "    }}")?;
+        }
+        writeln!(file, ")")
+    })
+}
+
+/// Writes the polyMesh of multi-layer prisms to `directory`, with their side, bottom and top
+/// faces in patches of those names.
+pub fn write_prisms(directory: impl AsRef<Path>, prisms: &PrismMesh) -> io::Result<()> {
+    write_foam_mesh(directory.as_ref(), &FoamMesh::from_prisms(prisms))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Patch {
+    pub(super) name: &'static str,
+    pub(super) kind: &'static str,
+    pub(super) start: usize,
+    pub(super) faces: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct FoamMesh {
+    points: Vec<(f64, f64, f64)>,
+    faces: Vec<Vec<usize>>,
+    owner: Vec<usize>,
+    neighbour: Vec<usize>,
+    pub(super) patches: Vec<Patch>,
+    cells: usize,
+}
+
+impl FoamMesh {
+    pub(super) fn from_mesh(mesh: &PolyMesh, thickness: f64) -> FoamMesh {
+        let n = mesh.vertices.len();
+        let mut points: Vec<(f64, f64, f64)> =
+            mesh.vertices.iter().map(|&(x, y)| (x, y, 0.0)).collect();
+        points.extend(mesh.vertices.iter().map(|&(x, y)| (x, y, thickness)));
+
+        // The side quad of a counter-clockwise edge `a -> b` faces out of its cell
+        let side = |a: usize, b: usize| vec![a, b, b + n, a + n];
+
+        // Internal faces come from the half-edge of their owner, the lower cell of the twins
+        let mut internal = Vec::new();
+        for (h, half_edge) in mesh.half_edges.iter().enumerate() {
+            if let Some(twin) = half_edge.twin {
+                if half_edge.face < mesh.half_edges[twin].face {
+                    let face = side(half_edge.origin, mesh.destination(h));
+                    internal.push((half_edge.face, mesh.half_edges[twin].face, face));
+                }
+            }
+        }
+        internal.sort_by_key(|&(owner, neighbour, _)| (owner, neighbour));
+
+        let sides = side_patches(mesh).map(|edges| {
+            edges
+                .into_iter()
+                .map(|h| {
+                    let (a, b) = (mesh.half_edges[h].origin, mesh.destination(h));
+                    (mesh.half_edges[h].face, side(a, b))
+                })
+                .collect::<Vec<_>>()
+        });
+        // Bottom faces are reversed to face down
+        let front_and_back = (0..mesh.faces.len()).flat_map(|cell| {
+            let polygon: Vec<usize> = mesh.face_vertices(cell).collect();
+            let bottom = polygon.iter().rev().copied().collect();
+            let top = polygon.iter().map(|&v| v + n).collect();
+            [(cell, bottom), (cell, top)]
+        });
+
+        let mut faces = Vec::new();
+        let mut owner = Vec::new();
+        let mut neighbour = Vec::new();
+        for (cell, other, face) in internal {
+            faces.push(face);
+            owner.push(cell);
+            neighbour.push(other);
+        }
+
+        let mut patches = Vec::new();
+        let patch_faces = sides
+            .into_iter()
+            .zip(SIDE_PATCHES)
+            .chain([(front_and_back.collect(), ("frontAndBack", "empty"))]);
+        for (patch, (name, kind)) in patch_faces {
+            patches.push(Patch {
+                name,
+                kind,
+                start: faces.len(),
+                faces: patch.len(),
+            });
+            for (cell, face) in patch {
+                faces.push(face);
+                owner.push(cell);
+            }
+        }
+
+        FoamMesh {
+            points,
+            faces,
+            owner,
+            neighbour,
+            patches,
+            cells: mesh.faces.len(),
+        }
+    }
+}
+
+// Boundary half-edges of `mesh` in each of `SIDE_PATCHES`, in order
+pub(super) fn side_patches(mesh: &PolyMesh) -> [Vec<usize>; 5] {
+    let (mut min, mut max) = ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN));
+    for &(x, y) in &mesh.vertices {
+        min = (min.0.min(x), min.1.min(y));
+        max = (max.0.max(x), max.1.max(y));
+    }
+    let tolerance = SIDE_TOLERANCE * (max.0 - min.0).max(max.1 - min.1);
+    let on = |value: f64, side: f64| (value - side).abs() <= tolerance;
+    let patch_of = |h: usize| {
+        let (a, b) = (mesh.half_edges[h].origin, mesh.destination(h));
+        let ((ax, ay), (bx, by)) = (mesh.vertices[a], mesh.vertices[b]);
+        if on(ax, min.0) && on(bx, min.0) {
+            0
+        } else if on(ax, max.0) && on(bx, max.0) {
+            1
+        } else if on(ay, min.1) && on(by, min.1) {
+            2
+        } else if on(ay, max.1) && on(by, max.1) {
+            3
+        } else {
+            4
+        }
+    };
+    let mut patches: [Vec<usize>; 5] = Default::default();
+    for h in mesh.boundary_edges() {
+        patches[patch_of(h)].push(h);
+    }
+    patches
+}
+
+impl FoamMesh {
+    fn from_prisms(prisms: &PrismMesh) -> FoamMesh {
+        let mut internal = Vec::new();
+        let mut patches: [Vec<usize>; 3] = Default::default();
+        for (i, tag) in prisms.tags.iter().enumerate() {
+            match (tag, prisms.neighbour[i]) {
+                (_, Some(neighbour)) => internal.push((prisms.owner[i], neighbour, i)),
+                (FaceTag::Side, None) => patches[0].push(i),
+                (FaceTag::Bottom, None) => patches[1].push(i),
+                (FaceTag::Top, None) => patches[2].push(i),
+                (FaceTag::Interior, None) => unreachable!("interior face without a neighbour"),
+            }
+        }
+        internal.sort_unstable();
+
+        let mut faces = Vec::with_capacity(prisms.faces.len());
+        let mut owner = Vec::with_capacity(prisms.faces.len());
+        let mut neighbour = Vec::with_capacity(internal.len());
+        for (cell, other, i) in internal {
+            faces.push(prisms.faces[i].clone());
+            owner.push(cell);
+            neighbour.push(other);
+        }
+        let mut foam_patches = Vec::new();
+        for (patch, name) in patches.into_iter().zip(["sides", "bottom", "top"]) {
+            foam_patches.push(Patch {
+                name,
+                kind: "patch",
+                start: faces.len(),
+                faces: patch.len(),
+            });
+            for i in patch {
+                faces.push(prisms.faces[i].clone());
+                owner.push(prisms.owner[i]);
+            }
+        }
+
+        FoamMesh {
+            points: prisms.points.clone(),
+            faces,
+            owner,
+            neighbour,
+            patches: foam_patches,
+            cells: prisms.cells.len(),
+        }
+    }
+}
+
+fn write_file(
+    directory: &Path,
+    object: &str,
+    class: &str,
+    note: Option<&str>,
+    contents: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(directory.join(object))?);
+    writeln!(file, "FoamFile\n{{")?;
+    writeln!(file, "    version     2.0;")?;
+    writeln!(file, "    format      ascii;")?;
+    writeln!(file, "    class       {class};")?;
+    if let Some(note) = note {
+        writeln!(file, "    note        \"{note}\";")?;
+    }
+    writeln!(file, "    location    \"constant/polyMesh\";")?;
+    writeln!(file, "    object      {object};")?;
+    writeln!(file, "}}\n")?;
+    contents(&mut file)?;
+    file.flush()
+}
+
+fn write_list<T>(
+    file: &mut impl Write,
+    items: &[T],
+    format: impl Fn(&T) -> String,
+) -> io::Result<()> {
+    writeln!(file, "{}\n(", items.len())?;
+    for item in items {
+        writeln!(file, "{}", format(item))?;
+    }
+    writeln!(file, ")")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extrude::extrude;
+
+    #[test]
+    fn test_prisms() {
+        let mesh = PolyMesh::from_cells(&[
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+            vec![(1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0)],
+        ]);
+
+        let mesh = FoamMesh::from_prisms(&extrude(&mesh, &[0.1, 0.1, 0.1]));
+
+        assert_eq!(mesh.cells, 6);
+        assert_eq!(mesh.points.len(), 24);
+        // 3 faces between the columns, and 2 between the layers of each column
+        assert_eq!(mesh.neighbour.len(), 3 + 2 * 2);
+        let internal: Vec<(usize, usize)> = mesh
+            .owner
+            .iter()
+            .copied()
+            .zip(mesh.neighbour.clone())
+            .collect();
+        let mut sorted = internal.clone();
+        sorted.sort();
+        assert_eq!(internal, sorted);
+        let counts: Vec<(&str, usize, usize)> = mesh
+            .patches
+            .iter()
+            .map(|patch| (patch.name, patch.start, patch.faces))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![("sides", 7, 18), ("bottom", 25, 2), ("top", 27, 2)]
+        );
+        assert_eq!(mesh.faces.len(), 29);
+    }
+
+    #[test]
+    fn test_two_cells() {
+        let mesh = PolyMesh::from_cells(&[
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+            vec![(1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0)],
+        ]);
+
+        let mesh = FoamMesh::from_mesh(&mesh, 0.1);
+
+        assert_eq!(mesh.points.len(), 12);
+        // One internal face, pointing from cell 0 to cell 1
+        assert_eq!(mesh.neighbour, vec![1]);
+        assert_eq!(mesh.faces[0], vec![1, 2, 8, 7]);
+        let counts: Vec<(&str, usize, usize)> = mesh
+            .patches
+            .iter()
+            .map(|patch| (patch.name, patch.start, patch.faces))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                ("left", 1, 1),
+                ("right", 2, 1),
+                ("bottom", 3, 2),
+                ("top", 5, 2),
+                ("walls", 7, 0),
+                ("frontAndBack", 7, 4),
+            ]
+        );
+        assert_eq!(mesh.faces.len(), 11);
+        assert_eq!(mesh.owner.len(), 11);
+        assert_eq!(mesh.faces[7], vec![3, 2, 1, 0]);
+    }
+}
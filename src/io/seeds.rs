@@ -0,0 +1,446 @@
+// Seed point readers, from CSV and JSON files, so seeds computed by other tools can be meshed.
+// Each seed may come with a weight, for power diagrams, a tag naming it and a flag pinning it
+// in place during relaxation and balancing. Malformed rows are
+// reported with their line number instead of being skipped, since a silently missing seed
+// changes the mesh.
+//
+// CSV rows are `x,y[,weight[,tag]]`. A first row which doesn't start with a number is a header
+// naming the columns `x`, `y`, `weight`, `tag` and `fixed`, in any order, other columns being
+// ignored, so seeds are only pinned by files with a header. Fixed fields are `true`, `false`, `1`
+// or `0`. Empty rows and rows starting with `#` are skipped. JSON files hold one array of seeds,
+// either `[x, y]` or `[x, y, weight]` arrays or
+// `{"x": .., "y": .., "weight": .., "tag": .., "fixed": ..}` objects, and are read with
+// serde_json, so only with the `serde` feature.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+#[cfg(feature = "serde")]
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+
+/// Seeds read from a file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Seeds {
+    pub points: Vec<(f64, f64)>,
+    /// Weight of each seed, in squared domain units, `None` when the file has none
+    pub weights: Option<Vec<f64>>,
+    /// Tag of each seed, empty for untagged seeds, `None` when the file has none
+    pub tags: Option<Vec<String>>,
+    /// Whether each seed stays in place, `None` when the file pins none
+    pub fixed: Option<Vec<bool>>,
+}
+
+impl Seeds {
+    /// Seeds with their weight, 0 when the file has none, as `jfa_wgpu::run_weighted` takes
+    /// them.
+    pub fn weighted(&self) -> Vec<(f64, f64, f64)> {
+        let weight = |i: usize| self.weights.as_ref().map_or(0.0, |weights| weights[i]);
+        (0..self.points.len())
+            .map(|i| (self.points[i].0, self.points[i].1, weight(i)))
+            .collect()
+    }
+
+    /// Whether each seed is fixed, empty when the file pins none, as `jfa_wgpu::relax` and
+    /// `jfa_wgpu::balance` take them.
+    pub fn pinned(&self) -> &[bool] {
+        self.fixed.as_deref().unwrap_or_default()
+    }
+}
+
+/// Errors of the seed readers.
+#[derive(Debug)]
+pub enum SeedError {
+    Io(io::Error),
+    /// The row or element starting on `line`, counted from 1, can't be read
+    Malformed {
+        line: usize,
+        message: String,
+    },
+    /// The file's format needs a feature the crate was built without
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for SeedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SeedError::Io(err) => write!(f, "{err}"),
+            SeedError::Malformed { line, message } => write!(f, "line {line}: {message}"),
+            SeedError::Unsupported(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for SeedError {}
+
+impl From<io::Error> for SeedError {
+    fn from(err: io::Error) -> Self {
+        SeedError::Io(err)
+    }
+}
+
+fn malformed<T>(line: usize, message: impl Into<String>) -> Result<T, SeedError> {
+    Err(SeedError::Malformed {
+        line,
+        message: message.into(),
+    })
+}
+
+/// Reads a `.json` file as JSON, which needs the `serde` feature, and any other file as CSV.
+pub fn read_seeds(path: impl AsRef<Path>) -> Result<Seeds, SeedError> {
+    let path = path.as_ref();
+    match path.extension().and_then(|extension| extension.to_str()) {
+        #[cfg(feature = "serde")]
+        Some(extension) if extension.eq_ignore_ascii_case("json") => {
+            parse_json(&fs::read_to_string(path)?)
+        }
+        #[cfg(not(feature = "serde"))]
+        Some(extension) if extension.eq_ignore_ascii_case("json") => Err(SeedError::Unsupported(
+            "JSON seeds need the `serde` feature",
+        )),
+        _ => parse_csv(&fs::read_to_string(path)?),
+    }
+}
+
+pub fn read_csv(reader: &mut impl Read) -> Result<Seeds, SeedError> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    parse_csv(&text)
+}
+
+#[cfg(feature = "serde")]
+pub fn read_json(reader: &mut impl Read) -> Result<Seeds, SeedError> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    parse_json(&text)
+}
+
+// Column of each field, or `None` when the file doesn't have it
+struct Columns {
+    x: usize,
+    y: usize,
+    weight: Option<usize>,
+    tag: Option<usize>,
+    fixed: Option<usize>,
+    count: usize,
+}
+
+fn parse_csv(text: &str) -> Result<Seeds, SeedError> {
+    let mut rows = text
+        .lines()
+        .enumerate()
+        .map(|(i, row)| (i + 1, row.trim()))
+        .filter(|(_, row)| !row.is_empty() && !row.starts_with('#'))
+        .map(|(line, row)| (line, row.split(',').map(str::trim).collect::<Vec<_>>()))
+        .peekable();
+
+    let Some((line, first)) = rows.peek() else {
+        return Ok(Seeds::default());
+    };
+    let columns = if first[0].parse::<f64>().is_ok() {
+        if first.len() < 2 {
+            return malformed(*line, "expected at least 2 fields, found 1");
+        }
+        // Without a header, the first row tells which of the optional columns there are
+        Columns {
+            x: 0,
+            y: 1,
+            weight: (first.len() > 2).then_some(2),
+            tag: (first.len() > 3).then_some(3),
+            fixed: None,
+            count: first.len(),
+        }
+    } else {
+        let column = |name: &str| {
+            first
+                .iter()
+                .position(|field| field.eq_ignore_ascii_case(name))
+        };
+        let (Some(x), Some(y)) = (column("x"), column("y")) else {
+            return malformed(*line, "header has no `x` and `y` columns");
+        };
+        let columns = Columns {
+            x,
+            y,
+            weight: column("weight"),
+            tag: column("tag"),
+            fixed: column("fixed"),
+            count: first.len(),
+        };
+        rows.next();
+        columns
+    };
+
+    let mut seeds = Seeds {
+        points: Vec::new(),
+        weights: columns.weight.map(|_| Vec::new()),
+        tags: columns.tag.map(|_| Vec::new()),
+        fixed: columns.fixed.map(|_| Vec::new()),
+    };
+    for (line, fields) in rows {
+        if fields.len() != columns.count {
+            return malformed(
+                line,
+                format!("expected {} fields, found {}", columns.count, fields.len()),
+            );
+        }
+        let number = |column: usize, name: &str| -> Result<f64, SeedError> {
+            match fields[column].parse::<f64>() {
+                Ok(value) if value.is_finite() => Ok(value),
+                _ => malformed(
+                    line,
+                    format!("{name} `{}` isn't a finite number", fields[column]),
+                ),
+            }
+        };
+        seeds
+            .points
+            .push((number(columns.x, "x")?, number(columns.y, "y")?));
+        if let (Some(weights), Some(column)) = (&mut seeds.weights, columns.weight) {
+            weights.push(number(column, "weight")?);
+        }
+        if let (Some(tags), Some(column)) = (&mut seeds.tags, columns.tag) {
+            tags.push(fields[column].to_string());
+        }
+        if let (Some(fixed), Some(column)) = (&mut seeds.fixed, columns.fixed) {
+            fixed.push(match fields[column].to_ascii_lowercase().as_str() {
+                "true" | "1" => true,
+                "false" | "0" => false,
+                _ => return malformed(line, format!("fixed `{}` isn't a boolean", fields[column])),
+            });
+        }
+    }
+    Ok(seeds)
+}
+
+#[cfg(feature = "serde")]
+fn parse_json(text: &str) -> Result<Seeds, SeedError> {
+    serde_json::from_str(text)
+        .map(|JsonSeeds(seeds)| seeds)
+        .map_err(|err| {
+            // The line is kept apart, as the CSV errors have it
+            let message = err.to_string();
+            let position = format!(" at line {} column {}", err.line(), err.column());
+            SeedError::Malformed {
+                line: err.line(),
+                message: message
+                    .strip_suffix(&position)
+                    .unwrap_or(&message)
+                    .to_string(),
+            }
+        })
+}
+
+// Seeds of a JSON file, checked as they are read so errors point at the seed at fault
+#[cfg(feature = "serde")]
+struct JsonSeeds(Seeds);
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for JsonSeeds {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SeedsVisitor;
+
+        impl<'de> Visitor<'de> for SeedsVisitor {
+            type Value = JsonSeeds;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("an array of seeds")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<JsonSeeds, A::Error> {
+                let mut points = Vec::new();
+                let mut weights = Vec::new();
+                let mut tags = Vec::new();
+                let mut fixed = Vec::new();
+                // Weights change every cell, so they are all or nothing, while tags can be
+                // left out
+                let mut weighted = None;
+                while let Some(JsonSeed {
+                    point,
+                    weight,
+                    tag,
+                    pinned,
+                }) = seq.next_element()?
+                {
+                    match (*weighted.get_or_insert(weight.is_some()), weight) {
+                        (true, None) => {
+                            return Err(de::Error::custom(
+                                "seed has no weight while others have one",
+                            ))
+                        }
+                        (false, Some(_)) => {
+                            return Err(de::Error::custom(
+                                "seed has a weight while others have none",
+                            ))
+                        }
+                        _ => {}
+                    }
+                    points.push(point);
+                    weights.extend(weight);
+                    tags.push(tag);
+                    fixed.push(pinned);
+                }
+                let tags = tags
+                    .iter()
+                    .any(Option::is_some)
+                    .then(|| tags.into_iter().map(Option::unwrap_or_default).collect());
+                let fixed = fixed
+                    .iter()
+                    .any(Option::is_some)
+                    .then(|| fixed.into_iter().map(Option::unwrap_or_default).collect());
+                Ok(JsonSeeds(Seeds {
+                    points,
+                    weights: weighted.unwrap_or(false).then_some(weights),
+                    tags,
+                    fixed,
+                }))
+            }
+        }
+
+        deserializer.deserialize_seq(SeedsVisitor)
+    }
+}
+
+// `[x, y]` or `[x, y, weight]` array, or `{"x": .., "y": .., "weight": .., "tag": .., "fixed": ..}`
+// object
+#[cfg(feature = "serde")]
+struct JsonSeed {
+    point: (f64, f64),
+    weight: Option<f64>,
+    tag: Option<String>,
+    pinned: Option<bool>,
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for JsonSeed {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SeedVisitor;
+
+        impl<'de> Visitor<'de> for SeedVisitor {
+            type Value = JsonSeed;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a seed array or object")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<JsonSeed, A::Error> {
+                let mut values = Vec::with_capacity(3);
+                while let Some(value) = seq.next_element::<f64>()? {
+                    values.push(value);
+                }
+                let (point, weight) = match values[..] {
+                    [x, y] => ((x, y), None),
+                    [x, y, weight] => ((x, y), Some(weight)),
+                    _ => {
+                        return Err(de::Error::custom(format!(
+                            "expected 2 or 3 numbers, found {}",
+                            values.len()
+                        )))
+                    }
+                };
+                Ok(JsonSeed {
+                    point,
+                    weight,
+                    tag: None,
+                    pinned: None,
+                })
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<JsonSeed, A::Error> {
+                let (mut x, mut y, mut weight, mut tag, mut pinned) =
+                    (None, None, None, None, None);
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "x" => x = Some(map.next_value()?),
+                        "y" => y = Some(map.next_value()?),
+                        "weight" => weight = Some(map.next_value()?),
+                        "tag" => tag = Some(map.next_value()?),
+                        "fixed" => pinned = Some(map.next_value()?),
+                        // Other fields are ignored, as other columns of CSV files are
+                        _ => {
+                            map.next_value::<de::IgnoredAny>()?;
+                        }
+                    }
+                }
+                let x = x.ok_or_else(|| de::Error::missing_field("x"))?;
+                let y = y.ok_or_else(|| de::Error::missing_field("y"))?;
+                Ok(JsonSeed {
+                    point: (x, y),
+                    weight,
+                    tag,
+                    pinned,
+                })
+            }
+        }
+
+        deserializer.deserialize_any(SeedVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv() {
+        let plain = parse_csv("# exported seeds\n0.5,1\n\n2, 3.25\n").unwrap();
+        assert_eq!(plain.points, [(0.5, 1.0), (2.0, 3.25)]);
+        assert_eq!(plain.weights, None);
+
+        let named = parse_csv("tag,y,x,weight\ninlet,1,2,0.5\n,3,4,0\n").unwrap();
+        assert_eq!(named.points, [(2.0, 1.0), (4.0, 3.0)]);
+        assert_eq!(named.weighted(), [(2.0, 1.0, 0.5), (4.0, 3.0, 0.0)]);
+        assert_eq!(named.tags.unwrap(), ["inlet", ""]);
+        assert_eq!(named.fixed, None);
+
+        let pinned = parse_csv("x,y,fixed\n1,2,true\n3,4,0\n").unwrap();
+        assert_eq!(pinned.pinned(), [true, false]);
+        let err = parse_csv("x,y,fixed\n1,2,yes\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: fixed `yes` isn't a boolean");
+
+        let err = parse_csv("x,y\n1,2\n3\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 3: expected 2 fields, found 1");
+        let err = parse_csv("1,2\n3,nan\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: y `nan` isn't a finite number");
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_json() {
+        let arrays = parse_json("[[0.5, 1], [2, 3.25e0]]").unwrap();
+        assert_eq!(arrays.points, [(0.5, 1.0), (2.0, 3.25)]);
+        assert_eq!(arrays.tags, None);
+
+        let text = r#"[
+            {"x": 1, "y": 2, "weight": 0.5, "tag": "inlet", "id": [7]},
+            {"y": 4, "x": 3, "weight": -1}
+        ]"#;
+        let objects = parse_json(text).unwrap();
+        assert_eq!(objects.weighted(), [(1.0, 2.0, 0.5), (3.0, 4.0, -1.0)]);
+        assert_eq!(objects.tags.unwrap(), ["inlet", ""]);
+        assert_eq!(objects.fixed, None);
+        let pinned = parse_json(r#"[{"x": 1, "y": 2, "fixed": true}, [3, 4]]"#).unwrap();
+        assert_eq!(pinned.pinned(), [true, false]);
+
+        let line = |text: &str| match parse_json(text).unwrap_err() {
+            SeedError::Malformed { line, message } => (line, message),
+            err => panic!("{err}"),
+        };
+        assert_eq!(
+            line("[\n  [1, 2, 3],\n  [4, 5]\n]"),
+            (3, "seed has no weight while others have one".to_string())
+        );
+        assert_eq!(
+            line("[\n  [1, 2],\n  [3, 4, 5, 6]\n]"),
+            (3, "expected 2 or 3 numbers, found 4".to_string())
+        );
+        assert_eq!(
+            line("[\n  {\"x\": 1, \"y\": 2},\n  {\"x\": 3}\n]"),
+            (3, "missing field `y`".to_string())
+        );
+        assert_eq!(line("[\n  [1, 2],\n  [3, \"4\"]\n]").0, 3);
+        assert_eq!(line(&"[".repeat(100_000)).0, 1);
+        assert_eq!(line("[[1, 2]").0, 1);
+    }
+}
@@ -0,0 +1,221 @@
+// VTK exports, legacy `.vtk` polydata and XML `.vtu` unstructured grids, both in ASCII so they
+// can be diffed. z is always 0, and the seed index of each polygon is written as cell data.
+// Hexahedral meshes go to legacy unstructured grids, with the seed of each hexahedron.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::hex::HexMesh;
+use crate::mesh::PolyMesh;
+
+const VTK_POLYGON: u8 = 7;
+const VTK_HEXAHEDRON: u8 = 12;
+
+/// Writes the mesh to a legacy `.vtk` file.
+pub fn write_vtk(path: impl AsRef<Path>, mesh: &PolyMesh) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    write_legacy(&mut file, mesh)?;
+    file.flush()
+}
+
+/// Writes the mesh to an XML `.vtu` file.
+pub fn write_vtu(path: impl AsRef<Path>, mesh: &PolyMesh) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    write_xml(&mut file, mesh)?;
+    file.flush()
+}
+
+pub fn write_legacy(writer: &mut impl Write, mesh: &PolyMesh) -> io::Result<()> {
+    writeln!(writer, "# vtk DataFile Version 3.0")?;
+    writeln!(writer, "Voronoi cells")?;
+    writeln!(writer, "ASCII")?;
+    writeln!(writer, "DATASET POLYDATA")?;
+
+    writeln!(writer, "POINTS {} double", mesh.vertices.len())?;
+    for (x, y) in &mesh.vertices {
+        writeln!(writer, "{x} {y} 0")?;
+    }
+
+    // The size counts the vertex count leading each polygon
+    let size = mesh.half_edges.len() + mesh.faces.len();
+    writeln!(writer, "POLYGONS {} {size}", mesh.faces.len())?;
+    for face in 0..mesh.faces.len() {
+        let polygon: Vec<usize> = mesh.face_vertices(face).collect();
+        writeln!(writer, "{} {}", polygon.len(), join(&polygon))?;
+    }
+
+    writeln!(writer, "CELL_DATA {}", mesh.faces.len())?;
+    writeln!(writer, "SCALARS seed int 1")?;
+    writeln!(writer, "LOOKUP_TABLE default")?;
+    for face in &mesh.faces {
+        writeln!(writer, "{}", face.seed)?;
+    }
+    Ok(())
+}
+
+/// Writes a hexahedral mesh to a legacy `.vtk` file.
+pub fn write_hex_vtk(path: impl AsRef<Path>, mesh: &HexMesh) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    write_hex_legacy(&mut file, mesh)?;
+    file.flush()
+}
+
+pub fn write_hex_legacy(writer: &mut impl Write, mesh: &HexMesh) -> io::Result<()> {
+    writeln!(writer, "# vtk DataFile Version 3.0")?;
+    writeln!(writer, "Hexahedral cells")?;
+    writeln!(writer, "ASCII")?;
+    writeln!(writer, "DATASET UNSTRUCTURED_GRID")?;
+
+    writeln!(writer, "POINTS {} double", mesh.nodes.len())?;
+    for (x, y, z) in &mesh.nodes {
+        writeln!(writer, "{x} {y} {z}")?;
+    }
+
+    let cells = mesh.hexes.len();
+    writeln!(writer, "CELLS {cells} {}", 9 * cells)?;
+    for hex in &mesh.hexes {
+        writeln!(writer, "8 {}", join(hex))?;
+    }
+    writeln!(writer, "CELL_TYPES {cells}")?;
+    for _ in 0..cells {
+        writeln!(writer, "{VTK_HEXAHEDRON}")?;
+    }
+
+    writeln!(writer, "CELL_DATA {cells}")?;
+    writeln!(writer, "SCALARS seed int 1")?;
+    writeln!(writer, "LOOKUP_TABLE default")?;
+    for seed in &mesh.seeds {
+        writeln!(writer, "{seed}")?;
+    }
+    Ok(())
+}
+
+pub fn write_xml(writer: &mut impl Write, mesh: &PolyMesh) -> io::Result<()> {
+    let points: Vec<String> = mesh
+        .vertices
+        .iter()
+        .map(|(x, y)| format!("{x} {y} 0"))
+        .collect();
+    let connectivity: Vec<usize> = (0..mesh.faces.len())
+        .flat_map(|face| mesh.face_vertices(face))
+        .collect();
+    let offsets: Vec<usize> = (0..mesh.faces.len())
+        .scan(0, |offset, face| {
+            *offset += mesh.face_edges(face).count();
+            Some(*offset)
+        })
+        .collect();
+    let types = vec![VTK_POLYGON; mesh.faces.len()];
+    let seeds: Vec<usize> = mesh.faces.iter().map(|face| face.seed).collect();
+
+    writeln!(writer, r#"<?xml version="1.0"?>"#)?;
+    writeln!(
+        writer,
+        r#"<VTKFile type="UnstructuredGrid" version="0.1" byte_order="LittleEndian">"#
+    )?;
+    writeln!(writer, "  <UnstructuredGrid>")?;
+    writeln!(
+        writer,
+        r#"    <Piece NumberOfPoints="{}" NumberOfCells="{}">"#,
+        mesh.vertices.len(),
+        mesh.faces.len()
+    )?;
+    writeln!(writer, "      <Points>")?;
+    data_array(writer, "Float64", None, Some(3), &points.join(" "))?;
+    writeln!(writer, "      </Points>")?;
+    writeln!(writer, "      <Cells>")?;
+    data_array(
+        writer,
+        "Int64",
+        Some("connectivity"),
+        None,
+        &join(&connectivity),
+    )?;
+    data_array(writer, "Int64", Some("offsets"), None, &join(&offsets))?;
+    data_array(writer, "UInt8", Some("types"), None, &join(&types))?;
+    writeln!(writer, "      </Cells>")?;
+    writeln!(writer, r#"      <CellData Scalars="seed">"#)?;
+    data_array(writer, "Int64", Some("seed"), None, &join(&seeds))?;
+    writeln!(writer, "      </CellData>")?;
+    writeln!(writer, "    </Piece>")?;
+    writeln!(writer, "  </UnstructuredGrid>")?;
+    writeln!(writer, "</VTKFile>")
+}
+
+fn data_array(
+    writer: &mut impl Write,
+    data_type: &str,
+    name: Option<&str>,
+    components: Option<usize>,
+    values: &str,
+) -> io::Result<()> {
+    write!(writer, r#"        <DataArray type="{data_type}""#)?;
+    if let Some(name) = name {
+        write!(writer, r#" Name="{name}""#)?;
+    }
+    if let Some(components) = components {
+        write!(writer, r#" NumberOfComponents="{components}""#)?;
+    }
+    writeln!(writer, r#" format="ascii">{values}</DataArray>"#)
+}
+
+fn join<T: ToString>(values: &[T]) -> String {
+    let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+    values.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_squares() -> PolyMesh {
+        PolyMesh::from_cells(&[
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+            vec![(1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0)],
+        ])
+    }
+
+    #[test]
+    fn test_legacy() {
+        let mut output = Vec::new();
+
+        write_legacy(&mut output, &two_squares()).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("POINTS 6 double\n0 0 0\n1 0 0\n"));
+        assert!(output.contains("POLYGONS 2 10\n4 0 1 2 3\n4 1 4 5 2\n"));
+        assert!(output.ends_with("CELL_DATA 2\nSCALARS seed int 1\nLOOKUP_TABLE default\n0\n1\n"));
+    }
+
+    #[test]
+    fn test_hex_legacy() {
+        let mesh = HexMesh {
+            nodes: (0..8)
+                .map(|i| ((i & 1) as f64, (i >> 1 & 1) as f64, (i >> 2) as f64))
+                .collect(),
+            hexes: vec![[0, 1, 3, 2, 4, 5, 7, 6]],
+            seeds: vec![3],
+        };
+        let mut output = Vec::new();
+
+        write_hex_legacy(&mut output, &mesh).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("DATASET UNSTRUCTURED_GRID\nPOINTS 8 double\n0 0 0\n1 0 0\n"));
+        assert!(output.contains("CELLS 1 9\n8 0 1 3 2 4 5 7 6\nCELL_TYPES 1\n12\n"));
+        assert!(output.ends_with("LOOKUP_TABLE default\n3\n"));
+    }
+
+    #[test]
+    fn test_xml() {
+        let mut output = Vec::new();
+
+        write_xml(&mut output, &two_squares()).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains(r#"<Piece NumberOfPoints="6" NumberOfCells="2">"#));
+        assert!(output.contains(r#"Name="offsets" format="ascii">4 8</DataArray>"#));
+        assert!(output.contains(r#"Name="types" format="ascii">7 7</DataArray>"#));
+    }
+}
@@ -1,82 +1,151 @@
-static RESO: usize = 512;
-
-fn jfa_step(pixel_grid: &mut [usize], normal_points: &[(usize, usize)], k: usize) {
-    for x in 0..RESO {
-        for y in 0..RESO {
-            let initial_poisition = x + y * RESO;
-            // Check the 8-neighborhood (jump in all directions) and update to the closest point
-            for dx in [-1, 0, 1] {
-                for dy in [-1, 0, 1] {
-                    let new_x = x as isize + dx * k as isize;
-                    let new_y = y as isize + dy * k as isize;
-
-                    if !(new_x >= 0 && new_x < RESO as isize && new_y >= 0 && new_y < RESO as isize)
-                    {
-                        continue;
-                    }
+use rayon::prelude::*;
 
-                    let new_position = (new_x as usize) + (new_y as usize) * RESO;
-                    let found_color = pixel_grid[new_position];
-                    let current_color = pixel_grid[initial_poisition];
+use crate::config::MesherConfig;
 
-                    if (dx == 0 && dy == 0) || found_color == 0 || current_color == found_color {
-                        continue;
-                    }
+// One pass from `source` into `target`, a row of texels per task. Ties go to the lowest label
+// when `deterministic` is set
+fn jfa_step(
+    source: &[usize],
+    target: &mut [usize],
+    normal_points: &[(usize, usize)],
+    k: usize,
+    reso: usize,
+    deterministic: bool,
+) {
+    target
+        .par_chunks_mut(reso)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for (x, texel) in row.iter_mut().enumerate() {
+                let mut current_color = source[x + y * reso];
+                // Check the 8-neighborhood (jump in all directions) and keep the closest point
+                for dx in [-1, 0, 1] {
+                    for dy in [-1, 0, 1] {
+                        let new_x = x as isize + dx * k as isize;
+                        let new_y = y as isize + dy * k as isize;
 
-                    if current_color == 0 {
-                        pixel_grid[initial_poisition] = found_color;
-                        continue;
-                    }
+                        if !(new_x >= 0
+                            && new_x < reso as isize
+                            && new_y >= 0
+                            && new_y < reso as isize)
+                        {
+                            continue;
+                        }
+
+                        let found_color = source[(new_x as usize) + (new_y as usize) * reso];
 
-                    // we're now in the case where we have two colors distinct colors
-                    // so we'll assign the closest color to the current pixel
-                    let point1 = normal_points[current_color - 1];
-                    let point2 = normal_points[found_color - 1];
+                        if (dx == 0 && dy == 0) || found_color == 0 || current_color == found_color
+                        {
+                            continue;
+                        }
 
-                    let dist1 = ((x as isize - point1.0 as isize).pow(2)
-                        + (y as isize - point1.1 as isize).pow(2))
-                        as f64;
-                    let dist2 = ((x as isize - point2.0 as isize).pow(2)
-                        + (y as isize - point2.1 as isize).pow(2))
-                        as f64;
+                        if current_color == 0 {
+                            current_color = found_color;
+                            continue;
+                        }
 
-                    //dbg!(point1, point2, (x,y), dist1, dist2);
+                        // we're now in the case where we have two colors distinct colors
+                        // so we'll assign the closest color to the current pixel
+                        let point1 = normal_points[current_color - 1];
+                        let point2 = normal_points[found_color - 1];
 
-                    if dist2 < dist1 {
-                        pixel_grid[initial_poisition] = found_color;
+                        let dist1 = ((x as isize - point1.0 as isize).pow(2)
+                            + (y as isize - point1.1 as isize).pow(2))
+                            as f64;
+                        let dist2 = ((x as isize - point2.0 as isize).pow(2)
+                            + (y as isize - point2.1 as isize).pow(2))
+                            as f64;
+
+                        if dist2 < dist1
+                            || (deterministic && dist2 == dist1 && found_color < current_color)
+                        {
+                            current_color = found_color;
+                        }
                     }
                 }
+                *texel = current_color;
             }
-        }
-    }
+        });
 }
 
-pub fn jfa(points: &[(f64, f64)], config: (f64, f64)) -> Result<Vec<usize>, &'static str> {
-    let normal_points: Vec<(usize, usize)> = points
+fn init_normal_points(
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    reso: usize,
+) -> Vec<(usize, usize)> {
+    points
         .iter()
         .map(|(a, b)| {
-            let x = ((a * RESO as f64 / config.0).min(RESO as f64 - 1.0)) as usize;
-            let y = ((b * RESO as f64 / config.1).min(RESO as f64 - 1.0)) as usize;
+            let x = ((a * reso as f64 / config.0).min(reso as f64 - 1.0)) as usize;
+            let y = ((b * reso as f64 / config.1).min(reso as f64 - 1.0)) as usize;
             (x, y)
         })
-        .collect();
+        .collect()
+}
+
+pub fn jfa(points: &[(f64, f64)], config: (f64, f64)) -> Result<Vec<usize>, &'static str> {
+    jfa_with_options(points, config, &MesherConfig::default())
+}
+
+/// Same as [`jfa`], writing the debug dumps requested in `options`.
+pub fn jfa_with_options(
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    options: &MesherConfig,
+) -> Result<Vec<usize>, &'static str> {
+    let reso = options.resolution;
+    if reso == 0 {
+        return Err("resolution must be at least 1");
+    }
+    let dump = options.debug_dump.as_ref();
+    let normal_points = init_normal_points(points, config, reso);
+    if let Some(dump) = dump {
+        dump.seeds(&normal_points);
+    }
 
-    let mut pixel_grid = vec![0; RESO * RESO];
+    let mut pixel_grid = vec![0; reso * reso];
 
     // Mark the initial points on the grid with their respective color
     for (i, point) in normal_points.iter().enumerate() {
         let color = i + 1; // 0 means uncolored
-        pixel_grid[point.0 + point.1 * RESO] = color;
+        pixel_grid[point.0 + point.1 * reso] = color;
     }
 
     // Main JFA loop
     let now = std::time::Instant::now();
 
-    let mut k = (RESO / 2).max(1);
-    jfa_step(&mut pixel_grid, &normal_points, 1); // 1+JFA for more precision
+    let mut k = (reso / 2).max(1);
+    let mut pass = 0;
+    let mut scratch = vec![0; reso * reso];
+    let deterministic = options.deterministic;
+    // 1+JFA for more precision
+    jfa_step(
+        &pixel_grid,
+        &mut scratch,
+        &normal_points,
+        1,
+        reso,
+        deterministic,
+    );
+    std::mem::swap(&mut pixel_grid, &mut scratch);
+    if let Some(dump) = dump {
+        dump.labels(pass, 1, reso, &pixel_grid);
+    }
     while k >= 1 {
         //println!("Entering loop with k = {}", k);
-        jfa_step(&mut pixel_grid, &normal_points, k);
+        jfa_step(
+            &pixel_grid,
+            &mut scratch,
+            &normal_points,
+            k,
+            reso,
+            deterministic,
+        );
+        std::mem::swap(&mut pixel_grid, &mut scratch);
+        pass += 1;
+        if let Some(dump) = dump {
+            dump.labels(pass, k as u32, reso, &pixel_grid);
+        }
         k /= 2;
     }
 
@@ -86,10 +155,302 @@ pub fn jfa(points: &[(f64, f64)], config: (f64, f64)) -> Result<Vec<usize>, &'st
     Ok(pixel_grid)
 }
 
+/// Order-k JFA: each texel ends with the colors of its `order` nearest seeds, nearest first,
+/// from the texel center to the seed's exact position, the lowest color first on ties.
+///
+/// Texel `i` owns `labels[i * order..(i + 1) * order]`, padded with 0 when there are fewer
+/// seeds than `order`. Order-2 regions are the sets `{labels[2i], labels[2i + 1]}`, on a grid
+/// of `reso` by `reso` texels.
+pub fn jfa_k(
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    order: usize,
+    reso: usize,
+) -> Result<Vec<usize>, &'static str> {
+    if order == 0 {
+        return Err("order must be at least 1");
+    }
+    if reso == 0 {
+        return Err("resolution must be at least 1");
+    }
+    let positions: Vec<(f64, f64)> = points
+        .iter()
+        .map(|&point| to_grid(point, config, reso))
+        .collect();
+
+    let mut labels = vec![0; reso * reso * order];
+    for (i, &position) in positions.iter().enumerate() {
+        let (x, y) = to_pixel(position, reso);
+        labels[(x + y * reso) * order] = i + 1;
+    }
+
+    let mut scratch = vec![0; reso * reso * order];
+    let mut k = (reso / 2).max(1);
+    jfa_k_step(&labels, &mut scratch, &positions, order, 1, reso);
+    std::mem::swap(&mut labels, &mut scratch);
+    while k >= 1 {
+        jfa_k_step(&labels, &mut scratch, &positions, order, k, reso);
+        std::mem::swap(&mut labels, &mut scratch);
+        k /= 2;
+    }
+
+    Ok(labels)
+}
+
+// One pass from `source` into `target`, a row of texels per task
+fn jfa_k_step(
+    source: &[usize],
+    target: &mut [usize],
+    positions: &[(f64, f64)],
+    order: usize,
+    k: usize,
+    reso: usize,
+) {
+    target
+        .par_chunks_mut(reso * order)
+        .enumerate()
+        .for_each(|(y, row)| {
+            let mut candidates = Vec::with_capacity(9 * order);
+            for (x, slots) in row.chunks_mut(order).enumerate() {
+                // Merge the slots of the texel and of its 8 jump neighbors, keeping the nearest
+                candidates.clear();
+                for dx in [-1, 0, 1] {
+                    for dy in [-1, 0, 1] {
+                        let new_x = x as isize + dx * k as isize;
+                        let new_y = y as isize + dy * k as isize;
+                        if !(new_x >= 0
+                            && new_x < reso as isize
+                            && new_y >= 0
+                            && new_y < reso as isize)
+                        {
+                            continue;
+                        }
+
+                        let new_position = (new_x as usize) + (new_y as usize) * reso;
+                        for &color in &source[new_position * order..(new_position + 1) * order] {
+                            if color != 0 && !candidates.contains(&color) {
+                                candidates.push(color);
+                            }
+                        }
+                    }
+                }
+
+                let distance = |color: usize| center_distance((x, y), positions[color - 1]);
+                candidates.sort_by(|&a, &b| distance(a).total_cmp(&distance(b)).then(a.cmp(&b)));
+                candidates.resize(order, 0);
+                slots.copy_from_slice(&candidates);
+            }
+        });
+}
+
+/// A Voronoi source, rasterized onto every texel it covers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Site {
+    Point((f64, f64)),
+    Segment((f64, f64), (f64, f64)),
+    /// Closed polygon, outline and interior
+    Polygon(Vec<(f64, f64)>),
+}
+
+/// JFA with generalized sites, distances being measured from texel centers to the nearest
+/// sample of each site: its position for a point, points along a segment or an outline a texel
+/// apart at most, and the centers of the texels a polygon covers.
+///
+/// Each texel of the `reso` by `reso` grid gets the index of its site plus one. Where sites
+/// overlap, the last one wins.
+pub fn jfa_sites(
+    sites: &[Site],
+    config: (f64, f64),
+    reso: usize,
+) -> Result<Vec<usize>, &'static str> {
+    if reso == 0 {
+        return Err("resolution must be at least 1");
+    }
+    let mut pixel_grid = vec![0; reso * reso];
+    // Nearest site sample found so far for each texel, in texels
+    let mut sources = vec![(0.0, 0.0); reso * reso];
+
+    for (i, site) in sites.iter().enumerate() {
+        for ((x, y), sample) in rasterize_site(site, config, reso) {
+            pixel_grid[x + y * reso] = i + 1;
+            sources[x + y * reso] = sample;
+        }
+    }
+
+    let mut scratch = (vec![0; reso * reso], vec![(0.0, 0.0); reso * reso]);
+    let mut k = (reso / 2).max(1);
+    jfa_step_sources(
+        (&pixel_grid, &sources),
+        (&mut scratch.0, &mut scratch.1),
+        1,
+        reso,
+    );
+    std::mem::swap(&mut pixel_grid, &mut scratch.0);
+    std::mem::swap(&mut sources, &mut scratch.1);
+    while k >= 1 {
+        jfa_step_sources(
+            (&pixel_grid, &sources),
+            (&mut scratch.0, &mut scratch.1),
+            k,
+            reso,
+        );
+        std::mem::swap(&mut pixel_grid, &mut scratch.0);
+        std::mem::swap(&mut sources, &mut scratch.1);
+        k /= 2;
+    }
+
+    Ok(pixel_grid)
+}
+
+// One pass from the `source` labels and samples into `target`, a row of texels per task
+fn jfa_step_sources(
+    (source_grid, source_samples): (&[usize], &[(f64, f64)]),
+    (target_grid, target_samples): (&mut [usize], &mut [(f64, f64)]),
+    k: usize,
+    reso: usize,
+) {
+    target_grid
+        .par_chunks_mut(reso)
+        .zip(target_samples.par_chunks_mut(reso))
+        .enumerate()
+        .for_each(|(y, (row, row_samples))| {
+            for (x, (texel, texel_sample)) in row.iter_mut().zip(row_samples).enumerate() {
+                let position = x + y * reso;
+                let mut color = source_grid[position];
+                let mut sample = source_samples[position];
+                for dx in [-1, 0, 1] {
+                    for dy in [-1, 0, 1] {
+                        let new_x = x as isize + dx * k as isize;
+                        let new_y = y as isize + dy * k as isize;
+
+                        if (dx == 0 && dy == 0)
+                            || !(new_x >= 0
+                                && new_x < reso as isize
+                                && new_y >= 0
+                                && new_y < reso as isize)
+                        {
+                            continue;
+                        }
+
+                        let new_position = (new_x as usize) + (new_y as usize) * reso;
+                        let found_color = source_grid[new_position];
+                        if found_color == 0 {
+                            continue;
+                        }
+
+                        let found_sample = source_samples[new_position];
+                        if color == 0
+                            || center_distance((x, y), found_sample)
+                                < center_distance((x, y), sample)
+                        {
+                            color = found_color;
+                            sample = found_sample;
+                        }
+                    }
+                }
+                *texel = color;
+                *texel_sample = sample;
+            }
+        });
+}
+
+// Squared distance from the center of `texel` to `point`, in texels
+fn center_distance(texel: (usize, usize), point: (f64, f64)) -> f64 {
+    (texel.0 as f64 + 0.5 - point.0).powi(2) + (texel.1 as f64 + 0.5 - point.1).powi(2)
+}
+
+// Site coordinates in (fractional) pixel units
+fn to_grid(point: (f64, f64), config: (f64, f64), reso: usize) -> (f64, f64) {
+    (
+        point.0 * reso as f64 / config.0,
+        point.1 * reso as f64 / config.1,
+    )
+}
+
+fn to_pixel(point: (f64, f64), reso: usize) -> (usize, usize) {
+    (
+        point.0.max(0.0).min(reso as f64 - 1.0) as usize,
+        point.1.max(0.0).min(reso as f64 - 1.0) as usize,
+    )
+}
+
+// Texels covered by `site`, each with the sample of the site in it, in texels
+fn rasterize_site(
+    site: &Site,
+    config: (f64, f64),
+    reso: usize,
+) -> Vec<((usize, usize), (f64, f64))> {
+    let to_grid = |point| to_grid(point, config, reso);
+    match site {
+        Site::Point(point) => {
+            let point = to_grid(*point);
+            vec![(to_pixel(point, reso), point)]
+        }
+        Site::Segment(a, b) => rasterize_segment(to_grid(*a), to_grid(*b), reso),
+        Site::Polygon(vertices) => {
+            let vertices: Vec<(f64, f64)> = vertices.iter().map(|&v| to_grid(v)).collect();
+            let mut pixels = Vec::new();
+            for (i, &a) in vertices.iter().enumerate() {
+                let b = vertices[(i + 1) % vertices.len()];
+                pixels.extend(rasterize_segment(a, b, reso));
+            }
+            pixels.extend(
+                fill_polygon(&vertices, reso)
+                    .into_iter()
+                    .map(|(x, y)| ((x, y), (x as f64 + 0.5, y as f64 + 0.5))),
+            );
+            pixels
+        }
+    }
+}
+
+fn rasterize_segment(
+    a: (f64, f64),
+    b: (f64, f64),
+    reso: usize,
+) -> Vec<((usize, usize), (f64, f64))> {
+    // At most one pixel between samples so the segment has no gaps
+    let steps = (b.0 - a.0).abs().max((b.1 - a.1).abs()).ceil().max(1.0) as usize;
+    (0..=steps)
+        .map(|s| {
+            let t = s as f64 / steps as f64;
+            let sample = (a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1));
+            (to_pixel(sample, reso), sample)
+        })
+        .collect()
+}
+
+// Even-odd scanline fill at pixel centers
+fn fill_polygon(vertices: &[(f64, f64)], reso: usize) -> Vec<(usize, usize)> {
+    let mut pixels = Vec::new();
+    for y in 0..reso {
+        let center_y = y as f64 + 0.5;
+        let mut crossings: Vec<f64> = Vec::new();
+        for (i, &a) in vertices.iter().enumerate() {
+            let b = vertices[(i + 1) % vertices.len()];
+            if (a.1 <= center_y) != (b.1 <= center_y) {
+                crossings.push(a.0 + (center_y - a.1) / (b.1 - a.1) * (b.0 - a.0));
+            }
+        }
+        crossings.sort_by(f64::total_cmp);
+
+        for span in crossings.chunks_exact(2) {
+            let start = (span[0] - 0.5).ceil().max(0.0);
+            let end = (span[1] - 0.5).floor().min(reso as f64 - 1.0);
+            if start <= end {
+                pixels.extend((start as usize..=end as usize).map(|x| (x, y)));
+            }
+        }
+    }
+    pixels
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const RESO: usize = 512;
+
     #[test]
     fn test_insert_pixel() {
         let points = vec![(1.0, 1.0)];
@@ -100,4 +461,125 @@ mod tests {
         assert_eq!(pixel_grid[12], 1);
         assert_eq!(pixel_grid[512 * RESO / 2 + RESO / 2], 1);
     }
+
+    #[test]
+    fn test_deterministic() {
+        let options = MesherConfig {
+            resolution: 16,
+            deterministic: true,
+            ..Default::default()
+        };
+        // Texel (2, 0) is as far from both seeds, whatever order they come in
+        for points in [[(0.5, 0.5), (4.5, 0.5)], [(4.5, 0.5), (0.5, 0.5)]] {
+            let labels: Vec<_> = [1, 4]
+                .into_iter()
+                .map(|threads| {
+                    let pool = rayon::ThreadPoolBuilder::new()
+                        .num_threads(threads)
+                        .build()
+                        .unwrap();
+                    pool.install(|| jfa_with_options(&points, (16.0, 16.0), &options).unwrap())
+                })
+                .collect();
+
+            assert_eq!(labels[0], labels[1]);
+            assert_eq!(labels[0][2], 1);
+        }
+    }
+
+    #[test]
+    fn test_resolution() {
+        let options = MesherConfig {
+            resolution: 64,
+            ..Default::default()
+        };
+
+        let pixel_grid =
+            jfa_with_options(&[(1.0, 1.0), (9.0, 9.0)], (10.0, 10.0), &options).unwrap();
+
+        assert_eq!(pixel_grid.len(), 64 * 64);
+        assert_eq!(pixel_grid[0], 1);
+        assert_eq!(pixel_grid[64 * 64 - 1], 2);
+
+        let options = MesherConfig {
+            resolution: 0,
+            ..Default::default()
+        };
+        assert!(jfa_with_options(&[(1.0, 1.0)], (10.0, 10.0), &options).is_err());
+        assert!(jfa_k(&[(1.0, 1.0)], (10.0, 10.0), 2, 0).is_err());
+        assert!(jfa_sites(&[Site::Point((1.0, 1.0))], (10.0, 10.0), 0).is_err());
+    }
+
+    #[test]
+    fn test_segment_site() {
+        let config = (10.0, 10.0);
+        let sites = vec![
+            Site::Segment((1.0, 5.0), (9.0, 5.0)),
+            Site::Point((5.0, 1.0)),
+        ];
+
+        let pixel_grid = jfa_sites(&sites, config, RESO).unwrap();
+
+        // Closer to the middle of the segment than to the point, though far from its ends
+        let (x, y) = to_pixel(to_grid((5.0, 3.5), config, RESO), RESO);
+        assert_eq!(pixel_grid[x + y * RESO], 1);
+        let (x, y) = to_pixel(to_grid((5.0, 2.0), config, RESO), RESO);
+        assert_eq!(pixel_grid[x + y * RESO], 2);
+    }
+
+    #[test]
+    fn test_sites_sub_pixel() {
+        // Texel 1 is centered at 1.5, nearer 2.9 than 0 though their texels are as near it
+        let sites = [Site::Point((0.0, 0.5)), Site::Point((2.9, 0.5))];
+
+        let pixel_grid = jfa_sites(&sites, (4.0, 4.0), 4).unwrap();
+
+        assert_eq!(pixel_grid[..4], [1, 2, 2, 2]);
+    }
+
+    #[test]
+    fn test_polygon_site_interior() {
+        let config = (10.0, 10.0);
+        let sites = vec![
+            Site::Polygon(vec![(1.0, 1.0), (4.0, 1.0), (4.0, 4.0), (1.0, 4.0)]),
+            Site::Point((3.0, 3.0)),
+        ];
+
+        let pixel_grid = jfa_sites(&sites, config, RESO).unwrap();
+
+        // The point overwrites its own texel only
+        let (x, y) = to_pixel(to_grid((3.0, 3.0), config, RESO), RESO);
+        assert_eq!(pixel_grid[x + y * RESO], 2);
+        let (x, y) = to_pixel(to_grid((2.0, 2.0), config, RESO), RESO);
+        assert_eq!(pixel_grid[x + y * RESO], 1);
+    }
+
+    #[test]
+    fn test_order_2() {
+        let points = vec![(1.0, 1.0), (9.0, 1.0), (5.0, 9.0)];
+        let config = (10.0, 10.0);
+
+        let labels = jfa_k(&points, config, 2, RESO).unwrap();
+
+        for (x, y) in [(10, 10), (100, 400), (300, 200), (500, 500), (256, 256)] {
+            let distance =
+                |color: usize| center_distance((x, y), to_grid(points[color - 1], config, RESO));
+            let mut nearest: Vec<usize> = (1..=points.len()).collect();
+            nearest.sort_by(|&a, &b| distance(a).total_cmp(&distance(b)));
+
+            let position = x + y * RESO;
+            assert_eq!(labels[position * 2..position * 2 + 2], nearest[..2]);
+        }
+
+        // Texel 1 is centered at 1.5, nearer 2.9 than 0 though their texels are as near it
+        let labels = jfa_k(&[(0.0, 0.5), (2.9, 0.5)], (4.0, 4.0), 2, 4).unwrap();
+        assert_eq!(labels[2..4], [2, 1]);
+    }
+
+    #[test]
+    fn test_order_above_seed_count() {
+        let labels = jfa_k(&[(1.0, 1.0)], (2.0, 2.0), 2, 64).unwrap();
+
+        assert!(labels.chunks(2).all(|slots| slots == [1, 0]));
+    }
 }
@@ -0,0 +1,323 @@
+// Passes over active tiles only, with `MesherConfig::active_tiles`. When the seeds are clustered
+// in a small part of a large grid, most texels are settled after the first passes, so before
+// each pass `active_tiles.wgsl` lists the tiles whose labels can still change, and the pass runs
+// on them through an indirect dispatch. The passes read and write the same storage buffers as
+// the full ones, so reading back, relaxing or binding the labels buffer work the same.
+
+use super::{WgpuContext, DISTANCE_SHADER, PARAMS_SIZE, PARAMS_STRIDE, WORKGROUP_SIZE};
+
+// Tiles `list_tiles` goes through in a workgroup
+const LIST_WORKGROUP_SIZE: u32 = 256;
+
+pub(super) struct ActiveTiles {
+    list_pipeline: wgpu::ComputePipeline,
+    finish_pipeline: wgpu::ComputePipeline,
+    pipeline: wgpu::ComputePipeline,
+    /// Bind group `i` lists the tiles of the passes reading `storage_buffers[i]`
+    list_bind_groups: [wgpu::BindGroup; 2],
+    /// Bind group `i` reads `storage_buffers[i]` and writes the other one
+    bind_groups: [wgpu::BindGroup; 2],
+    /// Tile states after the passes of each parity
+    states: [wgpu::Buffer; 2],
+    tiles: wgpu::Buffer,
+    count: wgpu::Buffer,
+    /// Workgroups of the indirect dispatch
+    groups: wgpu::Buffer,
+}
+
+// Layout entry of a compute storage buffer
+fn storage_entry(binding: u32, read_only: bool) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+impl ActiveTiles {
+    pub(super) fn new(context: &WgpuContext) -> ActiveTiles {
+        let device = &context.device;
+        let source = [
+            DISTANCE_SHADER,
+            include_str!("shader.wgsl"),
+            include_str!("active_tiles.wgsl"),
+        ]
+        .join("\n");
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+
+        let tile_count = tile_count(context);
+        let buffer = |size: usize, usage: wgpu::BufferUsages| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: (size * std::mem::size_of::<u32>()) as u64,
+                usage,
+                mapped_at_creation: false,
+            })
+        };
+        let states = [0, 1].map(|_| buffer(tile_count, wgpu::BufferUsages::STORAGE));
+        let tiles = buffer(tile_count, wgpu::BufferUsages::STORAGE);
+        let count = buffer(
+            1,
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        );
+        let groups = buffer(
+            3,
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::INDIRECT,
+        );
+
+        let params_entry = wgpu::BindGroupLayoutEntry {
+            binding: 1,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: true,
+                min_binding_size: wgpu::BufferSize::new(PARAMS_SIZE as u64),
+            },
+            count: None,
+        };
+        let params_binding = wgpu::BindGroupEntry {
+            binding: 1,
+            resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                buffer: &context.params_buffer,
+                offset: 0,
+                size: wgpu::BufferSize::new(PARAMS_SIZE as u64),
+            }),
+        };
+        // The indirect dispatch can't bind the buffer of its workgroups, so its layout leaves
+        // it out
+        let list_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                params_entry,
+                storage_entry(4, true),
+                storage_entry(5, false),
+                storage_entry(6, false),
+                storage_entry(7, false),
+                storage_entry(8, false),
+            ],
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                storage_entry(0, true),
+                params_entry,
+                storage_entry(2, true),
+                storage_entry(3, false),
+                storage_entry(5, false),
+                storage_entry(6, false),
+                storage_entry(7, false),
+            ],
+        });
+        let list_bind_groups = [0, 1].map(|input| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &list_layout,
+                entries: &[
+                    params_binding.clone(),
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: states[input].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 5,
+                        resource: states[1 - input].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 6,
+                        resource: tiles.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 7,
+                        resource: count.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 8,
+                        resource: groups.as_entire_binding(),
+                    },
+                ],
+            })
+        });
+        let bind_groups = [0, 1].map(|input| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: context.storage_buffers[input].as_entire_binding(),
+                    },
+                    params_binding.clone(),
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: context.normal_points.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: context.storage_buffers[1 - input].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 5,
+                        resource: states[1 - input].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 6,
+                        resource: tiles.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 7,
+                        resource: count.as_entire_binding(),
+                    },
+                ],
+            })
+        });
+
+        let pipeline = |layout: &wgpu::BindGroupLayout, entry_point: &str| {
+            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[layout],
+                push_constant_ranges: &[],
+            });
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: None,
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+
+        ActiveTiles {
+            list_pipeline: pipeline(&list_layout, "list_tiles"),
+            finish_pipeline: pipeline(&list_layout, "finish_list"),
+            pipeline: pipeline(&layout, "main_tiles"),
+            list_bind_groups,
+            bind_groups,
+            states,
+            tiles,
+            count,
+            groups,
+        }
+    }
+
+    // Lists the active tiles of pass `pass`, then runs it on them
+    pub(super) fn encode_step(
+        &self,
+        context: &WgpuContext,
+        command_encoder: &mut wgpu::CommandEncoder,
+        pass: u32,
+    ) {
+        let offset = pass * PARAMS_STRIDE as u32;
+        command_encoder.clear_buffer(&self.count, 0, None);
+        {
+            let mut compute_pass =
+                command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: None,
+                    timestamp_writes: None,
+                });
+            compute_pass.set_pipeline(&self.list_pipeline);
+            compute_pass.set_bind_group(0, &self.list_bind_groups[pass as usize % 2], &[offset]);
+            let groups = (tile_count(context) as u32).div_ceil(LIST_WORKGROUP_SIZE);
+            compute_pass.dispatch_workgroups(groups, 1, 1);
+            compute_pass.set_pipeline(&self.finish_pipeline);
+            compute_pass.dispatch_workgroups(1, 1, 1);
+        }
+        let mut compute_pass = command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: context.timestamp_writes(pass),
+        });
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &self.bind_groups[pass as usize % 2], &[offset]);
+        compute_pass.dispatch_workgroups_indirect(&self.groups, 0);
+    }
+
+    pub(super) fn memory_usage(&self) -> u64 {
+        self.states.iter().map(|buffer| buffer.size()).sum::<u64>()
+            + self.tiles.size()
+            + self.count.size()
+            + self.groups.size()
+    }
+}
+
+// Tiles of a workgroup each on the context's grid
+fn tile_count(context: &WgpuContext) -> usize {
+    let tile = WORKGROUP_SIZE as usize;
+    context.width.div_ceil(tile) * context.height.div_ceil(tile)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{Accuracy, BoundaryMode, GridShape, MesherConfig};
+    use crate::jfa_wgpu::{block_on, Mesher};
+    use crate::test_util::{gpu_or_skip, scattered};
+
+    #[test]
+    fn test_active_tiles_match_full_passes() {
+        // Seeds clustered in a corner of a grid of partial tiles
+        let clustered: Vec<(f64, f64)> = scattered(30, (1.0, 1.0))
+            .into_iter()
+            .map(|(x, y)| (x + 0.5, y + 0.25))
+            .collect();
+        for accuracy in [Accuracy::Fast, Accuracy::Balanced, Accuracy::Exact] {
+            for boundary in [
+                BoundaryMode::Bounded,
+                BoundaryMode::Periodic { x: true, y: false },
+            ] {
+                let options = MesherConfig {
+                    resolution: 150,
+                    grid: GridShape::FitDomain,
+                    accuracy,
+                    boundary,
+                    ..Default::default()
+                };
+                let Some(full) = gpu_or_skip(super::super::main(&clustered, (6.0, 4.0), &options))
+                else {
+                    return;
+                };
+                let active = super::super::main(
+                    &clustered,
+                    (6.0, 4.0),
+                    &MesherConfig {
+                        active_tiles: true,
+                        ..options
+                    },
+                )
+                .unwrap();
+
+                assert_eq!(full.len(), 150 * 100);
+                assert_eq!(active, full, "{accuracy:?} {boundary:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_active_tiles_across_runs() {
+        // The output grid of the first pass holds the labels of the previous run
+        let options = MesherConfig {
+            resolution: 64,
+            accuracy: Accuracy::Exact,
+            ..Default::default()
+        };
+        let Some(mut full) = gpu_or_skip(block_on(Mesher::new(options.clone()))) else {
+            return;
+        };
+        let mut active = block_on(Mesher::new(MesherConfig {
+            active_tiles: true,
+            ..options
+        }))
+        .unwrap();
+        for points in [scattered(50, (4.0, 4.0)), vec![(0.5, 0.5), (1.0, 0.75)]] {
+            let (expected, _) = block_on(full.run(&points, (4.0, 4.0))).unwrap();
+            let (labels, _) = block_on(active.run(&points, (4.0, 4.0))).unwrap();
+            assert_eq!(labels, expected);
+        }
+    }
+}
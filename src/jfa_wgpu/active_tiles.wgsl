@@ -0,0 +1,134 @@
+// Passes over active tiles only, appended to the distance shader and `shader.wgsl` with
+// `MesherConfig::active_tiles`. Before each JFA pass, `list_tiles` lists the tiles of 16 x 16
+// texels whose labels can change, `finish_list` turns their count into the workgroups of an
+// indirect dispatch, and `main_tiles` runs the pass on them.
+//
+// A texel's label after a pass only depends on the labels of itself and its 8 neighbors at the
+// pass's step, so a tile can be skipped when the tiles holding those neighbors are all
+// unlabeled, or when the last pass had the same step and none of them changed. Either way the
+// skipped tile didn't change in the last pass either, so the stale labels of the output grid,
+// written two passes before, are still its labels.
+
+const TILE: u32 = 16u;
+// Bits of a tile's state: one of its labels changed in the last pass, one of them isn't 0
+const CHANGED: u32 = 1u;
+const LABELED: u32 = 2u;
+// Workgroups along x of the indirect dispatch, the downlevel limit
+const MAX_GROUPS: u32 = 65535u;
+
+// States after the last pass, and the ones this pass writes
+@group(0) @binding(4) var<storage, read> previous_states: array<u32>;
+@group(0) @binding(5) var<storage, read_write> states: array<atomic<u32>>;
+// Active tiles, then their count
+@group(0) @binding(6) var<storage, read_write> tiles: array<u32>;
+@group(0) @binding(7) var<storage, read_write> tile_count: atomic<u32>;
+// Workgroups of the indirect dispatch
+@group(0) @binding(8) var<storage, read_write> groups: array<u32, 3>;
+
+fn grid_tiles() -> vec2<u32> {
+    return (vec2<u32>(params.width, params.height) + TILE - 1u) / TILE;
+}
+
+// Tiles holding texels `start` to `end`, at most 16 of them, along an axis of `size` texels,
+// -1 for none. Repeated tiles are harmless
+fn axis_tiles(start: i32, end: i32, size: i32, wraps: bool) -> vec4<i32> {
+    let tile = i32(TILE);
+    if wraps {
+        let low = (start % size + size) % size;
+        let high = (end % size + size) % size;
+        if low <= high {
+            return vec4<i32>(low / tile, high / tile, -1, -1);
+        }
+        return vec4<i32>(low / tile, (size - 1) / tile, 0, high / tile);
+    }
+    let low = max(start, 0);
+    let high = min(end, size - 1);
+    if low > high {
+        return vec4<i32>(-1, -1, -1, -1);
+    }
+    return vec4<i32>(low / tile, high / tile, -1, -1);
+}
+
+@compute @workgroup_size(256)
+fn list_tiles(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let counts = grid_tiles();
+    let tile = global_id.x;
+    if tile >= counts.x * counts.y {
+        return;
+    }
+
+    // Every tile runs the first pass, whose output grid holds the labels of another run
+    if params.previous_step == 0u {
+        atomicStore(&states[tile], 0u);
+        tiles[atomicAdd(&tile_count, 1u)] = tile;
+        return;
+    }
+    // Labels are never cleared, only replaced
+    atomicStore(&states[tile], previous_states[tile] & LABELED);
+
+    var dirty = LABELED;
+    if params.step == params.previous_step {
+        dirty = CHANGED;
+    }
+    let x = i32((tile % counts.x) * TILE);
+    let y = i32((tile / counts.x) * TILE);
+    let step = i32(params.step);
+    let last = i32(TILE) - 1;
+    let width = i32(params.width);
+    let height = i32(params.height);
+    var active = false;
+    for (var dx = -1; dx <= 1; dx = dx + 1) {
+        for (var dy = -1; dy <= 1; dy = dy + 1) {
+            let xs = axis_tiles(x + dx * step, x + last + dx * step, width, wraps_x());
+            let ys = axis_tiles(y + dy * step, y + last + dy * step, height, wraps_y());
+            for (var i = 0; i < 4; i = i + 1) {
+                for (var j = 0; j < 4; j = j + 1) {
+                    if xs[i] < 0 || ys[j] < 0 {
+                        continue;
+                    }
+                    let neighbor = u32(xs[i]) + u32(ys[j]) * counts.x;
+                    active = active || (previous_states[neighbor] & dirty) != 0u;
+                }
+            }
+        }
+    }
+    if active {
+        tiles[atomicAdd(&tile_count, 1u)] = tile;
+    }
+}
+
+@compute @workgroup_size(1)
+fn finish_list() {
+    let count = atomicLoad(&tile_count);
+    groups[0] = min(count, MAX_GROUPS);
+    groups[1] = (count + MAX_GROUPS - 1u) / MAX_GROUPS;
+    groups[2] = 1u;
+}
+
+@compute @workgroup_size(16, 16)
+fn main_tiles(
+    @builtin(workgroup_id) group: vec3<u32>,
+    @builtin(local_invocation_id) local: vec3<u32>,
+) {
+    let entry = group.x + group.y * MAX_GROUPS;
+    if entry >= atomicLoad(&tile_count) {
+        return;
+    }
+    let counts = grid_tiles();
+    let tile = tiles[entry];
+    let x = (tile % counts.x) * TILE + local.x;
+    let y = (tile / counts.x) * TILE + local.y;
+    if x >= params.width || y >= params.height {
+        return;
+    }
+
+    let index = x + y * params.width;
+    let color = jfa_texel(x, y);
+    output_grid[index] = color;
+    if color != input_grid[index] {
+        atomicOr(&states[tile], CHANGED);
+    }
+    if color != 0u && (atomicLoad(&states[tile]) & LABELED) == 0u {
+        atomicOr(&states[tile], LABELED);
+    }
+}
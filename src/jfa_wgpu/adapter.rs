@@ -0,0 +1,165 @@
+// Adapter selection, for machines with several GPUs or graphics APIs where the default adapter
+// isn't the one to mesh on, e.g. to pin the mesher to the discrete GPU of a laptop.
+
+use crate::error::MesherError;
+
+/// Graphics API to request adapters from.
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GraphicsApi {
+    /// Whichever the platform supports
+    #[default]
+    Any,
+    Vulkan,
+    Metal,
+    Dx12,
+    Gl,
+}
+
+/// Which kind of adapter to prefer when several are available.
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PowerPreference {
+    /// The platform's default adapter
+    #[default]
+    Default,
+    /// Usually an integrated GPU
+    LowPower,
+    /// Usually a discrete GPU
+    HighPerformance,
+}
+
+/// Adapter a run creates its device on.
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AdapterSelection {
+    pub api: GraphicsApi,
+    /// Only used when neither `name` nor `index` is set
+    pub power: PowerPreference,
+    /// Picks the adapters whose name contains this, ignoring case. Browsers don't list their
+    /// adapters, so no adapter is found there when this or `index` is set
+    pub name: Option<String>,
+    /// Picks this one of the matching adapters, in the order the platform lists them, the
+    /// first one when `None`
+    pub index: Option<usize>,
+}
+
+impl GraphicsApi {
+    fn backends(self) -> wgpu::Backends {
+        match self {
+            GraphicsApi::Any => wgpu::Backends::all(),
+            GraphicsApi::Vulkan => wgpu::Backends::VULKAN,
+            GraphicsApi::Metal => wgpu::Backends::METAL,
+            GraphicsApi::Dx12 => wgpu::Backends::DX12,
+            GraphicsApi::Gl => wgpu::Backends::GL,
+        }
+    }
+}
+
+pub(super) async fn select_adapter(
+    selection: &AdapterSelection,
+) -> Result<wgpu::Adapter, MesherError> {
+    let backends = selection.api.backends();
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends,
+        ..Default::default()
+    });
+
+    if selection.name.is_none() && selection.index.is_none() {
+        let power_preference = match selection.power {
+            PowerPreference::Default => wgpu::PowerPreference::default(),
+            PowerPreference::LowPower => wgpu::PowerPreference::LowPower,
+            PowerPreference::HighPerformance => wgpu::PowerPreference::HighPerformance,
+        };
+        return instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference,
+                ..Default::default()
+            })
+            .await
+            .ok_or(MesherError::NoAdapter);
+    }
+
+    let mut adapters = enumerate_adapters(&instance, backends);
+    let names: Vec<String> = adapters
+        .iter()
+        .map(|adapter| adapter.get_info().name)
+        .collect();
+    log::info!("Available adapters: {names:?}");
+    let index = pick(&names, selection).ok_or(MesherError::NoAdapter)?;
+    Ok(adapters.swap_remove(index))
+}
+
+// Every hardware adapter matching the API and name of `selection`, for runs spread across
+// GPUs. A GPU which several backends expose is only listed once, with the first of them
+pub(super) fn select_adapters(selection: &AdapterSelection) -> Vec<wgpu::Adapter> {
+    let backends = selection.api.backends();
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends,
+        ..Default::default()
+    });
+    let pattern = selection.name.as_deref().unwrap_or("").to_lowercase();
+
+    let mut seen = Vec::new();
+    let mut adapters = Vec::new();
+    for adapter in enumerate_adapters(&instance, backends) {
+        let info = adapter.get_info();
+        if info.device_type == wgpu::DeviceType::Cpu
+            || !info.name.to_lowercase().contains(&pattern)
+            || seen.contains(&(info.vendor, info.device))
+        {
+            continue;
+        }
+        log::info!("Using adapter {} ({:?})", info.name, info.backend);
+        seen.push((info.vendor, info.device));
+        adapters.push(adapter);
+    }
+    adapters
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn enumerate_adapters(instance: &wgpu::Instance, backends: wgpu::Backends) -> Vec<wgpu::Adapter> {
+    instance.enumerate_adapters(backends)
+}
+
+// Browsers only hand out the adapter they prefer, so none can be picked by name or index
+#[cfg(target_arch = "wasm32")]
+fn enumerate_adapters(_instance: &wgpu::Instance, _backends: wgpu::Backends) -> Vec<wgpu::Adapter> {
+    Vec::new()
+}
+
+// Index in `names` of the adapter `selection` asks for
+fn pick(names: &[String], selection: &AdapterSelection) -> Option<usize> {
+    let pattern = selection.name.as_deref().unwrap_or("").to_lowercase();
+    names
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| name.to_lowercase().contains(&pattern))
+        .nth(selection.index.unwrap_or(0))
+        .map(|(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick() {
+        let names = [
+            "Intel(R) UHD Graphics 630",
+            "NVIDIA GeForce RTX 3060",
+            "llvmpipe",
+        ]
+        .map(String::from);
+        let selection = |name: Option<&str>, index| AdapterSelection {
+            name: name.map(String::from),
+            index,
+            ..Default::default()
+        };
+
+        assert_eq!(pick(&names, &selection(Some("nvidia"), None)), Some(1));
+        assert_eq!(pick(&names, &selection(None, Some(2))), Some(2));
+        assert_eq!(pick(&names, &selection(Some("i"), Some(1))), Some(1));
+        assert_eq!(pick(&names, &selection(Some("amd"), None)), None);
+    }
+}
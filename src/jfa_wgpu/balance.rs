@@ -0,0 +1,190 @@
+// Capacity-constrained tessellation: the seeds stay in place and their weights are adjusted until
+// every cell of their power diagram has the same area, for partitions balancing a load or for
+// stippling. Each iteration runs the JFA on the same context, reduces the texel counts of the
+// cells on the GPU, and moves every weight by the area its cell lacks: raising a weight by `dw`
+// moves the cell's edges outwards by `dw` over twice the distance to each neighbor, so the area
+// of a cell of a regular grid grows by about `√3 dw`, less its neighbors' adjustments.
+
+use super::{
+    check_budget_with, dispatch_jfa, points_size, read_back, stats, CellStats, WgpuContext,
+};
+use crate::config::MesherConfig;
+use crate::error::MesherError;
+use crate::progress::Progress;
+
+// Fraction of the missing area added to a weight per iteration, stable up to about 0.58 for a
+// regular grid of cells
+const STEP: f64 = 0.4;
+
+/// Weights balancing the areas of the cells, and the labels of their power diagram.
+#[derive(Debug, Clone)]
+pub struct Balanced {
+    /// Weight of each seed, in squared domain units, for [`super::run_weighted`]
+    pub weights: Vec<f64>,
+    /// Label of every texel, as [`super::run_weighted`] computes them with `weights`
+    pub labels: Vec<u32>,
+    /// Weight adjustments made
+    pub iterations: usize,
+    /// Largest difference between the area of a cell and the mean area, relative to the latter
+    pub area_error: f64,
+}
+
+/// Adjusts the weights of `points`, starting from 0, at most `iterations` times until the area
+/// of every cell is within `tolerance` of the mean, relative to it. The area of a cell is
+/// resolved to a texel, so the tolerance can't go much below one over the texels per cell.
+///
+/// Seeds set in `fixed`, which is empty when none is, keep a weight of 0 while their cells count
+/// towards the area error.
+///
+/// Progress is reported once per iteration, and cancellation is checked between them.
+pub async fn balance(
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    iterations: usize,
+    tolerance: f64,
+    fixed: &[bool],
+    options: &MesherConfig,
+) -> Result<Balanced, MesherError> {
+    if !fixed.is_empty() && fixed.len() != points.len() {
+        return Err(MesherError::InvalidInput(
+            "fixed must be empty or one per seed",
+        ));
+    }
+    check_budget_with(points, config, options, |_| {
+        stats::memory_required(points.len())
+    })?;
+
+    let dimensions = options.grid_dimensions(config);
+    let mut context = WgpuContext::new(dimensions, points_size(points), &options.adapter).await?;
+    context.prepare(options)?;
+    // Progress and cancellation are handled per iteration rather than per pass
+    let quiet = MesherConfig {
+        debug_dump: None,
+        progress: None,
+        cancellation: None,
+        ..options.clone()
+    };
+
+    let target = config.0 * config.1 / points.len() as f64;
+    let mut weights = vec![0.0; points.len()];
+    let mut iteration = 0;
+    loop {
+        if let Some(token) = &options.cancellation {
+            if token.is_cancelled() {
+                return Err(MesherError::Cancelled {
+                    completed: iteration,
+                    total: iterations,
+                });
+            }
+        }
+        let passes = dispatch_jfa(&context, points, &weights, config, &quiet, None).await?;
+        let labels = &context.storage_buffers[passes as usize % 2];
+        let cells = stats::reduce(&context, labels, points.len(), config, &quiet).await?;
+        let area_error = area_error(&cells, target);
+        log::info!("Balancing iteration {iteration}: area error {area_error:.4}");
+
+        if area_error <= tolerance || iteration == iterations {
+            let mut labels = vec![0; context.width * context.height];
+            read_back(&context, &mut labels, passes).await?;
+            return Ok(Balanced {
+                weights,
+                labels,
+                iterations: iteration,
+                area_error,
+            });
+        }
+
+        update_weights(&mut weights, fixed, &cells, target);
+        iteration += 1;
+        if let Some(progress) = &options.progress {
+            progress.report(Progress::Balancing {
+                iteration,
+                iterations,
+            });
+        }
+    }
+}
+
+fn area_error(cells: &CellStats, target: f64) -> f64 {
+    (0..cells.pixel_counts.len())
+        .map(|cell| (cells.area(cell) - target).abs() / target)
+        .fold(0.0, f64::max)
+}
+
+// Grows the weights of the cells smaller than `target` and shrinks the others, keeping their
+// mean at 0 since only their differences matter. Fixed seeds keep a weight of 0, which anchors
+// the others, so the mean is only brought back when none is fixed. The sums run in seed order on
+// the CPU, so deterministic runs balance to the same weights
+fn update_weights(weights: &mut [f64], fixed: &[bool], cells: &CellStats, target: f64) {
+    for (cell, weight) in weights.iter_mut().enumerate() {
+        if fixed.get(cell) != Some(&true) {
+            *weight += STEP * (target - cells.area(cell));
+        }
+    }
+    if fixed.contains(&true) {
+        return;
+    }
+    let mean = weights.iter().sum::<f64>() / weights.len() as f64;
+    for weight in weights.iter_mut() {
+        *weight -= mean;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{gpu_or_skip, scattered};
+
+    fn cells(pixel_counts: Vec<u32>) -> CellStats {
+        let n = pixel_counts.len();
+        CellStats {
+            pixel_counts,
+            boundary_sides: vec![(0, 0); n],
+            bounding_boxes: vec![None; n],
+            texel_size: (0.5, 0.5),
+        }
+    }
+
+    #[test]
+    fn test_update_weights() {
+        // Areas 1, 2 and 3 around a mean of 2
+        let cells = cells(vec![4, 8, 12]);
+        let mut weights = vec![0.0; 3];
+
+        assert!((area_error(&cells, 2.0) - 0.5).abs() < 1e-12);
+        update_weights(&mut weights, &[], &cells, 2.0);
+        assert_eq!(weights, [STEP, 0.0, -STEP]);
+
+        // The pinned weight stays at 0 and the others aren't recentered
+        let mut weights = vec![0.0; 3];
+        update_weights(&mut weights, &[true, false, false], &cells, 2.0);
+        assert_eq!(weights, [0.0, 0.0, -STEP]);
+    }
+
+    #[test]
+    fn test_balance() {
+        // Clustered seeds, whose Voronoi cells are far from equal
+        let points: Vec<(f64, f64)> = scattered(16, (1.0, 1.0))
+            .into_iter()
+            .map(|(x, y)| (x * x, y))
+            .collect();
+        let options = MesherConfig {
+            resolution: 256,
+            ..Default::default()
+        };
+        let Some(balanced) = gpu_or_skip(crate::jfa_wgpu::block_on(balance(
+            &points,
+            (1.0, 1.0),
+            200,
+            0.05,
+            &[],
+            &options,
+        ))) else {
+            return;
+        };
+
+        assert!(balanced.area_error <= 0.05, "{}", balanced.area_error);
+        assert_eq!(balanced.labels.len(), 256 * 256);
+        assert!(balanced.weights.iter().sum::<f64>().abs() < 1e-9);
+    }
+}
@@ -0,0 +1,343 @@
+// Many small diagrams at once, for users generating thousands of them (texture synthesis,
+// dataset generation), whose runs would be dominated by the submission and read back of each
+// one. Every seed set gets its own grid buffers and bind groups on one device and pipeline, the
+// passes of all of them are encoded into one command buffer and their labels are mapped
+// together, in groups holding at most `GROUP_MEMORY` bytes of buffers. Each group is submitted
+// before the labels of the previous one are read back, so that the device computes the next
+// group while the CPU copies out of the staging buffers of the last.
+
+use super::{
+    check_budget, flatten_seeds, init_normal_points, input, mark_seeds, memory_required,
+    pass_params, points_size, poll_submission, run_steps, seed_positions, tiling, workgroup_count,
+    WgpuContext, PARAMS_STRIDE, POINT_SIZE,
+};
+use crate::config::MesherConfig;
+use crate::error::MesherError;
+
+// Buffers allocated for the seed sets of one submission, two of which are in flight at once
+const GROUP_MEMORY: u64 = 256 << 20;
+
+/// Seeds of one diagram of a batch, in their own box.
+#[derive(Debug, Clone, Copy)]
+pub struct SeedSet<'a> {
+    pub points: &'a [(f64, f64)],
+    pub config: (f64, f64),
+}
+
+/// Labels of every texel of each seed set, as [`super::mesh`] computes them, with all the sets
+/// of a batch dispatched on one device in as few submissions as their memory allows.
+///
+/// Each set must fit in a storage buffer since none is tiled, and runs at full resolution on
+/// storage buffers: the grid storage, the coarse factor, the debug dump and progress reports are
+/// ignored. Cancellation is checked between submissions, counting seed sets.
+pub async fn mesh_batch(
+    batches: &[SeedSet<'_>],
+    options: &MesherConfig,
+) -> Result<Vec<Vec<usize>>, MesherError> {
+    check_batch(batches, options)?;
+    if batches.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let context = WgpuContext::new((1, 1), POINT_SIZE, &options.adapter).await?;
+    let labels = run_batch(&context, batches, options).await?;
+    Ok(labels
+        .into_iter()
+        .map(|labels| labels.into_iter().map(|x| x as usize).collect())
+        .collect())
+}
+
+pub(super) fn check_batch(batches: &[SeedSet], options: &MesherConfig) -> Result<(), MesherError> {
+    for set in batches {
+        check_budget(set.points, set.config, options)?;
+        let dimensions = options.grid_dimensions(set.config);
+        if tiling::needs_tiling(options, dimensions) {
+            return Err(MesherError::InvalidInput(
+                "batched seed sets must fit in a storage buffer",
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Labels of each seed set, as `u32`, on the device and pipeline of `context`
+pub(super) async fn run_batch(
+    context: &WgpuContext,
+    batches: &[SeedSet<'_>],
+    options: &MesherConfig,
+) -> Result<Vec<Vec<u32>>, MesherError> {
+    let mut labels = Vec::with_capacity(batches.len());
+    let mut in_flight = None;
+    for group in groups(batches, options) {
+        if let Some(token) = &options.cancellation {
+            if token.is_cancelled() {
+                return Err(MesherError::Cancelled {
+                    completed: labels.len(),
+                    total: batches.len(),
+                });
+            }
+        }
+        let slots: Vec<Slot> = group
+            .iter()
+            .map(|set| Slot::new(context, set, options))
+            .collect();
+
+        let mut command_encoder = context
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        for slot in &slots {
+            slot.encode(context, &mut command_encoder);
+        }
+        let submission = context.queue.submit(Some(command_encoder.finish()));
+        let submitted = Submitted::new(slots, submission);
+
+        if let Some(previous) = in_flight.replace(submitted) {
+            previous.read_back(context, &mut labels).await?;
+        }
+    }
+    if let Some(last) = in_flight {
+        last.read_back(context, &mut labels).await?;
+    }
+    log::info!("{} seed sets meshed", batches.len());
+    Ok(labels)
+}
+
+// Slots of a submitted group, whose staging buffers are being mapped
+struct Submitted {
+    slots: Vec<Slot>,
+    submission: wgpu::SubmissionIndex,
+    receivers: Vec<flume::Receiver<Result<(), wgpu::BufferAsyncError>>>,
+}
+
+impl Submitted {
+    // All the mappings are requested before waiting for any of them
+    fn new(slots: Vec<Slot>, submission: wgpu::SubmissionIndex) -> Submitted {
+        let receivers = slots
+            .iter()
+            .map(|slot| {
+                let (sender, receiver) = flume::bounded(1);
+                slot.staging_buffer
+                    .slice(..)
+                    .map_async(wgpu::MapMode::Read, move |r| {
+                        let _ = sender.send(r);
+                    });
+                receiver
+            })
+            .collect();
+        Submitted {
+            slots,
+            submission,
+            receivers,
+        }
+    }
+
+    // Waits for this submission only, the next one running meanwhile
+    async fn read_back(
+        self,
+        context: &WgpuContext,
+        labels: &mut Vec<Vec<u32>>,
+    ) -> Result<(), MesherError> {
+        for (slot, receiver) in self.slots.iter().zip(&self.receivers) {
+            let mapped = poll_submission(&context.device, &self.submission, receiver)
+                .await
+                .unwrap_or(Err(wgpu::BufferAsyncError));
+            if let Some(err) = context.take_error() {
+                return Err(err);
+            }
+            mapped.map_err(|err| MesherError::BufferMapFailed(err.to_string()))?;
+            let range = slot.staging_buffer.slice(..).get_mapped_range();
+            labels.push(bytemuck::cast_slice(&range[..]).to_vec());
+            drop(range);
+            slot.staging_buffer.unmap();
+        }
+        Ok(())
+    }
+}
+
+// Consecutive seed sets whose buffers hold at most `GROUP_MEMORY` bytes, or a single one
+fn groups<'a, 'b>(batches: &'a [SeedSet<'b>], options: &MesherConfig) -> Vec<&'a [SeedSet<'b>]> {
+    let mut groups = Vec::new();
+    let (mut start, mut memory) = (0, 0);
+    for (i, set) in batches.iter().enumerate() {
+        let required = memory_required(set.points.len(), options.grid_dimensions(set.config));
+        if i > start && memory + required > GROUP_MEMORY {
+            groups.push(&batches[start..i]);
+            (start, memory) = (i, 0);
+        }
+        memory += required;
+    }
+    if start < batches.len() {
+        groups.push(&batches[start..]);
+    }
+    groups
+}
+
+// Buffers and bind groups of one seed set, run with the pipeline of the context
+struct Slot {
+    width: usize,
+    height: usize,
+    passes: u32,
+    storage_buffers: [wgpu::Buffer; 2],
+    staging_buffer: wgpu::Buffer,
+    bind_groups: [wgpu::BindGroup; 2],
+}
+
+impl Slot {
+    // Allocates the buffers of `set` and queues the uploads of its seeds, grid and parameters
+    fn new(context: &WgpuContext, set: &SeedSet, options: &MesherConfig) -> Slot {
+        let device = &context.device;
+        let (width, height) = options.grid_dimensions(set.config);
+        let (storage_buffers, staging_buffer, params_buffer) =
+            WgpuContext::create_grid_buffers(device, (width, height));
+        let normal_points = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: points_size(set.points) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_groups = WgpuContext::create_bind_groups(
+            device,
+            &context.bind_group_layout,
+            &storage_buffers,
+            &params_buffer,
+            &normal_points,
+        );
+
+        let seeds = init_normal_points(set.points, set.config, (width, height));
+        let positions = seed_positions(set.points, set.config, (width, height));
+        let kept = input::kept_seeds(set.points, set.config, options.out_of_domain);
+        let mut grid = vec![0; width * height];
+        mark_seeds(&mut grid, &seeds, &kept, width);
+        let steps = run_steps(options, (width, height));
+
+        let queue = &context.queue;
+        let weights = vec![0.0; seeds.len()];
+        let normal_points_data = flatten_seeds(&positions, &weights, 1.0);
+        queue.write_buffer(&normal_points, 0, bytemuck::cast_slice(&normal_points_data));
+        queue.write_buffer(&storage_buffers[0], 0, bytemuck::cast_slice(&grid));
+        let params = pass_params(options, &steps, (width, height));
+        queue.write_buffer(&params_buffer, 0, bytemuck::cast_slice(&params));
+
+        Slot {
+            width,
+            height,
+            passes: steps.len() as u32,
+            storage_buffers,
+            staging_buffer,
+            bind_groups,
+        }
+    }
+
+    // The passes, then the copy of the labels to the staging buffer
+    fn encode(&self, context: &WgpuContext, command_encoder: &mut wgpu::CommandEncoder) {
+        for pass in 0..self.passes {
+            let mut compute_pass =
+                command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: None,
+                    timestamp_writes: None,
+                });
+            compute_pass.set_pipeline(&context.pipeline);
+            compute_pass.set_bind_group(
+                0,
+                &self.bind_groups[pass as usize % 2],
+                &[pass * PARAMS_STRIDE as u32],
+            );
+            let (x, y) = workgroup_count(self.width, self.height);
+            compute_pass.dispatch_workgroups(x, y, 1);
+        }
+        command_encoder.copy_buffer_to_buffer(
+            &self.storage_buffers[self.passes as usize % 2],
+            0,
+            &self.staging_buffer,
+            0,
+            self.staging_buffer.size(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::gpu_or_skip;
+
+    #[test]
+    fn test_groups() {
+        let points = [(0.5, 0.5)];
+        let set = SeedSet {
+            points: &points,
+            config: (1.0, 1.0),
+        };
+        // 3 grids of 2048² texels take 48 MiB
+        let options = MesherConfig {
+            resolution: 2048,
+            ..Default::default()
+        };
+        let batches = [set; 12];
+        let sizes: Vec<usize> = groups(&batches, &options).iter().map(|g| g.len()).collect();
+        assert_eq!(sizes, [5, 5, 2]);
+
+        let options = MesherConfig {
+            resolution: 8192,
+            ..Default::default()
+        };
+        let sizes: Vec<usize> = groups(&batches[..2], &options)
+            .iter()
+            .map(|g| g.len())
+            .collect();
+        assert_eq!(sizes, [1, 1]);
+        assert!(groups(&[], &options).is_empty());
+    }
+
+    #[test]
+    fn test_mesh_batch() {
+        let first = [(1.0, 1.0), (3.0, 3.0)];
+        let second = [(0.5, 1.5), (1.5, 0.5), (1.0, 1.9)];
+        let batches = [
+            SeedSet {
+                points: &first,
+                config: (4.0, 4.0),
+            },
+            SeedSet {
+                points: &second,
+                config: (2.0, 2.0),
+            },
+        ];
+        let options = MesherConfig {
+            resolution: 32,
+            ..Default::default()
+        };
+        let Some(labels) = gpu_or_skip(crate::jfa_wgpu::block_on(mesh_batch(&batches, &options)))
+        else {
+            return;
+        };
+
+        assert_eq!(labels.len(), 2);
+        for (set, labels) in batches.iter().zip(&labels) {
+            let single = super::super::main(set.points, set.config, &options).unwrap();
+            assert_eq!(labels, &single);
+        }
+    }
+
+    #[test]
+    fn test_mesh_batch_groups() {
+        // Three groups, each read back while the next one runs
+        let points = [(1.0, 1.0), (3.0, 2.0)];
+        let set = SeedSet {
+            points: &points,
+            config: (4.0, 4.0),
+        };
+        let options = MesherConfig {
+            resolution: 2048,
+            ..Default::default()
+        };
+        assert_eq!(groups(&[set; 12], &options).len(), 3);
+        let Some(labels) = gpu_or_skip(crate::jfa_wgpu::block_on(mesh_batch(&[set; 12], &options)))
+        else {
+            return;
+        };
+
+        assert_eq!(labels.len(), 12);
+        let single = super::super::main(set.points, set.config, &options).unwrap();
+        assert!(labels.iter().all(|labels| labels == &single));
+    }
+}
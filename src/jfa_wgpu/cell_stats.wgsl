@@ -0,0 +1,55 @@
+// Per-cell texel counts, boundary sides and bounding boxes, 7 words per cell: the count, the
+// sides facing along x and along y, then the min x, min y, max x and max y of its texels
+@group(0) @binding(0) var<storage, read> pixel_grid: array<u32>;
+// Width, height and wrapped axes, bit 0 for x and bit 1 for y
+@group(0) @binding(1) var<uniform> grid: vec4<u32>;
+@group(0) @binding(2) var<storage, read_write> stats: array<atomic<u32>>;
+
+// Whether texel (x, y) belongs to another cell than `label`, the outside of a bounded axis
+// belonging to none
+fn differs(label: u32, x: i32, y: i32) -> u32 {
+    let width = i32(grid.x);
+    let height = i32(grid.y);
+    var new_x = x;
+    var new_y = y;
+    if (grid.z & 1u) != 0u {
+        new_x = (new_x + width) % width;
+    }
+    if (grid.z & 2u) != 0u {
+        new_y = (new_y + height) % height;
+    }
+    if !(new_x >= 0 && new_x < width && new_y >= 0 && new_y < height) {
+        return 1u;
+    }
+    return u32(pixel_grid[u32(new_x) + u32(new_y) * grid.x] != label);
+}
+
+@compute @workgroup_size(16, 16)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let x = global_id.x;
+    let y = global_id.y;
+
+    if (x >= grid.x || y >= grid.y) {
+        return;
+    }
+
+    let label = pixel_grid[x + y * grid.x];
+    if label == 0u {
+        return;
+    }
+
+    let base = (label - 1u) * 7u;
+    let sides_x = differs(label, i32(x) - 1, i32(y)) + differs(label, i32(x) + 1, i32(y));
+    let sides_y = differs(label, i32(x), i32(y) - 1) + differs(label, i32(x), i32(y) + 1);
+    atomicAdd(&stats[base], 1u);
+    if sides_x > 0u {
+        atomicAdd(&stats[base + 1u], sides_x);
+    }
+    if sides_y > 0u {
+        atomicAdd(&stats[base + 2u], sides_y);
+    }
+    atomicMin(&stats[base + 3u], x);
+    atomicMin(&stats[base + 4u], y);
+    atomicMax(&stats[base + 5u], x);
+    atomicMax(&stats[base + 6u], y);
+}
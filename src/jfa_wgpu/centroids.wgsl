@@ -0,0 +1,32 @@
+// Per-cell sums of the texel coordinates and texel counts, 5 words per cell: x and y as 64-bit
+// (low, high) pairs, so large grids don't overflow, then the count
+@group(0) @binding(0) var<storage, read> pixel_grid: array<u32>;
+@group(0) @binding(1) var<uniform> dimensions: vec2<u32>;
+@group(0) @binding(2) var<storage, read_write> sums: array<atomic<u32>>;
+
+fn add_wide(index: u32, value: u32) {
+    let old = atomicAdd(&sums[index], value);
+    if old > 0xffffffffu - value {
+        atomicAdd(&sums[index + 1u], 1u);
+    }
+}
+
+@compute @workgroup_size(16, 16)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let x = global_id.x;
+    let y = global_id.y;
+
+    if (x >= dimensions.x || y >= dimensions.y) {
+        return;
+    }
+
+    let label = pixel_grid[x + y * dimensions.x];
+    if label == 0u {
+        return;
+    }
+
+    let base = (label - 1u) * 5u;
+    add_wide(base, x);
+    add_wide(base + 2u, y);
+    atomicAdd(&sums[base + 4u], 1u);
+}
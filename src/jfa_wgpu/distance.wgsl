@@ -0,0 +1,121 @@
+// Parameters of a pass, seeds and distances of the JFA, shared by the buffer and texture
+// shaders, which are appended to this file and declare the `params` binding. `distance_f64.wgsl`
+// replaces it with the `f64` feature
+
+// Seeds are (x, y, weight) triplets of f32: their exact position in texels, which isn't rounded
+// to the texel they are marked on, and their weight in squared texels as measured by the norm
+@group(0) @binding(2) var<storage, read> normal_points: array<u32>;
+
+struct Params {
+    step: u32,
+    width: u32,
+    height: u32,
+    // Bit 0 when x wraps around, bit 1 for y
+    wrap: u32,
+    // Symmetric metric tensor, distances being d^T M d
+    metric_xx: f32,
+    metric_xy: f32,
+    metric_yy: f32,
+    // 0 for Euclidean distances, 1 for Manhattan, 2 for Chebyshev and 3 for Minkowski
+    norm: u32,
+    exponent: f32,
+    // Non-zero when ties go to the lowest label
+    deterministic: u32,
+    // 0 for f32 distance fields, 1 for f16 and 2 for 16-bit fixed point
+    distance_precision: u32,
+    // Step of the previous pass, 0 for the first one
+    previous_step: u32,
+}
+
+fn wraps_x() -> bool {
+    return (params.wrap & 1u) != 0u;
+}
+
+fn wraps_y() -> bool {
+    return (params.wrap & 2u) != 0u;
+}
+
+// Signed offset along an axis of `size` texels, shortest way around when it wraps
+fn axis_offset(a: f32, b: f32, size: u32, wraps: bool) -> f32 {
+    let d = a - b;
+    if wraps && 2.0 * abs(d) > f32(size) {
+        return d - sign(d) * f32(size);
+    }
+    return d;
+}
+
+// Squared norm, minus the weight, of the offset from the center of texel (x, y) to the seed
+// of label `color`
+fn power_distance(x: u32, y: u32, color: u32) -> f32 {
+    let seed_x = bitcast<f32>(normal_points[(color - 1u) * 3u]);
+    let seed_y = bitcast<f32>(normal_points[(color - 1u) * 3u + 1u]);
+    let weight = bitcast<f32>(normal_points[(color - 1u) * 3u + 2u]);
+    let dx = axis_offset(f32(x) + 0.5, seed_x, params.width, wraps_x());
+    let dy = axis_offset(f32(y) + 0.5, seed_y, params.height, wraps_y());
+    return squared_norm(dx, dy) - weight;
+}
+
+// Whether the seed of label `found` beats the one of `current` at texel (x, y), ties going to
+// the lowest label in deterministic runs
+fn nearer(x: u32, y: u32, found: u32, current: u32) -> bool {
+    let found_distance = power_distance(x, y, found);
+    let current_distance = power_distance(x, y, current);
+    return found_distance < current_distance
+        || (params.deterministic != 0u && found_distance == current_distance && found < current);
+}
+
+// Norm, in texels, of the offset from the center of texel (x, y) to the seed of label `color`
+fn seed_distance(x: u32, y: u32, color: u32) -> f32 {
+    let seed_x = bitcast<f32>(normal_points[(color - 1u) * 3u]);
+    let seed_y = bitcast<f32>(normal_points[(color - 1u) * 3u + 1u]);
+    let dx = axis_offset(f32(x) + 0.5, seed_x, params.width, wraps_x());
+    let dy = axis_offset(f32(y) + 0.5, seed_y, params.height, wraps_y());
+    return sqrt(max(squared_norm(dx, dy), 0.0));
+}
+
+// Norm, in texels, of the offset between the seeds of labels `a` and `b`
+fn seed_separation(a: u32, b: u32) -> f32 {
+    let dx = axis_offset(
+        bitcast<f32>(normal_points[(a - 1u) * 3u]),
+        bitcast<f32>(normal_points[(b - 1u) * 3u]),
+        params.width,
+        wraps_x(),
+    );
+    let dy = axis_offset(
+        bitcast<f32>(normal_points[(a - 1u) * 3u + 1u]),
+        bitcast<f32>(normal_points[(b - 1u) * 3u + 1u]),
+        params.height,
+        wraps_y(),
+    );
+    return sqrt(squared_norm(dx, dy));
+}
+
+// `pow` is undefined for a zero base
+fn power(base: f32, exponent: f32) -> f32 {
+    if base == 0.0 {
+        return 0.0;
+    }
+    return pow(base, exponent);
+}
+
+// Squared norm of an offset, to compare with weights in squared texels
+fn squared_norm(dx: f32, dy: f32) -> f32 {
+    switch params.norm {
+        case 1u: {
+            let n = abs(dx) + abs(dy);
+            return n * n;
+        }
+        case 2u: {
+            let n = max(abs(dx), abs(dy));
+            return n * n;
+        }
+        case 3u: {
+            let p = params.exponent;
+            return power(power(abs(dx), p) + power(abs(dy), p), 2.0 / p);
+        }
+        default: {
+            return params.metric_xx * dx * dx + 2.0 * params.metric_xy * dx * dy
+                + params.metric_yy * dy * dy;
+        }
+    }
+}
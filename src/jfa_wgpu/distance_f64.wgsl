@@ -0,0 +1,117 @@
+// Double precision `distance.wgsl`, used with the `f64` feature on devices supporting
+// `SHADER_F64`. Only the metric and the exponent of the norm stay f32, as uniforms, and the
+// powers of Minkowski norms are taken in f32, which `pow` is limited to on most backends
+
+struct Seed {
+    // Exact position in texels
+    x: f64,
+    y: f64,
+    // In squared texels as measured by the norm
+    weight: f64,
+}
+
+@group(0) @binding(2) var<storage, read> normal_points: array<Seed>;
+
+struct Params {
+    step: u32,
+    width: u32,
+    height: u32,
+    // Bit 0 when x wraps around, bit 1 for y
+    wrap: u32,
+    // Symmetric metric tensor, distances being d^T M d
+    metric_xx: f32,
+    metric_xy: f32,
+    metric_yy: f32,
+    // 0 for Euclidean distances, 1 for Manhattan, 2 for Chebyshev and 3 for Minkowski
+    norm: u32,
+    exponent: f32,
+    // Non-zero when ties go to the lowest label
+    deterministic: u32,
+    // 0 for f32 distance fields, 1 for f16 and 2 for 16-bit fixed point
+    distance_precision: u32,
+    // Step of the previous pass, 0 for the first one
+    previous_step: u32,
+}
+
+fn wraps_x() -> bool {
+    return (params.wrap & 1u) != 0u;
+}
+
+fn wraps_y() -> bool {
+    return (params.wrap & 2u) != 0u;
+}
+
+// Signed offset along an axis of `size` texels, shortest way around when it wraps
+fn axis_offset(a: f64, b: f64, size: u32, wraps: bool) -> f64 {
+    let d = a - b;
+    if wraps && 2.0lf * abs(d) > f64(size) {
+        return d - sign(d) * f64(size);
+    }
+    return d;
+}
+
+// Squared norm, minus the weight, of the offset from the center of texel (x, y) to the seed
+// of label `color`
+fn power_distance(x: u32, y: u32, color: u32) -> f64 {
+    let seed = normal_points[color - 1u];
+    let dx = axis_offset(f64(x) + 0.5lf, seed.x, params.width, wraps_x());
+    let dy = axis_offset(f64(y) + 0.5lf, seed.y, params.height, wraps_y());
+    return squared_norm(dx, dy) - seed.weight;
+}
+
+// Whether the seed of label `found` beats the one of `current` at texel (x, y), ties going to
+// the lowest label in deterministic runs
+fn nearer(x: u32, y: u32, found: u32, current: u32) -> bool {
+    let found_distance = power_distance(x, y, found);
+    let current_distance = power_distance(x, y, current);
+    return found_distance < current_distance
+        || (params.deterministic != 0u && found_distance == current_distance && found < current);
+}
+
+// Norm, in texels, of the offset from the center of texel (x, y) to the seed of label `color`
+fn seed_distance(x: u32, y: u32, color: u32) -> f32 {
+    let seed = normal_points[color - 1u];
+    let dx = axis_offset(f64(x) + 0.5lf, seed.x, params.width, wraps_x());
+    let dy = axis_offset(f64(y) + 0.5lf, seed.y, params.height, wraps_y());
+    return sqrt(f32(max(squared_norm(dx, dy), 0.0lf)));
+}
+
+// Norm, in texels, of the offset between the seeds of labels `a` and `b`
+fn seed_separation(a: u32, b: u32) -> f32 {
+    let seed_a = normal_points[a - 1u];
+    let seed_b = normal_points[b - 1u];
+    let dx = axis_offset(seed_a.x, seed_b.x, params.width, wraps_x());
+    let dy = axis_offset(seed_a.y, seed_b.y, params.height, wraps_y());
+    return sqrt(f32(squared_norm(dx, dy)));
+}
+
+// `pow` is undefined for a zero base
+fn power(base: f32, exponent: f32) -> f32 {
+    if base == 0.0 {
+        return 0.0;
+    }
+    return pow(base, exponent);
+}
+
+// Squared norm of an offset, to compare with weights in squared texels
+fn squared_norm(dx: f64, dy: f64) -> f64 {
+    switch params.norm {
+        case 1u: {
+            let n = abs(dx) + abs(dy);
+            return n * n;
+        }
+        case 2u: {
+            let n = max(abs(dx), abs(dy));
+            return n * n;
+        }
+        case 3u: {
+            let p = params.exponent;
+            let sum = power(f32(abs(dx)), p) + power(f32(abs(dy)), p);
+            return f64(power(sum, 2.0 / p));
+        }
+        default: {
+            return f64(params.metric_xx) * dx * dx + 2.0lf * f64(params.metric_xy) * dx * dy
+                + f64(params.metric_yy) * dy * dy;
+        }
+    }
+}
@@ -0,0 +1,268 @@
+// Distance fields of a diagram, for blending, erosion effects or sizing feedback: a pass after
+// the JFA measures, at every texel, the distance to the seed it was labeled with and to the
+// nearest boundary of its cell, from the labels and seeds already on the GPU.
+
+use super::{
+    check_budget_with, dispatch_jfa, get_data, points_size, read_back, workgroup_count,
+    WgpuContext, DISTANCE_SHADER, PARAMS_SIZE,
+};
+use crate::config::{DistancePrecision, MesherConfig};
+use crate::error::MesherError;
+
+// Words of a texel, two f32 or two packed 16-bit distances
+fn texel_words(precision: DistancePrecision) -> usize {
+    match precision {
+        DistancePrecision::F32 => 2,
+        DistancePrecision::F16 | DistancePrecision::Fixed16 => 1,
+    }
+}
+
+/// Distances of every texel center, in texels as measured by the norm of the run, indexed with
+/// `x + y * width`. Both are -1 for unlabeled texels. They are rounded to the
+/// [`crate::config::MesherConfig::distance_precision`] of the run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistanceField {
+    pub width: usize,
+    pub height: usize,
+    /// Distance to the seed of the texel's cell
+    pub seed: Vec<f32>,
+    /// Distance to the nearest edge of the texel's cell, the sides of the box included unless
+    /// their axis wraps around. Exact for Euclidean distances, an estimate for the other norms
+    pub boundary: Vec<f32>,
+}
+
+/// Labels of every texel, as [`super::run_with_stats`] computes them, and their distance field.
+pub async fn run_with_distances(
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    options: &MesherConfig,
+) -> Result<(Vec<u32>, DistanceField), MesherError> {
+    check_budget_with(points, config, options, |dimensions| {
+        memory_required(dimensions, options.distance_precision)
+    })?;
+
+    let dimensions = options.grid_dimensions(config);
+    let mut context = WgpuContext::new(dimensions, points_size(points), &options.adapter).await?;
+    context.prepare(options)?;
+    let dump = options.debug_dump.as_ref();
+    let passes = dispatch_jfa(&context, points, &[], config, options, dump).await?;
+    let mut labels = vec![0; context.width * context.height];
+    read_back(&context, &mut labels, passes).await?;
+    let buffer = &context.storage_buffers[passes as usize % 2];
+    let field = compute(&context, buffer, options.distance_precision).await?;
+    Ok((labels, field))
+}
+
+// Bytes `compute` allocates on a grid of `width` by `height` texels, the field and its staging
+// copy
+pub(super) fn memory_required(
+    (width, height): (usize, usize),
+    precision: DistancePrecision,
+) -> u64 {
+    (2 * width * height * texel_words(precision) * std::mem::size_of::<u32>()) as u64
+}
+
+// Distance field of `labels`, a grid of the context's dimensions labeled with the seeds and
+// parameters of the context's last run, whose options set `precision`
+pub(super) async fn compute(
+    context: &WgpuContext,
+    labels: &wgpu::Buffer,
+    precision: DistancePrecision,
+) -> Result<DistanceField, MesherError> {
+    let device = &context.device;
+    let (width, height) = (context.width, context.height);
+
+    let source = [DISTANCE_SHADER, include_str!("distance_field.wgsl")].join("\n");
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    let words = width * height * texel_words(precision);
+    let field_size = (words * std::mem::size_of::<u32>()) as u64;
+    let field_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: field_size,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    let field_staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: field_size,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    // The parameters of the first pass hold the grid and the norm, its step is not read
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: labels.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &context.params_buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(PARAMS_SIZE as u64),
+                }),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: context.normal_points.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: field_buffer.as_entire_binding(),
+            },
+        ],
+    });
+
+    let mut command_encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    {
+        let mut compute_pass = command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&pipeline);
+        compute_pass.set_bind_group(0, &bind_group, &[]);
+        let (x, y) = workgroup_count(width, height);
+        compute_pass.dispatch_workgroups(x, y, 1);
+    }
+    context.queue.submit(Some(command_encoder.finish()));
+
+    let mut distances = vec![0u32; words];
+    let mapped = get_data(
+        &mut distances,
+        &field_buffer,
+        &field_staging_buffer,
+        device,
+        &context.queue,
+    )
+    .await;
+    if let Some(err) = context.take_error() {
+        return Err(err);
+    }
+    mapped.map_err(|err| MesherError::BufferMapFailed(err.to_string()))?;
+
+    let (seed, boundary) = match precision {
+        DistancePrecision::F32 => distances
+            .chunks_exact(2)
+            .map(|texel| (f32::from_bits(texel[0]), f32::from_bits(texel[1])))
+            .unzip(),
+        DistancePrecision::F16 => distances
+            .iter()
+            .map(|&texel| (half_to_f32(texel as u16), half_to_f32((texel >> 16) as u16)))
+            .unzip(),
+        DistancePrecision::Fixed16 => {
+            let range = 2.0 * width.max(height) as f32;
+            let fixed = |value: u32| match value {
+                0xffff => -1.0,
+                value => value as f32 * range / 65534.0,
+            };
+            distances
+                .iter()
+                .map(|&texel| (fixed(texel & 0xffff), fixed(texel >> 16)))
+                .unzip()
+        }
+    };
+    Ok(DistanceField {
+        width,
+        height,
+        seed,
+        boundary,
+    })
+}
+
+// Value of the IEEE half precision float `bits`
+fn half_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 == 0 { 1.0 } else { -1.0 };
+    let exponent = i32::from((bits >> 10) & 0x1f);
+    let mantissa = f32::from(bits & 0x3ff);
+    sign * match exponent {
+        0 => mantissa * 2f32.powi(-24),
+        31 if mantissa == 0.0 => f32::INFINITY,
+        31 => f32::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::gpu_or_skip;
+
+    #[test]
+    fn test_distance_field() {
+        // Cells split at x = 4 texels of an 8 x 8 grid
+        let points = [(2.0, 4.0), (6.0, 4.0)];
+        let options = MesherConfig {
+            resolution: 8,
+            ..Default::default()
+        };
+        let Some((labels, field)) = gpu_or_skip(crate::jfa_wgpu::block_on(run_with_distances(
+            &points,
+            (8.0, 8.0),
+            &options,
+        ))) else {
+            return;
+        };
+
+        assert_eq!(labels[3 + 4 * 8], 1);
+        // Texel (3, 4) is centered at (3.5, 4.5), half a texel from its seed's row
+        let texel = 3 + 4 * 8;
+        assert!((field.seed[texel] - 1.5f32.hypot(0.5)).abs() < 1e-5);
+        assert!((field.boundary[texel] - 0.5).abs() < 1e-5);
+        // Texel (1, 1) is nearer the bottom and left sides of the box than the bisector
+        assert!((field.boundary[1 + 8] - 1.5).abs() < 1e-5);
+
+        // Distances of at most 16 texels, so within 2^-7 texels in f16 and 16 / 65534 in fixed
+        // point
+        for (distance_precision, tolerance) in [
+            (DistancePrecision::F16, 1.0 / 128.0),
+            (DistancePrecision::Fixed16, 16.0 / 65534.0),
+        ] {
+            let options = MesherConfig {
+                distance_precision,
+                ..options.clone()
+            };
+            let Some((_, packed)) = gpu_or_skip(crate::jfa_wgpu::block_on(run_with_distances(
+                &points,
+                (8.0, 8.0),
+                &options,
+            ))) else {
+                return;
+            };
+            let near =
+                |a: &[f32], b: &[f32]| a.iter().zip(b).all(|(a, b)| (a - b).abs() <= tolerance);
+            assert!(near(&packed.seed, &field.seed), "{distance_precision:?}");
+            assert!(
+                near(&packed.boundary, &field.boundary),
+                "{distance_precision:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_half_to_f32() {
+        assert_eq!(half_to_f32(0x0000), 0.0);
+        assert_eq!(half_to_f32(0x3c00), 1.0);
+        assert_eq!(half_to_f32(0xbc00), -1.0);
+        assert_eq!(half_to_f32(0x3e00), 1.5);
+        assert_eq!(half_to_f32(0x7bff), 65504.0);
+        assert_eq!(half_to_f32(0x0001), 2f32.powi(-24));
+        assert_eq!(half_to_f32(0x7c00), f32::INFINITY);
+        assert!(half_to_f32(0x7e00).is_nan());
+    }
+}
@@ -0,0 +1,101 @@
+// Distance from each texel center to its seed and to the nearest boundary of its cell, in texels
+// as measured by the norm, appended to the distance shader. The boundary with a neighboring cell
+// is its power bisector, whose distance is the difference of the power distances over twice the
+// distance between the seeds, exact for Euclidean distances and an estimate for the other norms.
+// Neighbors are the cells of the texels along 8 directions at doubling distances, and the box
+// sides of bounded axes are boundaries too
+@group(0) @binding(0) var<storage, read> labels: array<u32>;
+@group(0) @binding(1) var<uniform> params: Params;
+// Distance to the seed then to the boundary, both -1 for unlabeled texels, as two f32 words or
+// packed in a single one
+@group(0) @binding(3) var<storage, read_write> field: array<u32>;
+
+// 16-bit fixed point distance, in steps of the grid's longer side over 32767, 0xffff when
+// negative
+fn to_fixed(distance: f32) -> u32 {
+    if distance < 0.0 {
+        return 0xffffu;
+    }
+    let range = f32(2u * max(params.width, params.height));
+    return u32(round(min(distance / range, 1.0) * 65534.0));
+}
+
+fn store(index: u32, seed: f32, boundary: f32) {
+    switch params.distance_precision {
+        case 1u: {
+            field[index] = pack2x16float(vec2<f32>(seed, boundary));
+        }
+        case 2u: {
+            field[index] = to_fixed(seed) | (to_fixed(boundary) << 16u);
+        }
+        default: {
+            field[2u * index] = bitcast<u32>(seed);
+            field[2u * index + 1u] = bitcast<u32>(boundary);
+        }
+    }
+}
+
+// Label of texel (x, y), 0 outside a bounded axis
+fn label_at(x: i32, y: i32) -> u32 {
+    let width = i32(params.width);
+    let height = i32(params.height);
+    var new_x = x;
+    var new_y = y;
+    if wraps_x() {
+        new_x = (new_x % width + width) % width;
+    }
+    if wraps_y() {
+        new_y = (new_y % height + height) % height;
+    }
+    if !(new_x >= 0 && new_x < width && new_y >= 0 && new_y < height) {
+        return 0u;
+    }
+    return labels[u32(new_x) + u32(new_y) * params.width];
+}
+
+@compute @workgroup_size(16, 16)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let x = global_id.x;
+    let y = global_id.y;
+    let width = params.width;
+    let height = params.height;
+
+    if (x >= width || y >= height) {
+        return;
+    }
+
+    let index: u32 = x + y * width;
+    let label = labels[index];
+    if label == 0u {
+        store(index, -1.0, -1.0);
+        return;
+    }
+
+    var boundary = f32(2u * max(width, height));
+    if !wraps_x() {
+        boundary = min(boundary, min(f32(x) + 0.5, f32(width - x) - 0.5));
+    }
+    if !wraps_y() {
+        boundary = min(boundary, min(f32(y) + 0.5, f32(height - y) - 0.5));
+    }
+
+    let own = f32(power_distance(x, y, label));
+    for (var step = 1; step < i32(max(width, height)); step = step * 2) {
+        for (var dx = -1; dx <= 1; dx = dx + 1) {
+            for (var dy = -1; dy <= 1; dy = dy + 1) {
+                let other = label_at(i32(x) + dx * step, i32(y) + dy * step);
+                if other == 0u || other == label {
+                    continue;
+                }
+                let separation = seed_separation(label, other);
+                if separation == 0.0 {
+                    continue;
+                }
+                let distance = (f32(power_distance(x, y, other)) - own) / (2.0 * separation);
+                boundary = min(boundary, max(distance, 0.0));
+            }
+        }
+    }
+
+    store(index, seed_distance(x, y, label), boundary);
+}
@@ -0,0 +1,136 @@
+// Checks of the seeds before a run, so bad input fails with an error saying what is wrong with
+// it instead of giving a grid which looks fine but isn't. A single seed is valid, its cell
+// covering the whole grid. Seeds outside the domain box are handled following
+// `MesherConfig::out_of_domain`.
+
+use std::collections::HashMap;
+
+use super::init_normal_points;
+use crate::config::OutOfDomain;
+use crate::error::MesherError;
+
+// Labels are `i + 1` and 0 means unlabeled, so seed indices stop one short of `u32::MAX`
+pub(crate) const MAX_SEEDS: usize = u32::MAX as usize - 1;
+
+/// Indices of the seeds outside the `config` box, or with a NaN coordinate.
+pub fn seeds_outside(points: &[(f64, f64)], config: (f64, f64)) -> Vec<usize> {
+    (0..points.len())
+        .filter(|&i| is_outside(points[i], config))
+        .collect()
+}
+
+// NaN coordinates are in no range
+fn is_outside((x, y): (f64, f64), config: (f64, f64)) -> bool {
+    !((0.0..=config.0).contains(&x) && (0.0..=config.1).contains(&y))
+}
+
+// Whether each seed is labeled on the grid under `policy`, ignored seeds being uploaded but
+// never spreading their label
+pub(super) fn kept_seeds(
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    policy: OutOfDomain,
+) -> Vec<bool> {
+    points
+        .iter()
+        .map(|&point| policy != OutOfDomain::Ignore || !is_outside(point, config))
+        .collect()
+}
+
+pub(super) fn validate_seeds(
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    dimensions: (usize, usize),
+    policy: OutOfDomain,
+) -> Result<(), MesherError> {
+    if !(config.0 > 0.0 && config.1 > 0.0 && config.0.is_finite() && config.1.is_finite()) {
+        return Err(MesherError::InvalidInput(
+            "the domain must have a finite, positive width and height",
+        ));
+    }
+    if points.is_empty() {
+        return Err(MesherError::NoSeeds);
+    }
+    if points.len() > MAX_SEEDS {
+        return Err(MesherError::TooManySeeds {
+            count: points.len(),
+        });
+    }
+    // NaN coordinates can't be clamped
+    let rejected = |&(x, y): &(f64, f64)| match policy {
+        OutOfDomain::Reject => is_outside((x, y), config),
+        OutOfDomain::Clamp => x.is_nan() || y.is_nan(),
+        OutOfDomain::Ignore => false,
+    };
+    if let Some(index) = points.iter().position(rejected) {
+        return Err(MesherError::SeedOutsideDomain { index });
+    }
+
+    let kept = kept_seeds(points, config, policy);
+    if !kept.contains(&true) {
+        return Err(MesherError::NoSeeds);
+    }
+    let mut texels = HashMap::with_capacity(points.len());
+    for (second, texel) in init_normal_points(points, config, dimensions)
+        .into_iter()
+        .enumerate()
+        .filter(|&(i, _)| kept[i])
+    {
+        if let Some(first) = texels.insert(texel, second) {
+            return Err(MesherError::CoincidentSeeds { first, second });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_seeds() {
+        let validate =
+            |points: &[(f64, f64)]| validate_seeds(points, (4.0, 2.0), (8, 4), OutOfDomain::Reject);
+
+        assert_eq!(validate(&[(1.0, 1.0)]), Ok(()));
+        assert_eq!(validate(&[(1.0, 1.0), (4.0, 2.0)]), Ok(()));
+        assert_eq!(validate(&[]), Err(MesherError::NoSeeds));
+        assert_eq!(
+            validate(&[(1.0, 1.0), (4.5, 1.0)]),
+            Err(MesherError::SeedOutsideDomain { index: 1 })
+        );
+        assert_eq!(
+            validate(&[(f64::NAN, 1.0)]),
+            Err(MesherError::SeedOutsideDomain { index: 0 })
+        );
+        // Both on texel (2, 2)
+        assert_eq!(
+            validate(&[(0.0, 0.0), (1.1, 1.1), (1.4, 1.2)]),
+            Err(MesherError::CoincidentSeeds {
+                first: 1,
+                second: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_out_of_domain_policy() {
+        let points = [(1.0, 1.0), (-1.0, 0.5), (f64::NAN, 1.0)];
+        let validate = |policy| validate_seeds(&points, (4.0, 2.0), (8, 4), policy);
+
+        assert_eq!(seeds_outside(&points, (4.0, 2.0)), [1, 2]);
+        assert_eq!(
+            validate(OutOfDomain::Reject),
+            Err(MesherError::SeedOutsideDomain { index: 1 })
+        );
+        assert_eq!(
+            validate(OutOfDomain::Clamp),
+            Err(MesherError::SeedOutsideDomain { index: 2 })
+        );
+        assert_eq!(validate(OutOfDomain::Ignore), Ok(()));
+        assert_eq!(
+            kept_seeds(&points, (4.0, 2.0), OutOfDomain::Ignore),
+            [true, false, false]
+        );
+    }
+}
@@ -0,0 +1,237 @@
+// Mesher keeping its device and buffers between runs, so meshing many seed sets (an animation,
+// a relaxation driven from the CPU, a parameter sweep) only pays for the device and the
+// pipeline once. It can also run on the device of an application already rendering with wgpu,
+// whose pipelines can then bind the labels buffer directly.
+
+use std::sync::Arc;
+
+use super::batch::{self, SeedSet};
+use super::distance_field::{self, DistanceField};
+use super::pyramid::{self, PyramidLevel};
+use super::view::{self, LabelView, ViewOptions};
+use super::{
+    check_budget, check_features, check_required, points_size, run_with_context, stats, CellStats,
+    DispatchStats, RecoveryPolicy, WgpuContext, POINT_SIZE,
+};
+use crate::config::MesherConfig;
+use crate::error::MesherError;
+
+/// GPU mesher reusable across runs. The seed buffer grows with the largest seed set seen so far,
+/// and the grid buffers are reallocated when the box of a run needs other grid dimensions.
+pub struct Mesher {
+    options: MesherConfig,
+    context: WgpuContext,
+    /// Whether the device is the caller's, which can't be recreated after a loss
+    shared_device: bool,
+    /// Passes of the last successful run, whose parity tells where its labels are
+    last_passes: Option<u32>,
+    /// Seed count and box of the last successful run
+    last_seeds: (usize, (f64, f64)),
+}
+
+impl Mesher {
+    pub async fn new(options: MesherConfig) -> Result<Mesher, MesherError> {
+        if options.resolution == 0 {
+            return Err(MesherError::InvalidInput("resolution must be at least 1"));
+        }
+        let reso = options.resolution;
+        let context = WgpuContext::new((reso, reso), POINT_SIZE, &options.adapter).await?;
+        Ok(Mesher {
+            options,
+            context,
+            shared_device: false,
+            last_passes: None,
+            last_seeds: (0, (0.0, 0.0)),
+        })
+    }
+
+    /// Mesher sharing the device and queue of the caller instead of creating its own. Device
+    /// callbacks are left to the caller and a lost device is always returned as an error, so
+    /// the recovery policy is ignored.
+    pub fn with_device(
+        device: Arc<wgpu::Device>,
+        queue: Arc<wgpu::Queue>,
+        options: MesherConfig,
+    ) -> Result<Mesher, MesherError> {
+        if options.resolution == 0 {
+            return Err(MesherError::InvalidInput("resolution must be at least 1"));
+        }
+        check_features(&device)?;
+        let reso = options.resolution;
+        let context = WgpuContext::from_device(device, queue, (reso, reso), POINT_SIZE);
+        Ok(Mesher {
+            options,
+            context,
+            shared_device: true,
+            last_passes: None,
+            last_seeds: (0, (0.0, 0.0)),
+        })
+    }
+
+    pub fn options(&self) -> &MesherConfig {
+        &self.options
+    }
+
+    /// Device the mesher runs on, the caller's with [`Mesher::with_device`]. It is replaced when
+    /// a lost device is recreated.
+    pub fn device(&self) -> &Arc<wgpu::Device> {
+        &self.context.device
+    }
+
+    pub fn queue(&self) -> &Arc<wgpu::Queue> {
+        &self.context.queue
+    }
+
+    /// Storage buffer holding the labels of the last successful run, one `u32` per texel indexed
+    /// with `x + y * width`, for the caller's pipelines to bind without reading it back. It is
+    /// overwritten by the next run, and reallocated when the grid dimensions change.
+    pub fn labels_buffer(&self) -> Option<&wgpu::Buffer> {
+        self.last_passes
+            .map(|passes| &self.context.storage_buffers[passes as usize % 2])
+    }
+
+    /// Texture holding the labels of the last successful run in its red channel, with
+    /// [`crate::config::GridStorage::Texture`], for the caller's render passes to sample. It is
+    /// overwritten by the next run, and reallocated along with the grid buffers.
+    pub fn labels_texture(&self) -> Option<&wgpu::Texture> {
+        let textures = self.context.textures.as_ref()?;
+        self.last_passes
+            .map(|passes| &textures.textures[passes as usize % 2])
+    }
+
+    /// Statistics of the cells of the last successful run, reduced on the GPU from its labels
+    /// buffer, `None` before any run.
+    pub async fn cell_stats(&self) -> Result<Option<CellStats>, MesherError> {
+        let (Some(buffer), (cells, config)) = (self.labels_buffer(), self.last_seeds) else {
+            return Ok(None);
+        };
+        self.check_extra(stats::memory_required(cells))?;
+        stats::reduce(&self.context, buffer, cells, config, &self.options)
+            .await
+            .map(Some)
+    }
+
+    /// Distance field of the last successful run, computed on the GPU from its labels buffer,
+    /// `None` before any run.
+    pub async fn distance_field(&self) -> Result<Option<DistanceField>, MesherError> {
+        let Some(buffer) = self.labels_buffer() else {
+            return Ok(None);
+        };
+        let precision = self.options.distance_precision;
+        self.check_extra(distance_field::memory_required(
+            self.dimensions(),
+            precision,
+        ))?;
+        distance_field::compute(&self.context, buffer, precision)
+            .await
+            .map(Some)
+    }
+
+    /// Label pyramid of the last successful run, as [`super::run_with_pyramid`] computes it from
+    /// its labels buffer, `None` before any run. `levels` must be at least 1.
+    pub async fn label_pyramid(
+        &self,
+        levels: usize,
+    ) -> Result<Option<Vec<PyramidLevel>>, MesherError> {
+        pyramid::check_levels(levels)?;
+        let Some(buffer) = self.labels_buffer() else {
+            return Ok(None);
+        };
+        self.check_extra(pyramid::memory_required(self.dimensions(), levels))?;
+        pyramid::compute(&self.context, buffer, levels)
+            .await
+            .map(Some)
+    }
+
+    /// Draws the labels of the last successful run over the whole of `target`, `(width, height)`
+    /// pixels in the format of `view`, without reading them back. Returns false, drawing
+    /// nothing, before any run.
+    pub fn draw_labels(
+        &self,
+        view: &LabelView,
+        target: &wgpu::TextureView,
+        size: (u32, u32),
+        options: &ViewOptions,
+    ) -> bool {
+        let Some(buffer) = self.labels_buffer() else {
+            return false;
+        };
+        view::draw(view, &self.context, buffer, target, size, options);
+        true
+    }
+
+    // Rejects allocating `extra` bytes next to the buffers of the last run over the budget
+    fn check_extra(&self, extra: u64) -> Result<(), MesherError> {
+        check_required(self.context.memory_usage() + extra, &self.options)
+    }
+
+    /// Width and height of the label grid of the last run, in texels.
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.context.width, self.context.height)
+    }
+
+    /// Labels of `points` in the `config` box, as [`super::run_with_stats`] would return them.
+    ///
+    /// Under [`RecoveryPolicy::RetryOnce`] a lost device is recreated, and kept for the next
+    /// runs, before retrying once.
+    pub async fn run(
+        &mut self,
+        points: &[(f64, f64)],
+        config: (f64, f64),
+    ) -> Result<(Vec<u32>, DispatchStats), MesherError> {
+        self.run_seeds(points, &[], config).await
+    }
+
+    /// Power diagram of weighted seeds, as [`super::run_weighted`] would return it.
+    pub async fn run_weighted(
+        &mut self,
+        points: &[(f64, f64, f64)],
+        config: (f64, f64),
+    ) -> Result<(Vec<u32>, DispatchStats), MesherError> {
+        let (seeds, weights): (Vec<(f64, f64)>, Vec<f64>) =
+            points.iter().map(|&(x, y, w)| ((x, y), w)).unzip();
+        self.run_seeds(&seeds, &weights, config).await
+    }
+
+    /// Labels of each seed set, as [`super::mesh_batch`] computes them, on the device of this
+    /// mesher. The labels buffer and texture keep the last single run's labels.
+    pub async fn run_batch(&self, batches: &[SeedSet<'_>]) -> Result<Vec<Vec<u32>>, MesherError> {
+        batch::check_batch(batches, &self.options)?;
+        batch::run_batch(&self.context, batches, &self.options).await
+    }
+
+    async fn run_seeds(
+        &mut self,
+        points: &[(f64, f64)],
+        weights: &[f64],
+        config: (f64, f64),
+    ) -> Result<(Vec<u32>, DispatchStats), MesherError> {
+        check_budget(points, config, &self.options)?;
+
+        self.last_passes = None;
+        self.context
+            .reserve(self.options.grid_dimensions(config), points_size(points));
+        self.context.prepare(&self.options)?;
+        let result = match run_with_context(&self.context, points, weights, config, &self.options)
+            .await
+        {
+            Err(err @ MesherError::DeviceLost { .. })
+                if self.options.recovery == RecoveryPolicy::RetryOnce && !self.shared_device =>
+            {
+                log::warn!("{err}, recreating the context and retrying");
+                let dimensions = self.options.grid_dimensions(config);
+                self.context =
+                    WgpuContext::new(dimensions, points_size(points), &self.options.adapter)
+                        .await?;
+                self.context.prepare(&self.options)?;
+                run_with_context(&self.context, points, weights, config, &self.options).await
+            }
+            result => result,
+        };
+        if let Ok((_, stats)) = &result {
+            self.last_passes = Some(stats.passes);
+            self.last_seeds = (points.len(), config);
+        }
+        result
+    }
+}
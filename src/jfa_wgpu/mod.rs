@@ -1,103 +1,682 @@
-const RESO: usize = 512;
+use std::fmt;
+use std::sync::{Arc, Mutex};
 
-pub async fn run(points: &[(f64, f64)], config: (f64, f64)) -> Vec<u32> {
-    let context = WgpuContext::new(
-        RESO * RESO * std::mem::size_of::<u32>(),
-        points.len() * std::mem::size_of::<(u32, u32)>(),
-    )
-    .await;
+use crate::config::{
+    DistanceMetric, DistancePrecision, GridStorage, JfaVariant, MesherConfig, Metric,
+};
+use crate::debug::{DebugDump, Stage};
+use crate::error::MesherError;
+use crate::progress::Progress;
+
+mod active;
+mod adapter;
+mod balance;
+mod batch;
+mod distance_field;
+mod input;
+mod mesher;
+mod poll;
+#[cfg(feature = "profiling")]
+mod profile;
+mod pyramid;
+mod relax;
+mod stats;
+mod texture;
+mod tiling;
+mod view;
+
+use active::ActiveTiles;
+pub use adapter::{AdapterSelection, GraphicsApi, PowerPreference};
+pub use balance::{balance, Balanced};
+pub use batch::{mesh_batch, SeedSet};
+pub use distance_field::{run_with_distances, DistanceField};
+pub use input::seeds_outside;
+pub(crate) use input::MAX_SEEDS;
+pub use mesher::Mesher;
+#[cfg(not(target_arch = "wasm32"))]
+pub use poll::block_on;
+use poll::{poll_submission, poll_until};
+#[cfg(feature = "profiling")]
+pub use profile::{run_profiled, RunProfile};
+pub use pyramid::{run_with_pyramid, PyramidLevel};
+pub use relax::relax;
+pub use stats::{cell_stats, CellStats};
+use texture::TextureGrid;
+pub use tiling::{run_tiled, DEFAULT_TILE_SIZE};
+pub use view::{LabelView, ViewOptions};
 
-    let normal_points = init_normal_points(points, config);
+const WORKGROUP_SIZE: u32 = 16;
+// Step, grid width and height, wrapped axes, metric tensor, norm and its exponent and the tie
+// rule, the `Params` uniform of the shader, padded to a multiple of 16 bytes
+const PARAMS_SIZE: usize = 12 * std::mem::size_of::<u32>();
+// Precision of the seeds in the points buffer and of the distances in the shaders
+#[cfg(not(feature = "f64"))]
+type Real = f32;
+#[cfg(feature = "f64")]
+type Real = f64;
+// Texel coordinates and weight of a seed in the points buffer
+pub(crate) const POINT_SIZE: usize = 3 * std::mem::size_of::<Real>();
+// Bindings and distances the JFA shaders are appended to
+#[cfg(not(feature = "f64"))]
+const DISTANCE_SHADER: &str = include_str!("distance.wgsl");
+#[cfg(feature = "f64")]
+const DISTANCE_SHADER: &str = include_str!("distance_f64.wgsl");
+// Device features the shaders need
+#[cfg(not(feature = "f64"))]
+const REQUIRED_FEATURES: wgpu::Features = wgpu::Features::empty();
+#[cfg(feature = "f64")]
+const REQUIRED_FEATURES: wgpu::Features = wgpu::Features::SHADER_F64;
+// Device features requested when the adapter has them
+#[cfg(not(feature = "profiling"))]
+const OPTIONAL_FEATURES: wgpu::Features = wgpu::Features::empty();
+#[cfg(feature = "profiling")]
+const OPTIONAL_FEATURES: wgpu::Features = wgpu::Features::TIMESTAMP_QUERY;
+// Distance between the parameters of consecutive passes, the largest uniform offset alignment
+// a device may require
+const PARAMS_STRIDE: usize = 256;
+// Step 1 passes appended by the correction
+const CORRECTION_PASSES: usize = 2;
 
-    let mut local_buffer = vec![0; RESO * RESO];
+/// What to do when the device is lost during a run.
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RecoveryPolicy {
+    /// Return the error to the caller
+    #[default]
+    Fail,
+    /// Recreate the context and run the job a second time
+    RetryOnce,
+}
+
+/// What a GPU run actually dispatched, so performance can be diagnosed from logs.
+#[derive(Debug, Clone)]
+pub struct DispatchStats {
+    /// Passes at full resolution
+    pub passes: u32,
+    /// Passes on the coarse grid before them, 0 without [`MesherConfig::coarse_factor`]
+    pub coarse_passes: u32,
+    pub workgroup_size: (u32, u32),
+    pub workgroups: (u32, u32),
+    pub total_invocations: u64,
+    /// Size of each of the two ping-pong storage buffers
+    pub storage_buffer_size: u64,
+    pub staging_buffer_size: u64,
+    pub points_buffer_size: u64,
+    /// Total size of the buffers allocated for the run
+    pub memory_usage: u64,
+    /// Seeds outside the domain box, clamped or ignored following
+    /// [`MesherConfig::out_of_domain`]
+    pub outside_seeds: Vec<usize>,
+    /// `None` when running on a device provided by the caller
+    pub adapter: Option<wgpu::AdapterInfo>,
+    pub limits: wgpu::Limits,
+}
 
-    // Mark the initial points on the grid with their respective color
-    for (i, point) in normal_points.iter().enumerate() {
-        let color = i + 1; // 0 means uncolored
-        local_buffer[point.0 as usize + point.1 as usize * RESO] = color as u32;
+impl fmt::Display for DispatchStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.adapter {
+            Some(adapter) => writeln!(
+                f,
+                "adapter: {} ({:?}, {:?}, driver {} {})",
+                adapter.name,
+                adapter.backend,
+                adapter.device_type,
+                adapter.driver,
+                adapter.driver_info
+            )?,
+            None => writeln!(f, "adapter: external device")?,
+        }
+        writeln!(
+            f,
+            "passes: {} (+{} coarse), workgroup size: {}x{}, workgroups per pass: {}x{}, total invocations: {}",
+            self.passes,
+            self.coarse_passes,
+            self.workgroup_size.0,
+            self.workgroup_size.1,
+            self.workgroups.0,
+            self.workgroups.1,
+            self.total_invocations
+        )?;
+        writeln!(
+            f,
+            "buffers: storage 2 × {} B, staging {} B, points {} B, total {} B",
+            self.storage_buffer_size,
+            self.staging_buffer_size,
+            self.points_buffer_size,
+            self.memory_usage
+        )?;
+        if !self.outside_seeds.is_empty() {
+            writeln!(f, "seeds outside the domain: {:?}", self.outside_seeds)?;
+        }
+        write!(
+            f,
+            "limits: max storage binding {} B, max buffer {} B, max workgroups per dimension {}, max invocations per workgroup {}",
+            self.limits.max_storage_buffer_binding_size,
+            self.limits.max_buffer_size,
+            self.limits.max_compute_workgroups_per_dimension,
+            self.limits.max_compute_invocations_per_workgroup
+        )
     }
+}
 
-    // Flatten normal_points
-    let normal_points: Vec<u32> = normal_points
-        .iter()
-        .flat_map(|(x, y)| vec![*x, *y])
-        .collect();
+pub async fn run(points: &[(f64, f64)], config: (f64, f64)) -> Result<Vec<u32>, MesherError> {
+    run_with_stats(points, config, &MesherConfig::default())
+        .await
+        .map(|(labels, _)| labels)
+}
 
-    context.queue.write_buffer(
-        &context.normal_points,
-        0,
-        bytemuck::cast_slice(&normal_points),
-    );
+/// Same as [`run`] with the recovery policy and memory budget of `options`, also returning the
+/// dispatch statistics of the run.
+pub async fn run_with_stats(
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    options: &MesherConfig,
+) -> Result<(Vec<u32>, DispatchStats), MesherError> {
+    run_seeds(points, &[], config, options).await
+}
 
-    let mut k = (RESO / 2).max(1) as u32;
+/// Power diagram: the third component of each seed is its weight, in squared domain units, and
+/// texels go to the seed minimizing `|p - seed|² - weight`. Equal weights give the Voronoi
+/// diagram of [`run_with_stats`].
+///
+/// A seed with a much lower weight than its neighbors can have an empty cell, or a cell not
+/// containing the seed, which the jump flooding from the seed's own texel doesn't always reach.
+pub async fn run_weighted(
+    points: &[(f64, f64, f64)],
+    config: (f64, f64),
+    options: &MesherConfig,
+) -> Result<(Vec<u32>, DispatchStats), MesherError> {
+    let (seeds, weights): (Vec<(f64, f64)>, Vec<f64>) =
+        points.iter().map(|&(x, y, w)| ((x, y), w)).unzip();
+    run_seeds(&seeds, &weights, config, options).await
+}
 
-    log::info!("Starting JFA iterations...");
+// `weights` is either empty, for a plain Voronoi diagram, or holds one weight per seed
+async fn run_seeds(
+    points: &[(f64, f64)],
+    weights: &[f64],
+    config: (f64, f64),
+    options: &MesherConfig,
+) -> Result<(Vec<u32>, DispatchStats), MesherError> {
+    check_budget(points, config, options)?;
 
-    jfa_step(&context, &mut local_buffer, 1).await;
+    match run_once(points, weights, config, options).await {
+        Err(err @ MesherError::DeviceLost { .. })
+            if options.recovery == RecoveryPolicy::RetryOnce =>
+        {
+            log::warn!("{err}, recreating the context and retrying");
+            run_once(points, weights, config, options).await
+        }
+        result => result,
+    }
+}
+
+/// Same as [`run_with_stats`] on a device owned by the caller, e.g. an application already
+/// rendering with wgpu, so no second device is created.
+///
+/// The device's lost and uncaptured-error callbacks are left to the caller, and the recovery
+/// policy is ignored since the device can't be recreated here.
+pub async fn run_on_device(
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    options: &MesherConfig,
+) -> Result<(Vec<u32>, DispatchStats), MesherError> {
+    check_budget(points, config, options)?;
+    check_features(&device)?;
+
+    let dimensions = options.grid_dimensions(config);
+    let mut context = WgpuContext::from_device(device, queue, dimensions, points_size(points));
+    context.prepare(options)?;
+    run_with_context(&context, points, &[], config, options).await
+}
+
+async fn run_once(
+    points: &[(f64, f64)],
+    weights: &[f64],
+    config: (f64, f64),
+    options: &MesherConfig,
+) -> Result<(Vec<u32>, DispatchStats), MesherError> {
+    let dimensions = options.grid_dimensions(config);
+    let mut context = WgpuContext::new(dimensions, points_size(points), &options.adapter).await?;
+    context.prepare(options)?;
+    run_with_context(&context, points, weights, config, options).await
+}
+
+/// GPU memory a run over `point_count` seeds on a grid of `dimensions` texels allocates, in
+/// bytes.
+pub fn memory_required(point_count: usize, dimensions: (usize, usize)) -> u64 {
+    // Two storage grids and a staging grid, seed coordinates and the parameters of each pass
+    (3 * grid_size(dimensions) + point_count * POINT_SIZE + max_passes(dimensions) * PARAMS_STRIDE)
+        as u64
+}
+
+// Rejects devices provided by the caller without the features the shaders need
+fn check_features(device: &wgpu::Device) -> Result<(), MesherError> {
+    if device.features().contains(REQUIRED_FEATURES) {
+        Ok(())
+    } else {
+        Err(MesherError::InvalidInput(
+            "the device lacks features the shaders need",
+        ))
+    }
+}
+
+// Rejects what the GPU can't run before anything is allocated
+fn check_budget(
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    options: &MesherConfig,
+) -> Result<(), MesherError> {
+    check_budget_with(points, config, options, |_| 0)
+}
+
+// Same as `check_budget` for a run which also allocates `extra(dimensions)` bytes after the
+// JFA, e.g. for the statistics or the distance field of its labels
+fn check_budget_with(
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    options: &MesherConfig,
+    extra: impl FnOnce((usize, usize)) -> u64,
+) -> Result<(), MesherError> {
+    if options.resolution == 0 {
+        return Err(MesherError::InvalidInput("resolution must be at least 1"));
+    }
+    let dimensions = options.grid_dimensions(config);
+    if dimensions.0 == 0 || dimensions.1 == 0 {
+        return Err(MesherError::InvalidInput(
+            "the grid must have at least one texel along each side",
+        ));
+    }
+    input::validate_seeds(points, config, dimensions, options.out_of_domain)?;
+    if !options.metric.is_positive_definite() {
+        return Err(MesherError::InvalidInput(
+            "the metric must be positive definite",
+        ));
+    }
+    if !options.distance.is_norm() {
+        return Err(MesherError::InvalidInput(
+            "the Minkowski exponent must be finite and at least 1",
+        ));
+    }
+    let mut required = memory_required(points.len(), dimensions);
+    if let Some(factor) = options.coarse_factor {
+        if factor < 2 || factor > dimensions.0.max(dimensions.1) {
+            return Err(MesherError::InvalidInput(
+                "the coarse factor must be between 2 and the grid's longer side",
+            ));
+        }
+        required += memory_required(points.len(), coarse_dimensions(dimensions, factor));
+    }
+    if options.storage == GridStorage::Texture {
+        required += (2 * dimensions.0 * dimensions.1 * texture::TEXEL_SIZE) as u64;
+    }
+    required += extra(dimensions);
+    check_required(required, options)
+}
+
+// Rejects allocating `required` bytes in all over the memory budget of `options`
+fn check_required(required: u64, options: &MesherConfig) -> Result<(), MesherError> {
+    match options.memory_budget {
+        Some(budget) if required > budget => Err(MesherError::OverBudget { required, budget }),
+        _ => Ok(()),
+    }
+}
+
+/// Step sizes of the passes on a grid whose longer side is `reso`: a 1 step first, for more precision (1+JFA),
+/// then halving steps from `reso / 2` down to 1.
+pub(crate) fn jfa_steps(reso: usize) -> Vec<u32> {
+    jfa_schedule(reso, JfaVariant::OnePlusJfa, false)
+}
+
+/// Step sizes of the passes of `variant` on a grid whose longer side is `reso`, followed by the
+/// step 1 passes of the correction when `correction` is set.
+fn jfa_schedule(reso: usize, variant: JfaVariant, correction: bool) -> Vec<u32> {
+    let mut halving = Vec::new();
+    let mut k = (reso / 2).max(1) as u32;
     while k >= 1 {
-        jfa_step(&context, &mut local_buffer, k).await;
+        halving.push(k);
         k /= 2;
     }
 
+    let mut steps = match variant {
+        JfaVariant::Jfa => halving,
+        JfaVariant::OnePlusJfa => [vec![1], halving].concat(),
+        JfaVariant::JfaPlusOne => [halving, vec![1]].concat(),
+        JfaVariant::JfaSquared => halving.repeat(2),
+    };
+    if correction {
+        steps.extend([1; CORRECTION_PASSES]);
+    }
+    steps
+}
+
+// Step sizes of the passes of a run with `options` on a grid of `dimensions`
+fn run_steps(options: &MesherConfig, (width, height): (usize, usize)) -> Vec<u32> {
+    jfa_schedule(
+        width.max(height),
+        options.jfa_variant(),
+        options.accuracy.corrects(),
+    )
+}
+
+// Step sizes of the full resolution passes after a run on a grid `factor` times coarser,
+// halving from `factor` down to 1 and ending with another step 1 pass, then the correction
+fn refine_steps(factor: usize, correction: bool) -> Vec<u32> {
+    let mut steps = Vec::new();
+    let mut k = factor as u32;
+    while k >= 1 {
+        steps.push(k);
+        k /= 2;
+    }
+    steps.push(1);
+    if correction {
+        steps.extend([1; CORRECTION_PASSES]);
+    }
+    steps
+}
+
+fn coarse_dimensions((width, height): (usize, usize), factor: usize) -> (usize, usize) {
+    (width.div_ceil(factor), height.div_ceil(factor))
+}
+
+// Passes of the longest schedule, which the parameters buffer is sized for
+fn max_passes((width, height): (usize, usize)) -> usize {
+    jfa_schedule(width.max(height), JfaVariant::JfaSquared, true).len()
+}
+
+fn grid_size((width, height): (usize, usize)) -> usize {
+    width * height * std::mem::size_of::<u32>()
+}
+
+fn points_size(points: &[(f64, f64)]) -> usize {
+    points.len() * POINT_SIZE
+}
+
+async fn run_with_context(
+    context: &WgpuContext,
+    points: &[(f64, f64)],
+    weights: &[f64],
+    config: (f64, f64),
+    options: &MesherConfig,
+) -> Result<(Vec<u32>, DispatchStats), MesherError> {
+    let dump = options.debug_dump.as_ref();
+    let passes = dispatch_jfa(context, points, weights, config, options, dump).await?;
+    let mut local_buffer = vec![0; context.width * context.height];
+    read_back(context, &mut local_buffer, passes).await?;
+
     log::info!("done!");
 
-    local_buffer
+    let outside_seeds = input::seeds_outside(points, config);
+    if !outside_seeds.is_empty() {
+        log::warn!(
+            "{} seeds outside the domain, {:?}",
+            outside_seeds.len(),
+            options.out_of_domain
+        );
+    }
+    let stats = context.dispatch_stats(passes, options, outside_seeds);
+    log::info!("Dispatch statistics:\n{stats}");
+
+    Ok((local_buffer, stats))
+}
+
+// Uploads the seeds and submits all the passes, returning their count. The labels are left in
+// `storage_buffers[passes % 2]`
+async fn dispatch_jfa(
+    context: &WgpuContext,
+    points: &[(f64, f64)],
+    weights: &[f64],
+    config: (f64, f64),
+    options: &MesherConfig,
+    dump: Option<&DebugDump>,
+) -> Result<u32, MesherError> {
+    let (width, height) = (context.width, context.height);
+    let seeds = init_normal_points(points, config, (width, height));
+    let positions = seed_positions(points, config, (width, height));
+    let kept = input::kept_seeds(points, config, options.out_of_domain);
+    if let Some(dump) = dump {
+        dump.seeds(&seeds);
+    }
+    // Weights in squared texels
+    let weight_scale = (width * height) as f64 / (config.0 * config.1);
+    let weights: Vec<f64> = (0..seeds.len())
+        .map(|i| weights.get(i).copied().unwrap_or(0.0) * weight_scale)
+        .collect();
+
+    let Some((factor, coarse)) = &context.coarse else {
+        let steps = run_steps(options, (width, height));
+        let mut grid = vec![0; width * height];
+        mark_seeds(&mut grid, &seeds, &kept, width);
+        let normal_points = flatten_seeds(&positions, &weights, 1.0);
+        let counted = (0, steps.len());
+        return dispatch_passes(
+            context,
+            &normal_points,
+            grid,
+            &steps,
+            options,
+            dump,
+            counted,
+        )
+        .await;
+    };
+
+    // Coarse labels, upsampled, are all but right away from the cells' boundaries
+    let coarse_steps = run_steps(options, (coarse.width, coarse.height));
+    let steps = refine_steps(*factor, options.accuracy.corrects());
+    let total = coarse_steps.len() + steps.len();
+    let coarse_seeds: Vec<(u32, u32)> = seeds
+        .iter()
+        .map(|&(x, y)| (x / *factor as u32, y / *factor as u32))
+        .collect();
+    let mut coarse_grid = vec![0; coarse.width * coarse.height];
+    mark_seeds(&mut coarse_grid, &coarse_seeds, &kept, coarse.width);
+    let normal_points = flatten_seeds(&positions, &weights, *factor as f64);
+    let coarse_passes = dispatch_passes(
+        coarse,
+        &normal_points,
+        coarse_grid.clone(),
+        &coarse_steps,
+        options,
+        None,
+        (0, total),
+    )
+    .await?;
+    read_back(coarse, &mut coarse_grid, coarse_passes).await?;
+
+    let mut grid: Vec<u32> = (0..width * height)
+        .map(|i| coarse_grid[(i % width) / factor + (i / width) / factor * coarse.width])
+        .collect();
+    mark_seeds(&mut grid, &seeds, &kept, width);
+    let normal_points = flatten_seeds(&positions, &weights, 1.0);
+    let counted = (coarse_steps.len(), total);
+    dispatch_passes(
+        context,
+        &normal_points,
+        grid,
+        &steps,
+        options,
+        dump,
+        counted,
+    )
+    .await
+}
+
+// Labels each kept seed `i` with `i + 1` on its own texel, 0 meaning unlabeled
+fn mark_seeds(grid: &mut [u32], seeds: &[(u32, u32)], kept: &[bool], width: usize) {
+    for (i, &(x, y)) in seeds.iter().enumerate().filter(|&(i, _)| kept[i]) {
+        grid[x as usize + y as usize * width] = i as u32 + 1;
+    }
 }
 
-async fn jfa_step(context: &WgpuContext, local_buffer: &mut [u32], k: u32) {
-    //log::info!("Dispatching JFA step with k = {}", k);
+// Seeds as (x, y, weight) triplets, on a grid `factor` times coarser than the one of
+// `positions` and `weights`
+fn flatten_seeds(positions: &[(f64, f64)], weights: &[f64], factor: f64) -> Vec<Real> {
+    positions
+        .iter()
+        .zip(weights)
+        .flat_map(|(&(x, y), weight)| {
+            [x / factor, y / factor, weight / (factor * factor)].map(|v| v as Real)
+        })
+        .collect()
+}
 
+// Uploads the seeds and the initial grid, and submits the passes of `steps`. `counted` holds
+// how many passes of the whole run came before and how many there are in all, for the progress
+// and cancellation reports
+async fn dispatch_passes(
+    context: &WgpuContext,
+    normal_points: &[Real],
+    mut local_buffer: Vec<u32>,
+    steps: &[u32],
+    options: &MesherConfig,
+    dump: Option<&DebugDump>,
+    (before, total): (usize, usize),
+) -> Result<u32, MesherError> {
+    let width = context.width;
+    #[cfg(feature = "profiling")]
+    let upload_start = std::time::Instant::now();
+    context.write_params(options, steps);
     context.queue.write_buffer(
-        &context.storage_buffer,
+        &context.normal_points,
         0,
-        bytemuck::cast_slice(local_buffer),
+        bytemuck::cast_slice(normal_points),
     );
 
-    context
-        .queue
-        .write_buffer(&context.step_buffer, 0, bytemuck::cast_slice(&[k]));
+    match &context.textures {
+        Some(textures) => textures.upload(context, &local_buffer),
+        None => context.queue.write_buffer(
+            &context.storage_buffers[0],
+            0,
+            bytemuck::cast_slice(&local_buffer),
+        ),
+    }
+
+    #[cfg(feature = "profiling")]
+    if let Some(profiler) = &context.profiler {
+        profiler.finish_upload(context, upload_start).await?;
+    }
 
+    // Reading every pass back is only needed to dump it
+    let dump_passes = dump.filter(|dump| dump.enabled(Stage::Passes));
+
+    log::info!("Starting JFA iterations...");
+
+    // All the passes go in one submission, unless each of them is read back, reported or can be
+    // cancelled
+    let stepwise = options.progress.is_some() || options.cancellation.is_some();
     let mut command_encoder = context
         .device
         .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-    {
-        let mut compute_pass = command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
-            label: None,
-            timestamp_writes: None,
-        });
-        compute_pass.set_pipeline(&context.pipeline);
-        compute_pass.set_bind_group(0, &context.bind_group, &[]);
-        compute_pass.dispatch_workgroups((RESO / 16) as u32, (RESO / 16) as u32, 1);
+    let mut passes = 0;
+    for &step in steps {
+        if let Some(token) = &options.cancellation {
+            if token.is_cancelled() {
+                return Err(MesherError::Cancelled {
+                    completed: before + passes as usize,
+                    total,
+                });
+            }
+        }
+        match (&context.textures, &context.active) {
+            (Some(textures), _) => textures.encode_step(context, &mut command_encoder, passes),
+            (None, Some(active)) => active.encode_step(context, &mut command_encoder, passes),
+            (None, None) => encode_step(context, &mut command_encoder, passes),
+        }
+        passes += 1;
+        if let Some(dump) = dump_passes {
+            if let Some(textures) = &context.textures {
+                textures.encode_unpack(context, &mut command_encoder, passes);
+            }
+            context.queue.submit(Some(command_encoder.finish()));
+            read_back(context, &mut local_buffer, passes).await?;
+            dump.labels(passes as usize - 1, step, width, &local_buffer);
+        } else if stepwise {
+            context.queue.submit(Some(command_encoder.finish()));
+            work_done(context).await?;
+        } else {
+            continue;
+        }
+        command_encoder = context
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        if let Some(progress) = &options.progress {
+            progress.report(Progress::JfaPass {
+                pass: before + passes as usize,
+                passes: total,
+            });
+        }
     }
+    // Dumped passes are already unpacked
+    if let (Some(textures), None) = (&context.textures, dump_passes) {
+        textures.encode_unpack(context, &mut command_encoder, passes);
+    }
+    context.queue.submit(Some(command_encoder.finish()));
 
-    command_encoder.copy_buffer_to_buffer(
-        &context.storage_buffer,
-        0,
-        &context.output_staging_buffer,
+    Ok(passes)
+}
+
+// Pass `pass` reads the grid from one storage buffer and writes it to the other, with its step
+// at offset `pass * PARAMS_STRIDE` of the parameters buffer
+fn encode_step(context: &WgpuContext, command_encoder: &mut wgpu::CommandEncoder, pass: u32) {
+    let mut compute_pass = command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+        label: None,
+        timestamp_writes: context.timestamp_writes(pass),
+    });
+    compute_pass.set_pipeline(&context.pipeline);
+    compute_pass.set_bind_group(
         0,
-        context.storage_buffer.size(),
+        &context.bind_groups[pass as usize % 2],
+        &[pass * PARAMS_STRIDE as u32],
     );
+    let (x, y) = workgroup_count(context.width, context.height);
+    compute_pass.dispatch_workgroups(x, y, 1);
+}
 
-    context.queue.submit(Some(command_encoder.finish()));
-
-    //TODO: don't get data until the end https://github.com/gfx-rs/wgpu/wiki/Do's-and-Dont's
-    get_data(
+// Copies the grid written by the last of `passes` passes into `local_buffer`
+async fn read_back(
+    context: &WgpuContext,
+    local_buffer: &mut [u32],
+    passes: u32,
+) -> Result<(), MesherError> {
+    // Only the copy and the mapping are timed, not the passes still running
+    #[cfg(feature = "profiling")]
+    let download_start = match &context.profiler {
+        Some(_) => {
+            work_done(context).await?;
+            Some(std::time::Instant::now())
+        }
+        None => None,
+    };
+    let mapped = get_data(
         local_buffer,
-        &context.storage_buffer,
+        &context.storage_buffers[passes as usize % 2],
         &context.output_staging_buffer,
         &context.device,
         &context.queue,
     )
     .await;
+    #[cfg(feature = "profiling")]
+    if let (Some(profiler), Some(start)) = (&context.profiler, download_start) {
+        profiler.record_download(start.elapsed());
+    }
+
+    // An error reported by the device takes precedence over the mapping failure it caused
+    if let Some(err) = context.take_error() {
+        return Err(err);
+    }
+    mapped.map_err(|err| MesherError::BufferMapFailed(err.to_string()))
 }
 
-async fn get_data<T: bytemuck::Pod>(
+pub(crate) async fn get_data<T: bytemuck::Pod>(
     output: &mut [T],
     storage_buffer: &wgpu::Buffer,
     staging_buffer: &wgpu::Buffer,
-    device: &wgpu::Device,
+    device: &Arc<wgpu::Device>,
     queue: &wgpu::Queue,
-) {
+) -> Result<(), wgpu::BufferAsyncError> {
     let mut command_encoder =
         device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
     command_encoder.copy_buffer_to_buffer(
@@ -110,89 +689,266 @@ async fn get_data<T: bytemuck::Pod>(
     queue.submit(Some(command_encoder.finish()));
     let buffer_slice = staging_buffer.slice(..);
     let (sender, receiver) = flume::bounded(1);
-    buffer_slice.map_async(wgpu::MapMode::Read, move |r| sender.send(r).unwrap());
-    device.poll(wgpu::Maintain::wait()).panic_on_timeout();
-    receiver.recv_async().await.unwrap().unwrap();
+    buffer_slice.map_async(wgpu::MapMode::Read, move |r| {
+        let _ = sender.send(r);
+    });
+    // A callback dropped without being called went away with the device
+    poll_until(device, &receiver)
+        .await
+        .unwrap_or(Err(wgpu::BufferAsyncError))?;
     output.copy_from_slice(bytemuck::cast_slice(&buffer_slice.get_mapped_range()[..]));
     staging_buffer.unmap();
+    Ok(())
+}
+
+// Waits for the work submitted so far, reporting the device's error if it was lost meanwhile
+async fn work_done(context: &WgpuContext) -> Result<(), MesherError> {
+    let (sender, receiver) = flume::bounded(1);
+    context.queue.on_submitted_work_done(move || {
+        let _ = sender.send(());
+    });
+    let done = poll_until(&context.device, &receiver).await;
+    if let Some(err) = context.take_error() {
+        return Err(err);
+    }
+    done.ok_or_else(|| MesherError::Uncaptured("submitted work never completed".to_string()))
 }
 
-fn init_normal_points(points: &[(f64, f64)], config: (f64, f64)) -> Vec<(u32, u32)> {
+// Parameters of each pass of `steps` on a grid of `width` by `height` texels, `PARAMS_STRIDE`
+// bytes apart
+fn pass_params(options: &MesherConfig, steps: &[u32], (width, height): (usize, usize)) -> Vec<u32> {
+    let (wrap_x, wrap_y) = options.boundary.wraps();
+    let metric = options.metric;
+    let (norm, exponent) = match options.distance {
+        DistanceMetric::Euclidean => (0, 2.0),
+        DistanceMetric::Manhattan => (1, 1.0),
+        DistanceMetric::Chebyshev => (2, f32::INFINITY),
+        DistanceMetric::Minkowski(p) => (3, p as f32),
+    };
+    let wrap = u32::from(wrap_x) | (u32::from(wrap_y) << 1);
+    let precision = match options.distance_precision {
+        DistancePrecision::F32 => 0,
+        DistancePrecision::F16 => 1,
+        DistancePrecision::Fixed16 => 2,
+    };
+    let words = PARAMS_STRIDE / std::mem::size_of::<u32>();
+    let mut params = vec![0u32; steps.len() * words];
+    for (pass, &step) in steps.iter().enumerate() {
+        let previous_step = pass.checked_sub(1).map_or(0, |previous| steps[previous]);
+        params[pass * words..pass * words + 12].copy_from_slice(&[
+            step,
+            width as u32,
+            height as u32,
+            wrap,
+            (metric.xx as f32).to_bits(),
+            (metric.xy as f32).to_bits(),
+            (metric.yy as f32).to_bits(),
+            norm,
+            exponent.to_bits(),
+            u32::from(options.deterministic),
+            precision,
+            previous_step,
+        ]);
+    }
+    params
+}
+
+fn workgroup_count(width: usize, height: usize) -> (u32, u32) {
+    (
+        (width as u32).div_ceil(WORKGROUP_SIZE),
+        (height as u32).div_ceil(WORKGROUP_SIZE),
+    )
+}
+
+fn init_normal_points(
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    (width, height): (usize, usize),
+) -> Vec<(u32, u32)> {
     points
         .iter()
         .map(|(a, b)| {
-            let x = ((a * RESO as f64 / config.0).min(RESO as f64 - 1.0)) as u32;
-            let y = ((b * RESO as f64 / config.1).min(RESO as f64 - 1.0)) as u32;
+            let x = ((a * width as f64 / config.0).min(width as f64 - 1.0)) as u32;
+            let y = ((b * height as f64 / config.1).min(height as f64 - 1.0)) as u32;
             (x, y)
         })
         .collect()
 }
 
-pub fn main(points: &[(f64, f64)], config: (f64, f64)) -> Result<Vec<usize>, &'static str> {
+// Exact positions of the seeds in texels, clamped to the grid like their texels
+fn seed_positions(
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    (width, height): (usize, usize),
+) -> Vec<(f64, f64)> {
+    points
+        .iter()
+        .map(|(a, b)| {
+            (
+                (a * width as f64 / config.0).clamp(0.0, width as f64),
+                (b * height as f64 / config.1).clamp(0.0, height as f64),
+            )
+        })
+        .collect()
+}
+
+/// Label of every texel, as [`run_with_stats`] computes them, without blocking the calling
+/// thread: results are read back by polling the device and yielding in between, so this runs
+/// in any async runtime or inside a game loop.
+///
+/// Grids too large for a storage buffer, or any grid when [`MesherConfig::tile_size`] is set,
+/// are meshed tile by tile with [`run_tiled`].
+pub async fn mesh(
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    options: &MesherConfig,
+) -> Result<Vec<usize>, MesherError> {
+    mesh_weighted(points, &[], config, options).await
+}
+
+/// [`mesh`] of the power diagram of `weights`, one per seed as in [`run_weighted`], or of the
+/// Voronoi diagram when empty. Tiles are only settled for plain distances, so weighted grids
+/// must fit in a storage buffer.
+pub async fn mesh_weighted(
+    points: &[(f64, f64)],
+    weights: &[f64],
+    config: (f64, f64),
+    options: &MesherConfig,
+) -> Result<Vec<usize>, MesherError> {
+    if !weights.is_empty() && weights.len() != points.len() {
+        return Err(MesherError::InvalidInput(
+            "weights must be empty or one per seed",
+        ));
+    }
+    let labels = if tiling::needs_tiling(options, options.grid_dimensions(config)) {
+        if !weights.is_empty() {
+            return Err(MesherError::InvalidInput(
+                "weighted grids can't be meshed tile by tile",
+            ));
+        }
+        run_tiled(points, config, options).await?
+    } else {
+        run_seeds(points, weights, config, options).await?.0
+    };
+
+    Ok(labels.into_iter().map(|x| x as usize).collect())
+}
+
+/// Blocking [`mesh`], for callers outside of any async runtime. Browsers can't block on the GPU,
+/// so it doesn't exist on wasm, where [`mesh`] is awaited instead.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn main(
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    options: &MesherConfig,
+) -> Result<Vec<usize>, MesherError> {
     /*     env_logger::builder()
     .filter_level(log::LevelFilter::Info)
     .format_timestamp_nanos()
     .init(); */
-    let a = pollster::block_on(run(points, config));
-
-    Ok(a.into_iter().map(|x| x as usize).collect())
+    block_on(mesh(points, config, options))
 }
 
-struct WgpuContext {
-    device: wgpu::Device,
-    queue: wgpu::Queue,
+pub(crate) struct WgpuContext {
+    width: usize,
+    height: usize,
+    adapter_info: Option<wgpu::AdapterInfo>,
+    pub(crate) device: Arc<wgpu::Device>,
+    pub(crate) queue: Arc<wgpu::Queue>,
     pipeline: wgpu::ComputePipeline,
-    bind_group: wgpu::BindGroup,
-    storage_buffer: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    /// Bind group `i` reads `storage_buffers[i]` and writes the other one
+    bind_groups: [wgpu::BindGroup; 2],
+    storage_buffers: [wgpu::Buffer; 2],
     output_staging_buffer: wgpu::Buffer,
-    step_buffer: wgpu::Buffer,
+    params_buffer: wgpu::Buffer,
     normal_points: wgpu::Buffer,
+    /// Grid the passes run on instead of `storage_buffers`, with `GridStorage::Texture`
+    textures: Option<TextureGrid>,
+    /// Passes over the active tiles of `storage_buffers`, with `MesherConfig::active_tiles`
+    active: Option<ActiveTiles>,
+    /// Coarse factor and grid of `MesherConfig::coarse_factor`, on the same device
+    coarse: Option<(usize, Box<WgpuContext>)>,
+    error: Arc<Mutex<Option<MesherError>>>,
+    /// Timestamps and transfer times of [`run_profiled`]
+    #[cfg(feature = "profiling")]
+    profiler: Option<profile::Profiler>,
 }
 
 impl WgpuContext {
-    async fn new(buffer_size: usize, points_size: usize) -> WgpuContext {
-        let instance = wgpu::Instance::default();
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions::default())
-            .await
-            .unwrap();
+    pub(crate) async fn new(
+        dimensions: (usize, usize),
+        points_size: usize,
+        selection: &AdapterSelection,
+    ) -> Result<WgpuContext, MesherError> {
+        let adapter = adapter::select_adapter(selection).await?;
+        WgpuContext::on_adapter(&adapter, dimensions, points_size).await
+    }
+
+    async fn on_adapter(
+        adapter: &wgpu::Adapter,
+        dimensions: (usize, usize),
+        points_size: usize,
+    ) -> Result<WgpuContext, MesherError> {
+        if !adapter.features().contains(REQUIRED_FEATURES) {
+            return Err(MesherError::DeviceRequestFailed(format!(
+                "the adapter lacks {REQUIRED_FEATURES:?}"
+            )));
+        }
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: None,
-                    required_features: wgpu::Features::empty(),
-                    required_limits: wgpu::Limits::downlevel_defaults(),
+                    required_features: REQUIRED_FEATURES | (adapter.features() & OPTIONAL_FEATURES),
+                    required_limits: wgpu::Limits::downlevel_defaults()
+                        .using_resolution(adapter.limits()),
                     memory_hints: wgpu::MemoryHints::Performance,
                 },
                 None,
             )
             .await
-            .unwrap();
-
-        let shader = device.create_shader_module(wgpu::include_wgsl!("shader.wgsl"));
+            .map_err(|err| MesherError::DeviceRequestFailed(err.to_string()))?;
 
-        let storage_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: None,
-            size: buffer_size as wgpu::BufferAddress,
-            usage: wgpu::BufferUsages::STORAGE
-                | wgpu::BufferUsages::COPY_DST
-                | wgpu::BufferUsages::COPY_SRC,
-            mapped_at_creation: false,
+        // Keep the first error the device reports, it is checked after each step
+        let error = Arc::new(Mutex::new(None));
+        let lost_error = error.clone();
+        device.set_device_lost_callback(move |reason, message| {
+            lost_error
+                .lock()
+                .unwrap()
+                .get_or_insert(MesherError::DeviceLost {
+                    reason: format!("{reason:?}"),
+                    message,
+                });
         });
+        let uncaptured_error = error.clone();
+        device.on_uncaptured_error(Box::new(move |err| {
+            uncaptured_error
+                .lock()
+                .unwrap()
+                .get_or_insert(MesherError::Uncaptured(err.to_string()));
+        }));
 
-        let output_staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: None,
-            size: buffer_size as wgpu::BufferAddress,
-            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
-            mapped_at_creation: false,
-        });
+        let mut context =
+            WgpuContext::from_device(Arc::new(device), Arc::new(queue), dimensions, points_size);
+        context.adapter_info = Some(adapter.get_info());
+        context.error = error;
+        Ok(context)
+    }
 
-        let step_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+    fn from_device(
+        device: Arc<wgpu::Device>,
+        queue: Arc<wgpu::Queue>,
+        (width, height): (usize, usize),
+        points_size: usize,
+    ) -> WgpuContext {
+        let source = [DISTANCE_SHADER, include_str!("shader.wgsl")].join("\n");
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: None,
-            size: std::mem::size_of::<u32>() as wgpu::BufferAddress,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false, //TODO: usage ?
+            source: wgpu::ShaderSource::Wgsl(source.into()),
         });
+        let (storage_buffers, output_staging_buffer, params_buffer) =
+            WgpuContext::create_grid_buffers(&device, (width, height));
 
         let normal_points = device.create_buffer(&wgpu::BufferDescriptor {
             label: None,
@@ -208,7 +964,7 @@ impl WgpuContext {
                     binding: 0,
                     visibility: wgpu::ShaderStages::COMPUTE,
                     ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
                         has_dynamic_offset: false,
                         min_binding_size: None,
                     },
@@ -219,8 +975,8 @@ impl WgpuContext {
                     visibility: wgpu::ShaderStages::COMPUTE,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(PARAMS_SIZE as u64),
                     },
                     count: None,
                 },
@@ -234,28 +990,27 @@ impl WgpuContext {
                     },
                     count: None,
                 },
-            ],
-        });
-
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: None,
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: storage_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: step_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: normal_points.as_entire_binding(),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
                 },
             ],
         });
 
+        let bind_groups = WgpuContext::create_bind_groups(
+            &device,
+            &bind_group_layout,
+            &storage_buffers,
+            &params_buffer,
+            &normal_points,
+        );
+
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: None,
             bind_group_layouts: &[&bind_group_layout],
@@ -271,18 +1026,268 @@ impl WgpuContext {
         });
 
         WgpuContext {
+            width,
+            height,
+            adapter_info: None,
             device,
             queue,
             pipeline,
-            bind_group,
-            storage_buffer,
+            bind_group_layout,
+            bind_groups,
+            storage_buffers,
             output_staging_buffer,
-            step_buffer,
+            params_buffer,
             normal_points,
+            textures: None,
+            active: None,
+            coarse: None,
+            error: Arc::new(Mutex::new(None)),
+            #[cfg(feature = "profiling")]
+            profiler: None,
+        }
+    }
+
+    fn create_bind_groups(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        storage_buffers: &[wgpu::Buffer; 2],
+        params_buffer: &wgpu::Buffer,
+        normal_points: &wgpu::Buffer,
+    ) -> [wgpu::BindGroup; 2] {
+        [0, 1].map(|input| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: storage_buffers[input].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                            buffer: params_buffer,
+                            offset: 0,
+                            size: wgpu::BufferSize::new(PARAMS_SIZE as u64),
+                        }),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: normal_points.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: storage_buffers[1 - input].as_entire_binding(),
+                    },
+                ],
+            })
+        })
+    }
+
+    // Ping-pong grids, staging grid and per-pass parameters, which depend on the grid dimensions
+    fn create_grid_buffers(
+        device: &wgpu::Device,
+        (width, height): (usize, usize),
+    ) -> ([wgpu::Buffer; 2], wgpu::Buffer, wgpu::Buffer) {
+        let buffer_size = grid_size((width, height));
+
+        let storage_buffers = [0, 1].map(|_| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: buffer_size as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_DST
+                    | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            })
+        });
+
+        let output_staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: buffer_size as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (max_passes((width, height)) * PARAMS_STRIDE) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        (storage_buffers, output_staging_buffer, params_buffer)
+    }
+
+    // Reallocates the grid buffers for other grid dimensions, and grows the seed buffer to hold
+    // at least `points_size` bytes, doubling it to keep reallocations rare when the seed count
+    // keeps increasing
+    fn reserve(&mut self, dimensions: (usize, usize), points_size: usize) {
+        let resize_grid = dimensions != (self.width, self.height);
+        let size = points_size as wgpu::BufferAddress;
+        let grow_points = size > self.normal_points.size();
+        if !resize_grid && !grow_points {
+            return;
+        }
+
+        // The textures' and the active tiles' bind groups hold the buffers being replaced
+        self.textures = None;
+        self.active = None;
+        if resize_grid {
+            (
+                self.storage_buffers,
+                self.output_staging_buffer,
+                self.params_buffer,
+            ) = WgpuContext::create_grid_buffers(&self.device, dimensions);
+            (self.width, self.height) = dimensions;
+        }
+        if grow_points {
+            self.normal_points = self.device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: size.max(2 * self.normal_points.size()),
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+        }
+        self.bind_groups = WgpuContext::create_bind_groups(
+            &self.device,
+            &self.bind_group_layout,
+            &self.storage_buffers,
+            &self.params_buffer,
+            &self.normal_points,
+        );
+    }
+
+    // Creates what the grid storage and coarse factor of `options` need unless it already
+    // exists, and drops what they don't
+    fn prepare(&mut self, options: &MesherConfig) -> Result<(), MesherError> {
+        self.coarse = match (options.coarse_factor, self.coarse.take()) {
+            (None, _) => None,
+            (Some(factor), Some((_, mut coarse))) => {
+                let dimensions = coarse_dimensions((self.width, self.height), factor);
+                coarse.reserve(dimensions, self.normal_points.size() as usize);
+                Some((factor, coarse))
+            }
+            (Some(factor), None) => {
+                let dimensions = coarse_dimensions((self.width, self.height), factor);
+                let mut coarse = WgpuContext::from_device(
+                    self.device.clone(),
+                    self.queue.clone(),
+                    dimensions,
+                    self.normal_points.size() as usize,
+                );
+                coarse.error = self.error.clone();
+                Some((factor, Box::new(coarse)))
+            }
+        };
+
+        match options.storage {
+            GridStorage::Buffer => self.textures = None,
+            GridStorage::Texture if self.textures.is_some() => {}
+            GridStorage::Texture => {
+                let max = self.device.limits().max_texture_dimension_2d as usize;
+                if self.width > max || self.height > max {
+                    return Err(MesherError::InvalidInput(
+                        "the grid is larger than the device's textures",
+                    ));
+                }
+                self.textures = Some(TextureGrid::new(self));
+            }
+        }
+
+        if !options.active_tiles {
+            self.active = None;
+        } else if options.storage == GridStorage::Texture {
+            return Err(MesherError::InvalidInput(
+                "active tiles only run on GridStorage::Buffer grids",
+            ));
+        } else if self.active.is_none() {
+            self.active = Some(ActiveTiles::new(self));
+        }
+        Ok(())
+    }
+
+    // Parameters of every pass, written before each run since the options can change between
+    // runs
+    fn write_params(&self, options: &MesherConfig, steps: &[u32]) {
+        let params = pass_params(options, steps, (self.width, self.height));
+        self.queue
+            .write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&params));
+    }
+
+    fn memory_usage(&self) -> u64 {
+        self.storage_buffers
+            .iter()
+            .map(|buffer| buffer.size())
+            .sum::<u64>()
+            + self.output_staging_buffer.size()
+            + self.normal_points.size()
+            + self.params_buffer.size()
+            + self.textures.as_ref().map_or(0, |textures| {
+                textures
+                    .textures
+                    .iter()
+                    .map(|texture| (texture.width() * texture.height()) as u64)
+                    .sum::<u64>()
+                    * texture::TEXEL_SIZE as u64
+            })
+            + self.active.as_ref().map_or(0, ActiveTiles::memory_usage)
+            + self
+                .coarse
+                .as_ref()
+                .map_or(0, |(_, coarse)| coarse.memory_usage())
+    }
+
+    pub(crate) fn take_error(&self) -> Option<MesherError> {
+        self.error.lock().unwrap().take()
+    }
+
+    // Where pass `pass` writes its timestamps, nowhere unless profiled
+    fn timestamp_writes(&self, pass: u32) -> Option<wgpu::ComputePassTimestampWrites<'_>> {
+        #[cfg(feature = "profiling")]
+        if let Some(profiler) = &self.profiler {
+            return profiler.timestamp_writes(pass);
+        }
+        let _ = pass;
+        None
+    }
+
+    fn dispatch_stats(
+        &self,
+        passes: u32,
+        options: &MesherConfig,
+        outside_seeds: Vec<usize>,
+    ) -> DispatchStats {
+        let invocations_per_pass = |(width, height): (usize, usize)| {
+            let workgroups = workgroup_count(width, height);
+            (workgroups.0 * WORKGROUP_SIZE) as u64 * (workgroups.1 * WORKGROUP_SIZE) as u64
+        };
+        let (coarse_passes, coarse_invocations) = match &self.coarse {
+            Some((_, coarse)) => {
+                let dims = (coarse.width, coarse.height);
+                let passes = run_steps(options, dims).len() as u32;
+                (passes, invocations_per_pass(dims) * passes as u64)
+            }
+            None => (0, 0),
+        };
+
+        DispatchStats {
+            passes,
+            coarse_passes,
+            workgroup_size: (WORKGROUP_SIZE, WORKGROUP_SIZE),
+            workgroups: workgroup_count(self.width, self.height),
+            total_invocations: invocations_per_pass((self.width, self.height)) * passes as u64
+                + coarse_invocations,
+            storage_buffer_size: self.storage_buffers[0].size(),
+            staging_buffer_size: self.output_staging_buffer.size(),
+            points_buffer_size: self.normal_points.size(),
+            memory_usage: self.memory_usage(),
+            outside_seeds,
+            adapter: self.adapter_info.clone(),
+            limits: self.device.limits(),
         }
     }
 }
 
-/* #[cfg(test)]
+#[cfg(test)]
 mod tests;
- */
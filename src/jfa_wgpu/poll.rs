@@ -0,0 +1,101 @@
+// Waiting for the callbacks of buffer mappings and submitted work without blocking the thread.
+// Native devices only call them when polled, while browsers call them from their event loop,
+// which polling doesn't drive and which only runs once control goes back to it. Blocking
+// callers have no other task to run meanwhile, so under `block_on` the device is waited for on
+// the calling thread, and otherwise on a helper thread while the executor runs other tasks.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::cell::Cell;
+#[cfg(not(target_arch = "wasm32"))]
+use std::future::Future;
+use std::sync::Arc;
+
+#[cfg(not(target_arch = "wasm32"))]
+thread_local! {
+    // Whether the futures polled on this thread are driven by `block_on`
+    static BLOCKING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs `future`, e.g. [`super::mesh`], to completion on the calling thread, blocking on the
+/// device whenever it waits for the GPU. Browsers can't block, so it doesn't exist on wasm.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    // Restores the flag of an outer `block_on`, even when the future panics
+    struct Reset(bool);
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            BLOCKING.with(|blocking| blocking.set(self.0));
+        }
+    }
+
+    let _reset = Reset(BLOCKING.with(|blocking| blocking.replace(true)));
+    pollster::block_on(future)
+}
+
+// A callback only runs while the device is polled, so when it isn't ready yet a helper thread
+// waits on the device and the callback wakes us through the receiver, which never blocks the
+// thread of an async runtime or a game loop nor spins it. `None` when the callback was dropped
+// without being called
+#[cfg(not(target_arch = "wasm32"))]
+pub(super) async fn poll_until<T>(
+    device: &Arc<wgpu::Device>,
+    receiver: &flume::Receiver<T>,
+) -> Option<T> {
+    wait(device, wgpu::Maintain::Wait, receiver).await
+}
+
+// Same as `poll_until`, waiting for `submission` only, so the work submitted after it keeps the
+// device busy meanwhile
+#[cfg(not(target_arch = "wasm32"))]
+pub(super) async fn poll_submission<T>(
+    device: &Arc<wgpu::Device>,
+    submission: &wgpu::SubmissionIndex,
+    receiver: &flume::Receiver<T>,
+) -> Option<T> {
+    wait(
+        device,
+        wgpu::Maintain::wait_for(submission.clone()),
+        receiver,
+    )
+    .await
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn wait<T>(
+    device: &Arc<wgpu::Device>,
+    maintain: wgpu::Maintain,
+    receiver: &flume::Receiver<T>,
+) -> Option<T> {
+    if BLOCKING.with(Cell::get) {
+        device.poll(maintain);
+        return receiver.recv().ok();
+    }
+    device.poll(wgpu::Maintain::Poll);
+    match receiver.try_recv() {
+        Ok(value) => return Some(value),
+        Err(flume::TryRecvError::Disconnected) => return None,
+        Err(flume::TryRecvError::Empty) => {}
+    }
+    let device = Arc::clone(device);
+    std::thread::spawn(move || device.poll(maintain));
+    receiver.recv_async().await.ok()
+}
+
+// Yielding would only queue us again before the browser's event loop gets to run the callback,
+// so the sender wakes us instead
+#[cfg(target_arch = "wasm32")]
+pub(super) async fn poll_until<T>(
+    _device: &Arc<wgpu::Device>,
+    receiver: &flume::Receiver<T>,
+) -> Option<T> {
+    receiver.recv_async().await.ok()
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(super) async fn poll_submission<T>(
+    _device: &Arc<wgpu::Device>,
+    _submission: &wgpu::SubmissionIndex,
+    receiver: &flume::Receiver<T>,
+) -> Option<T> {
+    receiver.recv_async().await.ok()
+}
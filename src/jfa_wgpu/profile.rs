@@ -0,0 +1,217 @@
+// Where the time of a run goes, for users tuning the resolution against the seed count. Each
+// JFA pass writes GPU timestamps at its beginning and end, while the uploads and the download
+// are timed on the host, waiting for the device before and after them, since queue writes and
+// buffer mappings can't be timestamped. Only built with the `profiling` feature.
+
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::{
+    check_budget, get_data, max_passes, points_size, run_with_context, work_done, DispatchStats,
+    WgpuContext,
+};
+use crate::config::MesherConfig;
+use crate::error::MesherError;
+
+/// Times of a profiled run. Host times use `std::time::Instant`, which browsers don't have.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RunProfile {
+    /// GPU time of each JFA pass on the full resolution grid
+    pub passes: Vec<Duration>,
+    /// From the first upload until the device holds the seeds and the initial grid
+    pub upload: Duration,
+    /// From the end of the last pass until the labels are in host memory
+    pub download: Duration,
+    /// The whole run, the creation of the device excluded
+    pub total: Duration,
+}
+
+impl RunProfile {
+    /// GPU time of all the passes.
+    pub fn pass_time(&self) -> Duration {
+        self.passes.iter().sum()
+    }
+}
+
+impl fmt::Display for RunProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "upload: {:.2?}", self.upload)?;
+        writeln!(
+            f,
+            "passes: {:.2?} over {} passes",
+            self.pass_time(),
+            self.passes.len()
+        )?;
+        for (pass, time) in self.passes.iter().enumerate() {
+            writeln!(f, "  pass {pass}: {time:.2?}")?;
+        }
+        writeln!(f, "download: {:.2?}", self.download)?;
+        write!(f, "total: {:.2?}", self.total)
+    }
+}
+
+/// Same as [`super::run_with_stats`], without tiling or recovery, also returning where the
+/// time went. Fails with [`MesherError::InvalidInput`] on devices without timestamp queries.
+pub async fn run_profiled(
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    options: &MesherConfig,
+) -> Result<(Vec<u32>, DispatchStats, RunProfile), MesherError> {
+    check_budget(points, config, options)?;
+
+    let dimensions = options.grid_dimensions(config);
+    let mut context = WgpuContext::new(dimensions, points_size(points), &options.adapter).await?;
+    let timestamps = wgpu::Features::TIMESTAMP_QUERY;
+    if !context.device.features().contains(timestamps) {
+        return Err(MesherError::InvalidInput(
+            "the device doesn't support timestamp queries",
+        ));
+    }
+    context.prepare(options)?;
+    context.profiler = Some(Profiler::new(
+        &context.device,
+        max_passes(dimensions) as u32,
+    ));
+
+    let start = Instant::now();
+    let (labels, stats) = run_with_context(&context, points, &[], config, options).await?;
+    let total = start.elapsed();
+
+    let profiler = context.profiler.as_ref().unwrap();
+    let profile = RunProfile {
+        passes: profiler.pass_times(&context, stats.passes).await?,
+        upload: *profiler.upload.lock().unwrap(),
+        download: *profiler.download.lock().unwrap(),
+        total,
+    };
+    log::info!("Run profile:\n{profile}");
+    Ok((labels, stats, profile))
+}
+
+// Timestamps of the passes, two per pass, and the host times of the transfers
+pub(super) struct Profiler {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    staging_buffer: wgpu::Buffer,
+    capacity: u32,
+    upload: Mutex<Duration>,
+    download: Mutex<Duration>,
+}
+
+impl Profiler {
+    fn new(device: &wgpu::Device, passes: u32) -> Profiler {
+        let size = 2 * passes as u64 * std::mem::size_of::<u64>() as u64;
+        Profiler {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: None,
+                ty: wgpu::QueryType::Timestamp,
+                count: 2 * passes,
+            }),
+            resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            staging_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+            capacity: passes,
+            upload: Mutex::new(Duration::ZERO),
+            download: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    pub(super) fn timestamp_writes(
+        &self,
+        pass: u32,
+    ) -> Option<wgpu::ComputePassTimestampWrites<'_>> {
+        (pass < self.capacity).then_some(wgpu::ComputePassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(2 * pass),
+            end_of_pass_write_index: Some(2 * pass + 1),
+        })
+    }
+
+    // Waits for the uploads started at `start`, which queue writes leave pending until the next
+    // submission
+    pub(super) async fn finish_upload(
+        &self,
+        context: &WgpuContext,
+        start: Instant,
+    ) -> Result<(), MesherError> {
+        context.queue.submit([]);
+        work_done(context).await?;
+        *self.upload.lock().unwrap() = start.elapsed();
+        Ok(())
+    }
+
+    pub(super) fn record_download(&self, time: Duration) {
+        *self.download.lock().unwrap() = time;
+    }
+
+    async fn pass_times(
+        &self,
+        context: &WgpuContext,
+        passes: u32,
+    ) -> Result<Vec<Duration>, MesherError> {
+        let passes = passes.min(self.capacity);
+        if passes == 0 {
+            return Ok(Vec::new());
+        }
+        let mut command_encoder = context
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        command_encoder.resolve_query_set(&self.query_set, 0..2 * passes, &self.resolve_buffer, 0);
+        context.queue.submit(Some(command_encoder.finish()));
+
+        let mut ticks = vec![0u64; 2 * passes as usize];
+        let mapped = get_data(
+            &mut ticks,
+            &self.resolve_buffer,
+            &self.staging_buffer,
+            &context.device,
+            &context.queue,
+        )
+        .await;
+        if let Some(err) = context.take_error() {
+            return Err(err);
+        }
+        mapped.map_err(|err| MesherError::BufferMapFailed(err.to_string()))?;
+
+        let period = context.queue.get_timestamp_period() as f64;
+        Ok(ticks
+            .chunks(2)
+            .map(|pass| {
+                Duration::from_nanos((pass[1].saturating_sub(pass[0]) as f64 * period) as u64)
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_profile() {
+        let profile = RunProfile {
+            passes: vec![Duration::from_micros(300), Duration::from_micros(200)],
+            upload: Duration::from_millis(2),
+            download: Duration::from_millis(3),
+            total: Duration::from_millis(6),
+        };
+
+        assert_eq!(profile.pass_time(), Duration::from_micros(500));
+        let report = profile.to_string();
+        assert!(
+            report.contains("passes: 500.00µs over 2 passes"),
+            "{report}"
+        );
+        assert!(report.ends_with("total: 6.00ms"), "{report}");
+    }
+}
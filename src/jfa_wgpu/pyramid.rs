@@ -0,0 +1,233 @@
+// Multi-resolution pyramid of a label grid, for level-of-detail renderers and hierarchical
+// solvers: every level halves the one below by majority over 2×2 blocks in a pass on the GPU,
+// all chained in one submission from the labels left by the JFA, then read back together.
+
+use wgpu::util::DeviceExt;
+
+use super::{check_budget_with, dispatch_jfa, get_data, points_size, workgroup_count, WgpuContext};
+use crate::config::MesherConfig;
+use crate::error::MesherError;
+
+/// Label grid of one level of a pyramid, indexed with `x + y * width`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PyramidLevel {
+    pub width: usize,
+    pub height: usize,
+    pub labels: Vec<u32>,
+}
+
+/// Labels of every texel, as [`super::run_with_stats`] computes them, then halved up to
+/// `levels - 1` times: level 0 is the grid itself, and each texel of the next levels takes the
+/// most frequent label of the 2×2 block below it, the first one in scan order on ties. Stops
+/// early at a 1×1 grid. `levels` must be at least 1, for the grid alone.
+pub async fn run_with_pyramid(
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    levels: usize,
+    options: &MesherConfig,
+) -> Result<Vec<PyramidLevel>, MesherError> {
+    check_levels(levels)?;
+    check_budget_with(points, config, options, |dimensions| {
+        memory_required(dimensions, levels)
+    })?;
+
+    let dimensions = options.grid_dimensions(config);
+    let mut context = WgpuContext::new(dimensions, points_size(points), &options.adapter).await?;
+    context.prepare(options)?;
+    let dump = options.debug_dump.as_ref();
+    let passes = dispatch_jfa(&context, points, &[], config, options, dump).await?;
+    compute(
+        &context,
+        &context.storage_buffers[passes as usize % 2],
+        levels,
+    )
+    .await
+}
+
+// Rejects a pyramid without even the grid itself
+pub(super) fn check_levels(levels: usize) -> Result<(), MesherError> {
+    if levels == 0 {
+        return Err(MesherError::InvalidInput(
+            "a pyramid needs at least one level",
+        ));
+    }
+    Ok(())
+}
+
+// Width and height of each level of the pyramid of a grid of `dimensions`, level 0 included
+fn level_dimensions(dimensions: (usize, usize), levels: usize) -> Vec<(usize, usize)> {
+    let mut levels_dimensions = vec![dimensions];
+    while levels_dimensions.len() < levels
+        && levels_dimensions[levels_dimensions.len() - 1] != (1, 1)
+    {
+        let (width, height) = levels_dimensions[levels_dimensions.len() - 1];
+        levels_dimensions.push((width.div_ceil(2), height.div_ceil(2)));
+    }
+    levels_dimensions
+}
+
+// Bytes `compute` allocates for the pyramid of a grid of `dimensions`, the buffers of the levels
+// above 0 and the staging copies of them all
+pub(super) fn memory_required(dimensions: (usize, usize), levels: usize) -> u64 {
+    let texels = |&(width, height): &(usize, usize)| (width * height) as u64;
+    let levels = level_dimensions(dimensions, levels);
+    (2 * levels.iter().map(texels).sum::<u64>() - texels(&levels[0]))
+        * std::mem::size_of::<u32>() as u64
+}
+
+// Pyramid of `labels`, a grid of the context's dimensions, level 0 included
+pub(super) async fn compute(
+    context: &WgpuContext,
+    labels: &wgpu::Buffer,
+    levels: usize,
+) -> Result<Vec<PyramidLevel>, MesherError> {
+    let device = &context.device;
+    let dimensions = level_dimensions((context.width, context.height), levels);
+
+    let shader = device.create_shader_module(wgpu::include_wgsl!("pyramid.wgsl"));
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    // Level 0 is the labels buffer itself
+    let level_buffers: Vec<wgpu::Buffer> = dimensions[1..]
+        .iter()
+        .map(|&(width, height)| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: (width * height * std::mem::size_of::<u32>()) as u64,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            })
+        })
+        .collect();
+
+    let mut command_encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    for (level, pair) in dimensions.windows(2).enumerate() {
+        let (fine, coarse) = (pair[0], pair[1]);
+        let fine_buffer = match level {
+            0 => labels,
+            _ => &level_buffers[level - 1],
+        };
+        let grid_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&[
+                fine.0 as u32,
+                fine.1 as u32,
+                coarse.0 as u32,
+                coarse.1 as u32,
+            ]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: fine_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: grid_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: level_buffers[level].as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut compute_pass = command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&pipeline);
+        compute_pass.set_bind_group(0, &bind_group, &[]);
+        let (x, y) = workgroup_count(coarse.0, coarse.1);
+        compute_pass.dispatch_workgroups(x, y, 1);
+    }
+    context.queue.submit(Some(command_encoder.finish()));
+
+    let mut pyramid = Vec::with_capacity(dimensions.len());
+    for (level, &(width, height)) in dimensions.iter().enumerate() {
+        let buffer = match level {
+            0 => labels,
+            _ => &level_buffers[level - 1],
+        };
+        let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (width * height * std::mem::size_of::<u32>()) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut level_labels = vec![0; width * height];
+        let mapped = get_data(
+            &mut level_labels,
+            buffer,
+            &staging_buffer,
+            device,
+            &context.queue,
+        )
+        .await;
+        if let Some(err) = context.take_error() {
+            return Err(err);
+        }
+        mapped.map_err(|err| MesherError::BufferMapFailed(err.to_string()))?;
+        pyramid.push(PyramidLevel {
+            width,
+            height,
+            labels: level_labels,
+        });
+    }
+    Ok(pyramid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::gpu_or_skip;
+
+    #[test]
+    fn test_pyramid() {
+        // Vertical strips of 3, 2 and 3 texels on an 8 × 4 grid
+        let points = [(1.5, 2.0), (4.0, 2.0), (6.5, 2.0)];
+        let options = MesherConfig {
+            resolution: 8,
+            grid: crate::config::GridShape::FitDomain,
+            ..Default::default()
+        };
+        let Some(pyramid) = gpu_or_skip(crate::jfa_wgpu::block_on(run_with_pyramid(
+            &points,
+            (8.0, 4.0),
+            8,
+            &options,
+        ))) else {
+            return;
+        };
+
+        let sizes: Vec<(usize, usize)> = pyramid
+            .iter()
+            .map(|level| (level.width, level.height))
+            .collect();
+        assert_eq!(sizes, [(8, 4), (4, 2), (2, 1), (1, 1)]);
+        assert_eq!(pyramid[0].labels[..8], [1, 1, 1, 2, 2, 3, 3, 3]);
+        // The block of texels 2 and 3 is a tie, which goes to the first label
+        assert_eq!(pyramid[1].labels, [1, 1, 2, 3, 1, 1, 2, 3]);
+        assert_eq!(pyramid[2].labels, [1, 2]);
+        assert_eq!(pyramid[3].labels, [1]);
+
+        assert_eq!(
+            crate::jfa_wgpu::block_on(run_with_pyramid(&points, (8.0, 4.0), 0, &options)),
+            Err(MesherError::InvalidInput(
+                "a pyramid needs at least one level"
+            ))
+        );
+    }
+}
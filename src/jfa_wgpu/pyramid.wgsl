@@ -0,0 +1,45 @@
+// One level of a label pyramid: each texel takes the most frequent label of the 2×2 block below
+// it, the first one in scan order on ties. Odd grids have partial blocks on their last row and
+// column
+@group(0) @binding(0) var<storage, read> fine: array<u32>;
+// Width and height of the fine level, then of the coarse one
+@group(0) @binding(1) var<uniform> grid: vec4<u32>;
+@group(0) @binding(2) var<storage, read_write> coarse: array<u32>;
+
+@compute @workgroup_size(16, 16)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let x = global_id.x;
+    let y = global_id.y;
+
+    if (x >= grid.z || y >= grid.w) {
+        return;
+    }
+
+    var block: array<u32, 4>;
+    var count = 0u;
+    for (var dy = 0u; dy < 2u; dy = dy + 1u) {
+        for (var dx = 0u; dx < 2u; dx = dx + 1u) {
+            let fine_x = 2u * x + dx;
+            let fine_y = 2u * y + dy;
+            if fine_x < grid.x && fine_y < grid.y {
+                block[count] = fine[fine_x + fine_y * grid.x];
+                count = count + 1u;
+            }
+        }
+    }
+
+    var best = block[0];
+    var best_votes = 0u;
+    for (var i = 0u; i < count; i = i + 1u) {
+        var votes = 0u;
+        for (var j = 0u; j < count; j = j + 1u) {
+            votes = votes + u32(block[j] == block[i]);
+        }
+        if votes > best_votes {
+            best = block[i];
+            best_votes = votes;
+        }
+    }
+
+    coarse[x + y * grid.z] = best;
+}
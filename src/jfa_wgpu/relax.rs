@@ -0,0 +1,276 @@
+// Lloyd relaxation: the labels of each JFA stay on the GPU, where a reduction sums the texels
+// of every cell, and only the per-cell sums are read back to move the seeds to the centroids.
+// Two centroids can land on one texel, where the later seed would overwrite the earlier one in
+// the initial grid and its cell vanish for good, so such seeds stay where they were.
+
+use std::collections::HashMap;
+
+use wgpu::util::DeviceExt;
+
+use super::{
+    check_budget, dispatch_jfa, get_data, init_normal_points, input, points_size, workgroup_count,
+    WgpuContext,
+};
+use crate::config::{BoundaryMode, MesherConfig};
+use crate::error::MesherError;
+use crate::progress::Progress;
+
+const WORDS_PER_CELL: usize = 5;
+
+/// Moves the seeds to the centroids of their cells `iterations` times, converging towards a
+/// centroidal Voronoi tessellation. Seeds whose cell has no texel, or whose centroid is on the
+/// texel of another seed, stay in place, and the boundary mode of `options` is ignored, the box
+/// being bounded.
+///
+/// Seeds set in `fixed`, which is empty when none is, never move, their cells still bounding the
+/// others.
+pub async fn relax(
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    iterations: usize,
+    fixed: &[bool],
+    options: &MesherConfig,
+) -> Result<Vec<(f64, f64)>, MesherError> {
+    if !fixed.is_empty() && fixed.len() != points.len() {
+        return Err(MesherError::InvalidInput(
+            "fixed must be empty or one per seed",
+        ));
+    }
+    let mut points = points.to_vec();
+    if points.is_empty() || iterations == 0 {
+        return Ok(points);
+    }
+    check_budget(&points, config, options)?;
+
+    let (width, height) = options.grid_dimensions(config);
+    let mut context =
+        WgpuContext::new((width, height), points_size(&points), &options.adapter).await?;
+    context.prepare(options)?;
+    let device = &context.device;
+
+    let shader = device.create_shader_module(wgpu::include_wgsl!("centroids.wgsl"));
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    let sums_size = (points.len() * WORDS_PER_CELL * std::mem::size_of::<u32>()) as u64;
+    let sums_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: sums_size,
+        usage: wgpu::BufferUsages::STORAGE
+            | wgpu::BufferUsages::COPY_DST
+            | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    let sums_staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: sums_size,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let dimensions_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: None,
+        contents: bytemuck::cast_slice(&[width as u32, height as u32]),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+
+    // Progress and cancellation are handled per iteration rather than per pass
+    let bounded = MesherConfig {
+        boundary: BoundaryMode::Bounded,
+        debug_dump: None,
+        progress: None,
+        cancellation: None,
+        ..options.clone()
+    };
+    // One per storage buffer the JFA can leave its labels in
+    let bind_groups = context.storage_buffers.each_ref().map(|labels_buffer| {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: labels_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: dimensions_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: sums_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    });
+
+    let kept = input::kept_seeds(&points, config, options.out_of_domain);
+    let mut sums = vec![0u32; points.len() * WORDS_PER_CELL];
+    for iteration in 0..iterations {
+        if let Some(token) = &options.cancellation {
+            if token.is_cancelled() {
+                return Err(MesherError::Cancelled {
+                    completed: iteration,
+                    total: iterations,
+                });
+            }
+        }
+        // Centroids are plain averages of texel coordinates, which would be wrong for cells
+        // wrapping across a periodic edge
+        let passes = dispatch_jfa(&context, &points, &[], config, &bounded, None).await?;
+
+        let mut command_encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        command_encoder.clear_buffer(&sums_buffer, 0, None);
+        {
+            let mut compute_pass =
+                command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: None,
+                    timestamp_writes: None,
+                });
+            compute_pass.set_pipeline(&pipeline);
+            compute_pass.set_bind_group(0, &bind_groups[passes as usize % 2], &[]);
+            let (x, y) = workgroup_count(width, height);
+            compute_pass.dispatch_workgroups(x, y, 1);
+        }
+        context.queue.submit(Some(command_encoder.finish()));
+
+        let mapped = get_data(
+            &mut sums,
+            &sums_buffer,
+            &sums_staging_buffer,
+            device,
+            &context.queue,
+        )
+        .await;
+        if let Some(err) = context.take_error() {
+            return Err(err);
+        }
+        mapped.map_err(|err| MesherError::BufferMapFailed(err.to_string()))?;
+
+        let previous = points.clone();
+        for (i, (point, cell)) in points
+            .iter_mut()
+            .zip(sums.chunks(WORDS_PER_CELL))
+            .enumerate()
+        {
+            let count = cell[4] as f64;
+            if count == 0.0 || fixed.get(i) == Some(&true) {
+                continue;
+            }
+            let sum_x = cell[0] as u64 | ((cell[1] as u64) << 32);
+            let sum_y = cell[2] as u64 | ((cell[3] as u64) << 32);
+            // Texel centers are half a texel from their corner
+            *point = (
+                (sum_x as f64 / count + 0.5) * config.0 / width as f64,
+                (sum_y as f64 / count + 0.5) * config.1 / height as f64,
+            );
+        }
+        keep_apart(&mut points, &previous, &kept, config, (width, height));
+        if let Some(progress) = &options.progress {
+            progress.report(Progress::Relaxation {
+                iteration: iteration + 1,
+                iterations,
+            });
+        }
+    }
+
+    Ok(points)
+}
+
+// Moves back to `previous`, where the kept seeds were on distinct texels, the seeds whose new
+// position is on the texel of another one, until none is
+fn keep_apart(
+    points: &mut [(f64, f64)],
+    previous: &[(f64, f64)],
+    kept: &[bool],
+    config: (f64, f64),
+    dimensions: (usize, usize),
+) {
+    loop {
+        let mut texels = HashMap::with_capacity(points.len());
+        let seed_texels = init_normal_points(points, config, dimensions);
+        let coincident = seed_texels
+            .into_iter()
+            .enumerate()
+            .filter(|&(i, _)| kept[i])
+            .find_map(|(second, texel)| Some((texels.insert(texel, second)?, second)));
+        let Some((first, second)) = coincident else {
+            return;
+        };
+        // Both can't be back in place, so one of them moved
+        let moved = match points[second] != previous[second] {
+            true => second,
+            false => first,
+        };
+        points[moved] = previous[moved];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::gpu_or_skip;
+
+    #[test]
+    fn test_keep_apart() {
+        let previous = [(0.5, 0.5), (2.5, 0.5), (3.5, 3.5)];
+        // The first two centroids are on texel (1, 0)
+        let mut points = [(1.5, 0.5), (1.6, 0.6), (3.0, 3.0)];
+
+        keep_apart(&mut points, &previous, &[true; 3], (4.0, 4.0), (4, 4));
+
+        assert_eq!(points, [(1.5, 0.5), (2.5, 0.5), (3.0, 3.0)]);
+    }
+
+    #[test]
+    fn test_relax() {
+        // The bisector at x = 3 leaves columns 0 to 2 to the first cell of the 8 × 8 grid
+        let points = [(1.0, 1.0), (5.0, 1.0)];
+        let options = MesherConfig {
+            resolution: 8,
+            ..Default::default()
+        };
+        let Some(relaxed) = gpu_or_skip(crate::jfa_wgpu::block_on(relax(
+            &points,
+            (8.0, 8.0),
+            1,
+            &[],
+            &options,
+        ))) else {
+            return;
+        };
+
+        assert_eq!(relaxed, [(1.5, 4.0), (5.5, 4.0)]);
+    }
+
+    #[test]
+    fn test_relax_fixed() {
+        let points = [(1.0, 1.0), (5.0, 1.0)];
+        let options = MesherConfig {
+            resolution: 8,
+            ..Default::default()
+        };
+        let Some(relaxed) = gpu_or_skip(crate::jfa_wgpu::block_on(relax(
+            &points,
+            (8.0, 8.0),
+            2,
+            &[true, false],
+            &options,
+        ))) else {
+            return;
+        };
+
+        // The free seed still moves towards the centroid of the cell the pinned one bounds
+        assert_eq!(relaxed[0], points[0]);
+        assert_ne!(relaxed[1], points[1]);
+
+        let mismatch = crate::jfa_wgpu::block_on(relax(&points, (8.0, 8.0), 1, &[true], &options));
+        assert!(matches!(mismatch, Err(MesherError::InvalidInput(_))));
+    }
+}
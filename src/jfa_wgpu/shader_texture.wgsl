@@ -0,0 +1,60 @@
+// JFA on ping-pong R32Uint textures of labels instead of flat buffers, for 2D-local memory
+// accesses
+@group(0) @binding(0) var input_grid: texture_2d<u32>;
+@group(0) @binding(1) var<uniform> params: Params;
+@group(0) @binding(3) var output_grid: texture_storage_2d<r32uint, write>;
+// Only bound by `unpack`, which copies the labels to the buffer the host reads back
+@group(0) @binding(4) var<storage, read_write> labels: array<u32>;
+
+@compute @workgroup_size(16, 16)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let x = global_id.x;
+    let y = global_id.y;
+    let step = i32(params.step);
+    let width = i32(params.width);
+    let height = i32(params.height);
+
+    if (x >= params.width || y >= params.height) {
+        return;
+    }
+
+    var current = textureLoad(input_grid, vec2<i32>(i32(x), i32(y)), 0).x;
+
+    for (var dx = -1; dx <= 1; dx = dx + 1) {
+        for (var dy = -1; dy <= 1; dy = dy + 1) {
+            var new_x = i32(x) + dx * step;
+            var new_y = i32(y) + dy * step;
+            if wraps_x() {
+                new_x = (new_x % width + width) % width;
+            }
+            if wraps_y() {
+                new_y = (new_y % height + height) % height;
+            }
+
+            if !(new_x >= 0 && new_x < width && new_y >= 0 && new_y < height) {
+                continue;
+            }
+
+            let found = textureLoad(input_grid, vec2<i32>(new_x, new_y), 0).x;
+            if (dx == 0 && dy == 0) || found == 0u || current == found {
+                continue;
+            }
+
+            if current == 0u || nearer(x, y, found, current) {
+                current = found;
+            }
+        }
+    }
+
+    textureStore(output_grid, vec2<i32>(i32(x), i32(y)), vec4<u32>(current, 0u, 0u, 0u));
+}
+
+@compute @workgroup_size(16, 16)
+fn unpack(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let size = textureDimensions(input_grid);
+    if (global_id.x >= size.x || global_id.y >= size.y) {
+        return;
+    }
+    let texel = textureLoad(input_grid, vec2<i32>(i32(global_id.x), i32(global_id.y)), 0);
+    labels[global_id.x + global_id.y * size.x] = texel.x;
+}
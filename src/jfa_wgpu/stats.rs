@@ -0,0 +1,188 @@
+// Cell statistics reduced on the GPU from the labels buffer, so loops controlling the density or
+// the quality of the cells read back a few words per seed instead of scanning the whole grid.
+// The reductions are integer atomics, whose results don't depend on the order invocations run
+// in, so deterministic labels always give the same statistics.
+
+use wgpu::util::DeviceExt;
+
+use super::{check_budget_with, dispatch_jfa, get_data, points_size, workgroup_count, WgpuContext};
+use crate::config::MesherConfig;
+use crate::error::MesherError;
+
+const WORDS_PER_CELL: usize = 7;
+
+/// Measures of the cells of a label grid, indexed by seed, in texels unless stated otherwise.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CellStats {
+    /// Texels of each cell
+    pub pixel_counts: Vec<u32>,
+    /// Texel sides on the boundary of each cell, facing along x then along y. Sides on the
+    /// domain box count unless the axis wraps around
+    pub boundary_sides: Vec<(u32, u32)>,
+    /// Inclusive bounds `(min_x, min_y, max_x, max_y)` of the texels of each cell, `None` for an
+    /// empty cell. A cell wrapping across a periodic edge spans the whole axis
+    pub bounding_boxes: Vec<Option<(u32, u32, u32, u32)>>,
+    /// Width and height of a texel in domain units
+    pub texel_size: (f64, f64),
+}
+
+impl CellStats {
+    /// Area of `cell` in squared domain units.
+    pub fn area(&self, cell: usize) -> f64 {
+        self.pixel_counts[cell] as f64 * self.texel_size.0 * self.texel_size.1
+    }
+
+    /// Perimeter of `cell` in domain units, along the texel sides, so diagonal edges come out
+    /// longer than they are.
+    pub fn perimeter(&self, cell: usize) -> f64 {
+        // Sides facing along x are as long as a texel is high
+        let (x, y) = self.boundary_sides[cell];
+        x as f64 * self.texel_size.1 + y as f64 * self.texel_size.0
+    }
+
+    fn from_words(words: &[u32], texel_size: (f64, f64)) -> CellStats {
+        let cells = words.chunks(WORDS_PER_CELL);
+        CellStats {
+            pixel_counts: cells.clone().map(|cell| cell[0]).collect(),
+            boundary_sides: cells.clone().map(|cell| (cell[1], cell[2])).collect(),
+            bounding_boxes: cells
+                .map(|cell| (cell[0] > 0).then_some((cell[3], cell[4], cell[5], cell[6])))
+                .collect(),
+            texel_size,
+        }
+    }
+}
+
+/// Voronoi diagram of `points` in the `config` box, as [`super::run_with_stats`] would compute
+/// it, reduced to the statistics of its cells without reading the labels back.
+pub async fn cell_stats(
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    options: &MesherConfig,
+) -> Result<CellStats, MesherError> {
+    check_budget_with(points, config, options, |_| memory_required(points.len()))?;
+
+    let dimensions = options.grid_dimensions(config);
+    let mut context = WgpuContext::new(dimensions, points_size(points), &options.adapter).await?;
+    context.prepare(options)?;
+    let dump = options.debug_dump.as_ref();
+    let passes = dispatch_jfa(&context, points, &[], config, options, dump).await?;
+    let labels = &context.storage_buffers[passes as usize % 2];
+    reduce(&context, labels, points.len(), config, options).await
+}
+
+// Bytes `reduce` allocates for `cells` cells, their statistics and its staging copy
+pub(super) fn memory_required(cells: usize) -> u64 {
+    (2 * cells * WORDS_PER_CELL * std::mem::size_of::<u32>()) as u64
+}
+
+// Statistics of the `cells` cells labeled in `labels`, a grid of the context's dimensions over
+// the `config` box
+pub(super) async fn reduce(
+    context: &WgpuContext,
+    labels: &wgpu::Buffer,
+    cells: usize,
+    config: (f64, f64),
+    options: &MesherConfig,
+) -> Result<CellStats, MesherError> {
+    let device = &context.device;
+    let (width, height) = (context.width, context.height);
+
+    let shader = device.create_shader_module(wgpu::include_wgsl!("cell_stats.wgsl"));
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    // Bounds start empty, so the first texel of a cell sets them
+    let mut words = [0, 0, 0, u32::MAX, u32::MAX, 0, 0].repeat(cells);
+    let stats_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: None,
+        contents: bytemuck::cast_slice(&words),
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+    });
+    let stats_staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: stats_buffer.size(),
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let (wrap_x, wrap_y) = options.boundary.wraps();
+    let wrap = u32::from(wrap_x) | (u32::from(wrap_y) << 1);
+    let grid_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: None,
+        contents: bytemuck::cast_slice(&[width as u32, height as u32, wrap, 0]),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: labels.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: grid_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: stats_buffer.as_entire_binding(),
+            },
+        ],
+    });
+
+    let mut command_encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    {
+        let mut compute_pass = command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&pipeline);
+        compute_pass.set_bind_group(0, &bind_group, &[]);
+        let (x, y) = workgroup_count(width, height);
+        compute_pass.dispatch_workgroups(x, y, 1);
+    }
+    context.queue.submit(Some(command_encoder.finish()));
+
+    let mapped = get_data(
+        &mut words,
+        &stats_buffer,
+        &stats_staging_buffer,
+        device,
+        &context.queue,
+    )
+    .await;
+    if let Some(err) = context.take_error() {
+        return Err(err);
+    }
+    mapped.map_err(|err| MesherError::BufferMapFailed(err.to_string()))?;
+
+    let texel_size = (config.0 / width as f64, config.1 / height as f64);
+    Ok(CellStats::from_words(&words, texel_size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_words() {
+        // 3x2 texels in a corner, then an empty cell
+        let words = [6, 4, 6, 0, 0, 2, 1, 0, 0, 0, u32::MAX, u32::MAX, 0, 0];
+        let stats = CellStats::from_words(&words, (0.5, 0.25));
+
+        assert_eq!(stats.pixel_counts, [6, 0]);
+        assert_eq!(stats.bounding_boxes, [Some((0, 0, 2, 1)), None]);
+        assert_eq!(stats.area(0), 0.75);
+        assert_eq!(stats.perimeter(0), 4.0 * 0.25 + 6.0 * 0.5);
+        assert_eq!(stats.area(1), 0.0);
+    }
+}
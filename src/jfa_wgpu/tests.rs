@@ -0,0 +1,164 @@
+use super::*;
+use crate::config::BoundaryMode;
+use crate::reference;
+use crate::test_util::{gpu_or_skip, scattered};
+
+const WORDS: usize = PARAMS_STRIDE / std::mem::size_of::<u32>();
+
+#[test]
+fn test_pass_params() {
+    let norms = [
+        (DistanceMetric::Euclidean, 0, 2.0),
+        (DistanceMetric::Manhattan, 1, 1.0),
+        (DistanceMetric::Chebyshev, 2, f32::INFINITY),
+        (DistanceMetric::Minkowski(3.0), 3, 3.0),
+    ];
+    for (distance, norm, exponent) in norms {
+        let options = MesherConfig {
+            distance,
+            boundary: BoundaryMode::Periodic { x: false, y: true },
+            ..Default::default()
+        };
+        let params = pass_params(&options, &[4, 1], (8, 6));
+
+        assert_eq!(params.len(), 2 * WORDS);
+        assert_eq!(params[..4], [4, 8, 6, 2]);
+        assert_eq!(params[WORDS..WORDS + 4], [1, 8, 6, 2]);
+        assert_eq!(params[4], 1f32.to_bits());
+        assert_eq!(params[5], 0f32.to_bits());
+        assert_eq!(params[7], norm);
+        assert_eq!(params[8], f32::to_bits(exponent));
+        assert_eq!(params[9], 0);
+        assert_eq!(params[10], 0);
+        assert_eq!([params[11], params[WORDS + 11]], [0, 4]);
+    }
+
+    let options = MesherConfig {
+        deterministic: true,
+        distance_precision: DistancePrecision::Fixed16,
+        ..Default::default()
+    };
+    let params = pass_params(&options, &[4, 1], (8, 6));
+    assert_eq!([params[9], params[WORDS + 9]], [1, 1]);
+    assert_eq!([params[10], params[WORDS + 10]], [2, 2]);
+}
+
+#[test]
+fn test_invalid_minkowski_exponent() {
+    let points = [(1.0, 1.0), (3.0, 3.0)];
+    for p in [0.5, 0.0, -2.0, f64::NAN, f64::INFINITY] {
+        let options = MesherConfig {
+            distance: DistanceMetric::Minkowski(p),
+            ..Default::default()
+        };
+        assert!(
+            matches!(
+                check_budget(&points, (4.0, 4.0), &options),
+                Err(MesherError::InvalidInput(_))
+            ),
+            "p = {p}"
+        );
+    }
+}
+
+#[test]
+fn test_budget_counts_post_passes() {
+    let points = [(1.0, 1.0), (3.0, 3.0)];
+    let options = MesherConfig {
+        resolution: 8,
+        memory_budget: Some(memory_required(2, (8, 8))),
+        ..Default::default()
+    };
+    assert_eq!(check_budget(&points, (4.0, 4.0), &options), Ok(()));
+
+    // Levels of 64, 16, 4 and 1 texels, with a staging copy of each
+    assert_eq!(pyramid::memory_required((8, 8), 4), (2 * 85 - 64) * 4);
+    let extra = distance_field::memory_required((8, 8), DistancePrecision::F32);
+    assert_eq!(extra, 8 * 8 * 2 * 2 * 4);
+    let half = distance_field::memory_required((8, 8), DistancePrecision::F16);
+    assert_eq!(half, extra / 2);
+    assert_eq!(
+        check_budget_with(&points, (4.0, 4.0), &options, |_| extra),
+        Err(MesherError::OverBudget {
+            required: memory_required(2, (8, 8)) + extra,
+            budget: memory_required(2, (8, 8)),
+        })
+    );
+}
+
+#[test]
+fn test_norms_against_reference() {
+    let points = scattered(100, (10.0, 10.0));
+    for distance in [
+        DistanceMetric::Manhattan,
+        DistanceMetric::Chebyshev,
+        DistanceMetric::Minkowski(3.0),
+    ] {
+        let options = MesherConfig {
+            resolution: 128,
+            distance,
+            ..Default::default()
+        };
+        let Some(labels) = gpu_or_skip(main(&points, (10.0, 10.0), &options)) else {
+            return;
+        };
+        let report = reference::validate(&labels, &points, (10.0, 10.0), &options).unwrap();
+
+        assert!(report.error_rate() < 0.005, "{distance:?}: {report}");
+    }
+}
+
+#[test]
+fn test_coarse_against_full_resolution() {
+    // The last two seeds share coarse texel (32, 32), where only the second one is marked
+    let mut points = scattered(60, (16.0, 16.0));
+    points.extend([(8.0, 8.0), (8.1, 8.1)]);
+    let pair = [points.len() - 1, points.len()];
+    let options = MesherConfig {
+        resolution: 256,
+        ..Default::default()
+    };
+    let Some(full) = gpu_or_skip(main(&points, (16.0, 16.0), &options)) else {
+        return;
+    };
+    let coarse_options = MesherConfig {
+        coarse_factor: Some(4),
+        ..options
+    };
+    let (coarse, stats) = block_on(run_with_stats(&points, (16.0, 16.0), &coarse_options)).unwrap();
+    let coarse: Vec<usize> = coarse.into_iter().map(|label| label as usize).collect();
+
+    // The passes on the 64×64 grid are counted apart from the full resolution ones
+    assert_eq!(
+        stats.coarse_passes as usize,
+        run_steps(&coarse_options, (64, 64)).len()
+    );
+    assert_eq!(
+        stats.passes as usize,
+        refine_steps(4, coarse_options.accuracy.corrects()).len()
+    );
+
+    // Every seed keeps its own texel, the pair included
+    for (i, (x, y)) in init_normal_points(&points, (16.0, 16.0), (256, 256))
+        .into_iter()
+        .enumerate()
+    {
+        assert_eq!(coarse[x as usize + y as usize * 256], i + 1);
+    }
+    // The pair is closer than the factor, away from its cells the labels agree
+    let compared: Vec<(usize, usize)> = full
+        .iter()
+        .zip(&coarse)
+        .map(|(&full, &coarse)| (full, coarse))
+        .filter(|(full, coarse)| !pair.contains(full) && !pair.contains(coarse))
+        .collect();
+    let mismatched = compared
+        .iter()
+        .filter(|(full, coarse)| full != coarse)
+        .count();
+    assert!(
+        (mismatched as f64) < 0.005 * compared.len() as f64,
+        "{mismatched} of {} texels",
+        compared.len()
+    );
+}
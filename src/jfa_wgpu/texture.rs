@@ -0,0 +1,266 @@
+// Label grid kept in ping-pong textures during the passes, with `GridStorage::Texture`. The last
+// pass is unpacked into the storage buffer the buffer passes would have written, so reading
+// back, relaxing or binding the labels buffer work the same either way.
+//
+// Texels hold a single `R32Uint` label rather than the `Rg32Uint` seed coordinates first
+// considered: the passes look the position of a label's seed up in the seeds buffer, as the
+// buffer passes do, so a second channel would double the memory and bandwidth of every pass for
+// nothing, and the unpacked labels stay the ones the buffer passes write.
+
+use super::{workgroup_count, WgpuContext, DISTANCE_SHADER, PARAMS_SIZE, PARAMS_STRIDE};
+
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+pub(super) const TEXEL_SIZE: usize = std::mem::size_of::<u32>();
+
+pub(super) struct TextureGrid {
+    pub(super) textures: [wgpu::Texture; 2],
+    pipeline: wgpu::ComputePipeline,
+    /// Bind group `i` reads `textures[i]` and writes the other one
+    bind_groups: [wgpu::BindGroup; 2],
+    unpack_pipeline: wgpu::ComputePipeline,
+    /// Bind group `i` copies the labels of `textures[i]` to `storage_buffers[i]`
+    unpack_bind_groups: [wgpu::BindGroup; 2],
+}
+
+impl TextureGrid {
+    pub(super) fn new(context: &WgpuContext) -> TextureGrid {
+        let device = &context.device;
+        let source = [DISTANCE_SHADER, include_str!("shader_texture.wgsl")].join("\n");
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+
+        let textures = [0, 1].map(|_| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: None,
+                size: wgpu::Extent3d {
+                    width: context.width as u32,
+                    height: context.height as u32,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: FORMAT,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::STORAGE_BINDING
+                    | wgpu::TextureUsages::COPY_DST
+                    | wgpu::TextureUsages::COPY_SRC,
+                view_formats: &[],
+            })
+        });
+        let views = textures
+            .each_ref()
+            .map(|texture| texture.create_view(&Default::default()));
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Uint,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(PARAMS_SIZE as u64),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: FORMAT,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let bind_groups = [0, 1].map(|input| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&views[input]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                            buffer: &context.params_buffer,
+                            offset: 0,
+                            size: wgpu::BufferSize::new(PARAMS_SIZE as u64),
+                        }),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: context.normal_points.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::TextureView(&views[1 - input]),
+                    },
+                ],
+            })
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        let unpack_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: None,
+            module: &shader,
+            entry_point: Some("unpack"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        let unpack_layout = unpack_pipeline.get_bind_group_layout(0);
+        let unpack_bind_groups = [0, 1].map(|i| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &unpack_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&views[i]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: context.storage_buffers[i].as_entire_binding(),
+                    },
+                ],
+            })
+        });
+
+        TextureGrid {
+            textures,
+            pipeline,
+            bind_groups,
+            unpack_pipeline,
+            unpack_bind_groups,
+        }
+    }
+
+    pub(super) fn upload(&self, context: &WgpuContext, labels: &[u32]) {
+        context.queue.write_texture(
+            self.textures[0].as_image_copy(),
+            bytemuck::cast_slice(labels),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some((context.width * TEXEL_SIZE) as u32),
+                rows_per_image: None,
+            },
+            self.textures[0].size(),
+        );
+    }
+
+    pub(super) fn encode_step(
+        &self,
+        context: &WgpuContext,
+        command_encoder: &mut wgpu::CommandEncoder,
+        pass: u32,
+    ) {
+        let mut compute_pass = command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: context.timestamp_writes(pass),
+        });
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(
+            0,
+            &self.bind_groups[pass as usize % 2],
+            &[pass * PARAMS_STRIDE as u32],
+        );
+        let (x, y) = workgroup_count(context.width, context.height);
+        compute_pass.dispatch_workgroups(x, y, 1);
+    }
+
+    // Copies the labels written by the last of `passes` passes to `storage_buffers[passes % 2]`
+    pub(super) fn encode_unpack(
+        &self,
+        context: &WgpuContext,
+        command_encoder: &mut wgpu::CommandEncoder,
+        passes: u32,
+    ) {
+        let mut compute_pass = command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&self.unpack_pipeline);
+        compute_pass.set_bind_group(0, &self.unpack_bind_groups[passes as usize % 2], &[]);
+        let (x, y) = workgroup_count(context.width, context.height);
+        compute_pass.dispatch_workgroups(x, y, 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{BoundaryMode, GridShape, GridStorage, MesherConfig};
+    use crate::test_util::{gpu_or_skip, scattered};
+
+    #[test]
+    fn test_texture_matches_buffer() {
+        let points = scattered(40, (6.0, 4.0));
+        for boundary in [
+            BoundaryMode::Bounded,
+            BoundaryMode::Periodic { x: true, y: false },
+        ] {
+            let options = MesherConfig {
+                resolution: 96,
+                grid: GridShape::FitDomain,
+                boundary,
+                ..Default::default()
+            };
+            let Some(buffer) = gpu_or_skip(super::super::main(&points, (6.0, 4.0), &options))
+            else {
+                return;
+            };
+            let texture = super::super::main(
+                &points,
+                (6.0, 4.0),
+                &MesherConfig {
+                    storage: GridStorage::Texture,
+                    ..options
+                },
+            )
+            .unwrap();
+
+            assert_eq!(buffer.len(), 96 * 64);
+            assert_eq!(texture, buffer, "{boundary:?}");
+        }
+    }
+}
@@ -0,0 +1,383 @@
+// Grids whose buffers are larger than the storage bindings of downlevel devices are labeled one
+// tile at a time. Each tile runs on the seeds of its own texels and of a halo around them, and
+// keeps the labels of its own texels only. A texel is settled when no seed beyond the halo can
+// be nearer than the one it got, which the host checks after each tile: tiles with unsettled
+// texels run again with a halo twice as wide, so sparse seeds make for wide halos.
+//
+// With several GPUs, each one takes the next tile whenever it's done with its last one. The
+// devices are polled from the calling thread in turns, their tiles running concurrently.
+
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::ops::Range;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use super::{
+    adapter, check_budget, grid_size, init_normal_points, input, points_size, run_with_context,
+    seed_positions, WgpuContext, POINT_SIZE,
+};
+use crate::config::{BoundaryMode, DistanceMetric, GridShape, MesherConfig, Metric};
+use crate::error::MesherError;
+use crate::progress::Progress;
+
+/// Side of the tiles when [`MesherConfig::tile_size`] is `None`, in texels.
+pub const DEFAULT_TILE_SIZE: usize = 2048;
+// Narrowest halo, in texels
+const MIN_HALO: usize = 16;
+
+// Texel ranges along x and y of a part of the grid
+type Tile = (Range<usize>, Range<usize>);
+
+// What every tile of a run needs
+struct Job<'a> {
+    options: &'a MesherConfig,
+    dimensions: (usize, usize),
+    // Texel of each seed
+    seeds: Vec<(u32, u32)>,
+    // Exact position of each seed, in texels
+    positions: Vec<(f64, f64)>,
+    // Seeds which aren't ignored as out of the domain
+    kept: Vec<bool>,
+    first_halo: usize,
+}
+
+// Whether `mesh` splits a grid of `dimensions` into tiles
+pub(super) fn needs_tiling(options: &MesherConfig, dimensions: (usize, usize)) -> bool {
+    options.tile_size.is_some() || grid_size(dimensions) as u64 > max_binding_size()
+}
+
+fn max_binding_size() -> u64 {
+    wgpu::Limits::downlevel_defaults().max_storage_buffer_binding_size as u64
+}
+
+/// Labels of `points` in the `config` box, as [`super::run_with_stats`] computes them, one tile
+/// of [`MesherConfig::tile_size`] texels at a time, so grids too large for a storage buffer can
+/// be meshed. With [`MesherConfig::multi_gpu`] the tiles are spread across the GPUs.
+///
+/// Periodic boundaries aren't supported. The debug dump and the recovery policy are ignored,
+/// and progress is reported once per tile.
+pub async fn run_tiled(
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    options: &MesherConfig,
+) -> Result<Vec<u32>, MesherError> {
+    if options.resolution == 0 {
+        return Err(MesherError::InvalidInput("resolution must be at least 1"));
+    }
+    input::validate_seeds(
+        points,
+        config,
+        options.grid_dimensions(config),
+        options.out_of_domain,
+    )?;
+    if options.boundary != BoundaryMode::Bounded {
+        return Err(MesherError::InvalidInput(
+            "tiled runs don't support periodic boundaries",
+        ));
+    }
+    let tile_size = options.tile_size.unwrap_or(DEFAULT_TILE_SIZE);
+    if tile_size == 0 {
+        return Err(MesherError::InvalidInput(
+            "the tile size must be at least 1",
+        ));
+    }
+
+    let dimensions = options.grid_dimensions(config);
+    let (width, height) = dimensions;
+    // Twice the side of an average cell, which settles most tiles at the first try
+    let cell = ((width * height) as f64 / points.len() as f64).sqrt();
+    let job = Job {
+        options,
+        dimensions,
+        seeds: init_normal_points(points, config, dimensions),
+        positions: seed_positions(points, config, dimensions),
+        kept: input::kept_seeds(points, config, options.out_of_domain),
+        first_halo: ((2.0 * cell) as usize).max(MIN_HALO),
+    };
+
+    let adapters = if options.multi_gpu {
+        adapter::select_adapters(&options.adapter)
+    } else {
+        vec![adapter::select_adapter(&options.adapter).await?]
+    };
+    if adapters.is_empty() {
+        return Err(MesherError::NoAdapter);
+    }
+    let first_tile = (
+        (tile_size + 2 * job.first_halo).min(width),
+        (tile_size + 2 * job.first_halo).min(height),
+    );
+    let mut contexts = Vec::new();
+    for adapter in &adapters {
+        contexts.push(WgpuContext::on_adapter(adapter, first_tile, POINT_SIZE).await?);
+    }
+
+    let tiles = tiles(dimensions, tile_size);
+    let labels = RefCell::new(vec![0; width * height]);
+    let next = Cell::new(0);
+    let completed = Cell::new(0);
+    let workers = contexts.iter_mut().map(|context| {
+        let (job, tiles, labels, next, completed) = (&job, &tiles, &labels, &next, &completed);
+        Box::pin(async move {
+            while next.get() < tiles.len() {
+                if let Some(token) = &options.cancellation {
+                    if token.is_cancelled() {
+                        return Err(MesherError::Cancelled {
+                            completed: completed.get(),
+                            total: tiles.len(),
+                        });
+                    }
+                }
+                let core = &tiles[next.get()];
+                next.set(next.get() + 1);
+
+                let tile_labels = label_tile(context, core, job).await?;
+                let mut grid = labels.borrow_mut();
+                for (y, row) in core.1.clone().zip(tile_labels.chunks(core.0.len())) {
+                    grid[core.0.start + y * width..core.0.end + y * width].copy_from_slice(row);
+                }
+                completed.set(completed.get() + 1);
+                if let Some(progress) = &options.progress {
+                    progress.report(Progress::Tile {
+                        tile: completed.get(),
+                        tiles: tiles.len(),
+                    });
+                }
+            }
+            Ok(())
+        }) as Pin<Box<dyn Future<Output = Result<(), MesherError>> + '_>>
+    });
+    TryJoinAll(workers.map(Some).collect()).await?;
+
+    Ok(labels.into_inner())
+}
+
+// Labels of the texels of `core`, row by row, widening the halo until they are all settled
+async fn label_tile(
+    context: &mut WgpuContext,
+    core: &Tile,
+    job: &Job<'_>,
+) -> Result<Vec<u32>, MesherError> {
+    let (width, height) = job.dimensions;
+    let mut halo = job.first_halo;
+    loop {
+        let outer = (
+            core.0.start.saturating_sub(halo)..(core.0.end + halo).min(width),
+            core.1.start.saturating_sub(halo)..(core.1.end + halo).min(height),
+        );
+        let whole_grid = outer.0.len() == width && outer.1.len() == height;
+        let outer_dimensions = (outer.0.len(), outer.1.len());
+        if !whole_grid && grid_size(outer_dimensions) as u64 > max_binding_size() {
+            return Err(MesherError::InvalidInput(
+                "the seeds are too sparse for the tile size",
+            ));
+        }
+
+        let local: Vec<usize> = (0..job.seeds.len())
+            .filter(|&i| {
+                let (x, y) = job.seeds[i];
+                job.kept[i] && outer.0.contains(&(x as usize)) && outer.1.contains(&(y as usize))
+            })
+            .collect();
+        if local.is_empty() {
+            halo *= 2;
+            continue;
+        }
+
+        // The tile's domain is measured in texels, so the seeds land on the same texels as on
+        // the whole grid
+        let local_points: Vec<(f64, f64)> = local
+            .iter()
+            .map(|&i| {
+                let (x, y) = job.positions[i];
+                (x - outer.0.start as f64, y - outer.1.start as f64)
+            })
+            .collect();
+        let local_config = (outer_dimensions.0 as f64, outer_dimensions.1 as f64);
+        let tile_options = MesherConfig {
+            resolution: outer_dimensions.0.max(outer_dimensions.1),
+            grid: GridShape::FitDomain,
+            debug_dump: None,
+            progress: None,
+            ..job.options.clone()
+        };
+        check_budget(&local_points, local_config, &tile_options)?;
+
+        context.reserve(outer_dimensions, points_size(&local_points));
+        context.prepare(job.options)?;
+        let (tile_labels, _) =
+            run_with_context(context, &local_points, &[], local_config, &tile_options).await?;
+
+        let settled = settled(
+            &tile_labels,
+            &outer,
+            core,
+            &job.positions,
+            &local,
+            job.dimensions,
+            job.options,
+        );
+        if whole_grid || settled {
+            let mut labels = Vec::with_capacity(core.0.len() * core.1.len());
+            for y in core.1.clone() {
+                for x in core.0.clone() {
+                    let label =
+                        tile_labels[(x - outer.0.start) + (y - outer.1.start) * outer_dimensions.0];
+                    labels.push(match label {
+                        0 => 0,
+                        _ => local[label as usize - 1] as u32 + 1,
+                    });
+                }
+            }
+            return Ok(labels);
+        }
+        log::info!("tile unsettled with a halo of {halo} texels");
+        halo *= 2;
+    }
+}
+
+// Polls every future in turn until they are all done or one of them fails
+struct TryJoinAll<F>(Vec<Option<F>>);
+
+impl<F: Future<Output = Result<(), MesherError>> + Unpin> Future for TryJoinAll<F> {
+    type Output = Result<(), MesherError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        for slot in self.0.iter_mut() {
+            if let Some(future) = slot {
+                match Pin::new(future).poll(cx) {
+                    Poll::Ready(Ok(())) => *slot = None,
+                    Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                    Poll::Pending => {}
+                }
+            }
+        }
+        if self.0.iter().all(Option::is_none) {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+// Tiles of at most `size`×`size` texels covering the grid, row by row
+fn tiles((width, height): (usize, usize), size: usize) -> Vec<Tile> {
+    let ranges = |length: usize| {
+        (0..length)
+            .step_by(size)
+            .map(move |start| start..(start + size).min(length))
+    };
+    ranges(height)
+        .flat_map(|ys| ranges(width).map(move |xs| (xs, ys.clone())))
+        .collect()
+}
+
+// Whether no seed beyond `outer` can be nearer to a texel of `core` than the one it got in
+// `labels`, the labels of `outer` over the seeds `local`. A seed beyond an edge of `outer`
+// which isn't an edge of the grid is farther from the texel's center along that axis than the
+// edge, and so at least as far whatever the norm
+fn settled(
+    labels: &[u32],
+    outer: &Tile,
+    core: &Tile,
+    positions: &[(f64, f64)],
+    local: &[usize],
+    (width, height): (usize, usize),
+    options: &MesherConfig,
+) -> bool {
+    let stretch = smallest_stretch(options.metric);
+    core.1.clone().all(|y| {
+        core.0.clone().all(|x| {
+            let label = labels[(x - outer.0.start) + (y - outer.1.start) * outer.0.len()];
+            if label == 0 {
+                return false;
+            }
+            let (seed_x, seed_y) = positions[local[label as usize - 1]];
+            let (x, y) = (x as f64 + 0.5, y as f64 + 0.5);
+            let distance = squared_norm(options.distance, options.metric, x - seed_x, y - seed_y);
+
+            let mut gap = f64::INFINITY;
+            if outer.0.start > 0 {
+                gap = gap.min(x - outer.0.start as f64);
+            }
+            if outer.0.end < width {
+                gap = gap.min(outer.0.end as f64 - x);
+            }
+            if outer.1.start > 0 {
+                gap = gap.min(y - outer.1.start as f64);
+            }
+            if outer.1.end < height {
+                gap = gap.min(outer.1.end as f64 - y);
+            }
+            distance <= stretch * gap * gap
+        })
+    })
+}
+
+// Squared length of an offset in texels, as the shader measures it
+fn squared_norm(distance: DistanceMetric, metric: Metric, dx: f64, dy: f64) -> f64 {
+    let norm = match distance {
+        DistanceMetric::Euclidean => {
+            return metric.xx * dx * dx + 2.0 * metric.xy * dx * dy + metric.yy * dy * dy
+        }
+        DistanceMetric::Manhattan => dx.abs() + dy.abs(),
+        DistanceMetric::Chebyshev => dx.abs().max(dy.abs()),
+        DistanceMetric::Minkowski(p) => (dx.abs().powf(p) + dy.abs().powf(p)).powf(1.0 / p),
+    };
+    norm * norm
+}
+
+// Smallest eigenvalue of the metric tensor, by which it shrinks squared lengths at most. Other
+// norms are never shorter than the largest of `|dx|` and `|dy|`
+fn smallest_stretch(metric: Metric) -> f64 {
+    let mean = (metric.xx + metric.yy) / 2.0;
+    let spread = ((metric.xx - metric.yy) / 2.0).hypot(metric.xy);
+    (mean - spread).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tiles_cover_grid() {
+        let tiles = tiles((5, 3), 2);
+
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(tiles[0], (0..2, 0..2));
+        assert_eq!(tiles[2], (4..5, 0..2));
+        assert_eq!(tiles[5], (4..5, 2..3));
+        let texels: usize = tiles.iter().map(|(xs, ys)| xs.len() * ys.len()).sum();
+        assert_eq!(texels, 15);
+    }
+
+    #[test]
+    fn test_settled() {
+        let options = MesherConfig::default();
+        let core = (2..4, 0..1);
+
+        // Texel 2 is 1 texel from its seed and 3.5 from the right edge of the halo
+        let outer = (0..6, 0..1);
+        assert!(settled(
+            &[1; 6],
+            &outer,
+            &core,
+            &[(3.5, 0.5)],
+            &[0],
+            (8, 1),
+            &options
+        ));
+        // Texel 3 is 3 texels from its seed, and a seed beyond the halo could be 1.5 away
+        let outer = (0..5, 0..1);
+        assert!(!settled(
+            &[1; 5],
+            &outer,
+            &core,
+            &[(0.5, 0.5)],
+            &[0],
+            (8, 1),
+            &options
+        ));
+    }
+}
@@ -0,0 +1,243 @@
+// Live view of a diagram: a render pass draws the labels buffer of a mesher straight into a
+// texture view of the caller, a window surface or an offscreen target, so debugging and
+// interactive tools don't read the grid back.
+
+use super::{WgpuContext, DISTANCE_SHADER, PARAMS_SIZE};
+
+/// What [`LabelView`] draws over the cell colors, both in texels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewOptions {
+    /// Radius of the dots drawn at the seeds, 0 for none
+    pub seed_radius: f32,
+    /// Spacing of the iso-lines of the distance to the seed, 0 for none
+    pub iso_spacing: f32,
+}
+
+impl Default for ViewOptions {
+    fn default() -> Self {
+        ViewOptions {
+            seed_radius: 1.5,
+            iso_spacing: 0.0,
+        }
+    }
+}
+
+/// Render pipeline drawing the labels of a [`super::Mesher`] into targets of one texture
+/// format, with [`super::Mesher::draw_labels`]. It must be created on the mesher's device.
+pub struct LabelView {
+    pipeline: wgpu::RenderPipeline,
+    view_buffer: wgpu::Buffer,
+}
+
+impl LabelView {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> LabelView {
+        let source = [DISTANCE_SHADER, include_str!("view.wgsl")].join("\n");
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: None,
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vertex_main"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fragment_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+        let view_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 4 * std::mem::size_of::<f32>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        LabelView {
+            pipeline,
+            view_buffer,
+        }
+    }
+}
+
+// Draws `labels`, a grid of the context's dimensions labeled with the seeds and parameters of
+// the context's last run, over the whole of `target`, `(width, height)` pixels
+pub(super) fn draw(
+    view: &LabelView,
+    context: &WgpuContext,
+    labels: &wgpu::Buffer,
+    target: &wgpu::TextureView,
+    (width, height): (u32, u32),
+    options: &ViewOptions,
+) {
+    let device = &context.device;
+    let uniform = [
+        width as f32,
+        height as f32,
+        options.seed_radius,
+        options.iso_spacing,
+    ];
+    context
+        .queue
+        .write_buffer(&view.view_buffer, 0, bytemuck::cast_slice(&uniform));
+
+    // The parameters of the first pass hold the grid and the norm, its step is not read
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &view.pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: labels.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &context.params_buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(PARAMS_SIZE as u64),
+                }),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: context.normal_points.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: view.view_buffer.as_entire_binding(),
+            },
+        ],
+    });
+
+    let mut command_encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    {
+        let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(&view.pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+    context.queue.submit(Some(command_encoder.finish()));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::config::MesherConfig;
+    use crate::jfa_wgpu::{block_on, poll_until, Mesher};
+    use crate::test_util::gpu_or_skip;
+
+    // Width of the target, 256 bytes per row as texture copies require
+    const SIZE: u32 = 64;
+
+    // Pixels of `texture`, row by row from the top
+    fn read_pixels(
+        device: &Arc<wgpu::Device>,
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+    ) -> Vec<[u8; 4]> {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (SIZE * SIZE * 4) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut command_encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        command_encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(SIZE * 4),
+                    rows_per_image: None,
+                },
+            },
+            texture.size(),
+        );
+        queue.submit(Some(command_encoder.finish()));
+        let slice = buffer.slice(..);
+        let (sender, receiver) = flume::bounded(1);
+        slice.map_async(wgpu::MapMode::Read, move |r| {
+            let _ = sender.send(r);
+        });
+        block_on(poll_until(device, &receiver)).unwrap().unwrap();
+        let pixels = bytemuck::cast_slice(&slice.get_mapped_range()[..]).to_vec();
+        buffer.unmap();
+        pixels
+    }
+
+    #[test]
+    fn test_draw_labels() {
+        // Two cells of an 8 x 8 grid, drawn 8 pixels per texel
+        let options = MesherConfig {
+            resolution: 8,
+            ..Default::default()
+        };
+        let Some(mut mesher) = gpu_or_skip(block_on(Mesher::new(options))) else {
+            return;
+        };
+        let (device, queue) = (mesher.device().clone(), mesher.queue().clone());
+        let format = wgpu::TextureFormat::Rgba8Unorm;
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: SIZE,
+                height: SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let target = texture.create_view(&Default::default());
+        let view = LabelView::new(&device, format);
+        let options = ViewOptions::default();
+
+        assert!(!mesher.draw_labels(&view, &target, (SIZE, SIZE), &options));
+        block_on(mesher.run(&[(2.0, 4.0), (6.0, 2.0)], (8.0, 8.0))).unwrap();
+        assert!(mesher.draw_labels(&view, &target, (SIZE, SIZE), &options));
+        let pixels = read_pixels(&device, &queue, &texture);
+
+        // Pixel rows from the top, texel rows from the bottom
+        let pixel = |(x, y): (f64, f64)| {
+            let (x, y) = ((x * 8.0) as u32, SIZE - 1 - (y * 8.0) as u32);
+            pixels[(x + y * SIZE) as usize]
+        };
+        let rgb = |color: (u8, u8, u8)| [color.0, color.1, color.2, 255];
+        let near = |a: [u8; 4], b: [u8; 4]| a.iter().zip(b).all(|(&a, b)| a.abs_diff(b) <= 2);
+        assert!(near(pixel((0.5, 7.5)), rgb(crate::render::cell_color(0))));
+        assert!(near(pixel((7.5, 7.5)), rgb(crate::render::cell_color(1))));
+        assert_eq!(pixel((2.0, 4.0)), [0, 0, 0, 255]);
+        assert_eq!(pixel((6.0, 2.0)), [0, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,53 @@
+// Label grid drawn into a render target, appended to the distance shader: a color per cell,
+// seeds as dots and iso-lines of the distance to the seed. Row 0 is at the bottom of the target
+// so the y axis points up as in the domain
+@group(0) @binding(0) var<storage, read> labels: array<u32>;
+@group(0) @binding(1) var<uniform> params: Params;
+// Target width and height in pixels, then seed radius and iso-line spacing in texels, 0 for none
+@group(0) @binding(3) var<uniform> view: vec4<f32>;
+
+// Covers the target with a single triangle
+@vertex
+fn vertex_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let corner = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(corner * 2.0 - 1.0, 0.0, 1.0);
+}
+
+// Color of cell `index`, spreading hues by the golden angle as the SVG and PNG drawings do
+fn cell_color(index: u32) -> vec3<f32> {
+    let hue = (f32(index) * 137.507764) % 360.0;
+    let chroma = 0.9 * 0.55;
+    let x = chroma * (1.0 - abs((hue / 60.0) % 2.0 - 1.0));
+    var color: vec3<f32>;
+    switch u32(hue / 60.0) {
+        case 0u: { color = vec3<f32>(chroma, x, 0.0); }
+        case 1u: { color = vec3<f32>(x, chroma, 0.0); }
+        case 2u: { color = vec3<f32>(0.0, chroma, x); }
+        case 3u: { color = vec3<f32>(0.0, x, chroma); }
+        case 4u: { color = vec3<f32>(x, 0.0, chroma); }
+        default: { color = vec3<f32>(chroma, 0.0, x); }
+    }
+    return color + (0.9 - chroma);
+}
+
+@fragment
+fn fragment_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let x = min(u32(position.x * f32(params.width) / view.x), params.width - 1u);
+    let row = min(u32(position.y * f32(params.height) / view.y), params.height - 1u);
+    let y = params.height - 1u - row;
+
+    let label = labels[x + y * params.width];
+    if label == 0u {
+        return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+    }
+    var color = cell_color(label - 1u);
+    let distance = seed_distance(x, y, label);
+    // Lines a texel wide every spacing
+    if view.w > 0.0 && distance % view.w < 1.0 {
+        color = color * 0.6;
+    }
+    if distance < view.z {
+        color = vec3<f32>(0.0, 0.0, 0.0);
+    }
+    return vec4<f32>(color, 1.0);
+}
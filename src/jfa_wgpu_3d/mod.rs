@@ -0,0 +1,328 @@
+// Jump flooding on a 3D voxel grid, the polyhedral counterpart of `jfa_wgpu`. The grid is cubic
+// and its side is given by the caller, since a 512³ grid is well past most devices' limits. The
+// seeds are checked as `jfa_wgpu` checks them, those outside the box being rejected, and the
+// device comes from a `jfa_wgpu` context, with its adapter selection and error capture. Domains
+// other than the box are masked afterwards with an SDF, as `domain::mask_labels` does in 2D.
+// Large domains whose cells vary in size can be labeled leaf by leaf of a `SizingOctree`
+// instead, at a resolution following the target sizes.
+
+use std::collections::HashMap;
+
+use wgpu::util::DeviceExt;
+
+use crate::error::MesherError;
+use crate::jfa_wgpu::{get_data, jfa_steps, AdapterSelection, WgpuContext, MAX_SEEDS, POINT_SIZE};
+
+const WORKGROUP_SIZE: u32 = 4;
+
+/// Labels of the `reso`³ voxels of the `extent` box, indexed with
+/// `x + y * reso + z * reso * reso`, 0 being unassigned and seed `i` having label `i + 1`, on a
+/// device of the `adapter` GPU. The grid must fit in one storage buffer.
+pub async fn run(
+    points: &[(f64, f64, f64)],
+    extent: (f64, f64, f64),
+    reso: usize,
+    adapter: &AdapterSelection,
+) -> Result<Vec<u32>, MesherError> {
+    if reso == 0 {
+        return Err(MesherError::InvalidInput("resolution must be at least 1"));
+    }
+    validate_seeds(points, extent, reso)?;
+    let grid_size = reso
+        .checked_pow(3)
+        .and_then(|voxels| voxels.checked_mul(std::mem::size_of::<u32>()))
+        .ok_or(MesherError::InvalidInput("the voxel grid is too large"))?;
+
+    let context = WgpuContext::new((1, 1), POINT_SIZE, adapter).await?;
+    let (device, queue) = (&context.device, &context.queue);
+    let limits = device.limits();
+    if grid_size as u64 > limits.max_storage_buffer_binding_size as u64
+        || grid_size as u64 > limits.max_buffer_size
+    {
+        return Err(MesherError::InvalidInput(
+            "the voxel grid is larger than the device's storage buffers",
+        ));
+    }
+
+    let mut labels = vec![0u32; reso * reso * reso];
+    let normal_points = init_normal_points(points, extent, reso);
+    for (i, &(x, y, z)) in normal_points.iter().enumerate() {
+        labels[x as usize + y as usize * reso + z as usize * reso * reso] = i as u32 + 1;
+    }
+
+    let shader = device.create_shader_module(wgpu::include_wgsl!("shader.wgsl"));
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    let storage_buffers = [0, 1].map(|_| {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&labels),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        })
+    });
+    let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: storage_buffers[0].size(),
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let flat_points: Vec<u32> = normal_points
+        .iter()
+        .flat_map(|&(x, y, z)| [x, y, z])
+        .collect();
+    let points_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: None,
+        contents: bytemuck::cast_slice(&flat_points),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+
+    // One small uniform and bind group per pass, so the cascade is a single submission
+    let steps = jfa_steps(reso);
+    let params_buffers: Vec<wgpu::Buffer> = steps
+        .iter()
+        .map(|&step| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: bytemuck::cast_slice(&[step, reso as u32]),
+                usage: wgpu::BufferUsages::UNIFORM,
+            })
+        })
+        .collect();
+
+    let layout = pipeline.get_bind_group_layout(0);
+    let mut command_encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    for (pass, params_buffer) in params_buffers.iter().enumerate() {
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: storage_buffers[pass % 2].as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: points_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: storage_buffers[1 - pass % 2].as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut compute_pass = command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&pipeline);
+        compute_pass.set_bind_group(0, &bind_group, &[]);
+        let count = (reso as u32).div_ceil(WORKGROUP_SIZE);
+        compute_pass.dispatch_workgroups(count, count, count);
+    }
+    queue.submit(Some(command_encoder.finish()));
+
+    let mapped = get_data(
+        &mut labels,
+        &storage_buffers[steps.len() % 2],
+        &staging_buffer,
+        device,
+        queue,
+    )
+    .await;
+    if let Some(err) = context.take_error() {
+        return Err(err);
+    }
+    mapped.map_err(|err| MesherError::BufferMapFailed(err.to_string()))?;
+
+    Ok(labels)
+}
+
+/// Same as [`run`], then unassigning every voxel whose center is outside the domain of `sdf`,
+/// negative inside, e.g. built with [`crate::implicit::solid`].
+pub async fn run_masked(
+    points: &[(f64, f64, f64)],
+    extent: (f64, f64, f64),
+    reso: usize,
+    adapter: &AdapterSelection,
+    sdf: impl Fn(f64, f64, f64) -> f64,
+) -> Result<Vec<u32>, MesherError> {
+    let mut labels = run(points, extent, reso, adapter).await?;
+    mask_voxels(&mut labels, reso, extent, sdf);
+    Ok(labels)
+}
+
+/// Sets the label of every voxel whose center lies outside the domain of `sdf` to 0, the
+/// unassigned color. `labels` is a grid of `reso`³ voxels covering the `extent` box.
+pub fn mask_voxels(
+    labels: &mut [u32],
+    reso: usize,
+    extent: (f64, f64, f64),
+    sdf: impl Fn(f64, f64, f64) -> f64,
+) {
+    assert_eq!(labels.len(), reso * reso * reso, "voxel grid size mismatch");
+    let center = |i: usize, side: f64| (i as f64 + 0.5) * side / reso as f64;
+    for (i, label) in labels.iter_mut().enumerate() {
+        let (x, y, z) = (i % reso, i / reso % reso, i / (reso * reso));
+        if sdf(
+            center(x, extent.0),
+            center(y, extent.1),
+            center(z, extent.2),
+        ) > 0.0
+        {
+            *label = 0;
+        }
+    }
+}
+
+// The checks of `jfa_wgpu` in a box with a depth, under `OutOfDomain::Reject`
+fn validate_seeds(
+    points: &[(f64, f64, f64)],
+    extent: (f64, f64, f64),
+    reso: usize,
+) -> Result<(), MesherError> {
+    let (width, height, depth) = extent;
+    if ![width, height, depth]
+        .iter()
+        .all(|side| *side > 0.0 && side.is_finite())
+    {
+        return Err(MesherError::InvalidInput(
+            "the domain must have a finite, positive width, height and depth",
+        ));
+    }
+    if points.is_empty() {
+        return Err(MesherError::NoSeeds);
+    }
+    if points.len() > MAX_SEEDS {
+        return Err(MesherError::TooManySeeds {
+            count: points.len(),
+        });
+    }
+    // NaN coordinates are in no range
+    let outside = |&(x, y, z): &(f64, f64, f64)| {
+        !((0.0..=width).contains(&x) && (0.0..=height).contains(&y) && (0.0..=depth).contains(&z))
+    };
+    if let Some(index) = points.iter().position(outside) {
+        return Err(MesherError::SeedOutsideDomain { index });
+    }
+
+    let mut voxels = HashMap::with_capacity(points.len());
+    for (second, voxel) in init_normal_points(points, extent, reso)
+        .into_iter()
+        .enumerate()
+    {
+        if let Some(first) = voxels.insert(voxel, second) {
+            return Err(MesherError::CoincidentSeeds { first, second });
+        }
+    }
+    Ok(())
+}
+
+fn init_normal_points(
+    points: &[(f64, f64, f64)],
+    extent: (f64, f64, f64),
+    reso: usize,
+) -> Vec<(u32, u32, u32)> {
+    let quantize =
+        |value: f64, side: f64| (value * reso as f64 / side).min(reso as f64 - 1.0) as u32;
+    points
+        .iter()
+        .map(|&(x, y, z)| {
+            (
+                quantize(x, extent.0),
+                quantize(y, extent.1),
+                quantize(z, extent.2),
+            )
+        })
+        .collect()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn main(
+    points: &[(f64, f64, f64)],
+    extent: (f64, f64, f64),
+    reso: usize,
+    adapter: &AdapterSelection,
+) -> Result<Vec<usize>, MesherError> {
+    let labels = crate::jfa_wgpu::block_on(run(points, extent, reso, adapter))?;
+
+    Ok(labels.into_iter().map(|x| x as usize).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::gpu_or_skip;
+
+    #[test]
+    fn test_validate_seeds() {
+        let validate = |points: &[(f64, f64, f64)]| validate_seeds(points, (4.0, 4.0, 2.0), 8);
+
+        assert_eq!(validate(&[(1.0, 1.0, 1.0), (4.0, 4.0, 2.0)]), Ok(()));
+        assert_eq!(validate(&[]), Err(MesherError::NoSeeds));
+        assert_eq!(
+            validate(&[(1.0, 1.0, 1.0), (1.0, 1.0, 2.5)]),
+            Err(MesherError::SeedOutsideDomain { index: 1 })
+        );
+        // Both in voxel (2, 2, 4)
+        assert_eq!(
+            validate(&[(1.1, 1.1, 1.1), (1.4, 1.2, 1.2)]),
+            Err(MesherError::CoincidentSeeds {
+                first: 0,
+                second: 1
+            })
+        );
+        assert!(matches!(
+            validate_seeds(&[(1.0, 1.0, 1.0)], (4.0, 0.0, 2.0), 8),
+            Err(MesherError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_mask_voxels() {
+        let mut labels = vec![1; 4 * 4 * 4];
+
+        mask_voxels(
+            &mut labels,
+            4,
+            (4.0, 4.0, 4.0),
+            crate::implicit::solid::sphere((2.0, 2.0, 2.0), 1.0),
+        );
+
+        // The 8 voxels around the center, at sqrt(3) / 2 from it
+        assert_eq!(labels.iter().filter(|&&label| label == 1).count(), 8);
+        assert_eq!(labels[1 + 4 + 16], 1);
+        assert_eq!(labels[0], 0);
+    }
+
+    #[test]
+    fn test_run() {
+        // Seeds in voxels 1 and 6 of their row split the 8³ grid between x = 3 and x = 4
+        let points = [(1.0, 4.0, 4.0), (6.0, 4.0, 4.0)];
+        let Some(labels) = gpu_or_skip(main(
+            &points,
+            (8.0, 8.0, 8.0),
+            8,
+            &AdapterSelection::default(),
+        )) else {
+            return;
+        };
+
+        assert_eq!(labels.len(), 512);
+        for (i, &label) in labels.iter().enumerate() {
+            assert_eq!(label, if i % 8 < 4 { 1 } else { 2 }, "voxel {i}");
+        }
+    }
+}
@@ -0,0 +1,56 @@
+// 3D jump flooding over a reso³ voxel grid, indexed x + y * reso + z * reso * reso
+@group(0) @binding(0) var<storage, read> input_grid: array<u32>;
+@group(0) @binding(1) var<uniform> params: Params;
+@group(0) @binding(2) var<storage, read> normal_points: array<u32>;
+@group(0) @binding(3) var<storage, read_write> output_grid: array<u32>;
+
+struct Params {
+    step: u32,
+    reso: u32,
+}
+
+fn distance_squared(voxel: vec3<i32>, color: u32) -> i32 {
+    let seed = vec3<i32>(
+        i32(normal_points[(color - 1) * 3]),
+        i32(normal_points[(color - 1) * 3 + 1]),
+        i32(normal_points[(color - 1) * 3 + 2]),
+    );
+    let d = voxel - seed;
+    return dot(d, d);
+}
+
+@compute @workgroup_size(4, 4, 4)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let reso = i32(params.reso);
+    let step = i32(params.step);
+    let voxel = vec3<i32>(global_id);
+
+    if any(voxel >= vec3<i32>(reso)) {
+        return;
+    }
+
+    let index = u32(voxel.x + voxel.y * reso + voxel.z * reso * reso);
+    var current_color = input_grid[index];
+
+    for (var dz = -1; dz <= 1; dz = dz + 1) {
+        for (var dy = -1; dy <= 1; dy = dy + 1) {
+            for (var dx = -1; dx <= 1; dx = dx + 1) {
+                let other = voxel + vec3<i32>(dx, dy, dz) * step;
+                if any(other < vec3<i32>(0)) || any(other >= vec3<i32>(reso)) {
+                    continue;
+                }
+
+                let found_color = input_grid[u32(other.x + other.y * reso + other.z * reso * reso)];
+                if found_color == 0 || found_color == current_color {
+                    continue;
+                }
+                if current_color == 0
+                    || distance_squared(voxel, found_color) < distance_squared(voxel, current_color) {
+                    current_color = found_color;
+                }
+            }
+        }
+    }
+
+    output_grid[index] = current_color;
+}
@@ -1,15 +1,65 @@
+pub mod backend;
+#[cfg(feature = "bevy")]
+pub mod bevy_mesh;
+pub mod cells;
+pub mod cleanup;
+// The command-line pipeline plots and writes files, which browsers don't allow
+#[cfg(not(target_arch = "wasm32"))]
 pub mod cli;
+pub mod config;
+pub mod debug;
+pub mod domain;
+pub mod error;
+pub mod exact;
+pub mod extrema;
+pub mod extrude;
+#[cfg(feature = "geo")]
+pub mod geo;
+pub mod gpu;
+pub mod grain;
+pub mod graph;
+pub mod hex;
+pub mod implicit;
+pub mod io;
 pub mod jfa_cpu;
 pub mod jfa_wgpu;
+pub mod jfa_wgpu_3d;
+pub mod mesh;
+#[cfg(not(target_arch = "wasm32"))]
 mod mode1;
+#[cfg(not(target_arch = "wasm32"))]
 mod mode2;
+#[cfg(not(target_arch = "wasm32"))]
 mod mode3;
+pub mod octree;
+#[cfg(not(target_arch = "wasm32"))]
 mod plot;
+pub mod progress;
+pub mod quality;
+pub mod reference;
+pub mod remesh;
+pub mod render;
+pub mod seeds;
+pub mod symmetry;
+#[cfg(test)]
+mod test_util;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
 
-use std::fs::File;
-use std::io::Write;
-
-pub fn generate_points(cli: &cli::Cli) -> Result<Vec<(f64, f64)>, &'static str> {
+#[cfg(not(target_arch = "wasm32"))]
+pub fn generate_points(cli: &cli::Cli) -> Result<Vec<(f64, f64)>, String> {
+    if let Some(ref seeds_path) = cli.seeds {
+        let seeds = io::seeds::read_seeds(seeds_path)
+            .map_err(|err| format!("{}: {err}", seeds_path.display()))?;
+        // Meshing the seeds without their weights would give another diagram
+        if seeds.weights.is_some() {
+            return Err(format!(
+                "{}: weighted seeds can't be meshed here, use ppm",
+                seeds_path.display()
+            ));
+        }
+        return Ok(seeds.points);
+    }
     match cli.mode {
         cli::Mode::GridWithN => Ok(mode1::generate_points(
             cli.n,
@@ -21,24 +71,32 @@ pub fn generate_points(cli: &cli::Cli) -> Result<Vec<(f64, f64)>, &'static str>
     }
 }
 
-pub fn generate_cells(points: &[(f64, f64)], cli: &cli::Cli) -> Result<Vec<usize>, &'static str> {
-    match cli.jfa_mode {
-        cli::JfaMode::None => Ok(vec![]),
-        cli::JfaMode::Gpu => {
-            println!("Generating cells using GPU with resolution {}...", cli.res);
-            jfa_wgpu::main(points, (cli.x, cli.y))
-        }
-        cli::JfaMode::Cpu => {
-            println!("Generating cells using CPU with resolution {}...", cli.res);
-            jfa_cpu::jfa(points, (cli.x, cli.y))
-        }
+#[cfg(not(target_arch = "wasm32"))]
+pub fn generate_cells(
+    points: &[(f64, f64)],
+    cli: &cli::Cli,
+) -> Result<Vec<usize>, error::MesherError> {
+    if cli.jfa_mode == cli::JfaMode::None {
+        return Ok(vec![]);
     }
+    let options = cli::mesher_config(cli);
+    let backend = backend::from_config(&options)?;
+    println!(
+        "Generating cells using {} with resolution {}...",
+        backend.name(),
+        cli.res
+    );
+    let labeling = backend.labels(points, &[], (cli.x, cli.y), &options)?;
+    Ok(labeling.labels)
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 pub fn handle_output(cli: &cli::Cli, points: &Vec<(f64, f64)>, pixels: Option<&Vec<usize>>) {
+    use std::io::Write;
+
     // Export points to a CSV file if specified
     if let Some(ref export_path) = cli.export {
-        let mut file = File::create(export_path).expect("Unable to create file");
+        let mut file = std::fs::File::create(export_path).expect("Unable to create file");
         for (x, y) in points {
             file.write_all(format!("{},{}\n", x, y).as_bytes())
                 .expect("Unable to write data");
@@ -52,6 +110,30 @@ pub fn handle_output(cli: &cli::Cli, points: &Vec<(f64, f64)>, pixels: Option<&V
     }
 
     if let Some(pixels) = pixels {
+        if cli.cross_check && !pixels.is_empty() {
+            println!("Cross-checking cells against the exact diagram...");
+            let options = cli::mesher_config(cli);
+            match exact::cross_check(pixels, points, (cli.x, cli.y), &options, 0.05) {
+                Ok(report) => println!("{report}"),
+                Err(err) => println!("Problem cross-checking cells: {err}"),
+            }
+        }
+
+        if cli.quality && !pixels.is_empty() {
+            println!("Measuring the quality of the cells...");
+            let options = cli::mesher_config(cli);
+            let dimensions = options.grid_dimensions((cli.x, cli.y));
+            let polygons = cells::extract_cells(
+                pixels,
+                dimensions,
+                points.len(),
+                (cli.x, cli.y),
+                options.boundary,
+            );
+            let mesh = mesh::PolyMesh::from_cells(&polygons);
+            print!("{}", quality::quality_report(&mesh, 10));
+        }
+
         if matches!(cli.plot, cli::PlotMode::Jfa) {
             println!("Plotting cells...");
             plot::plot_heatmap_with_points(pixels, points, (cli.x, cli.y));
@@ -1,6 +1,7 @@
 use blue_noise::*;
 
 fn main() {
+    env_logger::init();
     let cli = cli::parse();
     cli::print_config(&cli);
 
@@ -0,0 +1,463 @@
+// Half-edge (DCEL) mesh of the cells, the form the exporters and the post-processing passes work
+// on. It is built from counter-clockwise polygons, from `cells::extract_cells` or clipped, whose
+// shared vertices are bitwise equal, and an edge between two cells is a pair of twin half-edges.
+// Edges on the domain boundary, or around a masked-out region, are a single half-edge without
+// twin, and there is no outer face.
+
+use std::collections::BTreeMap;
+
+/// Directed edge of a face, running counter-clockwise around it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HalfEdge {
+    pub origin: usize,
+    /// Half-edge running the other way in the adjacent face, `None` on a boundary
+    pub twin: Option<usize>,
+    pub next: usize,
+    pub prev: usize,
+    pub face: usize,
+}
+
+/// Cell of a seed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Face {
+    pub seed: usize,
+    /// Any half-edge of the face
+    pub half_edge: usize,
+}
+
+/// Half-edge mesh with one face per non-empty cell.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PolyMesh {
+    pub vertices: Vec<(f64, f64)>,
+    pub half_edges: Vec<HalfEdge>,
+    pub faces: Vec<Face>,
+    /// An outgoing half-edge of each vertex, one without twin on a boundary, so turning around
+    /// the vertex from it meets all the faces
+    vertex_edges: Vec<usize>,
+}
+
+impl PolyMesh {
+    /// Skips the empty cells, of seeds which have no texel.
+    pub fn from_cells(cells: &[Vec<(f64, f64)>]) -> PolyMesh {
+        let mut indices: BTreeMap<(u64, u64), usize> = BTreeMap::new();
+        let mut vertices = Vec::new();
+        let mut polygons = Vec::new();
+        let mut seeds = Vec::new();
+        for (seed, cell) in cells.iter().enumerate() {
+            if cell.is_empty() {
+                continue;
+            }
+            let polygon = cell
+                .iter()
+                .map(|&(x, y)| {
+                    // Junctions shared by neighboring cells are computed from the same lattice
+                    // point, so their coordinates are bitwise equal
+                    *indices
+                        .entry((x.to_bits(), y.to_bits()))
+                        .or_insert_with(|| {
+                            vertices.push((x, y));
+                            vertices.len() - 1
+                        })
+                })
+                .collect();
+            polygons.push(polygon);
+            seeds.push(seed);
+        }
+        PolyMesh::from_polygons(vertices, &polygons, &seeds)
+    }
+
+    /// Faces from counter-clockwise polygons indexing into `vertices`, polygon `i` being the
+    /// cell of `seeds[i]`. Vertices no polygon uses are dropped.
+    pub fn from_polygons(
+        vertices: Vec<(f64, f64)>,
+        polygons: &[Vec<usize>],
+        seeds: &[usize],
+    ) -> PolyMesh {
+        let mut indices = vec![usize::MAX; vertices.len()];
+        let mut used = Vec::new();
+        for &v in polygons.iter().flatten() {
+            if indices[v] == usize::MAX {
+                indices[v] = used.len();
+                used.push(vertices[v]);
+            }
+        }
+        let vertices = used;
+
+        let mut half_edges = Vec::new();
+        let mut faces = Vec::with_capacity(polygons.len());
+        for (face, (polygon, &seed)) in polygons.iter().zip(seeds).enumerate() {
+            let first = half_edges.len();
+            let n = polygon.len();
+            faces.push(Face {
+                seed,
+                half_edge: first,
+            });
+            half_edges.extend((0..n).map(|i| HalfEdge {
+                origin: indices[polygon[i]],
+                twin: None,
+                next: first + (i + 1) % n,
+                prev: first + (i + n - 1) % n,
+                face,
+            }));
+        }
+
+        let ends: BTreeMap<(usize, usize), usize> = (0..half_edges.len())
+            .map(|h| {
+                (
+                    (half_edges[h].origin, half_edges[half_edges[h].next].origin),
+                    h,
+                )
+            })
+            .collect();
+        for h in 0..half_edges.len() {
+            let (origin, destination) =
+                (half_edges[h].origin, half_edges[half_edges[h].next].origin);
+            half_edges[h].twin = ends.get(&(destination, origin)).copied();
+        }
+
+        PolyMesh {
+            vertex_edges: outgoing_edges(vertices.len(), &half_edges),
+            vertices,
+            half_edges,
+            faces,
+        }
+    }
+
+    /// Mesh of the given half-edges and faces, as another process wrote them, after checking
+    /// that they link up.
+    pub fn from_parts(
+        vertices: Vec<(f64, f64)>,
+        half_edges: Vec<HalfEdge>,
+        faces: Vec<Face>,
+    ) -> Result<PolyMesh, &'static str> {
+        let h_count = half_edges.len();
+        for (h, half_edge) in half_edges.iter().enumerate() {
+            if half_edge.origin >= vertices.len()
+                || half_edge.next >= h_count
+                || half_edge.prev >= h_count
+                || half_edge.face >= faces.len()
+            {
+                return Err("half-edge index out of range");
+            }
+            if half_edges[half_edge.next].prev != h
+                || half_edges[half_edge.next].face != half_edge.face
+            {
+                return Err("half-edges of a face don't link up");
+            }
+            if let Some(twin) = half_edge.twin {
+                if twin >= h_count
+                    || half_edges[twin].twin != Some(h)
+                    || half_edges[twin].origin != half_edges[half_edge.next].origin
+                {
+                    return Err("twin half-edges don't match");
+                }
+            }
+        }
+        // Every half-edge on one loop, started from its face
+        let mut seen = vec![false; h_count];
+        for (face, f) in faces.iter().enumerate() {
+            let mut h = f.half_edge;
+            if h >= h_count || half_edges[h].face != face {
+                return Err("face half-edge out of range");
+            }
+            while !seen[h] {
+                seen[h] = true;
+                h = half_edges[h].next;
+            }
+            if h != f.half_edge {
+                return Err("half-edges of a face don't link up");
+            }
+        }
+        if seen.contains(&false) {
+            return Err("half-edge outside of its face's loop");
+        }
+
+        Ok(PolyMesh {
+            vertex_edges: outgoing_edges(vertices.len(), &half_edges),
+            vertices,
+            half_edges,
+            faces,
+        })
+    }
+
+    /// One polygon per seed, empty for the seeds without a face, as `cells::extract_cells`
+    /// returns them.
+    pub fn to_cells(&self, seed_count: usize) -> Vec<Vec<(f64, f64)>> {
+        let mut cells = vec![Vec::new(); seed_count];
+        for (face, f) in self.faces.iter().enumerate() {
+            cells[f.seed] = self.face_polygon(face);
+        }
+        cells
+    }
+
+    pub fn destination(&self, half_edge: usize) -> usize {
+        self.half_edges[self.half_edges[half_edge].next].origin
+    }
+
+    pub fn edge_length(&self, half_edge: usize) -> f64 {
+        let (ax, ay) = self.vertices[self.half_edges[half_edge].origin];
+        let (bx, by) = self.vertices[self.destination(half_edge)];
+        (bx - ax).hypot(by - ay)
+    }
+
+    /// Half-edges of `face`, counter-clockwise.
+    pub fn face_edges(&self, face: usize) -> impl Iterator<Item = usize> + '_ {
+        let start = self.faces[face].half_edge;
+        let mut current = Some(start);
+        std::iter::from_fn(move || {
+            let h = current?;
+            let next = self.half_edges[h].next;
+            current = (next != start).then_some(next);
+            Some(h)
+        })
+    }
+
+    /// Vertices of `face`, counter-clockwise.
+    pub fn face_vertices(&self, face: usize) -> impl Iterator<Item = usize> + '_ {
+        self.face_edges(face).map(|h| self.half_edges[h].origin)
+    }
+
+    pub fn face_polygon(&self, face: usize) -> Vec<(f64, f64)> {
+        self.face_vertices(face).map(|v| self.vertices[v]).collect()
+    }
+
+    /// Faces across the edges of `face`, once per shared edge.
+    pub fn face_neighbors(&self, face: usize) -> impl Iterator<Item = usize> + '_ {
+        self.face_edges(face)
+            .filter_map(|h| self.half_edges[h].twin)
+            .map(|twin| self.half_edges[twin].face)
+    }
+
+    /// Half-edges leaving `vertex`, turning counter-clockwise around it. Vertices where several
+    /// fans of faces only touch, which conforming cells don't have, yield only one of them.
+    pub fn vertex_edges(&self, vertex: usize) -> impl Iterator<Item = usize> + '_ {
+        let start = self.vertex_edges[vertex];
+        let mut current = (start != usize::MAX).then_some(start);
+        std::iter::from_fn(move || {
+            let h = current?;
+            current = self.half_edges[self.half_edges[h].prev]
+                .twin
+                .filter(|&next| next != start);
+            Some(h)
+        })
+    }
+
+    /// Faces around `vertex`, turning counter-clockwise.
+    pub fn vertex_faces(&self, vertex: usize) -> impl Iterator<Item = usize> + '_ {
+        self.vertex_edges(vertex).map(|h| self.half_edges[h].face)
+    }
+
+    /// Half-edges without twin, on the boundary of the mesh.
+    pub fn boundary_edges(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.half_edges.len()).filter(|&h| self.half_edges[h].twin.is_none())
+    }
+
+    pub fn face_area(&self, face: usize) -> f64 {
+        self.face_moments(face).0
+    }
+
+    /// Center of mass of `face`.
+    pub fn face_centroid(&self, face: usize) -> (f64, f64) {
+        let (area, (x, y)) = self.face_moments(face);
+        (x / area, y / area)
+    }
+
+    // Area and first moments of `face`, from its vertices relative to the first one, which keeps
+    // the sums accurate
+    fn face_moments(&self, face: usize) -> (f64, (f64, f64)) {
+        let polygon = self.face_polygon(face);
+        let (ox, oy) = polygon[0];
+        let n = polygon.len();
+        let (mut area, mut mx, mut my) = (0.0, 0.0, 0.0);
+        for i in 0..n {
+            let (ax, ay) = (polygon[i].0 - ox, polygon[i].1 - oy);
+            let (bx, by) = (polygon[(i + 1) % n].0 - ox, polygon[(i + 1) % n].1 - oy);
+            let cross = ax * by - bx * ay;
+            area += cross / 2.0;
+            mx += (ax + bx) * cross / 6.0;
+            my += (ay + by) * cross / 6.0;
+        }
+        (area, (mx + ox * area, my + oy * area))
+    }
+
+    /// Vertices of each loop of boundary half-edges, following them, so counter-clockwise around
+    /// the outside of the mesh and clockwise around its holes.
+    pub fn boundary_loops(&self) -> Vec<Vec<usize>> {
+        let mut seen = vec![false; self.half_edges.len()];
+        let mut loops = Vec::new();
+        for start in self.boundary_edges() {
+            let mut ring = Vec::new();
+            let mut h = start;
+            while !seen[h] {
+                seen[h] = true;
+                ring.push(self.half_edges[h].origin);
+                // Turning around the destination through the faces to the next boundary edge
+                let mut next = self.half_edges[h].next;
+                while let Some(twin) = self.half_edges[next].twin {
+                    next = self.half_edges[twin].next;
+                }
+                h = next;
+            }
+            if !ring.is_empty() {
+                loops.push(ring);
+            }
+        }
+        loops
+    }
+
+    /// Counter-clockwise triangles of vertices covering `face`, one fewer than its vertices. Ear
+    /// clipping rather than a fan, since cells clipped to a domain can be non-convex.
+    pub fn face_triangles(&self, face: usize) -> Vec<[usize; 3]> {
+        let mut ring: Vec<usize> = self.face_vertices(face).collect();
+        let mut triangles = Vec::with_capacity(ring.len().saturating_sub(2));
+        let point = |v: usize| self.vertices[v];
+        while ring.len() > 3 {
+            let n = ring.len();
+            let corners = |i: usize| [ring[(i + n - 1) % n], ring[i], ring[(i + 1) % n]];
+            let is_ear = |i: usize| {
+                let [a, b, c] = corners(i).map(point);
+                cross(a, b, c) > 0.0
+                    && ring
+                        .iter()
+                        .filter(|v| !corners(i).contains(v))
+                        .all(|&v| !in_triangle(point(v), [a, b, c]))
+            };
+            // A ring left with collinear vertices only has no ear, its triangles are flat
+            let ear = (0..n).find(|&i| is_ear(i)).unwrap_or(0);
+            triangles.push(corners(ear));
+            ring.remove(ear);
+        }
+        if let [a, b, c] = ring[..] {
+            triangles.push([a, b, c]);
+        }
+        triangles
+    }
+}
+
+// An outgoing half-edge of each vertex, one without twin when there is one
+fn outgoing_edges(vertex_count: usize, half_edges: &[HalfEdge]) -> Vec<usize> {
+    let mut vertex_edges = vec![usize::MAX; vertex_count];
+    for (h, half_edge) in half_edges.iter().enumerate() {
+        if vertex_edges[half_edge.origin] == usize::MAX || half_edge.twin.is_none() {
+            vertex_edges[half_edge.origin] = h;
+        }
+    }
+    vertex_edges
+}
+
+// Twice the signed area of triangle abc, positive when counter-clockwise
+fn cross(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> f64 {
+    (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+}
+
+// Whether `p` is inside the counter-clockwise triangle or on its edges
+fn in_triangle(p: (f64, f64), [a, b, c]: [(f64, f64); 3]) -> bool {
+    cross(a, b, p) >= 0.0 && cross(b, c, p) >= 0.0 && cross(c, a, p) >= 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_squares() -> PolyMesh {
+        PolyMesh::from_cells(&[
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+            vec![],
+            vec![(1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0)],
+        ])
+    }
+
+    #[test]
+    fn test_from_cells() {
+        let mesh = two_squares();
+
+        assert_eq!(mesh.vertices.len(), 6);
+        assert_eq!(
+            mesh.faces.iter().map(|f| f.seed).collect::<Vec<_>>(),
+            [0, 2]
+        );
+        assert_eq!(mesh.face_vertices(1).collect::<Vec<_>>(), [1, 4, 5, 2]);
+        // Only the shared edge has twins
+        assert_eq!(mesh.boundary_edges().count(), 6);
+        assert_eq!(mesh.half_edges[1].twin, Some(7));
+        assert_eq!(
+            mesh.to_cells(3)[2],
+            [(1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0)]
+        );
+    }
+
+    #[test]
+    fn test_traversals() {
+        let mesh = two_squares();
+
+        assert_eq!(mesh.face_neighbors(0).collect::<Vec<_>>(), [1]);
+        // Vertex (1, 0) is on the boundary, between both faces
+        let mut faces: Vec<usize> = mesh.vertex_faces(1).collect();
+        faces.sort();
+        assert_eq!(faces, [0, 1]);
+        assert_eq!(mesh.vertex_faces(0).collect::<Vec<_>>(), [0]);
+        assert_eq!(mesh.edge_length(0), 1.0);
+    }
+
+    #[test]
+    fn test_face_centroid() {
+        let mesh = two_squares();
+
+        assert_eq!(mesh.face_area(1), 1.0);
+        assert_eq!(mesh.face_centroid(1), (1.5, 0.5));
+    }
+
+    #[test]
+    fn test_boundary_loops() {
+        // A ring of 8 squares around a hole
+        let square = |x: f64, y: f64| vec![(x, y), (x + 1.0, y), (x + 1.0, y + 1.0), (x, y + 1.0)];
+        let cells: Vec<_> = (0..9)
+            .filter(|&i| i != 4)
+            .map(|i| square((i % 3) as f64, (i / 3) as f64))
+            .collect();
+        let mesh = PolyMesh::from_cells(&cells);
+
+        let loops = mesh.boundary_loops();
+
+        assert_eq!(loops.len(), 2);
+        let areas: Vec<f64> = loops
+            .iter()
+            .map(|ring| {
+                let polygon: Vec<(f64, f64)> = ring.iter().map(|&v| mesh.vertices[v]).collect();
+                (0..polygon.len())
+                    .map(|i| cross((0.0, 0.0), polygon[i], polygon[(i + 1) % polygon.len()]))
+                    .sum::<f64>()
+                    / 2.0
+            })
+            .collect();
+        assert!(areas.contains(&9.0) && areas.contains(&-1.0), "{areas:?}");
+    }
+
+    #[test]
+    fn test_face_triangles() {
+        // L-shape, which a fan from its first vertex would leave
+        let l_shape = vec![
+            (2.0, 1.0),
+            (1.0, 1.0),
+            (1.0, 2.0),
+            (0.0, 2.0),
+            (0.0, 0.0),
+            (2.0, 0.0),
+        ];
+        let mesh = PolyMesh::from_cells(&[l_shape]);
+
+        let triangles = mesh.face_triangles(0);
+        assert_eq!(triangles.len(), 4);
+        let point = |v: usize| mesh.vertices[v];
+        let areas: Vec<f64> = triangles
+            .iter()
+            .map(|t| cross(point(t[0]), point(t[1]), point(t[2])) / 2.0)
+            .collect();
+        assert!(areas.iter().all(|&area| area > 0.0));
+        assert_eq!(areas.iter().sum::<f64>(), 3.0);
+        assert_eq!(two_squares().face_triangles(1).len(), 2);
+    }
+}
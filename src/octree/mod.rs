@@ -0,0 +1,429 @@
+// Octree sizing field for 3D meshing, so large domains don't need a uniform fine voxel grid. An
+// octant of the box is split while it is larger than the target cell size anywhere it samples,
+// its center and corners, so the leaves are about as large as the cells wanted over them.
+//
+// The leaves drive both the seeds, one jittered seed per leaf, and the labeling: every leaf is
+// voxelized with the same number of voxels across, fine where the leaves are small and coarse
+// elsewhere, and each voxel center is labeled with its nearest seed. Seeds are bucketed into the
+// leaves and the nearest one is found by descending the tree, skipping octants farther than the
+// best seed so far, so the labels are those of the exact diagram at the voxel centers.
+//
+// Target sizes come from a callback, such as `distance_sizing` to grade the cells away from a
+// boundary or `curvature_sizing` to resolve curved surfaces of an SDF.
+
+use rand::Rng;
+
+use crate::error::MesherError;
+
+type Point3 = (f64, f64, f64);
+
+// Splits below the root, which with a minimum size keeps a bad size callback from exhausting
+// memory
+const MAX_DEPTH: usize = 12;
+// Part of a leaf, around its center, seeds are drawn in, which keeps seeds of neighboring leaves
+// from falling on top of each other
+const JITTER: f64 = 0.5;
+
+/// Octant of a [`SizingOctree`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Octant {
+    pub min: Point3,
+    pub max: Point3,
+    /// Smallest target size sampled over the octant
+    pub size: f64,
+    // Index of the first of 8 consecutive children
+    children: Option<usize>,
+}
+
+/// Target cell sizes over a box, refined where they are small.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SizingOctree {
+    octants: Vec<Octant>,
+}
+
+/// Voxel labels of one leaf, in the layout of `jfa_wgpu_3d::run`: seed `i` has label `i + 1`
+/// and voxel `(x, y, z)` of the leaf is `x + y * reso + z * reso * reso`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeafLabels {
+    pub min: Point3,
+    pub max: Point3,
+    pub reso: usize,
+    pub labels: Vec<u32>,
+}
+
+impl SizingOctree {
+    /// Octree of the box from the origin to `extent`, splitting octants larger than `size` at
+    /// their center or corners, but none smaller than `min_size`.
+    pub fn new(
+        extent: Point3,
+        min_size: f64,
+        size: impl Fn(f64, f64, f64) -> f64,
+    ) -> Result<SizingOctree, MesherError> {
+        if ![extent.0, extent.1, extent.2]
+            .iter()
+            .all(|side| *side > 0.0 && side.is_finite())
+        {
+            return Err(MesherError::InvalidInput(
+                "the domain must have a finite, positive width, height and depth",
+            ));
+        }
+        if min_size.is_nan() || min_size <= 0.0 {
+            return Err(MesherError::InvalidInput(
+                "the minimum size must be positive",
+            ));
+        }
+
+        let mut octree = SizingOctree {
+            octants: vec![octant((0.0, 0.0, 0.0), extent, &size)],
+        };
+        let mut pending = vec![(0, 0)];
+        while let Some((i, depth)) = pending.pop() {
+            let Octant {
+                min,
+                max,
+                size: target,
+                ..
+            } = octree.octants[i];
+            let side = (max.0 - min.0).max(max.1 - min.1).max(max.2 - min.2);
+            if depth == MAX_DEPTH || side <= target || side / 2.0 < min_size {
+                continue;
+            }
+            let first = octree.octants.len();
+            octree.octants[i].children = Some(first);
+            let middle = center(min, max);
+            for child in 0..8 {
+                let corner = corner(min, max, child);
+                let (low, high) = (min3(corner, middle), max3(corner, middle));
+                octree.octants.push(octant(low, high, &size));
+                pending.push((first + child, depth + 1));
+            }
+        }
+        Ok(octree)
+    }
+
+    pub fn leaves(&self) -> impl Iterator<Item = &Octant> {
+        self.octants
+            .iter()
+            .filter(|octant| octant.children.is_none())
+    }
+
+    /// Target size of the leaf containing `point`, clamped into the box.
+    pub fn size_at(&self, point: Point3) -> f64 {
+        self.octants[self.leaf_of(point)].size
+    }
+
+    /// One seed per leaf, drawn around its center, so that the seed density follows the sizes.
+    pub fn seeds(&self, rng: &mut impl Rng) -> Vec<Point3> {
+        let mut jitter = |low: f64, high: f64| {
+            let middle = (low + high) / 2.0;
+            middle + JITTER * (high - low) * (rng.gen::<f64>() - 0.5)
+        };
+        self.leaves()
+            .map(|leaf| {
+                (
+                    jitter(leaf.min.0, leaf.max.0),
+                    jitter(leaf.min.1, leaf.max.1),
+                    jitter(leaf.min.2, leaf.max.2),
+                )
+            })
+            .collect()
+    }
+
+    /// Labels of the `reso`³ voxels of every leaf, in the order of [`SizingOctree::leaves`],
+    /// each voxel labeled with the seed nearest to its center.
+    pub fn label_leaves(
+        &self,
+        points: &[Point3],
+        reso: usize,
+    ) -> Result<Vec<LeafLabels>, MesherError> {
+        if reso == 0 {
+            return Err(MesherError::InvalidInput("resolution must be at least 1"));
+        }
+        if points.is_empty() {
+            return Err(MesherError::NoSeeds);
+        }
+        let (min, max) = (self.octants[0].min, self.octants[0].max);
+        let outside = |&(x, y, z): &Point3| {
+            !((min.0..=max.0).contains(&x)
+                && (min.1..=max.1).contains(&y)
+                && (min.2..=max.2).contains(&z))
+        };
+        if let Some(index) = points.iter().position(outside) {
+            return Err(MesherError::SeedOutsideDomain { index });
+        }
+
+        let mut buckets = vec![Vec::new(); self.octants.len()];
+        for (i, &point) in points.iter().enumerate() {
+            buckets[self.leaf_of(point)].push(i);
+        }
+        Ok(self
+            .leaves()
+            .map(|leaf| {
+                let step = (
+                    (leaf.max.0 - leaf.min.0) / reso as f64,
+                    (leaf.max.1 - leaf.min.1) / reso as f64,
+                    (leaf.max.2 - leaf.min.2) / reso as f64,
+                );
+                let labels = (0..reso * reso * reso)
+                    .map(|i| {
+                        let (x, y, z) = (i % reso, i / reso % reso, i / (reso * reso));
+                        let voxel = (
+                            leaf.min.0 + (x as f64 + 0.5) * step.0,
+                            leaf.min.1 + (y as f64 + 0.5) * step.1,
+                            leaf.min.2 + (z as f64 + 0.5) * step.2,
+                        );
+                        self.nearest(&buckets, points, voxel) as u32 + 1
+                    })
+                    .collect();
+                LeafLabels {
+                    min: leaf.min,
+                    max: leaf.max,
+                    reso,
+                    labels,
+                }
+            })
+            .collect())
+    }
+
+    fn leaf_of(&self, point: Point3) -> usize {
+        let mut i = 0;
+        while let Some(first) = self.octants[i].children {
+            let Octant { min, max, .. } = self.octants[i];
+            let middle = center(min, max);
+            i = first
+                + (point.0 >= middle.0) as usize
+                + 2 * (point.1 >= middle.1) as usize
+                + 4 * (point.2 >= middle.2) as usize;
+        }
+        i
+    }
+
+    // Nearest seed of the `buckets` of the leaves, there being at least one
+    fn nearest(&self, buckets: &[Vec<usize>], points: &[Point3], point: Point3) -> usize {
+        let mut best = (usize::MAX, f64::INFINITY);
+        let mut pending = vec![0];
+        while let Some(i) = pending.pop() {
+            let octant = &self.octants[i];
+            if box_distance(octant, point) >= best.1 {
+                continue;
+            }
+            match octant.children {
+                // Nearer children last, to be visited first
+                Some(first) => {
+                    let mut children: Vec<usize> = (first..first + 8).collect();
+                    children.sort_by(|&a, &b| {
+                        let distance = |c: usize| box_distance(&self.octants[c], point);
+                        distance(b).total_cmp(&distance(a))
+                    });
+                    pending.extend(children);
+                }
+                None => {
+                    for &seed in &buckets[i] {
+                        let distance = length(sub(points[seed], point));
+                        if distance < best.1 {
+                            best = (seed, distance);
+                        }
+                    }
+                }
+            }
+        }
+        best.0
+    }
+}
+
+/// Target size `near` on the boundary of the domain of `sdf`, growing by `growth` per unit of
+/// distance from it, up to `far`.
+pub fn distance_sizing(
+    sdf: impl Fn(f64, f64, f64) -> f64,
+    near: f64,
+    far: f64,
+    growth: f64,
+) -> impl Fn(f64, f64, f64) -> f64 {
+    move |x, y, z| (near + growth * sdf(x, y, z).abs()).min(far)
+}
+
+/// Target size spanning about `angle` radians of the curved level sets of `sdf`, between `near`
+/// and `far`. The curvature is the mean curvature, half the Laplacian of the distance, which
+/// finite differences of step `near / 2` estimate.
+pub fn curvature_sizing(
+    sdf: impl Fn(f64, f64, f64) -> f64,
+    angle: f64,
+    near: f64,
+    far: f64,
+) -> impl Fn(f64, f64, f64) -> f64 {
+    let h = near / 2.0;
+    move |x, y, z| {
+        let laplacian = (sdf(x + h, y, z)
+            + sdf(x - h, y, z)
+            + sdf(x, y + h, z)
+            + sdf(x, y - h, z)
+            + sdf(x, y, z + h)
+            + sdf(x, y, z - h)
+            - 6.0 * sdf(x, y, z))
+            / (h * h);
+        (2.0 * angle / laplacian.abs()).clamp(near, far)
+    }
+}
+
+fn octant(min: Point3, max: Point3, size: impl Fn(f64, f64, f64) -> f64) -> Octant {
+    let middle = center(min, max);
+    let size = (0..8)
+        .map(|i| corner(min, max, i))
+        .chain([middle])
+        .map(|(x, y, z)| size(x, y, z))
+        .fold(f64::INFINITY, f64::min);
+    Octant {
+        min,
+        max,
+        size,
+        children: None,
+    }
+}
+
+// Corner `i` of the box, its bits picking the high side along x, y and z
+fn corner(min: Point3, max: Point3, i: usize) -> Point3 {
+    let pick = |bit: usize, low: f64, high: f64| if i & bit == 0 { low } else { high };
+    (
+        pick(1, min.0, max.0),
+        pick(2, min.1, max.1),
+        pick(4, min.2, max.2),
+    )
+}
+
+fn center(min: Point3, max: Point3) -> Point3 {
+    (
+        (min.0 + max.0) / 2.0,
+        (min.1 + max.1) / 2.0,
+        (min.2 + max.2) / 2.0,
+    )
+}
+
+fn min3(a: Point3, b: Point3) -> Point3 {
+    (a.0.min(b.0), a.1.min(b.1), a.2.min(b.2))
+}
+
+fn max3(a: Point3, b: Point3) -> Point3 {
+    (a.0.max(b.0), a.1.max(b.1), a.2.max(b.2))
+}
+
+fn sub(a: Point3, b: Point3) -> Point3 {
+    (a.0 - b.0, a.1 - b.1, a.2 - b.2)
+}
+
+fn length((x, y, z): Point3) -> f64 {
+    (x * x + y * y + z * z).sqrt()
+}
+
+fn box_distance(octant: &Octant, (x, y, z): Point3) -> f64 {
+    let gap = |value: f64, low: f64, high: f64| (low - value).max(value - high).max(0.0);
+    length((
+        gap(x, octant.min.0, octant.max.0),
+        gap(y, octant.min.1, octant.max.1),
+        gap(z, octant.min.2, octant.max.2),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::implicit::solid;
+
+    #[test]
+    fn test_uniform() {
+        let octree = SizingOctree::new((4.0, 4.0, 2.0), 0.1, |_, _, _| 1.0).unwrap();
+
+        // Split twice to 1 × 1 × 0.5 leaves
+        assert_eq!(octree.leaves().count(), 64);
+        assert!(octree.leaves().all(|leaf| leaf.max.0 - leaf.min.0 == 1.0));
+        assert_eq!(octree.size_at((3.9, 0.1, 5.0)), 1.0);
+
+        // A tiny size stops at the minimum size
+        let fine = SizingOctree::new((1.0, 1.0, 1.0), 0.25, |_, _, _| 1e-6).unwrap();
+        assert_eq!(fine.leaves().count(), 64);
+        assert!(matches!(
+            SizingOctree::new((1.0, 0.0, 1.0), 0.25, |_, _, _| 1.0),
+            Err(MesherError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_graded() {
+        // Fine cells along the z = 0 face, coarse ones at the top
+        let sizing = distance_sizing(|_, _, z| -z, 0.125, 1.0, 0.5);
+        let octree = SizingOctree::new((4.0, 4.0, 4.0), 0.01, sizing).unwrap();
+        let mut rng = StdRng::seed_from_u64(7);
+
+        let seeds = octree.seeds(&mut rng);
+
+        assert!(octree.size_at((2.0, 2.0, 0.0)) < octree.size_at((2.0, 2.0, 3.9)));
+        assert!(octree
+            .leaves()
+            .all(|leaf| leaf.max.0 - leaf.min.0 <= leaf.size.max(0.125)));
+        assert_eq!(seeds.len(), octree.leaves().count());
+        let bottom = seeds.iter().filter(|seed| seed.2 < 1.0).count();
+        assert!(bottom > 3 * (seeds.len() - bottom));
+        for (seed, leaf) in seeds.iter().zip(octree.leaves()) {
+            assert!(leaf.min.0 < seed.0 && seed.0 < leaf.max.0);
+            assert!(leaf.min.2 < seed.2 && seed.2 < leaf.max.2);
+        }
+    }
+
+    #[test]
+    fn test_curvature_sizing() {
+        let sizing = curvature_sizing(solid::sphere((0.0, 0.0, 0.0), 2.0), 0.5, 0.01, 10.0);
+
+        // Level sets at 2 and 4 from the center, cells spanning half a radian of them
+        assert!((sizing(2.0, 0.0, 0.0) - 1.0).abs() < 1e-3);
+        assert!((sizing(0.0, 4.0, 0.0) - 2.0).abs() < 1e-3);
+        // Flat level sets of a half-space get the largest cells
+        assert_eq!(
+            curvature_sizing(|_, _, z| z, 0.5, 0.01, 10.0)(1.0, 1.0, 1.0),
+            10.0
+        );
+    }
+
+    #[test]
+    fn test_label_leaves() {
+        let octree = SizingOctree::new(
+            (2.0, 2.0, 2.0),
+            0.1,
+            |x, _, _| {
+                if x < 1.0 {
+                    0.5
+                } else {
+                    1.0
+                }
+            },
+        )
+        .unwrap();
+        let mut rng = StdRng::seed_from_u64(7);
+        let seeds = octree.seeds(&mut rng);
+
+        let leaves = octree.label_leaves(&seeds, 3).unwrap();
+
+        assert_eq!(leaves.len(), octree.leaves().count());
+        for leaf in &leaves {
+            assert_eq!(leaf.labels.len(), 27);
+            let step = (leaf.max.0 - leaf.min.0) / 3.0;
+            for (i, &label) in leaf.labels.iter().enumerate() {
+                let voxel = (
+                    leaf.min.0 + (i % 3) as f64 * step + step / 2.0,
+                    leaf.min.1 + (i / 3 % 3) as f64 * step + step / 2.0,
+                    leaf.min.2 + (i / 9) as f64 * step + step / 2.0,
+                );
+                let distance = |seed: usize| length(sub(seeds[seed], voxel));
+                let nearest = (0..seeds.len())
+                    .min_by(|&a, &b| distance(a).total_cmp(&distance(b)))
+                    .unwrap();
+                assert_eq!(distance(label as usize - 1), distance(nearest));
+            }
+        }
+        assert_eq!(
+            octree.label_leaves(&[(1.0, 1.0, 2.5)], 3),
+            Err(MesherError::SeedOutsideDomain { index: 0 })
+        );
+    }
+}
@@ -0,0 +1,82 @@
+// Feedback and control for long runs, e.g. at high resolution in an interactive application:
+// a callback told how far the run is, and a token another thread can set to abort it.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Step a run has just completed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+    /// JFA pass `pass`, counted from 1, out of `passes`
+    JfaPass { pass: usize, passes: usize },
+    /// Lloyd iteration `iteration`, counted from 1, out of `iterations`
+    Relaxation { iteration: usize, iterations: usize },
+    /// Weight adjustment `iteration` of a balancing, counted from 1, out of at most `iterations`
+    Balancing { iteration: usize, iterations: usize },
+    /// Tile `tile` of a tiled run, counted from 1, out of `tiles`
+    Tile { tile: usize, tiles: usize },
+}
+
+/// Function called after every step of a run, on the thread running it.
+#[derive(Clone)]
+pub struct ProgressCallback(Arc<dyn Fn(Progress) + Send + Sync>);
+
+impl ProgressCallback {
+    pub fn new(callback: impl Fn(Progress) + Send + Sync + 'static) -> ProgressCallback {
+        ProgressCallback(Arc::new(callback))
+    }
+
+    pub(crate) fn report(&self, progress: Progress) {
+        (self.0)(progress)
+    }
+}
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+/// Flag shared by its clones, checked by a run between its GPU submissions. A cancelled run
+/// returns `MesherError::Cancelled` with how far it got.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_shared_state() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&reports);
+        let callback = ProgressCallback::new(move |progress| sink.lock().unwrap().push(progress));
+
+        clone.cancel();
+        callback.report(Progress::JfaPass { pass: 1, passes: 3 });
+
+        assert!(token.is_cancelled());
+        assert_eq!(
+            *reports.lock().unwrap(),
+            vec![Progress::JfaPass { pass: 1, passes: 3 }]
+        );
+    }
+}
@@ -0,0 +1,248 @@
+// Shape metrics of the cells of a mesh, so solver users can tell whether it needs more
+// relaxation. A centroidal Voronoi tessellation tends towards
+// regular hexagons, which the regularity is measured against.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::mesh::PolyMesh;
+
+/// Shape of one cell, lengths being in domain units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CellQuality {
+    pub edges: usize,
+    pub min_edge: f64,
+    pub max_edge: f64,
+    /// Square root of the ratio of the principal second moments of area, 1 for regular
+    /// polygons and growing as the cell stretches
+    pub aspect_ratio: f64,
+    /// Isoperimetric quotient `4πA / P²` over that of the regular hexagon, 1 for a regular
+    /// hexagon and lower for less compact cells
+    pub regularity: f64,
+}
+
+/// Counts of values in equal bins between `min` and `max`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Histogram {
+    pub min: f64,
+    pub max: f64,
+    pub counts: Vec<usize>,
+}
+
+impl Histogram {
+    fn new(values: &[f64], bins: usize) -> Histogram {
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let mut counts = vec![0; bins];
+        for &value in values {
+            // The maximum goes to the last bin, and equal values to the first
+            let bin = ((value - min) / (max - min) * bins as f64) as usize;
+            counts[bin.min(bins - 1)] += 1;
+        }
+        Histogram { min, max, counts }
+    }
+
+    /// Lower and upper bounds of bin `i`.
+    pub fn bin_range(&self, i: usize) -> (f64, f64) {
+        let width = (self.max - self.min) / self.counts.len() as f64;
+        (
+            self.min + i as f64 * width,
+            self.min + (i + 1) as f64 * width,
+        )
+    }
+}
+
+/// Metrics of every cell and their distributions.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QualityReport {
+    /// Per face of the mesh, `None` for faces without area
+    pub cells: Vec<Option<CellQuality>>,
+    /// Cells by number of edges
+    pub edge_counts: BTreeMap<usize, usize>,
+    pub aspect_ratios: Histogram,
+    pub regularities: Histogram,
+    /// Lengths of all the half-edges, so shared edges count once per cell
+    pub edge_lengths: Histogram,
+}
+
+impl QualityReport {
+    fn measured(&self) -> impl Iterator<Item = &CellQuality> {
+        self.cells.iter().flatten()
+    }
+
+    pub fn mean_regularity(&self) -> f64 {
+        mean(self.measured().map(|cell| cell.regularity))
+    }
+
+    pub fn worst_aspect_ratio(&self) -> f64 {
+        self.measured()
+            .map(|cell| cell.aspect_ratio)
+            .fold(f64::NAN, f64::max)
+    }
+
+    /// Ratio of the longest edge of the mesh to its shortest one.
+    pub fn edge_length_ratio(&self) -> f64 {
+        self.edge_lengths.max / self.edge_lengths.min
+    }
+}
+
+impl fmt::Display for QualityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let measured = self.measured().count();
+        writeln!(f, "Cells measured: {} of {}", measured, self.cells.len())?;
+        if measured == 0 {
+            return Ok(());
+        }
+        writeln!(f, "Cells by edge count: {:?}", self.edge_counts)?;
+        writeln!(f, "Mean regularity: {:.3}", self.mean_regularity())?;
+        writeln!(f, "Worst aspect ratio: {:.3}", self.worst_aspect_ratio())?;
+        writeln!(
+            f,
+            "Edge lengths: {:.4} to {:.4}",
+            self.edge_lengths.min, self.edge_lengths.max
+        )?;
+        for (name, histogram) in [
+            ("Regularity", &self.regularities),
+            ("Aspect ratio", &self.aspect_ratios),
+        ] {
+            writeln!(f, "{name}:")?;
+            for (i, count) in histogram.counts.iter().enumerate() {
+                let (low, high) = histogram.bin_range(i);
+                writeln!(f, "  {low:.3} - {high:.3}: {count}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Metrics of a counter-clockwise polygon, `None` when it has no area.
+pub fn cell_quality(polygon: &[(f64, f64)]) -> Option<CellQuality> {
+    let n = polygon.len();
+    let edges = || (0..n).map(|i| (polygon[i], polygon[(i + 1) % n]));
+    let lengths: Vec<f64> = edges().map(|(a, b)| (b.0 - a.0).hypot(b.1 - a.1)).collect();
+
+    // Area, first and second moments about the origin, by Green's theorem
+    let (mut area, mut cx, mut cy, mut ixx, mut iyy, mut ixy) = (0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+    for ((x0, y0), (x1, y1)) in edges() {
+        let cross = x0 * y1 - x1 * y0;
+        area += cross / 2.0;
+        cx += (x0 + x1) * cross / 6.0;
+        cy += (y0 + y1) * cross / 6.0;
+        ixx += (y0 * y0 + y0 * y1 + y1 * y1) * cross / 12.0;
+        iyy += (x0 * x0 + x0 * x1 + x1 * x1) * cross / 12.0;
+        ixy += (x0 * y1 + 2.0 * x0 * y0 + 2.0 * x1 * y1 + x1 * y0) * cross / 24.0;
+    }
+    if n < 3 || area <= 0.0 {
+        return None;
+    }
+    // Moved to the centroid
+    let (cx, cy) = (cx / area, cy / area);
+    let ixx = ixx - area * cy * cy;
+    let iyy = iyy - area * cx * cx;
+    let ixy = ixy - area * cx * cy;
+    let spread = ((ixx - iyy).powi(2) / 4.0 + ixy * ixy).sqrt();
+    let (major, minor) = ((ixx + iyy) / 2.0 + spread, (ixx + iyy) / 2.0 - spread);
+
+    // 4πA / P² is π√3 / 6 for the regular hexagon
+    let perimeter: f64 = lengths.iter().sum();
+    Some(CellQuality {
+        edges: n,
+        min_edge: lengths.iter().copied().fold(f64::INFINITY, f64::min),
+        max_edge: lengths.iter().copied().fold(0.0, f64::max),
+        aspect_ratio: (major / minor).sqrt(),
+        regularity: 8.0 * 3f64.sqrt() * area / (perimeter * perimeter),
+    })
+}
+
+/// Metrics of the faces of `mesh`, with `bins` bins per histogram.
+pub fn quality_report(mesh: &PolyMesh, bins: usize) -> QualityReport {
+    let bins = bins.max(1);
+    let cells: Vec<Option<CellQuality>> = (0..mesh.faces.len())
+        .map(|face| cell_quality(&mesh.face_polygon(face)))
+        .collect();
+    let measured: Vec<CellQuality> = cells.iter().flatten().copied().collect();
+
+    let mut edge_counts = BTreeMap::new();
+    for cell in &measured {
+        *edge_counts.entry(cell.edges).or_insert(0) += 1;
+    }
+    let edge_lengths: Vec<f64> = (0..mesh.half_edges.len())
+        .filter(|&h| cells[mesh.half_edges[h].face].is_some())
+        .map(|h| mesh.edge_length(h))
+        .collect();
+    let histogram = |metric: fn(&CellQuality) -> f64| {
+        let values: Vec<f64> = measured.iter().map(metric).collect();
+        Histogram::new(&values, bins)
+    };
+
+    QualityReport {
+        aspect_ratios: histogram(|cell| cell.aspect_ratio),
+        regularities: histogram(|cell| cell.regularity),
+        edge_lengths: Histogram::new(&edge_lengths, bins),
+        edge_counts,
+        cells,
+    }
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+    sum / count as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{a} != {b}");
+    }
+
+    #[test]
+    fn test_regular_hexagon() {
+        let hexagon: Vec<(f64, f64)> = (0..6)
+            .map(|i| {
+                let angle = i as f64 * std::f64::consts::PI / 3.0;
+                (2.0 + angle.cos(), 3.0 + angle.sin())
+            })
+            .collect();
+        let quality = cell_quality(&hexagon).unwrap();
+
+        assert_eq!(quality.edges, 6);
+        assert_close(quality.min_edge, 1.0);
+        assert_close(quality.max_edge, 1.0);
+        assert_close(quality.aspect_ratio, 1.0);
+        assert_close(quality.regularity, 1.0);
+    }
+
+    #[test]
+    fn test_rectangle() {
+        let rectangle = [(0.0, 0.0), (4.0, 0.0), (4.0, 1.0), (0.0, 1.0)];
+        let quality = cell_quality(&rectangle).unwrap();
+
+        assert_close(quality.aspect_ratio, 4.0);
+        assert_close(quality.min_edge, 1.0);
+        assert_close(quality.max_edge, 4.0);
+        // Clockwise or degenerate polygons have no area
+        let clockwise: Vec<(f64, f64)> = rectangle.iter().rev().copied().collect();
+        assert_eq!(cell_quality(&clockwise), None);
+        assert_eq!(cell_quality(&[(0.0, 0.0), (1.0, 1.0)]), None);
+    }
+
+    #[test]
+    fn test_quality_report() {
+        let square = vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+        let rectangle = vec![(1.0, 0.0), (3.0, 0.0), (3.0, 1.0), (1.0, 1.0)];
+        let mesh = PolyMesh::from_cells(&[square, Vec::new(), rectangle]);
+        let report = quality_report(&mesh, 2);
+
+        assert_eq!(report.cells.len(), 2);
+        assert_eq!(report.edge_counts, BTreeMap::from([(4, 2)]));
+        assert_eq!(report.aspect_ratios.counts, [1, 1]);
+        assert_close(report.worst_aspect_ratio(), 2.0);
+        assert_close(report.edge_length_ratio(), 2.0);
+        assert_eq!(report.edge_lengths.counts, [6, 2]);
+    }
+}
@@ -0,0 +1,291 @@
+// Brute-force nearest-seed labels, to measure how far the JFA is from the diagram it
+// approximates. Every texel is compared against every seed, in parallel over the texels, with the
+// distances of the GPU engine: from texel centers to the exact seed positions, in texels, with
+// the configured norm, metric and wrapped axes. For a square grid over a box which isn't, texels
+// are stretched and these aren't domain distances. Ties go to the seed with the smallest index,
+// so the labels are deterministic whatever the thread count.
+
+use std::fmt;
+
+use rayon::prelude::*;
+
+use crate::config::{DistanceMetric, MesherConfig};
+use crate::error::MesherError;
+
+// Relative difference of squared distances below which two seeds are as near, covering the
+// rounding of the f32 distances of the GPU engine
+const TIE_TOLERANCE: f64 = 1e-5;
+
+/// Discrepancies between a label grid and the nearest-seed labels of the same seeds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MismatchReport {
+    pub texels: usize,
+    /// Texels labeled with a seed farther from them than their nearest one, in grid order
+    pub mismatched: Vec<usize>,
+    /// Texels labeled with another seed as near as their nearest one
+    pub ties: usize,
+    /// Texels left unlabeled, or labeled with no seed
+    pub unlabeled: usize,
+    /// Largest distance of a mismatched texel to its seed beyond the distance to its nearest
+    /// one, in texels, 0 when none is mismatched
+    pub max_distance_error: f64,
+}
+
+impl MismatchReport {
+    /// Fraction of the texels which are mismatched or unlabeled.
+    pub fn error_rate(&self) -> f64 {
+        (self.mismatched.len() + self.unlabeled) as f64 / self.texels as f64
+    }
+
+    pub fn is_exact(&self) -> bool {
+        self.mismatched.is_empty() && self.unlabeled == 0
+    }
+}
+
+impl fmt::Display for MismatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Error rate: {:.4}%", self.error_rate() * 100.0)?;
+        writeln!(
+            f,
+            "Mismatched texels: {} of {}",
+            self.mismatched.len(),
+            self.texels
+        )?;
+        writeln!(f, "Unlabeled texels: {}", self.unlabeled)?;
+        writeln!(f, "Ties: {}", self.ties)?;
+        write!(
+            f,
+            "Max distance error: {:.3} texels",
+            self.max_distance_error
+        )
+    }
+}
+
+/// Label of the nearest seed to each texel of the grid `options` describes over the `config`
+/// box, seed index plus one, indexed with `x + y * width`.
+pub fn reference_labels(
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    options: &MesherConfig,
+) -> Vec<usize> {
+    Grid::new(points, config, options)
+        .nearest()
+        .into_iter()
+        .map(|(label, _)| label)
+        .collect()
+}
+
+/// Label of the nearest seed to each texel, as [`reference_labels`], and its distance to the
+/// texel center in texels, infinite for texels without a seed.
+pub fn reference_nearest(
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    options: &MesherConfig,
+) -> Vec<(usize, f64)> {
+    Grid::new(points, config, options)
+        .nearest()
+        .into_iter()
+        .map(|(label, distance)| (label, distance.sqrt()))
+        .collect()
+}
+
+/// Compares `labels`, as the JFA computed them for `points`, against [`reference_labels`].
+pub fn validate(
+    labels: &[usize],
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    options: &MesherConfig,
+) -> Result<MismatchReport, MesherError> {
+    let grid = Grid::new(points, config, options);
+    if labels.len() != grid.width * grid.height {
+        return Err(MesherError::InvalidInput(
+            "labels must have one entry per texel of the grid",
+        ));
+    }
+
+    let mut report = MismatchReport {
+        texels: labels.len(),
+        mismatched: Vec::new(),
+        ties: 0,
+        unlabeled: 0,
+        max_distance_error: 0.0,
+    };
+    for (texel, (&label, (nearest, best))) in labels.iter().zip(grid.nearest()).enumerate() {
+        if label == nearest {
+            continue;
+        }
+        if label == 0 || label > points.len() {
+            report.unlabeled += 1;
+            continue;
+        }
+        let distance = grid.squared_distance(texel, label - 1);
+        if distance - best <= TIE_TOLERANCE * best.max(1.0) {
+            report.ties += 1;
+        } else {
+            report.mismatched.push(texel);
+            report.max_distance_error =
+                report.max_distance_error.max(distance.sqrt() - best.sqrt());
+        }
+    }
+    Ok(report)
+}
+
+struct Grid<'a> {
+    options: &'a MesherConfig,
+    width: usize,
+    height: usize,
+    /// Seed positions in texels, clamped to the grid as the GPU engine does
+    positions: Vec<(f64, f64)>,
+}
+
+impl Grid<'_> {
+    fn new<'a>(points: &[(f64, f64)], config: (f64, f64), options: &'a MesherConfig) -> Grid<'a> {
+        let (width, height) = options.grid_dimensions(config);
+        let positions = points
+            .iter()
+            .map(|&(x, y)| {
+                (
+                    (x * width as f64 / config.0).clamp(0.0, width as f64),
+                    (y * height as f64 / config.1).clamp(0.0, height as f64),
+                )
+            })
+            .collect();
+        Grid {
+            options,
+            width,
+            height,
+            positions,
+        }
+    }
+
+    // Label and squared distance of the nearest seed to each texel, 0 and infinity without seeds
+    fn nearest(&self) -> Vec<(usize, f64)> {
+        (0..self.width * self.height)
+            .into_par_iter()
+            .map(|texel| {
+                let mut nearest = (0, f64::INFINITY);
+                for seed in 0..self.positions.len() {
+                    let distance = self.squared_distance(texel, seed);
+                    if distance < nearest.1 {
+                        nearest = (seed + 1, distance);
+                    }
+                }
+                nearest
+            })
+            .collect()
+    }
+
+    // Squared norm of the offset from the center of `texel` to `seed`
+    fn squared_distance(&self, texel: usize, seed: usize) -> f64 {
+        let (wrap_x, wrap_y) = self.options.boundary.wraps();
+        let (seed_x, seed_y) = self.positions[seed];
+        let dx = axis_offset(
+            (texel % self.width) as f64 + 0.5,
+            seed_x,
+            self.width,
+            wrap_x,
+        );
+        let dy = axis_offset(
+            (texel / self.width) as f64 + 0.5,
+            seed_y,
+            self.height,
+            wrap_y,
+        );
+
+        let metric = &self.options.metric;
+        match self.options.distance {
+            DistanceMetric::Euclidean => {
+                metric.xx * dx * dx + 2.0 * metric.xy * dx * dy + metric.yy * dy * dy
+            }
+            DistanceMetric::Manhattan => (dx.abs() + dy.abs()).powi(2),
+            DistanceMetric::Chebyshev => dx.abs().max(dy.abs()).powi(2),
+            DistanceMetric::Minkowski(p) => (dx.abs().powf(p) + dy.abs().powf(p)).powf(2.0 / p),
+        }
+    }
+}
+
+// Signed offset along an axis of `size` texels, the shortest way around when it wraps
+fn axis_offset(a: f64, b: f64, size: usize, wraps: bool) -> f64 {
+    let d = a - b;
+    if wraps && 2.0 * d.abs() > size as f64 {
+        d - d.signum() * size as f64
+    } else {
+        d
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::BoundaryMode;
+    use crate::jfa_wgpu;
+    use crate::test_util::{gpu_or_skip, scattered};
+
+    fn options(resolution: usize) -> MesherConfig {
+        MesherConfig {
+            resolution,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_reference_labels() {
+        let points = [(1.0, 1.0), (3.0, 3.0), (3.5, 0.5)];
+        let labels = reference_labels(&points, (4.0, 4.0), &options(8));
+        // Texel (7, 0) is centered at (7.5, 0.5) texels, the third seed being at (7, 1)
+        assert_eq!((labels[0], labels[7], labels[63]), (1, 3, 2));
+
+        // Texel 3 of the middle row is nearer the first seed across the wrapped edge
+        let points = [(0.2, 2.0), (2.0, 2.0)];
+        let bounded = reference_labels(&points, (4.0, 4.0), &options(4));
+        let periodic = MesherConfig {
+            boundary: BoundaryMode::Periodic { x: true, y: false },
+            ..options(4)
+        };
+        assert_eq!(bounded[3 + 2 * 4], 2);
+        assert_eq!(
+            reference_labels(&points, (4.0, 4.0), &periodic)[3 + 2 * 4],
+            1
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let points = [(1.0, 1.0), (3.0, 3.0)];
+        let mut labels = reference_labels(&points, (4.0, 4.0), &options(4));
+        assert!(validate(&labels, &points, (4.0, 4.0), &options(4))
+            .unwrap()
+            .is_exact());
+        assert_eq!(
+            validate(&labels[1..], &points, (4.0, 4.0), &options(4)),
+            Err(MesherError::InvalidInput(
+                "labels must have one entry per texel of the grid"
+            ))
+        );
+
+        // Texel 12 is as near both seeds
+        labels[0] = 2;
+        labels[5] = 0;
+        labels[12] = 2;
+        let report = validate(&labels, &points, (4.0, 4.0), &options(4)).unwrap();
+
+        assert_eq!(report.mismatched, [0]);
+        assert_eq!(report.unlabeled, 1);
+        assert_eq!(report.ties, 1);
+        assert!((report.max_distance_error - 2.0 * 2f64.sqrt()).abs() < 1e-12);
+        assert_eq!(report.error_rate(), 2.0 / 16.0);
+    }
+
+    #[test]
+    fn test_gpu_labels() {
+        let points = scattered(200, (10.0, 10.0));
+        let options = options(256);
+
+        let Some(labels) = gpu_or_skip(jfa_wgpu::main(&points, (10.0, 10.0), &options)) else {
+            return;
+        };
+        let report = validate(&labels, &points, (10.0, 10.0), &options).unwrap();
+
+        assert!(report.error_rate() < 0.005, "{report}");
+    }
+}
@@ -0,0 +1,292 @@
+// Remeshing of an existing polygonal mesh, often a messy legacy one, into a clean Voronoi mesh of
+// the same region: the seeds are the centroids of its elements, or graded Poisson-disk samples
+// following their density, optionally Lloyd-relaxed, and the cells are clipped to the region the
+// input covers, its boundary loops becoming the outer boundaries and holes of a polygon domain.
+//
+// The mesher's box is the bounding box of the input, so the work is done shifted to its corner
+// and the results are shifted back.
+
+use rand::Rng;
+
+use crate::backend;
+use crate::config::MesherConfig;
+use crate::domain::{mesh_cells, Domain, DomainMesh, PolygonDomain};
+use crate::error::MesherError;
+use crate::mesh::PolyMesh;
+use crate::seeds::{poisson_disk_graded, raster_density};
+
+type Point = (f64, f64);
+
+#[derive(Debug, Clone, Default)]
+pub struct RemeshOptions {
+    /// Lloyd iterations moving the seeds to the centroids of their clipped cells, 0 for none
+    pub relax_iterations: usize,
+    /// Cells per input element to draw seeds for, following the local density of the input
+    /// elements, rather than one seed at each element centroid
+    pub density: Option<f64>,
+    /// Labeling and meshing options. Their domain is replaced by the region of the input,
+    /// and their boundary mode must be bounded
+    pub mesher: MesherConfig,
+}
+
+/// Output of [`remesh`], in the coordinates of the input.
+#[derive(Debug, Clone)]
+pub struct Remeshed {
+    pub seeds: Vec<Point>,
+    pub cells: DomainMesh,
+}
+
+/// Meshes the region `mesh` covers with the Voronoi cells of seeds taken from its elements.
+/// `rng` only draws the seeds of `options.density`.
+///
+/// The boundary loops of `mesh` must be simple polygons, as [`PolygonDomain`] requires.
+pub fn remesh(
+    mesh: &PolyMesh,
+    options: &RemeshOptions,
+    rng: &mut impl Rng,
+) -> Result<Remeshed, MesherError> {
+    if mesh.faces.is_empty() {
+        return Err(MesherError::InvalidInput("the mesh has no faces"));
+    }
+    let (mut min, mut max) = ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN));
+    for &(x, y) in &mesh.vertices {
+        min = (min.0.min(x), min.1.min(y));
+        max = (max.0.max(x), max.1.max(y));
+    }
+    let config = (max.0 - min.0, max.1 - min.1);
+    if !(config.0 > 0.0 && config.1 > 0.0) {
+        return Err(MesherError::InvalidInput("the mesh is flat"));
+    }
+    let shift = |(x, y): Point| (x - min.0, y - min.1);
+
+    let mut mesher = options.mesher.clone();
+    mesher.domain = Some(region(mesh, shift)?);
+    let mut seeds: Vec<Point> = match options.density {
+        None => (0..mesh.faces.len())
+            .map(|face| shift(mesh.face_centroid(face)))
+            .collect(),
+        Some(density) => {
+            let dimensions = mesher.grid_dimensions(config);
+            let field = element_density(mesh, shift, dimensions, config, density);
+            poisson_disk_graded(config, raster_density(&field, dimensions, config), rng)
+        }
+    };
+    if seeds.is_empty() {
+        return Err(MesherError::InvalidInput("no seeds were drawn"));
+    }
+
+    let backend = backend::from_config(&mesher)?;
+    let mut iteration = 0;
+    let mut cells = loop {
+        let mut labeling = backend.labels(&seeds, &[], config, &mesher)?;
+        let dimensions = (labeling.width, labeling.height);
+        let cells = mesh_cells(
+            &mut labeling.labels,
+            dimensions,
+            seeds.len(),
+            config,
+            &mesher,
+        )?;
+        if iteration == options.relax_iterations {
+            break cells;
+        }
+        iteration += 1;
+
+        // A cell cut into several faces moves to the centroid of all of them
+        let mut sums = vec![(0.0, (0.0, 0.0)); seeds.len()];
+        for (face, f) in cells.mesh.faces.iter().enumerate() {
+            let area = cells.mesh.face_area(face);
+            let (x, y) = cells.mesh.face_centroid(face);
+            let sum = &mut sums[f.seed];
+            *sum = (sum.0 + area, (sum.1 .0 + x * area, sum.1 .1 + y * area));
+        }
+        for (seed, (area, (x, y))) in seeds.iter_mut().zip(sums) {
+            if area > 0.0 {
+                *seed = (x / area, y / area);
+            }
+        }
+    };
+
+    for vertex in cells.mesh.vertices.iter_mut().chain(&mut seeds) {
+        *vertex = (vertex.0 + min.0, vertex.1 + min.1);
+    }
+    Ok(Remeshed { seeds, cells })
+}
+
+// Polygon domain of the region `mesh` covers, shifted by `shift`
+fn region(mesh: &PolyMesh, shift: impl Fn(Point) -> Point) -> Result<Domain, MesherError> {
+    let loops: Vec<Vec<Point>> = mesh
+        .boundary_loops()
+        .into_iter()
+        .map(|ring| ring.into_iter().map(|v| shift(mesh.vertices[v])).collect())
+        .collect();
+    let (outers, holes): (Vec<_>, Vec<_>) = loops.into_iter().partition(|ring| area(ring) > 0.0);
+    let mut polygons = outers
+        .into_iter()
+        .map(PolygonDomain::new)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(MesherError::InvalidInput)?;
+    for hole in holes {
+        // Holes are inside their outer boundary, so any of their vertices is
+        let (x, y) = hole[0];
+        let Some(outer) = polygons.iter_mut().find(|polygon| polygon.contains(x, y)) else {
+            return Err(MesherError::InvalidInput(
+                "a hole of the mesh is outside of its boundaries",
+            ));
+        };
+        *outer = outer
+            .clone()
+            .with_hole(hole)
+            .map_err(MesherError::InvalidInput)?;
+    }
+    Ok(match polygons.len() {
+        1 => Domain::polygon(polygons.remove(0)),
+        _ => Domain::polygons(polygons),
+    })
+}
+
+// Target seeds per unit area on a `dimensions` raster of the `config` box, `density` over the
+// area of the element under each texel center and 0 outside the mesh
+fn element_density(
+    mesh: &PolyMesh,
+    shift: impl Fn(Point) -> Point,
+    (width, height): (usize, usize),
+    config: (f64, f64),
+    density: f64,
+) -> Vec<f64> {
+    let texel = (config.0 / width as f64, config.1 / height as f64);
+    let mut field = vec![0.0; width * height];
+    for face in 0..mesh.faces.len() {
+        let polygon: Vec<Point> = mesh.face_polygon(face).into_iter().map(&shift).collect();
+        let area = mesh.face_area(face);
+        if area <= 0.0 {
+            continue;
+        }
+        let (mut low, mut high) = ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN));
+        for &(x, y) in &polygon {
+            low = (low.0.min(x), low.1.min(y));
+            high = (high.0.max(x), high.1.max(y));
+        }
+        let columns = (low.0 / texel.0) as usize..((high.0 / texel.0).ceil() as usize).min(width);
+        let rows = (low.1 / texel.1) as usize..((high.1 / texel.1).ceil() as usize).min(height);
+        for j in rows {
+            for i in columns.clone() {
+                let center = ((i as f64 + 0.5) * texel.0, (j as f64 + 0.5) * texel.1);
+                if inside(&polygon, center) {
+                    field[i + j * width] = density / area;
+                }
+            }
+        }
+    }
+    field
+}
+
+fn area(polygon: &[Point]) -> f64 {
+    let n = polygon.len();
+    (0..n)
+        .map(|i| {
+            let ((ax, ay), (bx, by)) = (polygon[i], polygon[(i + 1) % n]);
+            ax * by - bx * ay
+        })
+        .sum::<f64>()
+        / 2.0
+}
+
+// Even-odd test, which doesn't mind the degenerate elements of messy meshes
+fn inside(polygon: &[Point], (x, y): Point) -> bool {
+    let n = polygon.len();
+    let mut inside = false;
+    for i in 0..n {
+        let ((ax, ay), (bx, by)) = (polygon[i], polygon[(i + 1) % n]);
+        if (ay > y) != (by > y) && x < ax + (y - ay) / (by - ay) * (bx - ax) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::config::{Backend, GridShape};
+
+    // L-shaped block of unit squares away from the origin, one of them split into slivers
+    fn messy_mesh() -> PolyMesh {
+        let square = |x: f64, y: f64| vec![(x, y), (x + 1.0, y), (x + 1.0, y + 1.0), (x, y + 1.0)];
+        let mut cells: Vec<Vec<Point>> = [(0, 0), (1, 0), (2, 0), (0, 1), (0, 2)]
+            .iter()
+            .map(|&(x, y)| square(10.0 + x as f64, 5.0 + y as f64))
+            .collect();
+        cells.push(vec![(11.0, 6.0), (12.0, 6.0), (12.0, 6.1)]);
+        cells.push(vec![(11.0, 6.0), (12.0, 6.1), (12.0, 7.0), (11.0, 7.0)]);
+        PolyMesh::from_cells(&cells)
+    }
+
+    fn options(relax_iterations: usize, density: Option<f64>) -> RemeshOptions {
+        RemeshOptions {
+            relax_iterations,
+            density,
+            mesher: MesherConfig {
+                resolution: 96,
+                grid: GridShape::FitDomain,
+                backend: Backend::Exact,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn total_area(mesh: &PolyMesh) -> f64 {
+        (0..mesh.faces.len()).map(|face| mesh.face_area(face)).sum()
+    }
+
+    #[test]
+    fn test_remesh() {
+        let mesh = messy_mesh();
+        let mut rng = StdRng::seed_from_u64(7);
+
+        let remeshed = remesh(&mesh, &options(0, None), &mut rng).unwrap();
+
+        assert_eq!(remeshed.seeds.len(), 7);
+        assert_eq!(remeshed.seeds[0], (10.5, 5.5));
+        assert!((total_area(&remeshed.cells.mesh) - 6.0).abs() < 1e-9);
+        assert!(remeshed
+            .cells
+            .mesh
+            .vertices
+            .iter()
+            .all(|&(x, y)| (10.0..=13.0).contains(&x) && (5.0..=8.0).contains(&y)));
+    }
+
+    #[test]
+    fn test_remesh_relaxed() {
+        let mesh = messy_mesh();
+        let mut rng = StdRng::seed_from_u64(7);
+
+        let relaxed = remesh(&mesh, &options(3, None), &mut rng).unwrap();
+        let graded = remesh(&mesh, &options(0, Some(2.0)), &mut rng).unwrap();
+
+        assert!((total_area(&relaxed.cells.mesh) - 6.0).abs() < 1e-9);
+        // The sliver's seed moves off the corner of the square it shares
+        assert_ne!(relaxed.seeds[5], mesh.face_centroid(5));
+        assert!(
+            (6..=24).contains(&graded.seeds.len()),
+            "{}",
+            graded.seeds.len()
+        );
+        assert!((total_area(&graded.cells.mesh) - 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_remesh_errors() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let flat = PolyMesh::from_cells(&[vec![(0.0, 0.0), (1.0, 0.0), (2.0, 0.0)]]);
+
+        for mesh in [PolyMesh::from_cells(&[]), flat] {
+            let err = remesh(&mesh, &options(0, None), &mut rng).unwrap_err();
+            assert!(matches!(err, MesherError::InvalidInput(_)));
+        }
+    }
+}
@@ -0,0 +1,38 @@
+// Images of meshes for debugging and documentation.
+
+#[cfg(feature = "png")]
+pub mod png;
+pub mod svg;
+
+/// Fill color of cell `index`, spreading hues by the golden angle so neighboring indices get
+/// distinct colors.
+pub fn cell_color(index: usize) -> (u8, u8, u8) {
+    let hue = (index as f64 * 137.507_764) % 360.0;
+    let (saturation, value) = (0.55, 0.9);
+
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let (r, g, b) = match (hue / 60.0) as usize {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    let channel = |c: f64| ((c + m) * 255.0).round() as u8;
+    (channel(r), channel(g), channel(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cell_color() {
+        assert_eq!(cell_color(0), (230, 103, 103));
+        assert_ne!(cell_color(1), cell_color(0));
+        assert_eq!(cell_color(7), cell_color(7));
+    }
+}
@@ -0,0 +1,52 @@
+// PNG images of label grids, one pixel per texel, with the cell colors of the SVG drawings.
+
+use std::path::Path;
+
+use image::{ImageResult, Rgb, RgbImage};
+
+use super::cell_color;
+
+// Color of unassigned texels
+const BACKGROUND: Rgb<u8> = Rgb([255, 255, 255]);
+
+/// Image of a label grid of `(width, height)` texels, row 0 at the bottom so the y axis points
+/// up as in the domain.
+pub fn labels_image(labels: &[usize], (width, height): (usize, usize)) -> RgbImage {
+    assert_eq!(labels.len(), width * height, "label grid size mismatch");
+    let (width, height) = (width as u32, height as u32);
+    RgbImage::from_fn(width, height, |x, y| {
+        match labels[(x + (height - 1 - y) * width) as usize] {
+            0 => BACKGROUND,
+            label => {
+                let (r, g, b) = cell_color(label - 1);
+                Rgb([r, g, b])
+            }
+        }
+    })
+}
+
+/// Writes the label grid to a PNG file.
+pub fn write_png(
+    path: impl AsRef<Path>,
+    labels: &[usize],
+    dimensions: (usize, usize),
+) -> ImageResult<()> {
+    labels_image(labels, dimensions).save_with_format(path, image::ImageFormat::Png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_labels_image() {
+        let image = labels_image(&[1, 0, 2, 2, 0, 1], (3, 2));
+
+        assert_eq!(image.dimensions(), (3, 2));
+        let (r, g, b) = cell_color(1);
+        // The top row of the image is the last one of the grid
+        assert_eq!(*image.get_pixel(0, 0), Rgb([r, g, b]));
+        assert_eq!(*image.get_pixel(1, 0), BACKGROUND);
+        assert_eq!(*image.get_pixel(1, 1), BACKGROUND);
+    }
+}
@@ -0,0 +1,194 @@
+// SVG drawings of a diagram, either from the extracted cell polygons or straight from the label
+// grid, with the seeds on top. The y axis points up as in the domain, so drawings are flipped
+// relative to SVG's own coordinates.
+
+use std::fmt::Write;
+
+use super::cell_color;
+
+/// What to draw and at which size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SvgOptions {
+    /// Width of the image in pixels, the height following the aspect ratio of the domain
+    pub width: f64,
+    /// Radius of the seed markers in pixels, none are drawn when it is 0
+    pub seed_radius: f64,
+    /// Writes the index of each seed next to its marker
+    pub show_indices: bool,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        SvgOptions {
+            width: 800.0,
+            seed_radius: 2.0,
+            show_indices: false,
+        }
+    }
+}
+
+/// Draws the cells, as returned by `cells::extract_cells`, filled with the color of their seed.
+pub fn polygons_svg(
+    cells: &[Vec<(f64, f64)>],
+    seeds: &[(f64, f64)],
+    config: (f64, f64),
+    options: &SvgOptions,
+) -> String {
+    let canvas = Canvas::new(config, options);
+    let mut svg = canvas.header();
+
+    for (index, cell) in cells.iter().enumerate() {
+        if cell.is_empty() {
+            continue;
+        }
+        let points: Vec<String> = cell
+            .iter()
+            .map(|&point| {
+                let (x, y) = canvas.project(point);
+                format!("{x},{y}")
+            })
+            .collect();
+        let _ = writeln!(
+            svg,
+            r#"<polygon points="{}" fill="{}" stroke="black" stroke-width="0.5"/>"#,
+            points.join(" "),
+            fill(index)
+        );
+    }
+
+    canvas.finish(svg, seeds)
+}
+
+/// Draws the texels of a label grid of `(width, height)` texels, merging each row's runs of
+/// equal labels into one rectangle. Unassigned texels are left transparent.
+pub fn labels_svg(
+    labels: &[usize],
+    (width, height): (usize, usize),
+    seeds: &[(f64, f64)],
+    config: (f64, f64),
+    options: &SvgOptions,
+) -> String {
+    assert_eq!(labels.len(), width * height, "label grid size mismatch");
+    let canvas = Canvas::new(config, options);
+    let mut svg = canvas.header();
+    let texel = (canvas.width / width as f64, canvas.height / height as f64);
+
+    for (y, row) in labels.chunks(width.max(1)).enumerate() {
+        let mut start = 0;
+        while start < row.len() {
+            let label = row[start];
+            let end = start + row[start..].iter().take_while(|&&l| l == label).count();
+            if label != 0 {
+                // Row y covers the band between y and y + 1 in texels, from the bottom
+                let _ = writeln!(
+                    svg,
+                    r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
+                    start as f64 * texel.0,
+                    canvas.height - (y + 1) as f64 * texel.1,
+                    (end - start) as f64 * texel.0,
+                    texel.1,
+                    fill(label - 1)
+                );
+            }
+            start = end;
+        }
+    }
+
+    canvas.finish(svg, seeds)
+}
+
+fn fill(index: usize) -> String {
+    let (r, g, b) = cell_color(index);
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+struct Canvas {
+    config: (f64, f64),
+    width: f64,
+    height: f64,
+    options: SvgOptions,
+}
+
+impl Canvas {
+    fn new(config: (f64, f64), options: &SvgOptions) -> Canvas {
+        Canvas {
+            config,
+            width: options.width,
+            height: options.width * config.1 / config.0,
+            options: *options,
+        }
+    }
+
+    fn project(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        (
+            x * self.width / self.config.0,
+            self.height - y * self.height / self.config.1,
+        )
+    }
+
+    fn header(&self) -> String {
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+            w = self.width,
+            h = self.height
+        )
+    }
+
+    fn finish(&self, mut svg: String, seeds: &[(f64, f64)]) -> String {
+        for (index, &seed) in seeds.iter().enumerate() {
+            let (x, y) = self.project(seed);
+            if self.options.seed_radius > 0.0 {
+                let _ = writeln!(
+                    svg,
+                    r#"<circle cx="{x}" cy="{y}" r="{}" fill="black"/>"#,
+                    self.options.seed_radius
+                );
+            }
+            if self.options.show_indices {
+                let _ = writeln!(
+                    svg,
+                    r#"<text x="{}" y="{y}" font-size="10" font-family="sans-serif">{index}</text>"#,
+                    x + self.options.seed_radius + 1.0
+                );
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_polygons() {
+        let cells = vec![vec![(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (0.0, 1.0)], vec![]];
+        let options = SvgOptions {
+            width: 100.0,
+            show_indices: true,
+            ..Default::default()
+        };
+
+        let svg = polygons_svg(&cells, &[(1.0, 0.5), (3.0, 0.5)], (4.0, 2.0), &options);
+
+        assert!(svg.contains(r#"width="100" height="50""#));
+        assert_eq!(svg.matches("<polygon").count(), 1);
+        assert!(svg.contains(r##"points="0,50 50,50 50,25 0,25" fill="#e66767""##));
+        assert!(svg.contains(r#"<circle cx="75" cy="37.5""#));
+        assert!(svg.contains(">1</text>"));
+        assert!(svg.ends_with("</svg>\n"));
+    }
+
+    #[test]
+    fn test_label_runs() {
+        let labels = vec![1, 1, 2, 0];
+
+        let svg = labels_svg(&labels, (2, 2), &[], (2.0, 2.0), &SvgOptions::default());
+
+        assert_eq!(svg.matches("<rect").count(), 2);
+        assert!(svg.contains(r#"<rect x="0" y="400" width="800" height="400""#));
+        assert!(svg.contains(r#"<rect x="0" y="0" width="400" height="400""#));
+        assert!(!svg.contains("<circle"));
+    }
+}
@@ -0,0 +1,253 @@
+// Seed generators for users who just want a well-spaced point set over their domain.
+
+use std::f64::consts::{PI, SQRT_2, TAU};
+
+use rand::Rng;
+
+// Candidates tried around an active sample before it is retired, as in Bridson's paper
+const ATTEMPTS: usize = 30;
+// Fraction of the domain area a maximal Poisson-disk set of radius r covers with disks of
+// radius r / 2, measured on large boxes
+const PACKING_DENSITY: f64 = 0.547;
+// Density samples along each axis to size the background grid of graded sampling
+const DENSITY_PROBES: usize = 64;
+
+/// Poisson-disk samples of the `config` box, at least `radius` apart from each other.
+pub fn poisson_disk(config: (f64, f64), radius: f64, rng: &mut impl Rng) -> Vec<(f64, f64)> {
+    poisson_disk_in(config, radius, |_, _| -1.0, rng)
+}
+
+/// Same as [`poisson_disk`], keeping only the samples inside the domain of a signed distance
+/// function, negative inside. Samples grow from a first one, so parts of the domain which are
+/// disconnected from it, or only joined through necks narrower than `radius`, can stay empty.
+pub fn poisson_disk_in(
+    config: (f64, f64),
+    radius: f64,
+    sdf: impl Fn(f64, f64) -> f64,
+    rng: &mut impl Rng,
+) -> Vec<(f64, f64)> {
+    if radius.is_nan() || radius <= 0.0 {
+        return Vec::new();
+    }
+    bridson(config, radius, |_, _| radius, sdf, rng)
+}
+
+/// Graded Poisson-disk samples whose local count per unit area follows `density`, for meshes
+/// which are fine near features and coarse elsewhere. No samples are placed where the density
+/// is 0 or negative.
+///
+/// Two samples are at least the smaller of the spacings the density asks for at each of them
+/// apart, so the fine side of a steep density change spills a little into the coarse side.
+pub fn poisson_disk_graded(
+    config: (f64, f64),
+    density: impl Fn(f64, f64) -> f64,
+    rng: &mut impl Rng,
+) -> Vec<(f64, f64)> {
+    let radius = |x: f64, y: f64| match density(x, y) {
+        d if d > 0.0 => radius_for_density(d),
+        _ => f64::NAN,
+    };
+
+    // The background grid only needs the smallest radius to be fast, not to be correct
+    let mut min_radius = f64::INFINITY;
+    for i in 0..DENSITY_PROBES {
+        for j in 0..DENSITY_PROBES {
+            let x = (i as f64 + 0.5) * config.0 / DENSITY_PROBES as f64;
+            let y = (j as f64 + 0.5) * config.1 / DENSITY_PROBES as f64;
+            min_radius = min_radius.min(radius(x, y));
+        }
+    }
+    if !min_radius.is_finite() {
+        return Vec::new();
+    }
+
+    bridson(
+        config,
+        min_radius,
+        radius,
+        |x, y| if radius(x, y).is_nan() { 1.0 } else { -1.0 },
+        rng,
+    )
+}
+
+/// Density following a raster of `resolution.0 * resolution.1` samples covering the `config`
+/// box, indexed with `x + y * resolution.0`, constant over each texel.
+pub fn raster_density(
+    field: &[f64],
+    resolution: (usize, usize),
+    config: (f64, f64),
+) -> impl Fn(f64, f64) -> f64 + '_ {
+    let (width, height) = resolution;
+    assert_eq!(field.len(), width * height, "field size mismatch");
+    move |x, y| {
+        let i = ((x / config.0 * width as f64) as usize).min(width - 1);
+        let j = ((y / config.1 * height as f64) as usize).min(height - 1);
+        field[i + j * width]
+    }
+}
+
+/// Radius for which [`poisson_disk`] gives about `count` samples in the `config` box.
+pub fn radius_for_count(config: (f64, f64), count: usize) -> f64 {
+    radius_for_density(count.max(1) as f64 / (config.0 * config.1))
+}
+
+fn radius_for_density(density: f64) -> f64 {
+    // A maximal set covers `PACKING_DENSITY` of the area with its disks of radius r / 2
+    (4.0 * PACKING_DENSITY / (PI * density)).sqrt()
+}
+
+// Bridson's algorithm with a radius which can vary over the domain: a candidate is rejected
+// when a sample is closer than the smaller of their two radii. The background grid has cells of
+// side `min_radius / √2`, so with a constant radius each cell holds at most one sample and
+// checking a candidate only looks at the 5×5 cells around it
+fn bridson(
+    config: (f64, f64),
+    min_radius: f64,
+    radius: impl Fn(f64, f64) -> f64,
+    sdf: impl Fn(f64, f64) -> f64,
+    rng: &mut impl Rng,
+) -> Vec<(f64, f64)> {
+    if config.0 <= 0.0 || config.1 <= 0.0 {
+        return Vec::new();
+    }
+
+    let cell_size = min_radius / SQRT_2;
+    let grid_width = ((config.0 / cell_size).ceil() as usize).max(1);
+    let grid_height = ((config.1 / cell_size).ceil() as usize).max(1);
+    let mut grid: Vec<Vec<usize>> = vec![Vec::new(); grid_width * grid_height];
+    let cell_of = |point: (f64, f64)| {
+        (
+            ((point.0 / cell_size) as usize).min(grid_width - 1),
+            ((point.1 / cell_size) as usize).min(grid_height - 1),
+        )
+    };
+    let inside = |point: (f64, f64)| {
+        point.0 >= 0.0
+            && point.0 < config.0
+            && point.1 >= 0.0
+            && point.1 < config.1
+            && sdf(point.0, point.1) <= 0.0
+    };
+
+    let mut samples: Vec<(f64, f64)> = Vec::new();
+    let mut radii = Vec::new();
+    let mut active = Vec::new();
+
+    let first = (0..ATTEMPTS * ATTEMPTS)
+        .map(|_| (rng.gen::<f64>() * config.0, rng.gen::<f64>() * config.1))
+        .find(|&point| inside(point));
+    let Some(first) = first else {
+        return samples;
+    };
+    let (x, y) = cell_of(first);
+    grid[x + y * grid_width].push(0);
+    samples.push(first);
+    radii.push(radius(first.0, first.1));
+    active.push(0);
+
+    while !active.is_empty() {
+        let slot = rng.gen_range(0..active.len());
+        let center = samples[active[slot]];
+        let center_radius = radii[active[slot]];
+
+        let mut accepted = false;
+        for _ in 0..ATTEMPTS {
+            // Uniform in the annulus between the radius and twice the radius
+            let angle = rng.gen::<f64>() * TAU;
+            let distance = center_radius * (1.0 + 3.0 * rng.gen::<f64>()).sqrt();
+            let candidate = (
+                center.0 + distance * angle.cos(),
+                center.1 + distance * angle.sin(),
+            );
+            if !inside(candidate) {
+                continue;
+            }
+
+            let candidate_radius = radius(candidate.0, candidate.1);
+            let clear = |other: &usize| {
+                let point = samples[*other];
+                (point.0 - candidate.0).hypot(point.1 - candidate.1)
+                    >= candidate_radius.min(radii[*other])
+            };
+            let reach = (candidate_radius / cell_size).ceil() as usize;
+            let (cx, cy) = cell_of(candidate);
+            let xs = cx.saturating_sub(reach)..(cx + reach + 1).min(grid_width);
+            let far_enough =
+                (cy.saturating_sub(reach)..(cy + reach + 1).min(grid_height)).all(|y| {
+                    xs.clone()
+                        .all(|x| grid[x + y * grid_width].iter().all(clear))
+                });
+            if far_enough {
+                grid[cx + cy * grid_width].push(samples.len());
+                active.push(samples.len());
+                samples.push(candidate);
+                radii.push(candidate_radius);
+                accepted = true;
+                break;
+            }
+        }
+
+        if !accepted {
+            active.swap_remove(slot);
+        }
+    }
+
+    samples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_minimum_distance() {
+        let mut rng = StdRng::seed_from_u64(7);
+
+        let samples = poisson_disk((10.0, 5.0), 0.5, &mut rng);
+
+        for (i, a) in samples.iter().enumerate() {
+            assert!(a.0 >= 0.0 && a.0 < 10.0 && a.1 >= 0.0 && a.1 < 5.0);
+            for b in &samples[i + 1..] {
+                assert!((a.0 - b.0).hypot(a.1 - b.1) >= 0.5);
+            }
+        }
+    }
+
+    #[test]
+    fn test_target_count() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let radius = radius_for_count((20.0, 10.0), 500);
+
+        let count = poisson_disk((20.0, 10.0), radius, &mut rng).len();
+
+        assert!((400..600).contains(&count), "{count} samples");
+    }
+
+    #[test]
+    fn test_inside_domain() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let circle = |x: f64, y: f64| (x - 5.0).hypot(y - 5.0) - 4.0;
+
+        let samples = poisson_disk_in((10.0, 10.0), 0.5, circle, &mut rng);
+
+        assert!(!samples.is_empty());
+        assert!(samples.iter().all(|&(x, y)| circle(x, y) <= 0.0));
+    }
+
+    #[test]
+    fn test_graded_density() {
+        let mut rng = StdRng::seed_from_u64(7);
+        // Four times denser on the left half, nothing on the top strip
+        let field = [8.0, 2.0, 8.0, 2.0, 0.0, 0.0];
+        let density = raster_density(&field, (2, 3), (10.0, 15.0));
+
+        let samples = poisson_disk_graded((10.0, 15.0), density, &mut rng);
+
+        let left = samples.iter().filter(|p| p.0 < 5.0).count() as f64;
+        let right = samples.iter().filter(|p| p.0 >= 5.0).count() as f64;
+        assert!(samples.iter().all(|p| p.1 < 10.0));
+        assert!((2.5..6.0).contains(&(left / right)), "{left} / {right}");
+    }
+}
@@ -0,0 +1,420 @@
+// Symmetric seed sets and label grids. Seeds of the fundamental domain are replicated by the
+// symmetry. With a mirror, the cells of the fundamental half only hold seeds of that half, since
+// a texel is always nearer a seed on its side than that seed's image, so only the half grid is
+// labeled, from the fundamental seeds, and reflected. The cells of the fundamental domain of a
+// rotation are bounded by images of seeds from across its edges, so rotations label the whole
+// grid and rebuild it from one representative texel per orbit, making the result exactly
+// symmetric.
+
+use crate::backend::{Labeling, VoronoiBackend};
+use crate::config::{GridShape, MesherConfig};
+use crate::error::MesherError;
+
+const EPSILON: f64 = 1e-9;
+
+/// Symmetries about the center of the domain box.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Symmetry {
+    /// Mirror across the vertical line `x = width / 2`
+    MirrorX,
+    /// Mirror across the horizontal line `y = height / 2`
+    MirrorY,
+    /// Rotation by 180°
+    HalfTurn,
+    /// Rotation by 90°, square domains only
+    QuarterTurn,
+}
+
+impl Symmetry {
+    fn order(self) -> usize {
+        match self {
+            Symmetry::QuarterTurn => 4,
+            _ => 2,
+        }
+    }
+
+    fn apply(self, point: (f64, f64), config: (f64, f64)) -> (f64, f64) {
+        let (cx, cy) = (config.0 / 2.0, config.1 / 2.0);
+        match self {
+            Symmetry::MirrorX => (config.0 - point.0, point.1),
+            Symmetry::MirrorY => (point.0, config.1 - point.1),
+            Symmetry::HalfTurn => (config.0 - point.0, config.1 - point.1),
+            Symmetry::QuarterTurn => (cx - (point.1 - cy), cy + (point.0 - cx)),
+        }
+    }
+
+    fn apply_pixel(self, pixel: (usize, usize), (width, height): (usize, usize)) -> (usize, usize) {
+        let (x, y) = pixel;
+        match self {
+            Symmetry::MirrorX => (width - 1 - x, y),
+            Symmetry::MirrorY => (x, height - 1 - y),
+            Symmetry::HalfTurn => (width - 1 - x, height - 1 - y),
+            Symmetry::QuarterTurn => (width - 1 - y, x),
+        }
+    }
+
+    fn in_fundamental_domain(self, point: (f64, f64), config: (f64, f64)) -> bool {
+        let (dx, dy) = (point.0 - config.0 / 2.0, point.1 - config.1 / 2.0);
+        match self {
+            Symmetry::MirrorX => dx <= EPSILON,
+            Symmetry::MirrorY => dy <= EPSILON,
+            Symmetry::HalfTurn => dy < -EPSILON || (dy.abs() <= EPSILON && dx <= EPSILON),
+            // Angles in [0°, 90°), plus the center
+            Symmetry::QuarterTurn => {
+                (dx > EPSILON && dy >= -EPSILON) || (dx.abs() <= EPSILON && dy.abs() <= EPSILON)
+            }
+        }
+    }
+}
+
+/// A seed set invariant under a symmetry.
+#[derive(Debug, Clone)]
+pub struct SymmetricSeeds {
+    pub symmetry: Symmetry,
+    /// Fundamental domain seeds first, followed by their images
+    pub points: Vec<(f64, f64)>,
+    /// Number of fundamental domain seeds
+    pub fundamental: usize,
+    /// Index of the image of each seed by one application of the symmetry
+    pub image: Vec<usize>,
+}
+
+/// Keeps the seeds of the fundamental domain and adds their images.
+pub fn symmetrize(
+    points: &[(f64, f64)],
+    config: (f64, f64),
+    symmetry: Symmetry,
+) -> Result<SymmetricSeeds, &'static str> {
+    if symmetry == Symmetry::QuarterTurn && (config.0 - config.1).abs() > EPSILON {
+        return Err("a quarter-turn symmetry needs a square domain");
+    }
+
+    let mut symmetric_points: Vec<(f64, f64)> = points
+        .iter()
+        .copied()
+        .filter(|&point| symmetry.in_fundamental_domain(point, config))
+        .collect();
+
+    let fundamental = symmetric_points.len();
+    // Orbit of each fundamental seed, as indices into the symmetric set
+    let mut orbits: Vec<Vec<usize>> = (0..symmetric_points.len()).map(|i| vec![i]).collect();
+    for orbit in &mut orbits {
+        let seed = symmetric_points[orbit[0]];
+        let mut point = symmetry.apply(seed, config);
+        while orbit.len() < symmetry.order() && !same_point(point, seed) {
+            symmetric_points.push(point);
+            orbit.push(symmetric_points.len() - 1);
+            point = symmetry.apply(point, config);
+        }
+    }
+
+    let mut image = vec![0; symmetric_points.len()];
+    for orbit in &orbits {
+        for (r, &index) in orbit.iter().enumerate() {
+            image[index] = orbit[(r + 1) % orbit.len()];
+        }
+    }
+
+    Ok(SymmetricSeeds {
+        symmetry,
+        points: symmetric_points,
+        fundamental,
+        image,
+    })
+}
+
+/// Rebuilds a label grid of `(width, height)` texels from one texel per orbit of the symmetry,
+/// so that the labels of symmetric texels are exactly the images of each other. A quarter turn
+/// needs a square grid.
+pub fn enforce_symmetry(labels: &mut [usize], dimensions: (usize, usize), seeds: &SymmetricSeeds) {
+    let (width, height) = dimensions;
+    assert_eq!(labels.len(), width * height, "label grid size mismatch");
+    let symmetry = seeds.symmetry;
+    assert!(
+        symmetry != Symmetry::QuarterTurn || width == height,
+        "a quarter-turn symmetry needs a square grid"
+    );
+
+    for y in 0..height {
+        for x in 0..width {
+            let index = x + y * width;
+            let mut orbit = vec![index];
+            let mut pixel = symmetry.apply_pixel((x, y), dimensions);
+            while orbit.len() < symmetry.order() && pixel != (x, y) {
+                orbit.push(pixel.0 + pixel.1 * width);
+                pixel = symmetry.apply_pixel(pixel, dimensions);
+            }
+
+            // The texel of smallest index stands for its orbit
+            if orbit.iter().any(|&other| other < index) {
+                continue;
+            }
+            let mut label = labels[index];
+            for &other in &orbit[1..] {
+                if label != 0 {
+                    label = seeds.image[label - 1] + 1;
+                }
+                labels[other] = label;
+            }
+        }
+    }
+}
+
+/// Backend labeling seed sets invariant under a symmetry, as [`symmetrize`] returns them, with
+/// another backend, which [`crate::backend::from_config`] wraps when
+/// [`MesherConfig::symmetry`] is set. Weights must be symmetric too.
+pub struct SymmetricBackend {
+    inner: Box<dyn VoronoiBackend>,
+    symmetry: Symmetry,
+}
+
+impl SymmetricBackend {
+    pub fn new(inner: Box<dyn VoronoiBackend>, symmetry: Symmetry) -> SymmetricBackend {
+        SymmetricBackend { inner, symmetry }
+    }
+
+    // Labels the fundamental half with its own seeds, then reflects it
+    fn mirrored(
+        &self,
+        seeds: &SymmetricSeeds,
+        weights: &[f64],
+        config: (f64, f64),
+        options: &MesherConfig,
+    ) -> Result<Labeling, MesherError> {
+        let (width, height) = options.grid_dimensions(config);
+        let mirror_x = self.symmetry == Symmetry::MirrorX;
+        // The middle column or row of an odd grid is on the mirror, and in the half
+        let (half_width, half_height) = match mirror_x {
+            true => (width.div_ceil(2), height),
+            false => (width, height.div_ceil(2)),
+        };
+        let half_config = (
+            config.0 * half_width as f64 / width as f64,
+            config.1 * half_height as f64 / height as f64,
+        );
+        // Seeds on the mirror may be a rounding error past the half box
+        let points: Vec<(f64, f64)> = seeds.points[..seeds.fundamental]
+            .iter()
+            .map(|&(x, y)| match mirror_x {
+                true => (x.min(half_config.0), y),
+                false => (x, y.min(half_config.1)),
+            })
+            .collect();
+        let half_options = MesherConfig {
+            grid: GridShape::Texels {
+                width: half_width,
+                height: half_height,
+            },
+            ..options.clone()
+        };
+        let weights = &weights[..weights.len().min(seeds.fundamental)];
+        let half = self
+            .inner
+            .labels(&points, weights, half_config, &half_options)?;
+
+        // Texel of the half each texel is read from, and whether it is reflected
+        let sources: Vec<(usize, bool)> = (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                match mirror_x {
+                    true if x >= half_width => (width - 1 - x + y * half_width, true),
+                    false if y >= half_height => (x + (height - 1 - y) * half_width, true),
+                    _ => (x + y * half_width, false),
+                }
+            })
+            .collect();
+        let labels = sources
+            .iter()
+            .map(|&(source, reflected)| match half.labels[source] {
+                label if reflected && label != 0 => seeds.image[label - 1] + 1,
+                label => label,
+            })
+            .collect();
+        let distances = half.distances.map(|distances| {
+            sources
+                .iter()
+                .map(|&(source, _)| distances[source])
+                .collect()
+        });
+
+        Ok(Labeling {
+            labels,
+            width,
+            height,
+            distances,
+        })
+    }
+}
+
+impl VoronoiBackend for SymmetricBackend {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn labels(
+        &self,
+        points: &[(f64, f64)],
+        weights: &[f64],
+        config: (f64, f64),
+        options: &MesherConfig,
+    ) -> Result<Labeling, MesherError> {
+        let seeds = symmetrize(points, config, self.symmetry).map_err(MesherError::InvalidInput)?;
+        if seeds.points != points {
+            return Err(MesherError::InvalidInput(
+                "the seeds must be symmetric, as symmetry::symmetrize returns them",
+            ));
+        }
+        if !weights.is_empty()
+            && (weights.len() != points.len()
+                || (0..weights.len()).any(|i| weights[seeds.image[i]] != weights[i]))
+        {
+            return Err(MesherError::InvalidInput(
+                "weights must be empty or one per seed, equal along each orbit",
+            ));
+        }
+        let options = MesherConfig {
+            symmetry: None,
+            ..options.clone()
+        };
+        let dimensions = options.grid_dimensions(config);
+        check_invariant(self.symmetry, &options, dimensions)?;
+
+        match self.symmetry {
+            Symmetry::MirrorX | Symmetry::MirrorY => {
+                self.mirrored(&seeds, weights, config, &options)
+            }
+            Symmetry::HalfTurn | Symmetry::QuarterTurn => {
+                let mut labeling = self.inner.labels(points, weights, config, &options)?;
+                enforce_symmetry(&mut labeling.labels, dimensions, &seeds);
+                // Rebuilt texels may no longer hold the distance to their seed
+                labeling.distances = None;
+                Ok(labeling)
+            }
+        }
+    }
+}
+
+// Rejects the options under which the diagram of symmetric seeds isn't symmetric, or a
+// mirrored half can't be labeled on its own
+fn check_invariant(
+    symmetry: Symmetry,
+    options: &MesherConfig,
+    (width, height): (usize, usize),
+) -> Result<(), MesherError> {
+    let metric = options.metric;
+    let (wrap_x, wrap_y) = options.boundary.wraps();
+    let invariant = match symmetry {
+        Symmetry::MirrorX => metric.xy == 0.0 && !wrap_x,
+        Symmetry::MirrorY => metric.xy == 0.0 && !wrap_y,
+        Symmetry::HalfTurn => true,
+        Symmetry::QuarterTurn => {
+            metric.xy == 0.0 && metric.xx == metric.yy && wrap_x == wrap_y && width == height
+        }
+    };
+    match invariant {
+        true => Ok(()),
+        false => Err(MesherError::InvalidInput(
+            "the symmetry needs an invariant metric, grid and boundary, not wrapping across a mirror",
+        )),
+    }
+}
+
+fn same_point(a: (f64, f64), b: (f64, f64)) -> bool {
+    (a.0 - b.0).abs() <= EPSILON && (a.1 - b.1).abs() <= EPSILON
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mirror_seeds() {
+        let points = [(1.0, 1.0), (5.0, 3.0), (8.0, 8.0)];
+
+        let seeds = symmetrize(&points, (10.0, 10.0), Symmetry::MirrorX).unwrap();
+
+        // The seed on the mirror line is its own image, the one outside is dropped
+        assert_eq!(seeds.points, vec![(1.0, 1.0), (5.0, 3.0), (9.0, 1.0)]);
+        assert_eq!(seeds.image, vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn test_quarter_turn_seeds() {
+        let seeds = symmetrize(
+            &[(7.0, 5.0), (5.0, 5.0)],
+            (10.0, 10.0),
+            Symmetry::QuarterTurn,
+        );
+        let seeds = seeds.unwrap();
+
+        assert_eq!(seeds.points.len(), 5);
+        assert!(same_point(seeds.points[3], (3.0, 5.0)));
+        assert_eq!(seeds.image, vec![2, 1, 3, 4, 0]);
+
+        assert!(symmetrize(&[(1.0, 1.0)], (10.0, 5.0), Symmetry::QuarterTurn).is_err());
+    }
+
+    #[test]
+    fn test_enforce_symmetry() {
+        let seeds = symmetrize(&[(1.0, 2.0)], (4.0, 4.0), Symmetry::HalfTurn).unwrap();
+        // Label 1 on the whole grid, as an inexact JFA could leave it
+        let mut labels = vec![1; 16];
+
+        enforce_symmetry(&mut labels, (4, 4), &seeds);
+
+        for y in 0..4 {
+            for x in 0..4 {
+                let label = labels[x + y * 4];
+                let image = labels[(3 - x) + (3 - y) * 4];
+                assert_eq!(seeds.image[label - 1], image - 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_symmetric_backend() {
+        let config = (9.0, 6.0);
+        let seeds = symmetrize(
+            &[(1.2, 1.3), (3.1, 4.4), (4.5, 2.7)],
+            config,
+            Symmetry::MirrorX,
+        )
+        .unwrap();
+        let options = MesherConfig {
+            backend: crate::config::Backend::Exact,
+            symmetry: Some(Symmetry::MirrorX),
+            resolution: 9,
+            grid: GridShape::FitDomain,
+            ..Default::default()
+        };
+        let backend = crate::backend::from_config(&options).unwrap();
+
+        // The reflected half matches the whole odd grid labeled at once
+        let labeling = backend
+            .labels(&seeds.points, &[], config, &options)
+            .unwrap();
+        let whole = crate::backend::ExactBackend
+            .labels(
+                &seeds.points,
+                &[],
+                config,
+                &MesherConfig {
+                    symmetry: None,
+                    ..options.clone()
+                },
+            )
+            .unwrap();
+        assert_eq!((labeling.width, labeling.height), (9, 6));
+        assert_eq!(labeling.labels, whole.labels);
+        let distances = labeling.distances.unwrap().into_iter();
+        assert!(distances
+            .zip(whole.distances.unwrap())
+            .all(|(a, b)| (a - b).abs() < 1e-12));
+
+        assert!(matches!(
+            backend.labels(&seeds.points[..2], &[], config, &options),
+            Err(MesherError::InvalidInput(_))
+        ));
+    }
+}
@@ -0,0 +1,24 @@
+// Helpers shared by the unit tests.
+
+use crate::error::MesherError;
+
+/// `count` reproducible points spread over the `size` box by two irrational rotations.
+pub(crate) fn scattered(count: usize, size: (f64, f64)) -> Vec<(f64, f64)> {
+    (0..count)
+        .map(|i| {
+            let t = i as f64;
+            (
+                (t * 0.618_034).fract() * size.0,
+                (t * 0.414_214).fract() * size.1,
+            )
+        })
+        .collect()
+}
+
+/// Result of a GPU run, `None` on machines without a usable GPU, whose tests are skipped.
+pub(crate) fn gpu_or_skip<T>(result: Result<T, MesherError>) -> Option<T> {
+    match result {
+        Err(MesherError::NoAdapter | MesherError::DeviceRequestFailed(_)) => None,
+        result => Some(result.unwrap()),
+    }
+}
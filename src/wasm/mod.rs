@@ -0,0 +1,96 @@
+// Bindings for browser demos meshing interactively on the client GPU through WebGPU. Browsers
+// can't block on the GPU, so every call returns a promise. Points cross the boundary as flat
+// `[x0, y0, x1, y1, ...]` arrays, which JavaScript passes as a `Float64Array`.
+
+use wasm_bindgen::prelude::*;
+
+use crate::cells;
+use crate::config::{BoundaryMode, MesherConfig};
+use crate::jfa_wgpu;
+
+/// Cells of a Voronoi diagram, for drawing.
+#[wasm_bindgen]
+pub struct Diagram {
+    labels: Vec<u32>,
+    cells: Vec<Vec<(f64, f64)>>,
+}
+
+#[wasm_bindgen]
+impl Diagram {
+    /// Seed index plus one of each texel of the square label grid, row by row from the bottom,
+    /// 0 when unassigned.
+    pub fn labels(&self) -> Vec<u32> {
+        self.labels.clone()
+    }
+
+    #[wasm_bindgen(js_name = cellCount)]
+    pub fn cell_count(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Counter-clockwise outline of the cell of seed `index` as a flat array, empty when the
+    /// seed has no texel.
+    pub fn cell(&self, index: usize) -> Vec<f64> {
+        self.cells
+            .get(index)
+            .map(|cell| cell.iter().flat_map(|&(x, y)| [x, y]).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Voronoi diagram of `points` in the `width` by `height` box, on a square grid of
+/// `resolution` texels per side.
+#[wasm_bindgen]
+pub async fn mesh(
+    points: Vec<f64>,
+    width: f64,
+    height: f64,
+    resolution: usize,
+    periodic: bool,
+) -> Result<Diagram, JsError> {
+    let points = pairs(&points);
+    let config = (width, height);
+    let options = options(resolution, periodic);
+    let labels = jfa_wgpu::mesh(&points, config, &options).await?;
+    let dimensions = options.grid_dimensions(config);
+    let cells = cells::extract_cells(&labels, dimensions, points.len(), config, options.boundary);
+    Ok(Diagram {
+        labels: labels.into_iter().map(|label| label as u32).collect(),
+        cells,
+    })
+}
+
+/// `points` after `iterations` steps of Lloyd relaxation, as a flat array.
+#[wasm_bindgen]
+pub async fn relax(
+    points: Vec<f64>,
+    width: f64,
+    height: f64,
+    resolution: usize,
+    periodic: bool,
+    iterations: usize,
+) -> Result<Vec<f64>, JsError> {
+    let options = options(resolution, periodic);
+    let relaxed =
+        jfa_wgpu::relax(&pairs(&points), (width, height), iterations, &[], &options).await?;
+    Ok(relaxed.into_iter().flat_map(|(x, y)| [x, y]).collect())
+}
+
+fn pairs(points: &[f64]) -> Vec<(f64, f64)> {
+    points
+        .chunks_exact(2)
+        .map(|pair| (pair[0], pair[1]))
+        .collect()
+}
+
+fn options(resolution: usize, periodic: bool) -> MesherConfig {
+    MesherConfig {
+        resolution,
+        boundary: if periodic {
+            BoundaryMode::Periodic { x: true, y: true }
+        } else {
+            BoundaryMode::Bounded
+        },
+        ..Default::default()
+    }
+}